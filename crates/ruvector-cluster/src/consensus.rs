@@ -1,16 +1,20 @@
 //! DAG-based consensus protocol inspired by QuDAG
 //!
 //! Implements a directed acyclic graph for transaction ordering and consensus.
+//! Vertices and their transactions are signed by the creating node, and
+//! vertices from other nodes are only accepted with a valid signature from
+//! a key in the `KeyRegistry`.
 
 use chrono::{DateTime, Utc};
 use dashmap::DashMap;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 use tracing::{debug, info, warn};
 use uuid::Uuid;
 
+use crate::identity::{KeyRegistry, NodeIdentity};
 use crate::{ClusterError, Result};
 
 /// A vertex in the consensus DAG
@@ -28,7 +32,7 @@ pub struct DagVertex {
     pub timestamp: DateTime<Utc>,
     /// Vector clock for causality tracking
     pub vector_clock: HashMap<String, u64>,
-    /// Signature (in production, this would be cryptographic)
+    /// Hex-encoded Ed25519 signature by `node_id` over the other fields
     pub signature: String,
 }
 
@@ -47,14 +51,40 @@ impl DagVertex {
             parents,
             timestamp: Utc::now(),
             vector_clock,
-            signature: String::new(), // Would be computed cryptographically
+            signature: String::new(),
         }
     }
 
-    /// Verify the vertex signature
-    pub fn verify_signature(&self) -> bool {
-        // In production, verify cryptographic signature
-        true
+    /// Bytes covered by the signature
+    ///
+    /// The vector clock is sorted by node, so every node derives the same
+    /// bytes for the same vertex.
+    pub fn signable_content(&self) -> Vec<u8> {
+        let clock: BTreeMap<&String, &u64> = self.vector_clock.iter().collect();
+        serde_json::to_vec(&(
+            &self.id,
+            &self.node_id,
+            &self.transaction,
+            &self.parents,
+            &self.timestamp,
+            clock,
+        ))
+        .expect("vertex fields serialize")
+    }
+
+    /// Sign the vertex with the creating node's identity
+    pub fn sign(&mut self, identity: &NodeIdentity) {
+        self.signature = identity.sign(&self.signable_content());
+    }
+
+    /// Verify the vertex and its transaction were signed by `node_id`
+    pub fn verify_signature(&self, keys: &KeyRegistry) -> Result<()> {
+        keys.verify(
+            &self.node_id,
+            &self.transaction.signable_content(),
+            &self.transaction.signature,
+        )?;
+        keys.verify(&self.node_id, &self.signable_content(), &self.signature)
     }
 }
 
@@ -69,6 +99,17 @@ pub struct Transaction {
     pub data: Vec<u8>,
     /// Nonce for ordering
     pub nonce: u64,
+    /// Hex-encoded Ed25519 signature by the submitting node
+    #[serde(default)]
+    pub signature: String,
+}
+
+impl Transaction {
+    /// Bytes covered by the signature
+    pub fn signable_content(&self) -> Vec<u8> {
+        serde_json::to_vec(&(&self.id, &self.tx_type, &self.data, self.nonce))
+            .expect("transaction fields serialize")
+    }
 }
 
 /// Type of transaction
@@ -90,6 +131,10 @@ pub enum TransactionType {
 pub struct DagConsensus {
     /// Node ID
     node_id: String,
+    /// Key signing this node's vertices and transactions
+    identity: NodeIdentity,
+    /// Keys vertices from other nodes are verified against
+    keys: Arc<KeyRegistry>,
     /// DAG vertices (vertex_id -> vertex)
     vertices: Arc<DashMap<String, DagVertex>>,
     /// Finalized vertices
@@ -105,13 +150,23 @@ pub struct DagConsensus {
 }
 
 impl DagConsensus {
-    /// Create a new DAG consensus engine
-    pub fn new(node_id: String, min_quorum_size: usize) -> Self {
+    /// Create a DAG consensus engine signing with `identity`
+    ///
+    /// Peers pin the identity's public key, so it should be the node's
+    /// persisted key (see [`NodeIdentity::load_or_create`]), not a fresh one.
+    pub fn new(identity: NodeIdentity, min_quorum_size: usize) -> Self {
+        let node_id = identity.node_id().to_string();
         let mut vector_clock = HashMap::new();
         vector_clock.insert(node_id.clone(), 0);
 
+        let keys = Arc::new(KeyRegistry::new());
+        keys.register(&node_id, &identity.public_key())
+            .expect("own key registers in an empty registry");
+
         Self {
             node_id,
+            identity,
+            keys,
             vertices: Arc::new(DashMap::new()),
             finalized: Arc::new(RwLock::new(HashSet::new())),
             vector_clock: Arc::new(RwLock::new(vector_clock)),
@@ -126,12 +181,14 @@ impl DagConsensus {
         let mut nonce = self.nonce_counter.write();
         *nonce += 1;
 
-        let transaction = Transaction {
+        let mut transaction = Transaction {
             id: Uuid::new_v4().to_string(),
             tx_type,
             data,
             nonce: *nonce,
+            signature: String::new(),
         };
+        transaction.signature = self.identity.sign(&transaction.signable_content());
 
         let tx_id = transaction.id.clone();
 
@@ -161,7 +218,8 @@ impl DagConsensus {
         let count = clock.entry(self.node_id.clone()).or_insert(0);
         *count += 1;
 
-        let vertex = DagVertex::new(self.node_id.clone(), transaction, parents, clock.clone());
+        let mut vertex = DagVertex::new(self.node_id.clone(), transaction, parents, clock.clone());
+        vertex.sign(&self.identity);
 
         let vertex_id = vertex.id.clone();
         self.vertices.insert(vertex_id.clone(), vertex.clone());
@@ -192,13 +250,27 @@ impl DagConsensus {
             .collect()
     }
 
+    /// Hex-encoded public key of this node
+    pub fn public_key(&self) -> String {
+        self.identity.public_key()
+    }
+
+    /// Keys of the nodes whose vertices are accepted
+    pub fn key_registry(&self) -> Arc<KeyRegistry> {
+        Arc::clone(&self.keys)
+    }
+
     /// Add a vertex from another node
+    ///
+    /// Unsigned vertices, vertices from nodes without a registered key and
+    /// vertices whose content doesn't match the signature are rejected.
     pub fn add_vertex(&self, vertex: DagVertex) -> Result<()> {
-        // Verify signature
-        if !vertex.verify_signature() {
-            return Err(ClusterError::ConsensusError(
-                "Invalid vertex signature".to_string(),
-            ));
+        if let Err(e) = vertex.verify_signature(&self.keys) {
+            warn!(
+                "Rejected vertex {} from {}: {}",
+                vertex.id, vertex.node_id, e
+            );
+            return Err(e);
         }
 
         // Verify parents exist
@@ -222,6 +294,21 @@ impl DagConsensus {
         Ok(())
     }
 
+    /// All vertices in the DAG, in causal order
+    ///
+    /// A vertex's vector clock dominates those of its parents, so ordering
+    /// by clock total puts parents first, as `add_vertex` on a peer needs.
+    pub fn vertices(&self) -> Vec<DagVertex> {
+        let mut vertices: Vec<DagVertex> = self
+            .vertices
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect();
+        vertices
+            .sort_by_key(|vertex| (vertex.vector_clock.values().sum::<u64>(), vertex.timestamp));
+        vertices
+    }
+
     /// Check if a vertex is finalized
     pub fn is_finalized(&self, vertex_id: &str) -> bool {
         let finalized = self.finalized.read();
@@ -386,7 +473,7 @@ mod tests {
 
     #[test]
     fn test_consensus_creation() {
-        let consensus = DagConsensus::new("node1".to_string(), 2);
+        let consensus = DagConsensus::new(NodeIdentity::generate("node1"), 2);
         let stats = consensus.get_stats();
 
         assert_eq!(stats.total_vertices, 0);
@@ -395,7 +482,7 @@ mod tests {
 
     #[test]
     fn test_submit_transaction() {
-        let consensus = DagConsensus::new("node1".to_string(), 2);
+        let consensus = DagConsensus::new(NodeIdentity::generate("node1"), 2);
 
         let tx_id = consensus
             .submit_transaction(TransactionType::Write, vec![1, 2, 3])
@@ -409,7 +496,7 @@ mod tests {
 
     #[test]
     fn test_create_vertex() {
-        let consensus = DagConsensus::new("node1".to_string(), 2);
+        let consensus = DagConsensus::new(NodeIdentity::generate("node1"), 2);
 
         consensus
             .submit_transaction(TransactionType::Write, vec![1, 2, 3])
@@ -439,13 +526,14 @@ mod tests {
 
     #[test]
     fn test_conflict_detection() {
-        let consensus = DagConsensus::new("node1".to_string(), 2);
+        let consensus = DagConsensus::new(NodeIdentity::generate("node1"), 2);
 
         let tx1 = Transaction {
             id: "1".to_string(),
             tx_type: TransactionType::Write,
             data: vec![1],
             nonce: 1,
+            signature: String::new(),
         };
 
         let tx2 = Transaction {
@@ -453,14 +541,64 @@ mod tests {
             tx_type: TransactionType::Write,
             data: vec![2],
             nonce: 2,
+            signature: String::new(),
         };
 
         assert!(consensus.detect_conflicts(&tx1, &tx2));
     }
 
+    #[test]
+    fn test_add_vertex_checks_signatures() {
+        let node1 = DagConsensus::new(NodeIdentity::generate("node1"), 1);
+        let node2 = DagConsensus::new(NodeIdentity::generate("node2"), 1);
+        node1
+            .submit_transaction(TransactionType::System, vec![1])
+            .unwrap();
+        let vertex = node1.create_vertex().unwrap().unwrap();
+
+        // Unknown signer
+        assert!(matches!(
+            node2.add_vertex(vertex.clone()),
+            Err(ClusterError::SignatureError(_))
+        ));
+        node2
+            .key_registry()
+            .register("node1", &node1.public_key())
+            .unwrap();
+
+        // Unsigned, tampered or re-attributed vertices are rejected
+        let mut unsigned = vertex.clone();
+        unsigned.signature.clear();
+        let mut tampered = vertex.clone();
+        tampered.transaction.data = vec![2];
+        let mut resigned = tampered.clone();
+        resigned.sign(&NodeIdentity::generate("node1"));
+        let mut stolen = vertex.clone();
+        stolen.node_id = "node2".to_string();
+        for forged in [unsigned, tampered, resigned, stolen] {
+            assert!(node2.add_vertex(forged).is_err());
+        }
+        assert_eq!(node2.get_stats().total_vertices, 0);
+
+        // A transaction signed by someone else can't be wrapped in a
+        // validly signed vertex
+        let mut wrapped = vertex.clone();
+        wrapped.transaction.signature =
+            NodeIdentity::generate("node1").sign(&wrapped.transaction.signable_content());
+        wrapped.sign(&node1.identity);
+        assert!(node2.add_vertex(wrapped).is_err());
+
+        // The genuine vertex survives serialization and is accepted
+        let json = serde_json::to_vec(&vertex).unwrap();
+        node2
+            .add_vertex(serde_json::from_slice(&json).unwrap())
+            .unwrap();
+        assert_eq!(node2.get_stats().total_vertices, 1);
+    }
+
     #[test]
     fn test_finalization() {
-        let consensus = DagConsensus::new("node1".to_string(), 2);
+        let consensus = DagConsensus::new(NodeIdentity::generate("node1"), 2);
 
         // Create some vertices
         for i in 0..5 {
@@ -172,9 +172,16 @@ impl GossipDiscovery {
     }
 
     /// Merge gossip information from another node
+    ///
+    /// A node's public key is pinned once known: updates announcing a
+    /// different key are dropped.
     pub fn merge_gossip(&self, remote_nodes: Vec<ClusterNode>) {
         for node in remote_nodes {
             if let Some(mut existing) = self.nodes.get_mut(&node.node_id) {
+                if existing.public_key.is_some() && existing.public_key != node.public_key {
+                    warn!("Ignoring gossip changing the key of node {}", node.node_id);
+                    continue;
+                }
                 // Update if remote has newer information
                 if node.last_seen > existing.last_seen {
                     *existing = node;
@@ -380,4 +387,28 @@ mod tests {
         let stats = discovery.get_stats();
         assert_eq!(stats.total_nodes, 3); // local + 2 remote
     }
+
+    #[test]
+    fn test_gossip_keeps_known_keys() {
+        let local_node = create_test_node("local", 8000);
+        let discovery = GossipDiscovery::new(
+            local_node,
+            vec![],
+            Duration::from_secs(5),
+            Duration::from_secs(30),
+        );
+
+        let key = crate::NodeIdentity::generate("node1").public_key();
+        discovery.merge_gossip(vec![create_test_node("node1", 8001).with_public_key(&key)]);
+
+        // A newer announcement with another key doesn't replace it
+        let forged = crate::NodeIdentity::generate("node1").public_key();
+        let mut impostor = create_test_node("node1", 9001).with_public_key(forged);
+        impostor.last_seen = Utc::now() + chrono::Duration::seconds(10);
+        discovery.merge_gossip(vec![impostor]);
+
+        let node = discovery.nodes.get("node1").unwrap();
+        assert_eq!(node.public_key.as_deref(), Some(key.as_str()));
+        assert_eq!(node.address.port(), 8001);
+    }
 }
@@ -0,0 +1,263 @@
+//! Node identities for signing consensus messages
+//!
+//! Each node signs its DAG vertices and transactions with an Ed25519 key.
+//! Public keys travel with `ClusterNode` through discovery and are pinned
+//! in a `KeyRegistry`, which consensus checks every incoming vertex against.
+
+use dashmap::DashMap;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use rand::rngs::OsRng;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use tracing::{info, warn};
+
+use crate::{ClusterError, Result};
+
+/// A node's Ed25519 signing key
+pub struct NodeIdentity {
+    /// Node the key belongs to
+    node_id: String,
+    /// Secret signing key
+    signing_key: SigningKey,
+}
+
+impl NodeIdentity {
+    /// Generate a fresh key for `node_id`
+    pub fn generate(node_id: impl Into<String>) -> Self {
+        Self {
+            node_id: node_id.into(),
+            signing_key: SigningKey::generate(&mut OsRng),
+        }
+    }
+
+    /// Identity from a 32-byte secret key, e.g. loaded from disk
+    pub fn from_secret(node_id: impl Into<String>, secret: &[u8; 32]) -> Self {
+        Self {
+            node_id: node_id.into(),
+            signing_key: SigningKey::from_bytes(secret),
+        }
+    }
+
+    /// Identity stored in the key file at `path`, created there on first use
+    ///
+    /// The file holds the hex-encoded secret key. A new one is written to a
+    /// temporary file readable only by the owner and renamed into place, so
+    /// a crash never leaves a truncated key behind.
+    pub fn load_or_create(node_id: impl Into<String>, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        match fs::read_to_string(path) {
+            Ok(contents) => {
+                let secret: [u8; 32] = hex::decode(contents.trim())
+                    .ok()
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .ok_or_else(|| {
+                        ClusterError::SignatureError(format!(
+                            "Malformed node key file {}",
+                            path.display()
+                        ))
+                    })?;
+                Ok(Self::from_secret(node_id, &secret))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let identity = Self::generate(node_id);
+                identity.save(path)?;
+                info!(
+                    "Created node key {} for node {}",
+                    path.display(),
+                    identity.node_id
+                );
+                Ok(identity)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp_path = path.with_extension("tmp");
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        {
+            let mut file = options.open(&tmp_path)?;
+            file.write_all(hex::encode(self.secret()).as_bytes())?;
+            file.sync_all()?;
+        }
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// The 32-byte secret key, for storing the identity
+    pub fn secret(&self) -> [u8; 32] {
+        self.signing_key.to_bytes()
+    }
+
+    /// Node the key belongs to
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    /// Hex-encoded public key, as published in `ClusterNode::public_key`
+    pub fn public_key(&self) -> String {
+        hex::encode(self.signing_key.verifying_key().as_bytes())
+    }
+
+    /// Hex-encoded signature of `message`
+    pub fn sign(&self, message: &[u8]) -> String {
+        hex::encode(self.signing_key.sign(message).to_bytes())
+    }
+}
+
+impl std::fmt::Debug for NodeIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NodeIdentity")
+            .field("node_id", &self.node_id)
+            .field("public_key", &self.public_key())
+            .finish()
+    }
+}
+
+/// Public keys of the nodes whose signatures are accepted
+///
+/// A key is pinned on first registration: a different key for a known
+/// node is refused until the old one is removed, so a node announcing a
+/// forged key through discovery can't take over another's identity.
+#[derive(Debug, Default)]
+pub struct KeyRegistry {
+    /// Verifying key by node id
+    keys: DashMap<String, VerifyingKey>,
+}
+
+impl KeyRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accept signatures from `node_id` made with the hex-encoded
+    /// `public_key`
+    pub fn register(&self, node_id: &str, public_key: &str) -> Result<()> {
+        let key = parse_public_key(public_key)?;
+        match self.keys.get(node_id) {
+            Some(existing) if *existing == key => Ok(()),
+            Some(_) => {
+                warn!("Refusing new public key for node {}", node_id);
+                Err(ClusterError::SignatureError(format!(
+                    "Node {} already has a different public key",
+                    node_id
+                )))
+            }
+            None => {
+                self.keys.insert(node_id.to_string(), key);
+                info!("Registered public key for node {}", node_id);
+                Ok(())
+            }
+        }
+    }
+
+    /// Stop accepting signatures from `node_id`
+    pub fn remove(&self, node_id: &str) {
+        self.keys.remove(node_id);
+    }
+
+    /// Whether `node_id` has a registered key
+    pub fn contains(&self, node_id: &str) -> bool {
+        self.keys.contains_key(node_id)
+    }
+
+    /// Hex-encoded public key registered for `node_id`
+    pub fn public_key(&self, node_id: &str) -> Option<String> {
+        self.keys
+            .get(node_id)
+            .map(|key| hex::encode(key.as_bytes()))
+    }
+
+    /// Check that `signature` is `node_id`'s hex-encoded signature of
+    /// `message`
+    pub fn verify(&self, node_id: &str, message: &[u8], signature: &str) -> Result<()> {
+        let key = self.keys.get(node_id).map(|key| *key).ok_or_else(|| {
+            ClusterError::SignatureError(format!("No public key for node {}", node_id))
+        })?;
+        let bytes: [u8; 64] = hex::decode(signature)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| {
+                ClusterError::SignatureError(format!("Malformed signature from {}", node_id))
+            })?;
+        key.verify(message, &Signature::from_bytes(&bytes))
+            .map_err(|_| {
+                ClusterError::SignatureError(format!("Invalid signature from {}", node_id))
+            })
+    }
+}
+
+fn parse_public_key(public_key: &str) -> Result<VerifyingKey> {
+    let bytes: [u8; 32] = hex::decode(public_key)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| ClusterError::SignatureError("Malformed public key".to_string()))?;
+    VerifyingKey::from_bytes(&bytes)
+        .map_err(|_| ClusterError::SignatureError("Invalid public key".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let identity = NodeIdentity::generate("node1");
+        let registry = KeyRegistry::new();
+        registry.register("node1", &identity.public_key()).unwrap();
+
+        let signature = identity.sign(b"payload");
+        assert!(registry.verify("node1", b"payload", &signature).is_ok());
+        assert!(registry.verify("node1", b"tampered", &signature).is_err());
+        assert!(registry.verify("node2", b"payload", &signature).is_err());
+        assert!(registry.verify("node1", b"payload", "not hex").is_err());
+
+        // The same key survives a round trip through its secret
+        let restored = NodeIdentity::from_secret("node1", &identity.secret());
+        assert_eq!(restored.public_key(), identity.public_key());
+    }
+
+    #[test]
+    fn test_key_file_keeps_identity() {
+        let dir = std::env::temp_dir().join(format!("ruvector-node-key-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("node1.key");
+
+        let created = NodeIdentity::load_or_create("node1", &path).unwrap();
+        let loaded = NodeIdentity::load_or_create("node1", &path).unwrap();
+        assert_eq!(loaded.public_key(), created.public_key());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        fs::write(&path, "not a key").unwrap();
+        assert!(NodeIdentity::load_or_create("node1", &path).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_keys_are_pinned() {
+        let registry = KeyRegistry::new();
+        let key = NodeIdentity::generate("node1").public_key();
+        let other = NodeIdentity::generate("node1").public_key();
+
+        registry.register("node1", &key).unwrap();
+        registry.register("node1", &key).unwrap();
+        assert!(registry.register("node1", &other).is_err());
+        assert_eq!(registry.public_key("node1"), Some(key));
+        assert!(registry.register("node2", "abcd").is_err());
+
+        registry.remove("node1");
+        registry.register("node1", &other).unwrap();
+    }
+}
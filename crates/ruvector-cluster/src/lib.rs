@@ -3,27 +3,38 @@
 //! This crate provides distributed coordination capabilities including:
 //! - Cluster node management and health monitoring
 //! - Consistent hashing for shard distribution
-//! - DAG-based consensus protocol
+//! - DAG-based consensus protocol with Ed25519-signed vertices
 //! - Dynamic node discovery and topology management
+//! - Scatter-gather search and write routing across shards
+//! - Shard rebalancing with live data migration
 
 pub mod consensus;
 pub mod discovery;
+pub mod identity;
+pub mod query;
+pub mod rebalance;
 pub mod shard;
 
 use chrono::{DateTime, Utc};
 use dashmap::DashMap;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 use thiserror::Error;
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
+use consensus::TransactionType;
+
 pub use consensus::DagConsensus;
 pub use discovery::{DiscoveryService, GossipDiscovery, StaticDiscovery};
+pub use identity::{KeyRegistry, NodeIdentity};
+pub use query::{DistributedSearchResponse, LocalShardNode, QueryCoordinator, ShardNode};
+pub use rebalance::{MigrationPhase, MigrationProgress, Rebalancer};
 pub use shard::{ConsistentHashRing, ShardRouter};
 
 /// Cluster-related errors
@@ -50,6 +61,18 @@ pub enum ClusterError {
     #[error("Serialization error: {0}")]
     SerializationError(String),
 
+    #[error("Storage error: {0}")]
+    StorageError(String),
+
+    #[error("Migration error: {0}")]
+    MigrationError(String),
+
+    #[error("Shard {0} has moved off this node")]
+    ShardMoved(u32),
+
+    #[error("Signature error: {0}")]
+    SignatureError(String),
+
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 }
@@ -84,6 +107,9 @@ pub struct ClusterNode {
     pub metadata: HashMap<String, String>,
     /// Node capacity (for load balancing)
     pub capacity: f64,
+    /// Hex-encoded Ed25519 key the node signs consensus messages with
+    #[serde(default)]
+    pub public_key: Option<String>,
 }
 
 impl ClusterNode {
@@ -96,9 +122,16 @@ impl ClusterNode {
             last_seen: Utc::now(),
             metadata: HashMap::new(),
             capacity: 1.0,
+            public_key: None,
         }
     }
 
+    /// Publish the node's consensus public key
+    pub fn with_public_key(mut self, public_key: impl Into<String>) -> Self {
+        self.public_key = Some(public_key.into());
+        self
+    }
+
     /// Check if the node is healthy (seen recently)
     pub fn is_healthy(&self, timeout: Duration) -> bool {
         let now = Utc::now();
@@ -132,6 +165,77 @@ pub struct ShardInfo {
     pub created_at: DateTime<Utc>,
     /// Last modified timestamp
     pub modified_at: DateTime<Utc>,
+    /// Node receiving the shard while it is migrating
+    #[serde(default)]
+    pub migrating_to: Option<String>,
+    /// Number of ownership changes committed for this shard
+    #[serde(default)]
+    pub version: u64,
+}
+
+/// A change of shard placement computed by [`ClusterManager::plan_rebalance`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShardMove {
+    /// Shard to move
+    pub shard_id: u32,
+    /// Current primary, holding the shard's data
+    pub from: String,
+    /// New primary; the same as `from` when only the replicas change
+    pub to: String,
+    /// Replica nodes once the move is committed
+    pub replica_nodes: Vec<String>,
+}
+
+/// Step of a shard migration, agreed through the consensus log
+///
+/// Every coordinator applies the steps in the finalized order and checks
+/// each against the shard's state at that point, so competing proposals
+/// for a shard resolve the same way everywhere: whichever is finalized
+/// first wins and the rest are dropped.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum OwnershipChange {
+    /// Start mirroring the shard's writes to `to`
+    Begin(ShardMove),
+    /// Hand the shard over to `to` and the move's replicas
+    Commit(ShardMove),
+    /// Give up the migration, leaving the shard with `from`
+    Abort(ShardMove),
+}
+
+impl OwnershipChange {
+    /// The move this step belongs to
+    pub fn shard_move(&self) -> &ShardMove {
+        match self {
+            Self::Begin(mv) | Self::Commit(mv) | Self::Abort(mv) => mv,
+        }
+    }
+
+    /// Check the step can follow the shard's current state
+    fn check(&self, shard: &ShardInfo) -> Result<()> {
+        let mv = self.shard_move();
+        let refuse = |reason: String| {
+            Err(ClusterError::MigrationError(format!(
+                "Shard {} can't move from {} to {}: {}",
+                mv.shard_id, mv.from, mv.to, reason
+            )))
+        };
+        if shard.primary_node != mv.from {
+            return refuse(format!("it is owned by {}", shard.primary_node));
+        }
+        match (self, &shard.migrating_to) {
+            (Self::Begin(_), Some(target)) => refuse(format!("it is migrating to {}", target)),
+            (Self::Begin(_), None) if mv.from == mv.to => {
+                refuse("only the replicas change".to_string())
+            }
+            (Self::Begin(_), None) => Ok(()),
+            (Self::Commit(_) | Self::Abort(_), Some(target)) if *target == mv.to => Ok(()),
+            (Self::Commit(_), None) if mv.from == mv.to => Ok(()),
+            // Nothing to undo; may still come before its begin is applied
+            (Self::Abort(_), None) => Ok(()),
+            _ => refuse("no migration is in progress".to_string()),
+        }
+    }
 }
 
 /// Status of a shard
@@ -158,10 +262,15 @@ pub struct ClusterConfig {
     pub heartbeat_interval: Duration,
     /// Timeout before considering a node offline
     pub node_timeout: Duration,
-    /// Enable DAG-based consensus
+    /// Enable DAG-based consensus; off by default, as it needs
+    /// [`identity_path`](Self::identity_path)
     pub enable_consensus: bool,
     /// Minimum nodes required for quorum
     pub min_quorum_size: usize,
+    /// File holding this node's consensus signing key, created on first
+    /// start; required by [`ClusterManager::new`] when consensus is enabled
+    #[serde(default)]
+    pub identity_path: Option<PathBuf>,
 }
 
 impl Default for ClusterConfig {
@@ -171,8 +280,9 @@ impl Default for ClusterConfig {
             shard_count: 64,
             heartbeat_interval: Duration::from_secs(5),
             node_timeout: Duration::from_secs(30),
-            enable_consensus: true,
+            enable_consensus: false,
             min_quorum_size: 2,
+            identity_path: None,
         }
     }
 }
@@ -195,15 +305,43 @@ pub struct ClusterManager {
     discovery: Box<dyn DiscoveryService>,
     /// Current node ID
     node_id: String,
+    /// Consensus transactions already looked at by `apply_committed`
+    applied: Mutex<HashSet<String>>,
 }
 
 impl ClusterManager {
     /// Create a new cluster manager
+    ///
+    /// With consensus enabled, the node key is loaded from
+    /// [`ClusterConfig::identity_path`], or created there on first start, so
+    /// the node keeps the identity its peers have pinned across restarts.
     pub fn new(
         config: ClusterConfig,
         node_id: String,
         discovery: Box<dyn DiscoveryService>,
     ) -> Result<Self> {
+        let identity = match &config.identity_path {
+            Some(path) => NodeIdentity::load_or_create(node_id, path)?,
+            None if config.enable_consensus => {
+                return Err(ClusterError::InvalidConfig(
+                    "consensus needs a persistent node key: set identity_path or use \
+                     ClusterManager::with_identity"
+                        .to_string(),
+                ))
+            }
+            // Nothing is signed without consensus
+            None => NodeIdentity::generate(node_id),
+        };
+        Self::with_identity(config, identity, discovery)
+    }
+
+    /// Create a cluster manager signing consensus messages with `identity`
+    pub fn with_identity(
+        config: ClusterConfig,
+        identity: NodeIdentity,
+        discovery: Box<dyn DiscoveryService>,
+    ) -> Result<Self> {
+        let node_id = identity.node_id().to_string();
         let nodes = Arc::new(DashMap::new());
         let shards = Arc::new(DashMap::new());
         let hash_ring = Arc::new(RwLock::new(ConsistentHashRing::new(
@@ -213,7 +351,7 @@ impl ClusterManager {
 
         let consensus = if config.enable_consensus {
             Some(Arc::new(DagConsensus::new(
-                node_id.clone(),
+                identity,
                 config.min_quorum_size,
             )))
         } else {
@@ -229,13 +367,28 @@ impl ClusterManager {
             consensus,
             discovery,
             node_id,
+            applied: Mutex::new(HashSet::new()),
         })
     }
 
     /// Add a node to the cluster
+    ///
+    /// Unassigned shards are placed right away. Shards that already have an
+    /// owner keep it until the moves from [`ClusterManager::plan_rebalance`]
+    /// are carried out, since their data has to follow.
+    ///
+    /// The node's public key, if it has one, is registered with consensus;
+    /// a key conflicting with the one already known for the node is
+    /// refused and the node isn't added.
     pub async fn add_node(&self, node: ClusterNode) -> Result<()> {
         info!("Adding node {} to cluster", node.node_id);
 
+        if let (Some(consensus), Some(public_key)) = (&self.consensus, &node.public_key) {
+            consensus
+                .key_registry()
+                .register(&node.node_id, public_key)?;
+        }
+
         // Add to hash ring
         {
             let mut ring = self.hash_ring.write();
@@ -253,6 +406,9 @@ impl ClusterManager {
     }
 
     /// Remove a node from the cluster
+    ///
+    /// Shards owned by the node stay with it until they are moved off; keep
+    /// its `ShardNode` registered until the rebalance has finished.
     pub async fn remove_node(&self, node_id: &str) -> Result<()> {
         info!("Removing node {} from cluster", node_id);
 
@@ -264,6 +420,9 @@ impl ClusterManager {
 
         // Remove node information
         self.nodes.remove(node_id);
+        if let Some(consensus) = &self.consensus {
+            consensus.key_registry().remove(node_id);
+        }
 
         // Rebalance shards
         self.rebalance_shards().await?;
@@ -331,34 +490,171 @@ impl ClusterManager {
             status: ShardStatus::Active,
             created_at: Utc::now(),
             modified_at: Utc::now(),
+            migrating_to: None,
+            version: 0,
         };
 
         self.shards.insert(shard_id, shard_info.clone());
         Ok(shard_info)
     }
 
-    /// Rebalance shards across nodes
+    /// Assign shards that have no owner yet
     async fn rebalance_shards(&self) -> Result<()> {
-        debug!("Rebalancing shards across cluster");
+        debug!("Assigning unplaced shards");
 
         for shard_id in 0..self.config.shard_count {
-            if let Some(mut shard) = self.shards.get_mut(&shard_id) {
-                let ring = self.hash_ring.read();
-                let key = format!("shard:{}", shard_id);
-                let nodes = ring.get_nodes(&key, self.config.replication_factor);
-
-                if !nodes.is_empty() {
-                    shard.primary_node = nodes[0].clone();
-                    shard.replica_nodes = nodes.into_iter().skip(1).collect();
-                    shard.modified_at = Utc::now();
-                }
-            } else {
-                // Create new shard assignment
+            if !self.shards.contains_key(&shard_id) {
                 self.assign_shard(shard_id)?;
             }
         }
 
-        debug!("Shard rebalancing complete");
+        Ok(())
+    }
+
+    /// Moves that bring every shard to its place on the hash ring
+    ///
+    /// Shards already migrating are left out.
+    pub fn plan_rebalance(&self) -> Vec<ShardMove> {
+        let ring = self.hash_ring.read();
+        let mut moves: Vec<ShardMove> = self
+            .shards
+            .iter()
+            .filter(|entry| entry.value().migrating_to.is_none())
+            .filter_map(|entry| {
+                let shard = entry.value();
+                let key = format!("shard:{}", shard.shard_id);
+                let mut nodes = ring.get_nodes(&key, self.config.replication_factor);
+                if nodes.is_empty() {
+                    return None;
+                }
+                let to = nodes.remove(0);
+                if to == shard.primary_node && nodes == shard.replica_nodes {
+                    return None;
+                }
+                Some(ShardMove {
+                    shard_id: shard.shard_id,
+                    from: shard.primary_node.clone(),
+                    to,
+                    replica_nodes: nodes,
+                })
+            })
+            .collect();
+        moves.sort_by_key(|mv| mv.shard_id);
+        moves
+    }
+
+    /// Propose marking a shard as migrating to `mv.to`
+    ///
+    /// Once applied, writes keep going to the current primary and are
+    /// mirrored to the new owner until the migration is committed or
+    /// aborted. See [`ClusterManager::propose`] for when that is.
+    pub fn begin_migration(&self, mv: &ShardMove) -> Result<()> {
+        self.propose(OwnershipChange::Begin(mv.clone()))
+    }
+
+    /// Propose giving up a migration, leaving the shard with `mv.from`
+    pub fn abort_migration(&self, mv: &ShardMove) -> Result<()> {
+        self.propose(OwnershipChange::Abort(mv.clone()))
+    }
+
+    /// Propose handing a shard over to `mv.to` and `mv.replica_nodes`
+    ///
+    /// A move to a new primary must have been begun with
+    /// [`ClusterManager::begin_migration`].
+    pub fn commit_migration(&self, mv: &ShardMove) -> Result<()> {
+        self.propose(OwnershipChange::Commit(mv.clone()))
+    }
+
+    /// Propose a migration step
+    ///
+    /// The step is checked against this node's view of the shard first.
+    /// Without consensus it is applied at once. With consensus it is
+    /// submitted as a system transaction and only takes effect, here as on
+    /// every other node, when [`ClusterManager::apply_committed`] finds it
+    /// finalized; poll the shard to learn whether it won.
+    pub fn propose(&self, change: OwnershipChange) -> Result<()> {
+        let Some(consensus) = &self.consensus else {
+            return self.apply_change(&change);
+        };
+
+        let shard_id = change.shard_move().shard_id;
+        let shard = self
+            .get_shard(shard_id)
+            .ok_or(ClusterError::ShardNotFound(shard_id))?;
+        change.check(&shard)?;
+
+        let data = serde_json::to_vec(&change)
+            .map_err(|e| ClusterError::SerializationError(e.to_string()))?;
+        consensus.submit_transaction(TransactionType::System, data)?;
+        consensus.create_vertex()?;
+        debug!("Proposed {:?}", change);
+        Ok(())
+    }
+
+    /// Apply migration steps finalized by consensus
+    ///
+    /// Each transaction is looked at once, in the finalized order, so this
+    /// can be called repeatedly. Steps that no longer fit the shard's state,
+    /// because a competing one was finalized first, are skipped. Returns
+    /// how many steps were applied.
+    pub fn apply_committed(&self) -> Result<usize> {
+        let Some(consensus) = &self.consensus else {
+            return Ok(0);
+        };
+        let mut seen = self.applied.lock();
+        consensus.finalize_vertices()?;
+
+        let mut applied = 0;
+        for tx in consensus.get_finalized_order() {
+            if !matches!(tx.tx_type, TransactionType::System) || !seen.insert(tx.id) {
+                continue;
+            }
+            let Ok(change) = serde_json::from_slice::<OwnershipChange>(&tx.data) else {
+                continue;
+            };
+            match self.apply_change(&change) {
+                Ok(()) => applied += 1,
+                Err(e) => debug!("Skipping finalized migration step: {}", e),
+            }
+        }
+        Ok(applied)
+    }
+
+    fn apply_change(&self, change: &OwnershipChange) -> Result<()> {
+        let mv = change.shard_move();
+        let mut shard = self
+            .shards
+            .get_mut(&mv.shard_id)
+            .ok_or(ClusterError::ShardNotFound(mv.shard_id))?;
+        change.check(&shard)?;
+
+        match change {
+            OwnershipChange::Begin(_) => {
+                shard.status = ShardStatus::Migrating;
+                shard.migrating_to = Some(mv.to.clone());
+                info!(
+                    "Shard {} migrating from {} to {}",
+                    mv.shard_id, mv.from, mv.to
+                );
+            }
+            OwnershipChange::Commit(_) => {
+                shard.primary_node = mv.to.clone();
+                shard.replica_nodes = mv.replica_nodes.clone();
+                shard.version += 1;
+                shard.status = ShardStatus::Active;
+                shard.migrating_to = None;
+                info!(
+                    "Shard {} now owned by {} (version {})",
+                    mv.shard_id, mv.to, shard.version
+                );
+            }
+            OwnershipChange::Abort(_) => {
+                shard.status = ShardStatus::Active;
+                shard.migrating_to = None;
+                warn!("Migration of shard {} to {} aborted", mv.shard_id, mv.to);
+            }
+        }
+        shard.modified_at = Utc::now();
         Ok(())
     }
 
@@ -393,8 +689,14 @@ impl ClusterManager {
         // Start discovery service
         let discovered = self.discovery.discover_nodes().await?;
         for node in discovered {
-            if node.node_id != self.node_id {
-                self.add_node(node).await?;
+            if node.node_id == self.node_id {
+                continue;
+            }
+            // One node announced with a conflicting key mustn't keep the
+            // rest of the cluster from starting
+            let node_id = node.node_id.clone();
+            if let Err(e) = self.add_node(node).await {
+                warn!("Skipping discovered node {}: {}", node_id, e);
             }
         }
 
@@ -425,6 +727,11 @@ impl ClusterManager {
         }
     }
 
+    /// Get the cluster configuration
+    pub fn config(&self) -> &ClusterConfig {
+        &self.config
+    }
+
     /// Get the shard router
     pub fn router(&self) -> Arc<ShardRouter> {
         Arc::clone(&self.router)
@@ -434,6 +741,14 @@ impl ClusterManager {
     pub fn consensus(&self) -> Option<Arc<DagConsensus>> {
         self.consensus.as_ref().map(Arc::clone)
     }
+
+    /// Public key this node signs consensus messages with, to publish in
+    /// its `ClusterNode` so peers can verify them
+    pub fn public_key(&self) -> Option<String> {
+        self.consensus
+            .as_ref()
+            .map(|consensus| consensus.public_key())
+    }
 }
 
 /// Cluster statistics
@@ -466,19 +781,76 @@ mod tests {
         assert!(node.is_healthy(Duration::from_secs(60)));
     }
 
+    fn test_manager(config: ClusterConfig, node_id: &str) -> ClusterManager {
+        let discovery = Box::new(StaticDiscovery::new(vec![]));
+        ClusterManager::with_identity(config, NodeIdentity::generate(node_id), discovery).unwrap()
+    }
+
     #[tokio::test]
     async fn test_cluster_manager_creation() {
-        let config = ClusterConfig::default();
-        let discovery = Box::new(StaticDiscovery::new(vec![]));
-        let manager = ClusterManager::new(config, "test-node".to_string(), discovery);
-        assert!(manager.is_ok());
+        let discovery = || Box::new(StaticDiscovery::new(vec![]));
+
+        let manager = ClusterManager::new(
+            ClusterConfig::default(),
+            "test-node".to_string(),
+            discovery(),
+        )
+        .unwrap();
+        assert!(manager.public_key().is_none());
+
+        // Consensus won't start with a throwaway key
+        let config = ClusterConfig {
+            enable_consensus: true,
+            ..Default::default()
+        };
+        let manager = ClusterManager::new(config, "test-node".to_string(), discovery());
+        assert!(matches!(manager, Err(ClusterError::InvalidConfig(_))));
+
+        // The key file keeps the identity across restarts
+        let dir = std::env::temp_dir().join(format!("ruvector-cluster-{}", Uuid::new_v4()));
+        let config = ClusterConfig {
+            enable_consensus: true,
+            identity_path: Some(dir.join("node.key")),
+            ..Default::default()
+        };
+        let first = ClusterManager::new(config.clone(), "test-node".to_string(), discovery())
+            .unwrap()
+            .public_key();
+        let second = ClusterManager::new(config, "test-node".to_string(), discovery())
+            .unwrap()
+            .public_key();
+        assert!(first.is_some());
+        assert_eq!(first, second);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_start_skips_conflicting_nodes() {
+        let key = NodeIdentity::generate("node1").public_key();
+        let forged = NodeIdentity::generate("node1").public_key();
+        let discovery = Box::new(StaticDiscovery::new(vec![
+            create_test_node("node1", 8001).with_public_key(&key),
+            create_test_node("node1", 9001).with_public_key(forged),
+            create_test_node("node2", 8002),
+        ]));
+        let config = ClusterConfig {
+            enable_consensus: true,
+            ..Default::default()
+        };
+        let manager =
+            ClusterManager::with_identity(config, NodeIdentity::generate("test-node"), discovery)
+                .unwrap();
+
+        manager.start().await.unwrap();
+        assert_eq!(manager.list_nodes().len(), 2);
+        assert_eq!(manager.get_node("node1").unwrap().address.port(), 8001);
+        let registry = manager.consensus().unwrap().key_registry();
+        assert_eq!(registry.public_key("node1"), Some(key));
     }
 
     #[tokio::test]
     async fn test_add_remove_node() {
-        let config = ClusterConfig::default();
-        let discovery = Box::new(StaticDiscovery::new(vec![]));
-        let manager = ClusterManager::new(config, "test-node".to_string(), discovery).unwrap();
+        let manager = test_manager(ClusterConfig::default(), "test-node");
 
         let node = create_test_node("node1", 8000);
         manager.add_node(node).await.unwrap();
@@ -496,8 +868,7 @@ mod tests {
             replication_factor: 2,
             ..Default::default()
         };
-        let discovery = Box::new(StaticDiscovery::new(vec![]));
-        let manager = ClusterManager::new(config, "test-node".to_string(), discovery).unwrap();
+        let manager = test_manager(config, "test-node");
 
         // Add some nodes
         for i in 0..3 {
@@ -510,4 +881,228 @@ mod tests {
         assert_eq!(shard.shard_id, 0);
         assert!(!shard.primary_node.is_empty());
     }
+
+    async fn manager_with_nodes(
+        config: ClusterConfig,
+        node_id: &str,
+        count: u16,
+    ) -> ClusterManager {
+        let manager = test_manager(config, node_id);
+        for i in 0..count {
+            let node = create_test_node(&format!("node{}", i), 8000 + i);
+            manager.add_node(node).await.unwrap();
+        }
+        manager
+    }
+
+    #[tokio::test]
+    async fn test_node_keys_registered_on_join() {
+        let config = ClusterConfig {
+            enable_consensus: true,
+            ..Default::default()
+        };
+        let manager = manager_with_nodes(config, "test-node", 0).await;
+        let registry = manager.consensus().unwrap().key_registry();
+        assert_eq!(registry.public_key("test-node"), manager.public_key());
+
+        let key = NodeIdentity::generate("node1").public_key();
+        let node = create_test_node("node1", 8001).with_public_key(&key);
+        manager.add_node(node).await.unwrap();
+        assert_eq!(registry.public_key("node1"), Some(key.clone()));
+
+        // Another key for a known node is refused, and the node left as is
+        let forged = NodeIdentity::generate("node1").public_key();
+        let impostor = create_test_node("node1", 9001).with_public_key(forged);
+        assert!(matches!(
+            manager.add_node(impostor).await,
+            Err(ClusterError::SignatureError(_))
+        ));
+        assert_eq!(manager.get_node("node1").unwrap().address.port(), 8001);
+        assert_eq!(registry.public_key("node1"), Some(key));
+
+        manager.remove_node("node1").await.unwrap();
+        assert!(!registry.contains("node1"));
+    }
+
+    #[tokio::test]
+    async fn test_plan_and_commit_migration() {
+        let config = ClusterConfig {
+            shard_count: 8,
+            replication_factor: 2,
+            enable_consensus: false,
+            ..Default::default()
+        };
+        let manager = manager_with_nodes(config, "test-node", 1).await;
+        assert!(manager.plan_rebalance().is_empty());
+
+        // Placed shards stay put when a node joins, until moved
+        manager
+            .add_node(create_test_node("node1", 8001))
+            .await
+            .unwrap();
+        assert!(manager
+            .list_shards()
+            .iter()
+            .all(|shard| shard.primary_node == "node0"));
+        let moves = manager.plan_rebalance();
+        assert!(!moves.is_empty());
+
+        let mv = moves.iter().find(|mv| mv.from != mv.to).unwrap();
+        assert!(manager.commit_migration(mv).is_err());
+        manager.begin_migration(mv).unwrap();
+        assert!(manager.begin_migration(mv).is_err());
+        let shard = manager.get_shard(mv.shard_id).unwrap();
+        assert_eq!(shard.status, ShardStatus::Migrating);
+        assert_eq!(shard.migrating_to.as_deref(), Some(mv.to.as_str()));
+        assert!(!manager
+            .plan_rebalance()
+            .iter()
+            .any(|planned| planned.shard_id == mv.shard_id));
+
+        manager.abort_migration(mv).unwrap();
+        let shard = manager.get_shard(mv.shard_id).unwrap();
+        assert_eq!(shard.status, ShardStatus::Active);
+        assert_eq!(shard.primary_node, mv.from);
+
+        manager.begin_migration(mv).unwrap();
+        manager.commit_migration(mv).unwrap();
+        let shard = manager.get_shard(mv.shard_id).unwrap();
+        assert_eq!(shard.primary_node, mv.to);
+        assert_eq!(shard.replica_nodes, mv.replica_nodes);
+        assert_eq!(shard.status, ShardStatus::Active);
+        assert_eq!(shard.migrating_to, None);
+        assert_eq!(shard.version, 1);
+        assert_eq!(manager.plan_rebalance().len(), moves.len() - 1);
+    }
+
+    /// Copy every vertex `from` has to `to`
+    fn ship(from: &ClusterManager, to: &ClusterManager) {
+        let dag = to.consensus().unwrap();
+        for vertex in from.consensus().unwrap().vertices() {
+            dag.add_vertex(vertex).unwrap();
+        }
+    }
+
+    /// Exchange vertices, confirm each other's, and apply what's finalized
+    fn sync(a: &ClusterManager, b: &ClusterManager) {
+        ship(a, b);
+        ship(b, a);
+        for manager in [a, b] {
+            let dag = manager.consensus().unwrap();
+            dag.submit_transaction(TransactionType::System, b"ack".to_vec())
+                .unwrap();
+            dag.create_vertex().unwrap();
+        }
+        ship(a, b);
+        ship(b, a);
+        for manager in [a, b] {
+            manager.apply_committed().unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ownership_change_through_consensus() {
+        let config = ClusterConfig {
+            shard_count: 8,
+            replication_factor: 2,
+            enable_consensus: true,
+            min_quorum_size: 1,
+            ..Default::default()
+        };
+        let proposer = manager_with_nodes(config.clone(), "coordinator", 1).await;
+        let peer = manager_with_nodes(config, "peer", 1).await;
+        for manager in [&proposer, &peer] {
+            manager
+                .add_node(create_test_node("node1", 8001))
+                .await
+                .unwrap();
+        }
+
+        let moves = proposer.plan_rebalance();
+        assert_eq!(moves, peer.plan_rebalance());
+        let mv = moves.iter().find(|mv| mv.from != mv.to).unwrap();
+
+        // Nothing changes until the proposal is finalized, not even on
+        // the proposer
+        proposer.begin_migration(mv).unwrap();
+        assert_eq!(proposer.get_shard(mv.shard_id).unwrap().migrating_to, None);
+        assert_eq!(proposer.apply_committed().unwrap(), 0);
+
+        // Vertices from a node without a registered key are refused
+        let vertices = proposer.consensus().unwrap().vertices();
+        assert!(peer
+            .consensus()
+            .unwrap()
+            .add_vertex(vertices[0].clone())
+            .is_err());
+        peer.consensus()
+            .unwrap()
+            .key_registry()
+            .register("coordinator", &proposer.public_key().unwrap())
+            .unwrap();
+        proposer
+            .consensus()
+            .unwrap()
+            .key_registry()
+            .register("peer", &peer.public_key().unwrap())
+            .unwrap();
+
+        sync(&proposer, &peer);
+        for manager in [&proposer, &peer] {
+            let shard = manager.get_shard(mv.shard_id).unwrap();
+            assert_eq!(shard.migrating_to.as_deref(), Some(mv.to.as_str()));
+        }
+
+        for other in moves.iter().filter(|other| *other != mv) {
+            if other.from != other.to {
+                proposer.begin_migration(other).unwrap();
+                sync(&proposer, &peer);
+            }
+        }
+        for mv in &moves {
+            proposer.commit_migration(mv).unwrap();
+            sync(&proposer, &peer);
+        }
+        assert_eq!(proposer.apply_committed().unwrap(), 0);
+        for manager in [&proposer, &peer] {
+            for mv in &moves {
+                let shard = manager.get_shard(mv.shard_id).unwrap();
+                assert_eq!(shard.primary_node, mv.to);
+                assert_eq!(shard.replica_nodes, mv.replica_nodes);
+                assert_eq!(shard.version, 1);
+            }
+            assert!(manager.plan_rebalance().is_empty());
+        }
+
+        // Competing moves of one shard: both nodes keep the same winner
+        for manager in [&proposer, &peer] {
+            manager
+                .add_node(create_test_node("node2", 8002))
+                .await
+                .unwrap();
+        }
+        let shard = proposer.get_shard(mv.shard_id).unwrap();
+        let first = ShardMove {
+            shard_id: mv.shard_id,
+            from: shard.primary_node.clone(),
+            to: "node2".to_string(),
+            replica_nodes: Vec::new(),
+        };
+        let other = if shard.primary_node == "node0" {
+            "node1"
+        } else {
+            "node0"
+        };
+        let second = ShardMove {
+            to: other.to_string(),
+            ..first.clone()
+        };
+        proposer.begin_migration(&first).unwrap();
+        peer.begin_migration(&second).unwrap();
+        sync(&proposer, &peer);
+
+        let winner = proposer.get_shard(mv.shard_id).unwrap().migrating_to;
+        assert!(winner.is_some());
+        assert_eq!(peer.get_shard(mv.shard_id).unwrap().migrating_to, winner);
+    }
 }
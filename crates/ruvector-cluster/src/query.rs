@@ -0,0 +1,567 @@
+//! Distributed query coordination
+//!
+//! Fans searches out to the nodes owning each shard, merges their top-k
+//! results, and routes writes by vector id to the shard's primary node.
+//! While a shard migrates, its writes are mirrored to the new owner. A node
+//! that no longer holds a shard says so, and the coordinator then picks up
+//! the committed placement and tries again.
+
+use async_trait::async_trait;
+use dashmap::mapref::entry::Entry;
+use dashmap::mapref::one::Ref;
+use dashmap::{DashMap, DashSet};
+use futures::future::join_all;
+use ruvector_core::types::{
+    DbOptions, ScrollPage, ScrollRequest, SearchQuery, SearchResult, VectorEntry, VectorId,
+};
+use ruvector_core::VectorDB;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tokio::time::timeout;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::{ClusterError, ClusterManager, NodeStatus, Result};
+
+/// A node serving shard data
+///
+/// Implemented by network clients for remote nodes and by
+/// [`LocalShardNode`] for shards stored in this process.
+#[async_trait]
+pub trait ShardNode: Send + Sync {
+    /// Search `shards` on this node, returning at most `query.k` results
+    /// ordered best first
+    async fn search(&self, shards: &[u32], query: &SearchQuery) -> Result<Vec<SearchResult>>;
+
+    /// Insert or replace a vector in `shard`
+    async fn upsert(&self, shard: u32, entry: VectorEntry) -> Result<VectorId>;
+
+    /// Get a vector from `shard`
+    async fn get(&self, shard: u32, id: &str) -> Result<Option<VectorEntry>>;
+
+    /// Delete a vector from `shard`, returning whether it existed
+    async fn delete(&self, shard: u32, id: &str) -> Result<bool>;
+
+    /// Up to `limit` vectors of `shard` with their data, in id order,
+    /// starting after `offset`
+    async fn scroll(
+        &self,
+        shard: u32,
+        offset: Option<VectorId>,
+        limit: usize,
+    ) -> Result<ScrollPage>;
+
+    /// Discard everything stored for `shard`
+    ///
+    /// Later requests for the shard fail with [`ClusterError::ShardMoved`]
+    /// until [`ShardNode::accept_shard`] is called for it.
+    async fn drop_shard(&self, shard: u32) -> Result<()>;
+
+    /// Take writes for `shard` again, as the target of a migration
+    async fn accept_shard(&self, shard: u32) -> Result<()>;
+}
+
+/// Client for a node serving shard data
+type NodeClient = Arc<dyn ShardNode>;
+
+/// Result of a distributed search
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DistributedSearchResponse {
+    /// Merged top-k results, best first
+    pub results: Vec<SearchResult>,
+    /// Whether some shards didn't answer, so results may be missing
+    pub partial: bool,
+    /// Shards that failed, timed out or had no available node
+    pub failed_shards: Vec<u32>,
+}
+
+/// Coordinates queries and writes across the nodes of a cluster
+///
+/// Shard placement comes from the `ClusterManager`; nodes are reached
+/// through the `ShardNode` registered under their node id.
+pub struct QueryCoordinator {
+    /// Cluster membership and shard placement
+    cluster: Arc<ClusterManager>,
+    /// Clients for each node, by node id
+    nodes: DashMap<String, Arc<dyn ShardNode>>,
+    /// Time allowed for each node to answer
+    timeout: Duration,
+    /// Per-shard gates: writes hold them shared, migration steps exclusively
+    gates: DashMap<u32, Arc<RwLock<()>>>,
+}
+
+impl QueryCoordinator {
+    /// Create a coordinator giving each node `timeout` to answer
+    pub fn new(cluster: Arc<ClusterManager>, timeout: Duration) -> Self {
+        Self {
+            cluster,
+            nodes: DashMap::new(),
+            timeout,
+            gates: DashMap::new(),
+        }
+    }
+
+    /// Register the client used to reach `node_id`
+    pub fn register_node(&self, node_id: impl Into<String>, node: Arc<dyn ShardNode>) {
+        self.nodes.insert(node_id.into(), node);
+    }
+
+    /// Forget the client for `node_id`
+    pub fn unregister_node(&self, node_id: &str) {
+        self.nodes.remove(node_id);
+    }
+
+    /// Search every shard and merge the results
+    ///
+    /// Each shard is queried on its primary, the only node its writes go
+    /// to. Shards whose primary is offline, fails or doesn't answer in time
+    /// are listed in the response, which is then marked partial; the search
+    /// fails only if no shard answered.
+    pub async fn search(&self, query: &SearchQuery) -> Result<DistributedSearchResponse> {
+        let shard_count = self.cluster.config().shard_count;
+        let (mut lists, mut failed_shards, moved) = self.search_shards(0..shard_count, query).await;
+        if !moved.is_empty() {
+            // Some node gave its shards away; ask the new owners
+            self.cluster.apply_committed()?;
+            let (retried, failed, moved) = self.search_shards(moved, query).await;
+            lists.extend(retried);
+            failed_shards.extend(failed);
+            failed_shards.extend(moved);
+        }
+
+        let answered = shard_count as usize - failed_shards.len();
+        if answered == 0 {
+            return Err(ClusterError::NetworkError(
+                "No shard answered the search".to_string(),
+            ));
+        }
+
+        failed_shards.sort_unstable();
+        debug!(
+            "Search answered by {} shards, {} failed",
+            answered,
+            failed_shards.len()
+        );
+        Ok(DistributedSearchResponse {
+            results: merge_top_k(lists, query.k),
+            partial: !failed_shards.is_empty(),
+            failed_shards,
+        })
+    }
+
+    /// Search `shards` on their owners, returning the result lists, the
+    /// shards that failed, and the shards whose node no longer holds them
+    async fn search_shards(
+        &self,
+        shards: impl IntoIterator<Item = u32>,
+        query: &SearchQuery,
+    ) -> (Vec<Vec<SearchResult>>, Vec<u32>, Vec<u32>) {
+        let mut failed_shards = Vec::new();
+        let mut moved_shards = Vec::new();
+        let mut by_node: BTreeMap<String, Vec<u32>> = BTreeMap::new();
+        for shard in shards {
+            match self.owner(shard) {
+                Some(node_id) => by_node.entry(node_id).or_default().push(shard),
+                None => failed_shards.push(shard),
+            }
+        }
+
+        let requests = by_node.into_iter().map(|(node_id, shards)| async move {
+            let result = match self.nodes.get(&node_id).map(|node| node.value().clone()) {
+                Some(node) => match timeout(self.timeout, node.search(&shards, query)).await {
+                    Ok(result) => result,
+                    Err(_) => Err(ClusterError::NetworkError(format!(
+                        "Node {} timed out",
+                        node_id
+                    ))),
+                },
+                None => Err(ClusterError::NodeNotFound(node_id.clone())),
+            };
+            (node_id, shards, result)
+        });
+
+        let mut lists = Vec::new();
+        for (node_id, shards, result) in join_all(requests).await {
+            match result {
+                Ok(results) => lists.push(results),
+                Err(ClusterError::ShardMoved(shard)) => {
+                    debug!("Node {} no longer holds shard {}", node_id, shard);
+                    moved_shards.extend(shards);
+                }
+                Err(e) => {
+                    warn!("Search on node {} failed: {}", node_id, e);
+                    failed_shards.extend(shards);
+                }
+            }
+        }
+        (lists, failed_shards, moved_shards)
+    }
+
+    /// Insert or replace a vector on the primary of its shard
+    ///
+    /// Entries without an id get a generated one, so the vector can be
+    /// found again by id. While the shard migrates the write is repeated
+    /// on the new owner.
+    pub async fn upsert(&self, mut entry: VectorEntry) -> Result<VectorId> {
+        let id = entry
+            .id
+            .get_or_insert_with(|| Uuid::new_v4().to_string())
+            .clone();
+        let shard = self.cluster.router().get_shard_for_vector(&id);
+        let gate = self.gate(shard);
+        let _guard = gate.read().await;
+
+        self.retry_moved(|| async {
+            let (primary, target) = self.writers(shard)?;
+            let id = self
+                .with_timeout(primary.upsert(shard, entry.clone()))
+                .await?;
+            if let Some(target) = target {
+                self.with_timeout(target.upsert(shard, entry.clone()))
+                    .await?;
+            }
+            Ok(id)
+        })
+        .await
+    }
+
+    /// Get a vector from the primary of its shard
+    pub async fn get(&self, id: &str) -> Result<Option<VectorEntry>> {
+        let shard = self.cluster.router().get_shard_for_vector(id);
+        let gate = self.gate(shard);
+        let _guard = gate.read().await;
+
+        self.retry_moved(|| async {
+            let (primary, _) = self.writers(shard)?;
+            self.with_timeout(primary.get(shard, id)).await
+        })
+        .await
+    }
+
+    /// Delete a vector from the primary of its shard, and from the new
+    /// owner while the shard migrates
+    pub async fn delete(&self, id: &str) -> Result<bool> {
+        let shard = self.cluster.router().get_shard_for_vector(id);
+        let gate = self.gate(shard);
+        let _guard = gate.read().await;
+
+        self.retry_moved(|| async {
+            let (primary, target) = self.writers(shard)?;
+            let existed = self.with_timeout(primary.delete(shard, id)).await?;
+            if let Some(target) = target {
+                self.with_timeout(target.delete(shard, id)).await?;
+            }
+            Ok(existed)
+        })
+        .await
+    }
+
+    /// Run `request` again with the committed placement if a node turned
+    /// out not to hold the shard any more
+    ///
+    /// Requests are only retried once, and must be safe to repeat.
+    async fn retry_moved<T, F>(&self, request: impl Fn() -> F) -> Result<T>
+    where
+        F: std::future::Future<Output = Result<T>>,
+    {
+        match request().await {
+            Err(ClusterError::ShardMoved(shard)) => {
+                debug!("Shard {} moved; refreshing placement", shard);
+                self.cluster.apply_committed()?;
+                request().await
+            }
+            result => result,
+        }
+    }
+
+    /// Apply finalized migration steps, then wait for writes routed by the
+    /// old placement to finish
+    ///
+    /// Acknowledge the steps to peers, e.g. with the next consensus vertex,
+    /// only after this returns: from then on every write through this
+    /// coordinator follows the new placement.
+    pub async fn apply_committed(&self) -> Result<usize> {
+        let applied = self.cluster.apply_committed()?;
+        if applied > 0 {
+            let gates: Vec<_> = self
+                .gates
+                .iter()
+                .map(|entry| entry.value().clone())
+                .collect();
+            for gate in gates {
+                drop(gate.write().await);
+            }
+        }
+        Ok(applied)
+    }
+
+    /// Cluster whose shards this coordinator serves
+    pub fn cluster(&self) -> &Arc<ClusterManager> {
+        &self.cluster
+    }
+
+    /// Client registered for `node_id`
+    pub(crate) fn node(&self, node_id: &str) -> Result<Arc<dyn ShardNode>> {
+        self.nodes
+            .get(node_id)
+            .map(|node| node.value().clone())
+            .ok_or_else(|| ClusterError::NodeNotFound(node_id.to_string()))
+    }
+
+    /// Gate ordering writes to `shard` against migration steps
+    pub(crate) fn gate(&self, shard: u32) -> Arc<RwLock<()>> {
+        self.gates.entry(shard).or_default().value().clone()
+    }
+
+    /// Node to query for `shard`: its primary, if available
+    ///
+    /// Replicas aren't written to, so answers from them would silently
+    /// miss the shard's data.
+    fn owner(&self, shard: u32) -> Option<String> {
+        let info = self.cluster.get_shard(shard)?;
+        Some(info.primary_node).filter(|node_id| self.is_available(node_id))
+    }
+
+    fn is_available(&self, node_id: &str) -> bool {
+        self.nodes.contains_key(node_id)
+            && self
+                .cluster
+                .get_node(node_id)
+                .map_or(true, |node| node.status != NodeStatus::Offline)
+    }
+
+    /// Clients for the primary of `shard` and, while it migrates, its new
+    /// owner
+    fn writers(&self, shard: u32) -> Result<(NodeClient, Option<NodeClient>)> {
+        let info = self
+            .cluster
+            .get_shard(shard)
+            .ok_or(ClusterError::ShardNotFound(shard))?;
+        let primary = self.node(&info.primary_node)?;
+        let target = match &info.migrating_to {
+            Some(node_id) => Some(self.node(node_id)?),
+            None => None,
+        };
+        Ok((primary, target))
+    }
+
+    pub(crate) async fn with_timeout<T>(
+        &self,
+        request: impl std::future::Future<Output = Result<T>>,
+    ) -> Result<T> {
+        timeout(self.timeout, request)
+            .await
+            .map_err(|_| ClusterError::NetworkError("Request timed out".to_string()))?
+    }
+}
+
+/// Merge result lists into the best `k`, keeping one result per id
+pub fn merge_top_k(
+    lists: impl IntoIterator<Item = Vec<SearchResult>>,
+    k: usize,
+) -> Vec<SearchResult> {
+    let mut results: Vec<SearchResult> = lists.into_iter().flatten().collect();
+    // Lower scores are better
+    results.sort_by(|a, b| a.score.total_cmp(&b.score).then_with(|| a.id.cmp(&b.id)));
+
+    let mut seen = HashSet::new();
+    results.retain(|result| seen.insert(result.id.clone()));
+    results.truncate(k);
+    results
+}
+
+/// Shards stored in this process, one `VectorDB` per shard
+pub struct LocalShardNode {
+    /// Options for new shard databases; `storage_path` is the directory
+    /// holding them
+    options: DbOptions,
+    /// Open shard databases
+    shards: DashMap<u32, Arc<VectorDB>>,
+    /// Shards dropped from this node and not accepted back since
+    dropped: DashSet<u32>,
+}
+
+impl LocalShardNode {
+    /// Store shards under the directory `options.storage_path`
+    pub fn new(options: DbOptions) -> Self {
+        Self {
+            options,
+            shards: DashMap::new(),
+            dropped: DashSet::new(),
+        }
+    }
+
+    /// Ids of the shards holding data on this node
+    pub fn shard_ids(&self) -> Vec<u32> {
+        let mut ids: Vec<u32> = self.shards.iter().map(|entry| *entry.key()).collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Database for `shard`, if it has been written to
+    pub fn shard(&self, shard: u32) -> Option<Arc<VectorDB>> {
+        self.shards.get(&shard).map(|db| db.value().clone())
+    }
+
+    /// Database for `shard` if it holds data here
+    ///
+    /// Fails for dropped shards, so a coordinator routing by an old
+    /// placement finds out. The guard holds off `drop_shard` while the
+    /// request runs.
+    fn serving(&self, shard: u32) -> Result<Option<Ref<'_, u32, Arc<VectorDB>>>> {
+        let db = self.shards.get(&shard);
+        if self.dropped.contains(&shard) {
+            return Err(ClusterError::ShardMoved(shard));
+        }
+        Ok(db)
+    }
+
+    /// Database to write `shard` to, created on first use
+    fn writable(&self, shard: u32) -> Result<Ref<'_, u32, Arc<VectorDB>>> {
+        loop {
+            if let Some(db) = self.serving(shard)? {
+                return Ok(db);
+            }
+            self.open_shard(shard)?;
+        }
+    }
+
+    fn open_shard(&self, shard: u32) -> Result<()> {
+        std::fs::create_dir_all(&self.options.storage_path)?;
+        if let Entry::Vacant(entry) = self.shards.entry(shard) {
+            // Checked under the entry lock: `drop_shard` marks the shard
+            // before removing it, so a dropped shard is never recreated
+            if self.dropped.contains(&shard) {
+                return Err(ClusterError::ShardMoved(shard));
+            }
+            let options = DbOptions {
+                storage_path: self.shard_path(shard).to_string_lossy().to_string(),
+                ..self.options.clone()
+            };
+            entry.insert(Arc::new(VectorDB::new(options).map_err(storage_error)?));
+        }
+        Ok(())
+    }
+
+    fn shard_path(&self, shard: u32) -> PathBuf {
+        Path::new(&self.options.storage_path).join(format!("shard-{}.db", shard))
+    }
+}
+
+#[async_trait]
+impl ShardNode for LocalShardNode {
+    async fn search(&self, shards: &[u32], query: &SearchQuery) -> Result<Vec<SearchResult>> {
+        let mut lists = Vec::new();
+        for &shard in shards {
+            if let Some(db) = self.serving(shard)? {
+                lists.push(db.search(query.clone()).map_err(storage_error)?);
+            }
+        }
+        Ok(merge_top_k(lists, query.k))
+    }
+
+    async fn upsert(&self, shard: u32, entry: VectorEntry) -> Result<VectorId> {
+        self.writable(shard)?.upsert(entry).map_err(storage_error)
+    }
+
+    async fn get(&self, shard: u32, id: &str) -> Result<Option<VectorEntry>> {
+        match self.serving(shard)? {
+            Some(db) => db.get(id).map_err(storage_error),
+            None => Ok(None),
+        }
+    }
+
+    async fn delete(&self, shard: u32, id: &str) -> Result<bool> {
+        match self.serving(shard)? {
+            Some(db) => db.delete(id).map_err(storage_error),
+            None => Ok(false),
+        }
+    }
+
+    async fn scroll(
+        &self,
+        shard: u32,
+        offset: Option<VectorId>,
+        limit: usize,
+    ) -> Result<ScrollPage> {
+        match self.serving(shard)? {
+            Some(db) => db
+                .scroll(&ScrollRequest {
+                    filter: None,
+                    limit,
+                    offset,
+                    with_vectors: true,
+                    with_payload: true,
+                })
+                .map_err(storage_error),
+            None => Ok(ScrollPage {
+                points: Vec::new(),
+                next_offset: None,
+            }),
+        }
+    }
+
+    async fn drop_shard(&self, shard: u32) -> Result<()> {
+        self.dropped.insert(shard);
+        let Some((_, db)) = self.shards.remove(&shard) else {
+            return Ok(());
+        };
+        drop(db);
+
+        // The database file and the index snapshot kept beside it
+        let path = self.shard_path(shard);
+        for path in [path.clone(), path.with_extension("db.hnsw")] {
+            match std::fs::remove_file(&path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
+        debug!("Dropped shard {}", shard);
+        Ok(())
+    }
+
+    async fn accept_shard(&self, shard: u32) -> Result<()> {
+        self.dropped.remove(&shard);
+        Ok(())
+    }
+}
+
+fn storage_error(e: ruvector_core::RuvectorError) -> ClusterError {
+    ClusterError::StorageError(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(id: &str, score: f32) -> SearchResult {
+        SearchResult {
+            id: id.to_string(),
+            score,
+            vector: None,
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn test_merge_top_k() {
+        let merged = merge_top_k(
+            vec![
+                vec![result("a", 0.1), result("b", 0.4)],
+                vec![result("c", 0.2), result("a", 0.3)],
+                vec![],
+                vec![result("d", 0.2)],
+            ],
+            3,
+        );
+        let ids: Vec<&str> = merged.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "c", "d"]);
+        assert_eq!(merged[0].score, 0.1);
+
+        assert!(merge_top_k(Vec::new(), 10).is_empty());
+    }
+}
@@ -0,0 +1,306 @@
+//! Shard rebalancing and live data migration
+//!
+//! Carries out the moves planned by the `ClusterManager` when nodes join or
+//! leave: each shard is copied to its new owner while writes continue, then
+//! ownership is flipped in one step and the old copy is dropped. Every step
+//! goes through consensus and waits until it is finalized, so all
+//! coordinators route the shard the same way before the next one.
+
+use ruvector_core::types::VectorEntry;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use crate::query::QueryCoordinator;
+use crate::{ClusterError, Result, ShardInfo, ShardMove};
+
+/// How long to wait for a migration step to be finalized by default
+pub const DEFAULT_FINALIZE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Interval between checks for a finalized migration step
+const FINALIZE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Stage reached by a shard migration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MigrationPhase {
+    /// Waiting for earlier moves to finish
+    Pending,
+    /// Copying vectors to the new owner
+    Copying,
+    /// Ownership handed over
+    Committed,
+    /// Given up; the shard stays with its old owner
+    Failed,
+}
+
+/// Progress of one shard migration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigrationProgress {
+    /// Shard being moved
+    pub shard_id: u32,
+    /// Node the shard is moving from
+    pub from: String,
+    /// Node the shard is moving to
+    pub to: String,
+    /// Current stage
+    pub phase: MigrationPhase,
+    /// Vectors copied so far
+    pub copied: usize,
+    /// Why the migration failed
+    pub error: Option<String>,
+}
+
+/// Moves shards to the nodes the hash ring assigns them
+///
+/// A migration marks the shard as migrating, so coordinators mirror its
+/// writes to the new owner, then copies the existing vectors over in
+/// batches. Each batch holds the shard's write gate, so no write through
+/// this coordinator lands between reading a batch and copying it; other
+/// coordinators have applied the migration, and mirror their writes, before
+/// the first batch is read.
+///
+/// The old owner drops its copy only after the handover is finalized. A
+/// coordinator still routing to it is told the shard moved and retries
+/// with the new placement.
+pub struct Rebalancer {
+    /// Coordinator routing writes and reaching the nodes
+    coordinator: Arc<QueryCoordinator>,
+    /// Vectors copied per batch
+    batch_size: usize,
+    /// Time allowed for each migration step to be finalized
+    finalize_timeout: Duration,
+    /// Progress of the current or last rebalance
+    progress: watch::Sender<Vec<MigrationProgress>>,
+}
+
+impl Rebalancer {
+    /// Create a rebalancer copying `batch_size` vectors at a time
+    pub fn new(coordinator: Arc<QueryCoordinator>, batch_size: usize) -> Self {
+        Self {
+            coordinator,
+            batch_size: batch_size.max(1),
+            finalize_timeout: DEFAULT_FINALIZE_TIMEOUT,
+            progress: watch::channel(Vec::new()).0,
+        }
+    }
+
+    /// Allow `timeout` for each migration step to be finalized
+    pub fn with_finalize_timeout(mut self, timeout: Duration) -> Self {
+        self.finalize_timeout = timeout;
+        self
+    }
+
+    /// Watch the progress of each migration
+    pub fn subscribe(&self) -> watch::Receiver<Vec<MigrationProgress>> {
+        self.progress.subscribe()
+    }
+
+    /// Progress of the current or last rebalance
+    pub fn progress(&self) -> Vec<MigrationProgress> {
+        self.progress.borrow().clone()
+    }
+
+    /// Plan and carry out the moves needed after membership changes
+    ///
+    /// Moves run one at a time. A failed move leaves its shard with the
+    /// old owner and doesn't stop the others; the returned progress says
+    /// how each one ended.
+    pub async fn rebalance(&self) -> Vec<MigrationProgress> {
+        let moves = self.coordinator.cluster().plan_rebalance();
+        info!("Rebalancing {} shards", moves.len());
+        self.progress.send_replace(
+            moves
+                .iter()
+                .map(|mv| MigrationProgress {
+                    shard_id: mv.shard_id,
+                    from: mv.from.clone(),
+                    to: mv.to.clone(),
+                    phase: MigrationPhase::Pending,
+                    copied: 0,
+                    error: None,
+                })
+                .collect(),
+        );
+
+        for (index, mv) in moves.iter().enumerate() {
+            let phase = match self.migrate(index, mv).await {
+                Ok(()) => MigrationPhase::Committed,
+                Err(e) => {
+                    warn!("Migration of shard {} failed: {}", mv.shard_id, e);
+                    self.update(index, |progress| progress.error = Some(e.to_string()));
+                    MigrationPhase::Failed
+                }
+            };
+            self.update(index, |progress| progress.phase = phase);
+        }
+        self.progress()
+    }
+
+    /// Move one shard, undoing the migration if it fails
+    async fn migrate(&self, index: usize, mv: &ShardMove) -> Result<()> {
+        let cluster = self.coordinator.cluster();
+        let gate = self.coordinator.gate(mv.shard_id);
+        let version = cluster
+            .get_shard(mv.shard_id)
+            .ok_or(ClusterError::ShardNotFound(mv.shard_id))?
+            .version;
+
+        // Only the replicas change; there is no data to move
+        if mv.from == mv.to {
+            cluster.commit_migration(mv)?;
+            return self
+                .finalized(mv, |shard| committed(mv, version, shard))
+                .await;
+        }
+
+        let source = self.coordinator.node(&mv.from)?;
+        let target = self.coordinator.node(&mv.to)?;
+        self.coordinator
+            .with_timeout(target.accept_shard(mv.shard_id))
+            .await?;
+        cluster.begin_migration(mv)?;
+
+        let copied = async {
+            self.finalized(mv, |shard| begun(mv, shard)).await?;
+            // Writes routed before the migration began have finished
+            drop(gate.write().await);
+            self.update(index, |progress| progress.phase = MigrationPhase::Copying);
+
+            let mut offset = None;
+            loop {
+                let _guard = gate.write().await;
+                let page = self
+                    .coordinator
+                    .with_timeout(source.scroll(mv.shard_id, offset, self.batch_size))
+                    .await?;
+                let count = page.points.len();
+                for point in page.points {
+                    let vector = point.vector.ok_or_else(|| {
+                        ClusterError::MigrationError(format!("Vector {} has no data", point.id))
+                    })?;
+                    let entry = VectorEntry {
+                        id: Some(point.id),
+                        vector,
+                        metadata: point.metadata,
+                    };
+                    self.coordinator
+                        .with_timeout(target.upsert(mv.shard_id, entry))
+                        .await?;
+                }
+                self.update(index, |progress| progress.copied += count);
+
+                offset = page.next_offset;
+                if offset.is_none() {
+                    break;
+                }
+            }
+
+            cluster.commit_migration(mv)?;
+            self.finalized(mv, |shard| committed(mv, version, shard))
+                .await
+        }
+        .await;
+
+        if let Err(e) = copied {
+            if let Err(e) = self.abort(mv).await {
+                warn!("Aborting migration of shard {} failed: {}", mv.shard_id, e);
+            }
+            if let Err(e) = target.drop_shard(mv.shard_id).await {
+                warn!(
+                    "Dropping partial copy of shard {} failed: {}",
+                    mv.shard_id, e
+                );
+            }
+            return Err(e);
+        }
+
+        // Every coordinator routes to the new owner once it applies the
+        // handover; one that hasn't yet is refused by the old owner
+        drop(gate.write().await);
+        if let Err(e) = source.drop_shard(mv.shard_id).await {
+            warn!(
+                "Dropping shard {} from {} failed: {}",
+                mv.shard_id, mv.from, e
+            );
+        }
+        Ok(())
+    }
+
+    /// Propose giving up `mv` and wait until it is off
+    ///
+    /// The abort follows the begin in the finalized order even if the
+    /// begin hasn't been applied here yet, so the shard can't be left
+    /// migrating.
+    async fn abort(&self, mv: &ShardMove) -> Result<()> {
+        self.coordinator.cluster().abort_migration(mv)?;
+        self.finalized(mv, |shard| {
+            (shard.migrating_to.as_deref() != Some(mv.to.as_str())).then_some(Ok(()))
+        })
+        .await
+    }
+
+    /// Apply finalized migration steps until `settled` says how the step
+    /// proposed for `mv` turned out
+    async fn finalized(
+        &self,
+        mv: &ShardMove,
+        settled: impl Fn(&ShardInfo) -> Option<Result<()>>,
+    ) -> Result<()> {
+        let cluster = self.coordinator.cluster();
+        let wait = async {
+            loop {
+                cluster.apply_committed()?;
+                let shard = cluster
+                    .get_shard(mv.shard_id)
+                    .ok_or(ClusterError::ShardNotFound(mv.shard_id))?;
+                if let Some(result) = settled(&shard) {
+                    return result;
+                }
+                tokio::time::sleep(FINALIZE_POLL_INTERVAL).await;
+            }
+        };
+        tokio::time::timeout(self.finalize_timeout, wait)
+            .await
+            .map_err(|_| {
+                ClusterError::MigrationError(format!(
+                    "Move of shard {} to {} was not finalized in time",
+                    mv.shard_id, mv.to
+                ))
+            })?
+    }
+
+    fn update(&self, index: usize, f: impl FnOnce(&mut MigrationProgress)) {
+        self.progress.send_modify(|all| f(&mut all[index]));
+    }
+}
+
+/// Whether the begin of `mv` was applied, or lost to another step
+fn begun(mv: &ShardMove, shard: &ShardInfo) -> Option<Result<()>> {
+    match &shard.migrating_to {
+        Some(target) if *target == mv.to => Some(Ok(())),
+        None if shard.primary_node == mv.from => None,
+        _ => Some(Err(superseded(mv))),
+    }
+}
+
+/// Whether the commit of `mv` was applied, or the shard changed otherwise
+fn committed(mv: &ShardMove, version: u64, shard: &ShardInfo) -> Option<Result<()>> {
+    if shard.version > version {
+        return Some(if shard.primary_node == mv.to {
+            Ok(())
+        } else {
+            Err(superseded(mv))
+        });
+    }
+    let pending = mv.from == mv.to || shard.migrating_to.as_deref() == Some(mv.to.as_str());
+    (!pending).then(|| Err(superseded(mv)))
+}
+
+fn superseded(mv: &ShardMove) -> ClusterError {
+    ClusterError::MigrationError(format!(
+        "Shard {} changed hands before its move to {} took effect",
+        mv.shard_id, mv.to
+    ))
+}
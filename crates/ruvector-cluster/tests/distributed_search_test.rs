@@ -0,0 +1,363 @@
+//! Scatter-gather search and write routing across in-process nodes
+//!
+//! Each node keeps its shards in a `LocalShardNode`; a wrapper can make a
+//! node slow or fail to exercise partial results.
+
+use async_trait::async_trait;
+use ruvector_cluster::query::merge_top_k;
+use ruvector_cluster::{
+    ClusterConfig, ClusterError, ClusterManager, ClusterNode, LocalShardNode, NodeStatus,
+    QueryCoordinator, Rebalancer, ShardNode, StaticDiscovery,
+};
+use ruvector_core::types::{
+    DbOptions, DistanceMetric, HnswConfig, QuantizationConfig, ScrollPage, SearchQuery,
+    SearchResult, VectorEntry, VectorId,
+};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+const SHARDS: u32 = 8;
+const DIMENSIONS: usize = 4;
+
+/// Delegates to a local node, optionally stalling or failing searches
+struct FaultyNode {
+    inner: LocalShardNode,
+    stall: AtomicBool,
+    fail: AtomicBool,
+}
+
+#[async_trait]
+impl ShardNode for FaultyNode {
+    async fn search(
+        &self,
+        shards: &[u32],
+        query: &SearchQuery,
+    ) -> ruvector_cluster::Result<Vec<SearchResult>> {
+        if self.stall.load(Ordering::SeqCst) {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        }
+        if self.fail.load(Ordering::SeqCst) {
+            return Err(ClusterError::NetworkError("connection refused".to_string()));
+        }
+        self.inner.search(shards, query).await
+    }
+
+    async fn upsert(&self, shard: u32, entry: VectorEntry) -> ruvector_cluster::Result<VectorId> {
+        self.inner.upsert(shard, entry).await
+    }
+
+    async fn get(&self, shard: u32, id: &str) -> ruvector_cluster::Result<Option<VectorEntry>> {
+        self.inner.get(shard, id).await
+    }
+
+    async fn delete(&self, shard: u32, id: &str) -> ruvector_cluster::Result<bool> {
+        self.inner.delete(shard, id).await
+    }
+
+    async fn scroll(
+        &self,
+        shard: u32,
+        offset: Option<VectorId>,
+        limit: usize,
+    ) -> ruvector_cluster::Result<ScrollPage> {
+        self.inner.scroll(shard, offset, limit).await
+    }
+
+    async fn drop_shard(&self, shard: u32) -> ruvector_cluster::Result<()> {
+        self.inner.drop_shard(shard).await
+    }
+
+    async fn accept_shard(&self, shard: u32) -> ruvector_cluster::Result<()> {
+        self.inner.accept_shard(shard).await
+    }
+}
+
+struct TestCluster {
+    dir: PathBuf,
+    manager: Arc<ClusterManager>,
+    coordinator: Arc<QueryCoordinator>,
+    nodes: Vec<(String, Arc<FaultyNode>)>,
+}
+
+impl TestCluster {
+    async fn start(name: &str, size: usize) -> Self {
+        let dir = std::env::temp_dir().join(format!("ruvector-cluster-search-{}", name));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let config = ClusterConfig {
+            shard_count: SHARDS,
+            replication_factor: 2,
+            enable_consensus: false,
+            ..Default::default()
+        };
+        let manager = Arc::new(
+            ClusterManager::new(
+                config,
+                "coordinator".to_string(),
+                Box::new(StaticDiscovery::new(vec![])),
+            )
+            .unwrap(),
+        );
+        let coordinator = Arc::new(QueryCoordinator::new(
+            manager.clone(),
+            Duration::from_millis(200),
+        ));
+
+        let mut nodes = Vec::new();
+        for i in 0..size {
+            let node_id = format!("node{}", i);
+            let address = format!("127.0.0.1:{}", 9000 + i).parse().unwrap();
+            manager
+                .add_node(ClusterNode::new(node_id.clone(), address))
+                .await
+                .unwrap();
+
+            let options = DbOptions {
+                dimensions: DIMENSIONS,
+                distance_metric: DistanceMetric::Euclidean,
+                storage_path: dir.join(&node_id).to_string_lossy().to_string(),
+                hnsw_config: Some(HnswConfig {
+                    max_elements: 10_000,
+                    ..Default::default()
+                }),
+                quantization: Some(QuantizationConfig::None),
+            };
+            let node = Arc::new(FaultyNode {
+                inner: LocalShardNode::new(options),
+                stall: AtomicBool::new(false),
+                fail: AtomicBool::new(false),
+            });
+            coordinator.register_node(node_id.clone(), node.clone());
+            nodes.push((node_id, node));
+        }
+
+        // Spread the shards, all placed on the first node, over the others
+        Rebalancer::new(coordinator.clone(), 100).rebalance().await;
+
+        Self {
+            dir,
+            manager,
+            coordinator,
+            nodes,
+        }
+    }
+
+    /// Shards whose primary is node `i`
+    fn primary_shards(&self, i: usize) -> Vec<u32> {
+        (0..SHARDS)
+            .filter(|&shard| self.manager.get_shard(shard).unwrap().primary_node == self.nodes[i].0)
+            .collect()
+    }
+}
+
+impl Drop for TestCluster {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Deterministic pseudo-random vector, so distances rarely tie
+fn vector(i: usize) -> Vec<f32> {
+    let mut state = (i as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    (0..DIMENSIONS)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % 10_000) as f32 / 100.0
+        })
+        .collect()
+}
+
+fn query(i: usize, k: usize) -> SearchQuery {
+    SearchQuery {
+        vector: vector(i),
+        k,
+        filter: None,
+        ef_search: Some(200),
+    }
+}
+
+/// Exact nearest ids among `count` vectors
+fn brute_force(target: &[f32], count: usize, k: usize) -> Vec<String> {
+    let mut scored: Vec<(f32, String)> = (0..count)
+        .map(|i| {
+            let distance: f32 = vector(i)
+                .iter()
+                .zip(target)
+                .map(|(a, b)| (a - b) * (a - b))
+                .sum::<f32>()
+                .sqrt();
+            (distance, format!("v{}", i))
+        })
+        .collect();
+    scored.sort_by(|a, b| a.0.total_cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+    scored.into_iter().take(k).map(|(_, id)| id).collect()
+}
+
+async fn insert(cluster: &TestCluster, count: usize) {
+    for i in 0..count {
+        let id = cluster
+            .coordinator
+            .upsert(VectorEntry {
+                id: Some(format!("v{}", i)),
+                vector: vector(i),
+                metadata: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(id, format!("v{}", i));
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_writes_route_to_primary() {
+    let cluster = TestCluster::start("routing", 3).await;
+    insert(&cluster, 60).await;
+
+    // Every vector lives only in its shard on that shard's primary
+    let router = cluster.manager.router();
+    for i in 0..60 {
+        let id = format!("v{}", i);
+        let shard = router.get_shard_for_vector(&id);
+        let primary = cluster.manager.get_shard(shard).unwrap().primary_node;
+        for (node_id, node) in &cluster.nodes {
+            // Nodes that gave the shard away refuse to answer for it
+            let stored = node.inner.get(shard, &id).await;
+            assert!(stored.is_ok() || *node_id != primary);
+            assert_eq!(
+                matches!(stored, Ok(Some(_))),
+                *node_id == primary,
+                "{} on {}",
+                id,
+                node_id
+            );
+        }
+        assert!(cluster.coordinator.get(&id).await.unwrap().is_some());
+    }
+    let stored: usize = cluster
+        .nodes
+        .iter()
+        .flat_map(|(_, node)| {
+            let node = node.clone();
+            node.inner
+                .shard_ids()
+                .into_iter()
+                .map(move |shard| node.inner.shard(shard).unwrap().len().unwrap())
+        })
+        .sum();
+    assert_eq!(stored, 60);
+
+    // Ids are generated when missing, and deletes follow the same route
+    let id = cluster
+        .coordinator
+        .upsert(VectorEntry {
+            id: None,
+            vector: vector(100),
+            metadata: None,
+        })
+        .await
+        .unwrap();
+    assert!(cluster.coordinator.get(&id).await.unwrap().is_some());
+    assert!(cluster.coordinator.delete(&id).await.unwrap());
+    assert!(cluster.coordinator.get(&id).await.unwrap().is_none());
+    assert!(!cluster.coordinator.delete(&id).await.unwrap());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_search_merges_top_k_across_shards() {
+    let cluster = TestCluster::start("merge", 3).await;
+    insert(&cluster, 120).await;
+
+    for target in [0, 17, 64, 119] {
+        let response = cluster
+            .coordinator
+            .search(&query(target, 10))
+            .await
+            .unwrap();
+        assert!(!response.partial);
+        assert!(response.failed_shards.is_empty());
+        assert_eq!(response.results.len(), 10);
+        assert_eq!(response.results[0].id, format!("v{}", target));
+        assert!(response
+            .results
+            .windows(2)
+            .all(|pair| pair[0].score <= pair[1].score));
+
+        let ids: Vec<String> = response.results.iter().map(|r| r.id.clone()).collect();
+        assert_eq!(ids, brute_force(&vector(target), 120, 10));
+    }
+
+    // Fewer vectors than k: each id at most once
+    let response = cluster.coordinator.search(&query(0, 500)).await.unwrap();
+    let ids: std::collections::HashSet<&str> =
+        response.results.iter().map(|r| r.id.as_str()).collect();
+    assert_eq!(ids.len(), response.results.len());
+    assert!(ids.len() <= 120);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_partial_results_on_timeout_and_failure() {
+    let cluster = TestCluster::start("partial", 3).await;
+    insert(&cluster, 90).await;
+
+    // A stalled node is cut off at the timeout and its shards reported
+    let slow = (0..3)
+        .find(|&i| !cluster.primary_shards(i).is_empty())
+        .unwrap();
+    cluster.nodes[slow].1.stall.store(true, Ordering::SeqCst);
+    let started = std::time::Instant::now();
+    let response = cluster.coordinator.search(&query(5, 90)).await.unwrap();
+    assert!(started.elapsed() < Duration::from_secs(2));
+    assert!(response.partial);
+    assert_eq!(response.failed_shards, cluster.primary_shards(slow));
+
+    // What did answer is exactly what the other nodes hold
+    let mut lists = Vec::new();
+    for i in (0..3).filter(|&i| i != slow) {
+        let shards = cluster.primary_shards(i);
+        lists.push(
+            cluster.nodes[i]
+                .1
+                .inner
+                .search(&shards, &query(5, 90))
+                .await
+                .unwrap(),
+        );
+    }
+    let expected: Vec<String> = merge_top_k(lists, 90).into_iter().map(|r| r.id).collect();
+    let ids: Vec<String> = response.results.iter().map(|r| r.id.clone()).collect();
+    assert_eq!(ids, expected);
+    let router = cluster.manager.router();
+    assert!(ids.iter().all(|id| !response
+        .failed_shards
+        .contains(&router.get_shard_for_vector(id))));
+    cluster.nodes[slow].1.stall.store(false, Ordering::SeqCst);
+
+    // A node that errors is reported the same way
+    cluster.nodes[slow].1.fail.store(true, Ordering::SeqCst);
+    let response = cluster.coordinator.search(&query(5, 10)).await.unwrap();
+    assert!(response.partial);
+    assert_eq!(response.failed_shards, cluster.primary_shards(slow));
+    cluster.nodes[slow].1.fail.store(false, Ordering::SeqCst);
+
+    // An offline primary's shards fail too: replicas hold no data, so
+    // answers from them would look complete while missing vectors
+    let node_id = cluster.nodes[slow].0.clone();
+    let mut offline = cluster.manager.get_node(&node_id).unwrap();
+    offline.status = NodeStatus::Offline;
+    cluster.manager.add_node(offline).await.unwrap();
+    let response = cluster.coordinator.search(&query(5, 90)).await.unwrap();
+    assert!(response.partial);
+    assert_eq!(response.failed_shards, cluster.primary_shards(slow));
+    let ids: Vec<String> = response.results.iter().map(|r| r.id.clone()).collect();
+    assert_eq!(ids, expected);
+
+    // With every node failing there is nothing to return
+    for (_, node) in &cluster.nodes {
+        node.fail.store(true, Ordering::SeqCst);
+    }
+    assert!(cluster.coordinator.search(&query(5, 10)).await.is_err());
+}
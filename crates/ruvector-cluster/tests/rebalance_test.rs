@@ -0,0 +1,542 @@
+//! Shard rebalancing with live data migration across in-process nodes
+//!
+//! Nodes join and leave a cluster holding data while writes continue; each
+//! shard must end up on the node the hash ring assigns it, with every
+//! acknowledged write and no deleted vector.
+
+use async_trait::async_trait;
+use ruvector_cluster::consensus::TransactionType;
+use ruvector_cluster::{
+    ClusterConfig, ClusterError, ClusterManager, ClusterNode, LocalShardNode, MigrationPhase,
+    NodeIdentity, QueryCoordinator, Rebalancer, ShardNode, ShardStatus, StaticDiscovery,
+};
+use ruvector_core::types::{
+    DbOptions, DistanceMetric, HnswConfig, QuantizationConfig, ScrollPage, SearchQuery,
+    SearchResult, VectorEntry, VectorId,
+};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+const SHARDS: u32 = 16;
+const DIMENSIONS: usize = 4;
+
+/// Local node whose writes can be made to fail
+struct TestNode {
+    inner: LocalShardNode,
+    fail_writes: AtomicBool,
+}
+
+#[async_trait]
+impl ShardNode for TestNode {
+    async fn search(
+        &self,
+        shards: &[u32],
+        query: &SearchQuery,
+    ) -> ruvector_cluster::Result<Vec<SearchResult>> {
+        self.inner.search(shards, query).await
+    }
+
+    async fn upsert(&self, shard: u32, entry: VectorEntry) -> ruvector_cluster::Result<VectorId> {
+        if self.fail_writes.load(Ordering::SeqCst) {
+            return Err(ClusterError::StorageError("disk full".to_string()));
+        }
+        self.inner.upsert(shard, entry).await
+    }
+
+    async fn get(&self, shard: u32, id: &str) -> ruvector_cluster::Result<Option<VectorEntry>> {
+        self.inner.get(shard, id).await
+    }
+
+    async fn delete(&self, shard: u32, id: &str) -> ruvector_cluster::Result<bool> {
+        self.inner.delete(shard, id).await
+    }
+
+    async fn scroll(
+        &self,
+        shard: u32,
+        offset: Option<VectorId>,
+        limit: usize,
+    ) -> ruvector_cluster::Result<ScrollPage> {
+        self.inner.scroll(shard, offset, limit).await
+    }
+
+    async fn drop_shard(&self, shard: u32) -> ruvector_cluster::Result<()> {
+        self.inner.drop_shard(shard).await
+    }
+
+    async fn accept_shard(&self, shard: u32) -> ruvector_cluster::Result<()> {
+        self.inner.accept_shard(shard).await
+    }
+}
+
+struct TestCluster {
+    dir: PathBuf,
+    manager: Arc<ClusterManager>,
+    coordinator: Arc<QueryCoordinator>,
+    nodes: HashMap<String, Arc<TestNode>>,
+}
+
+impl TestCluster {
+    async fn start(name: &str, size: usize) -> Self {
+        let dir = std::env::temp_dir().join(format!("ruvector-cluster-rebalance-{}", name));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let config = ClusterConfig {
+            shard_count: SHARDS,
+            replication_factor: 2,
+            enable_consensus: false,
+            ..Default::default()
+        };
+        let manager = Arc::new(
+            ClusterManager::new(
+                config,
+                "coordinator".to_string(),
+                Box::new(StaticDiscovery::new(vec![])),
+            )
+            .unwrap(),
+        );
+        let coordinator = Arc::new(QueryCoordinator::new(
+            manager.clone(),
+            Duration::from_secs(5),
+        ));
+
+        let mut cluster = Self {
+            dir,
+            manager,
+            coordinator,
+            nodes: HashMap::new(),
+        };
+        for i in 0..size {
+            cluster.join(i).await;
+        }
+        Rebalancer::new(cluster.coordinator.clone(), 100)
+            .rebalance()
+            .await;
+        cluster
+    }
+
+    /// Register node `i` and add it to the ring, without moving any data
+    async fn join(&mut self, i: usize) -> Arc<TestNode> {
+        let node_id = format!("node{}", i);
+        let node = test_node(&self.dir, &node_id);
+        self.coordinator
+            .register_node(node_id.clone(), node.clone());
+        self.nodes.insert(node_id.clone(), node.clone());
+
+        let address = format!("127.0.0.1:{}", 9100 + i).parse().unwrap();
+        self.manager
+            .add_node(ClusterNode::new(node_id, address))
+            .await
+            .unwrap();
+        node
+    }
+
+    /// Check every shard is where the ring puts it and holds exactly
+    /// `expected`, and that no node keeps data for shards it doesn't own
+    async fn assert_placed(&self, expected: &BTreeSet<String>) {
+        assert!(self.manager.plan_rebalance().is_empty());
+
+        let mut stored = BTreeSet::new();
+        for (node_id, node) in &self.nodes {
+            for shard in node.inner.shard_ids() {
+                let info = self.manager.get_shard(shard).unwrap();
+                assert_eq!(info.status, ShardStatus::Active);
+                assert_eq!(
+                    info.primary_node, *node_id,
+                    "shard {} left on {}",
+                    shard, node_id
+                );
+                stored.extend(node.inner.shard(shard).unwrap().keys().unwrap());
+            }
+        }
+        assert_eq!(stored, *expected);
+
+        for id in expected {
+            let entry = self.coordinator.get(id).await.unwrap().unwrap();
+            assert_eq!(entry.vector, vector(index(id)));
+        }
+    }
+}
+
+fn test_node(dir: &Path, node_id: &str) -> Arc<TestNode> {
+    let options = DbOptions {
+        dimensions: DIMENSIONS,
+        distance_metric: DistanceMetric::Euclidean,
+        storage_path: dir.join(node_id).to_string_lossy().to_string(),
+        hnsw_config: Some(HnswConfig {
+            max_elements: 10_000,
+            ..Default::default()
+        }),
+        quantization: Some(QuantizationConfig::None),
+    };
+    Arc::new(TestNode {
+        inner: LocalShardNode::new(options),
+        fail_writes: AtomicBool::new(false),
+    })
+}
+
+impl Drop for TestCluster {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn vector(i: usize) -> Vec<f32> {
+    (0..DIMENSIONS)
+        .map(|d| (i * DIMENSIONS + d) as f32)
+        .collect()
+}
+
+fn index(id: &str) -> usize {
+    id.trim_start_matches('v').parse().unwrap()
+}
+
+async fn upsert(coordinator: &QueryCoordinator, i: usize) {
+    coordinator
+        .upsert(VectorEntry {
+            id: Some(format!("v{}", i)),
+            vector: vector(i),
+            metadata: None,
+        })
+        .await
+        .unwrap();
+}
+
+async fn insert(cluster: &TestCluster, ids: std::ops::Range<usize>) -> BTreeSet<String> {
+    for i in ids.clone() {
+        upsert(&cluster.coordinator, i).await;
+    }
+    ids.map(|i| format!("v{}", i)).collect()
+}
+
+/// Insert new vectors and delete every third existing one until `done`,
+/// returning the ids written and deleted
+fn spawn_writer(
+    coordinator: Arc<QueryCoordinator>,
+    existing: usize,
+    done: Arc<AtomicBool>,
+) -> tokio::task::JoinHandle<(BTreeSet<String>, BTreeSet<String>)> {
+    tokio::spawn(async move {
+        let mut written = BTreeSet::new();
+        let mut deleted = BTreeSet::new();
+        let mut i = 0;
+        while !done.load(Ordering::SeqCst) || i < 50 {
+            upsert(&coordinator, existing + i).await;
+            written.insert(format!("v{}", existing + i));
+            if i * 3 < existing {
+                let id = format!("v{}", i * 3);
+                assert!(coordinator.delete(&id).await.unwrap());
+                deleted.insert(id);
+            }
+            i += 1;
+            tokio::task::yield_now().await;
+        }
+        (written, deleted)
+    })
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_join_migrates_shards_during_writes() {
+    let mut cluster = TestCluster::start("join", 2).await;
+    let mut expected = insert(&cluster, 0..400).await;
+    cluster.assert_placed(&expected).await;
+
+    cluster.join(2).await;
+    let moves = cluster.manager.plan_rebalance();
+    assert!(moves.iter().any(|mv| mv.to == "node2"));
+
+    let rebalancer = Rebalancer::new(cluster.coordinator.clone(), 16);
+    let mut updates = rebalancer.subscribe();
+    let watcher = tokio::spawn(async move {
+        let mut phases = BTreeSet::new();
+        while updates.changed().await.is_ok() {
+            for progress in updates.borrow_and_update().iter() {
+                phases.insert(format!("{:?}", progress.phase));
+            }
+        }
+        phases
+    });
+
+    let done = Arc::new(AtomicBool::new(false));
+    let writer = spawn_writer(cluster.coordinator.clone(), 400, done.clone());
+    let progress = rebalancer.rebalance().await;
+    done.store(true, Ordering::SeqCst);
+    let (written, deleted) = writer.await.unwrap();
+
+    assert_eq!(progress.len(), moves.len());
+    assert!(progress
+        .iter()
+        .all(|p| p.phase == MigrationPhase::Committed && p.error.is_none()));
+    assert!(progress
+        .iter()
+        .filter(|p| p.from != p.to)
+        .any(|p| p.copied > 0));
+    drop(rebalancer);
+    let phases = watcher.await.unwrap();
+    assert!(phases.contains("Copying"), "{:?}", phases);
+
+    expected.extend(written);
+    for id in &deleted {
+        expected.remove(id);
+    }
+    cluster.assert_placed(&expected).await;
+    assert!(!cluster.nodes["node2"].inner.shard_ids().is_empty());
+
+    // Searches reach the moved shards and see each vector at most once,
+    // never a deleted one
+    let response = cluster
+        .coordinator
+        .search(&SearchQuery {
+            vector: vector(1),
+            k: 2000,
+            filter: None,
+            ef_search: Some(2000),
+        })
+        .await
+        .unwrap();
+    assert!(!response.partial);
+    assert_eq!(response.results[0].id, "v1");
+    let count = response.results.len();
+    let found: BTreeSet<String> = response.results.into_iter().map(|r| r.id).collect();
+    assert_eq!(found.len(), count);
+    assert!(found.is_subset(&expected));
+    assert!(found.len() > expected.len() * 9 / 10);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_leaving_node_is_drained() {
+    let cluster = TestCluster::start("leave", 3).await;
+    let mut expected = insert(&cluster, 0..300).await;
+    let leaving = cluster
+        .nodes
+        .keys()
+        .find(|node_id| !cluster.nodes[*node_id].inner.shard_ids().is_empty())
+        .unwrap()
+        .clone();
+
+    // The node stays registered with the coordinator while its shards move
+    cluster.manager.remove_node(&leaving).await.unwrap();
+    let done = Arc::new(AtomicBool::new(false));
+    let writer = spawn_writer(cluster.coordinator.clone(), 300, done.clone());
+    let progress = Rebalancer::new(cluster.coordinator.clone(), 25)
+        .rebalance()
+        .await;
+    done.store(true, Ordering::SeqCst);
+    let (written, deleted) = writer.await.unwrap();
+
+    assert!(progress
+        .iter()
+        .all(|p| p.phase == MigrationPhase::Committed));
+    assert!(progress.iter().any(|p| p.from == leaving));
+    assert!(cluster.nodes[&leaving].inner.shard_ids().is_empty());
+    cluster.coordinator.unregister_node(&leaving);
+
+    expected.extend(written);
+    for id in &deleted {
+        expected.remove(id);
+    }
+    cluster.assert_placed(&expected).await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_failed_migration_keeps_old_owner() {
+    let mut cluster = TestCluster::start("failed", 2).await;
+    let expected = insert(&cluster, 0..200).await;
+    let before: Vec<_> = cluster.manager.list_shards();
+
+    let joining = cluster.join(2).await;
+    joining.fail_writes.store(true, Ordering::SeqCst);
+    let progress = Rebalancer::new(cluster.coordinator.clone(), 16)
+        .rebalance()
+        .await;
+
+    let failed: Vec<_> = progress
+        .iter()
+        .filter(|p| p.phase == MigrationPhase::Failed)
+        .collect();
+    assert!(!failed.is_empty());
+    assert!(failed
+        .iter()
+        .all(|p| p.to == "node2" && p.error.as_deref().unwrap().contains("disk full")));
+    for p in &failed {
+        let shard = cluster.manager.get_shard(p.shard_id).unwrap();
+        let old = before.iter().find(|s| s.shard_id == p.shard_id).unwrap();
+        assert_eq!(shard.primary_node, old.primary_node);
+        assert_eq!(shard.status, ShardStatus::Active);
+        assert_eq!(shard.migrating_to, None);
+    }
+    assert!(joining.inner.shard_ids().is_empty());
+    for id in &expected {
+        assert!(cluster.coordinator.get(id).await.unwrap().is_some());
+    }
+
+    // Retrying once the node recovers finishes the job
+    joining.fail_writes.store(false, Ordering::SeqCst);
+    let progress = Rebalancer::new(cluster.coordinator.clone(), 16)
+        .rebalance()
+        .await;
+    assert_eq!(progress.len(), failed.len());
+    assert!(progress
+        .iter()
+        .all(|p| p.phase == MigrationPhase::Committed));
+    cluster.assert_placed(&expected).await;
+}
+
+/// Copy the vertices `to` hasn't seen yet, returning how many there were
+fn ship(from: &ClusterManager, to: &ClusterManager) -> usize {
+    let dag = to.consensus().unwrap();
+    let known: HashSet<String> = dag.vertices().into_iter().map(|v| v.id).collect();
+    let mut shipped = 0;
+    for vertex in from.consensus().unwrap().vertices() {
+        if !known.contains(&vertex.id) {
+            dag.add_vertex(vertex).unwrap();
+            shipped += 1;
+        }
+    }
+    shipped
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_second_coordinator_writes_during_migration() {
+    let dir = std::env::temp_dir().join("ruvector-cluster-rebalance-two-coordinators");
+    let _ = std::fs::remove_dir_all(&dir);
+
+    // Two coordinators with their own view of the cluster, learning each
+    // other's migration steps only through consensus
+    let config = ClusterConfig {
+        shard_count: SHARDS,
+        replication_factor: 2,
+        enable_consensus: true,
+        min_quorum_size: 1,
+        ..Default::default()
+    };
+    let mut coordinators = Vec::new();
+    for name in ["coordinator-a", "coordinator-b"] {
+        let manager = ClusterManager::with_identity(
+            config.clone(),
+            NodeIdentity::generate(name),
+            Box::new(StaticDiscovery::new(vec![])),
+        )
+        .unwrap();
+        coordinators.push(Arc::new(QueryCoordinator::new(
+            Arc::new(manager),
+            Duration::from_secs(5),
+        )));
+    }
+    let (a, b) = (coordinators[0].clone(), coordinators[1].clone());
+    for (one, other, name) in [(&a, &b, "coordinator-b"), (&b, &a, "coordinator-a")] {
+        one.cluster()
+            .consensus()
+            .unwrap()
+            .key_registry()
+            .register(name, &other.cluster().public_key().unwrap())
+            .unwrap();
+    }
+
+    let nodes: Vec<_> = ["node0", "node1"]
+        .into_iter()
+        .map(|node_id| (node_id, test_node(&dir, node_id)))
+        .collect();
+    let join = |i: usize| {
+        let (a, b, (node_id, node)) = (a.clone(), b.clone(), nodes[i].clone());
+        async move {
+            for coordinator in [&a, &b] {
+                coordinator.register_node(node_id, node.clone());
+                let address = format!("127.0.0.1:{}", 9200 + i).parse().unwrap();
+                coordinator
+                    .cluster()
+                    .add_node(ClusterNode::new(node_id.to_string(), address))
+                    .await
+                    .unwrap();
+            }
+        }
+    };
+    join(0).await;
+    let mut expected = BTreeSet::new();
+    for i in 0..200 {
+        upsert(&a, i).await;
+        expected.insert(format!("v{}", i));
+    }
+    join(1).await;
+    let moves = a.cluster().plan_rebalance();
+    assert!(moves.iter().any(|mv| mv.to == "node1"));
+
+    // B confirms A's steps with a vertex of its own, which finalizes them,
+    // but A only sees it once B has applied them
+    let done = Arc::new(AtomicBool::new(false));
+    let sync = {
+        let (a, b, done) = (a.clone(), b.clone(), done.clone());
+        tokio::spawn(async move {
+            while !done.load(Ordering::SeqCst) {
+                if ship(a.cluster(), b.cluster()) > 0 {
+                    let dag = b.cluster().consensus().unwrap();
+                    dag.submit_transaction(TransactionType::System, b"ack".to_vec())
+                        .unwrap();
+                    dag.create_vertex().unwrap();
+                    b.apply_committed().await.unwrap();
+                }
+                ship(b.cluster(), a.cluster());
+                tokio::time::sleep(Duration::from_millis(2)).await;
+            }
+        })
+    };
+
+    // B inserts throughout; A inserts and deletes
+    let writer_a = spawn_writer(a.clone(), 200, done.clone());
+    let writer_b = {
+        let (b, done) = (b.clone(), done.clone());
+        tokio::spawn(async move {
+            let mut written = BTreeSet::new();
+            let mut i = 10_000;
+            while !done.load(Ordering::SeqCst) || written.len() < 50 {
+                upsert(&b, i).await;
+                written.insert(format!("v{}", i));
+                i += 1;
+                tokio::task::yield_now().await;
+            }
+            written
+        })
+    };
+
+    let progress = Rebalancer::new(a.clone(), 16).rebalance().await;
+    done.store(true, Ordering::SeqCst);
+    let (written, deleted) = writer_a.await.unwrap();
+    expected.extend(written);
+    expected.extend(writer_b.await.unwrap());
+    for id in &deleted {
+        expected.remove(id);
+    }
+    sync.await.unwrap();
+
+    assert_eq!(progress.len(), moves.len());
+    assert!(progress
+        .iter()
+        .all(|p| p.phase == MigrationPhase::Committed && p.error.is_none()));
+
+    // Both coordinators agree on the placement, and each node holds
+    // exactly the shards it owns with every acknowledged write
+    for shard in a.cluster().list_shards() {
+        let seen_by_b = b.cluster().get_shard(shard.shard_id).unwrap();
+        assert_eq!(seen_by_b.primary_node, shard.primary_node);
+        assert_eq!(seen_by_b.migrating_to, None);
+    }
+    let mut stored = BTreeSet::new();
+    for (node_id, node) in &nodes {
+        for shard in node.inner.shard_ids() {
+            assert_eq!(a.cluster().get_shard(shard).unwrap().primary_node, *node_id);
+            stored.extend(node.inner.shard(shard).unwrap().keys().unwrap());
+        }
+    }
+    assert_eq!(stored, expected);
+    for id in &expected {
+        for coordinator in [&a, &b] {
+            let entry = coordinator.get(id).await.unwrap().unwrap();
+            assert_eq!(entry.vector, vector(index(id)));
+        }
+    }
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
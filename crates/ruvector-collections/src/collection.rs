@@ -3,6 +3,7 @@
 use ruvector_core::types::{DistanceMetric, HnswConfig, QuantizationConfig};
 use ruvector_core::vector_db::VectorDB;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
 use crate::error::{CollectionError, Result};
 
@@ -84,8 +85,9 @@ pub struct Collection {
     /// Collection configuration
     pub config: CollectionConfig,
 
-    /// Underlying vector database
-    pub db: VectorDB,
+    /// Underlying vector database, shareable with callers that outlive a
+    /// lock on the collection
+    pub db: Arc<VectorDB>,
 
     /// When the collection was created (Unix timestamp in seconds)
     pub created_at: i64,
@@ -121,7 +123,7 @@ impl Collection {
             quantization: config.quantization.clone(),
         };
 
-        let db = VectorDB::new(db_options)?;
+        let db = Arc::new(VectorDB::new(db_options)?);
 
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
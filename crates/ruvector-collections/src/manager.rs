@@ -3,7 +3,8 @@
 use dashmap::DashMap;
 use parking_lot::RwLock;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use crate::collection::{Collection, CollectionConfig, CollectionStats};
@@ -428,7 +429,7 @@ impl CollectionManager {
         let metadata_path = self.base_path.join(&collection.name).join("metadata.json");
 
         let json = serde_json::to_string_pretty(&metadata)?;
-        std::fs::write(metadata_path, json)?;
+        write_atomic(&metadata_path, json.as_bytes())?;
 
         Ok(())
     }
@@ -451,7 +452,7 @@ impl CollectionManager {
 
         let aliases_path = self.base_path.join("aliases.json");
         let json = serde_json::to_string_pretty(&aliases)?;
-        std::fs::write(aliases_path, json)?;
+        write_atomic(&aliases_path, json.as_bytes())?;
 
         Ok(())
     }
@@ -475,6 +476,22 @@ impl CollectionManager {
     }
 }
 
+/// Replace a file so that a crash leaves either the old or the new contents
+fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let tmp_path = path.with_extension("json.tmp");
+    let mut file = std::fs::File::create(&tmp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    std::fs::rename(&tmp_path, path)?;
+
+    // Persist the rename itself
+    if let Some(parent) = path.parent() {
+        std::fs::File::open(parent)?.sync_all()?;
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
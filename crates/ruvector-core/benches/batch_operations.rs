@@ -1,6 +1,8 @@
 use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
-use ruvector_core::types::{DistanceMetric, SearchQuery};
-use ruvector_core::{DbOptions, VectorDB, VectorEntry};
+use ruvector_core::index::hnsw::HnswIndex;
+use ruvector_core::index::VectorIndex;
+use ruvector_core::types::{DbOptions, DistanceMetric, HnswConfig, SearchQuery};
+use ruvector_core::{VectorDB, VectorEntry};
 use tempfile::tempdir;
 
 fn bench_batch_insert(c: &mut Criterion) {
@@ -109,6 +111,72 @@ fn bench_individual_insert_vs_batch(c: &mut Criterion) {
     group.finish();
 }
 
+fn bench_hnsw_bulk_build(c: &mut Criterion) {
+    let mut group = c.benchmark_group("hnsw_bulk_build");
+    group.sample_size(10);
+
+    for size in [1000, 10000].iter() {
+        let entries: Vec<(String, Vec<f32>)> = (0..*size)
+            .map(|i| {
+                let vector = (0..128)
+                    .map(|j| (((i * 31 + j * 17) % 1000) as f32) * 0.001)
+                    .collect();
+                (format!("vec_{}", i), vector)
+            })
+            .collect();
+        let config = HnswConfig {
+            m: 16,
+            ef_construction: 100,
+            ef_search: 50,
+            max_elements: *size,
+        };
+
+        // One insert at a time, as before bulk building was parallel
+        group.bench_with_input(
+            BenchmarkId::new("sequential_add", size),
+            &entries,
+            |bench, entries| {
+                bench.iter_batched(
+                    || {
+                        let index =
+                            HnswIndex::new(128, DistanceMetric::Euclidean, config.clone()).unwrap();
+                        (index, entries.clone())
+                    },
+                    |(mut index, entries)| {
+                        for (id, vector) in entries {
+                            index.add(id, vector).unwrap();
+                        }
+                        index
+                    },
+                    criterion::BatchSize::LargeInput,
+                );
+            },
+        );
+
+        // Rayon-parallel graph construction with the `parallel` feature
+        group.bench_with_input(
+            BenchmarkId::new("add_batch", size),
+            &entries,
+            |bench, entries| {
+                bench.iter_batched(
+                    || {
+                        let index =
+                            HnswIndex::new(128, DistanceMetric::Euclidean, config.clone()).unwrap();
+                        (index, entries.clone())
+                    },
+                    |(mut index, entries)| {
+                        index.add_batch(black_box(entries)).unwrap();
+                        index
+                    },
+                    criterion::BatchSize::LargeInput,
+                );
+            },
+        );
+    }
+
+    group.finish();
+}
+
 fn bench_parallel_searches(c: &mut Criterion) {
     let dir = tempdir().unwrap();
     let mut options = DbOptions::default();
@@ -198,6 +266,7 @@ criterion_group!(
     benches,
     bench_batch_insert,
     bench_individual_insert_vs_batch,
+    bench_hnsw_bulk_build,
     bench_parallel_searches,
     bench_batch_delete
 );
@@ -21,6 +21,23 @@ pub enum FilterStrategy {
     Auto,
 }
 
+impl FilterStrategy {
+    /// Selectivity below which pre-filtering is preferred
+    pub const PRE_FILTER_SELECTIVITY: f32 = 0.2;
+
+    /// Select a concrete strategy for a filter matching `selectivity` of the data
+    ///
+    /// Highly selective filters (< 20%) are cheaper to brute-force over the
+    /// matching ids; everything else is better served by the graph.
+    pub fn for_selectivity(selectivity: f32) -> Self {
+        if selectivity < Self::PRE_FILTER_SELECTIVITY {
+            FilterStrategy::PreFilter
+        } else {
+            FilterStrategy::PostFilter
+        }
+    }
+}
+
 /// Filter expression for metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum FilterExpression {
@@ -170,14 +187,7 @@ impl FilteredSearch {
     /// Automatically select strategy based on filter selectivity
     pub fn auto_select_strategy(&self) -> FilterStrategy {
         let selectivity = self.filter.estimate_selectivity(self.metadata_store.len());
-
-        // If filter is highly selective (< 20%), use pre-filtering
-        // Otherwise use post-filtering
-        if selectivity < 0.2 {
-            FilterStrategy::PreFilter
-        } else {
-            FilterStrategy::PostFilter
-        }
+        FilterStrategy::for_selectivity(selectivity)
     }
 
     /// Get list of vector IDs that pass the filter (for pre-filtering)
@@ -111,3 +111,9 @@ impl From<redb::CommitError> for RuvectorError {
         RuvectorError::DatabaseError(err.to_string())
     }
 }
+
+impl From<ruvector_filter::FilterError> for RuvectorError {
+    fn from(err: ruvector_filter::FilterError) -> Self {
+        RuvectorError::InvalidParameter(err.to_string())
+    }
+}
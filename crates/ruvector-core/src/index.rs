@@ -3,10 +3,59 @@
 pub mod flat;
 #[cfg(feature = "hnsw")]
 pub mod hnsw;
+#[cfg(feature = "storage")]
+pub mod persistence;
+#[cfg(feature = "hnsw")]
+pub mod quantized_hnsw;
 
 use crate::error::Result;
 use crate::types::{SearchResult, VectorId};
 
+/// Per-query search parameters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchParams {
+    /// Size of the dynamic candidate list for graph indexes (HNSW efSearch).
+    /// `None` uses the index default; exhaustive indexes ignore it.
+    pub ef_search: Option<usize>,
+    /// Scan every matching vector when a filtered graph search still finds
+    /// fewer than k at the widest efSearch. Exact, but costs a read of each
+    /// match, or of every stored vector for filters on unindexed fields.
+    pub exact_filter_fallback: bool,
+}
+
+impl SearchParams {
+    /// Parameters overriding efSearch for a single query
+    pub fn with_ef_search(ef_search: usize) -> Self {
+        Self {
+            ef_search: Some(ef_search),
+            ..Self::default()
+        }
+    }
+
+    /// Enable or disable the exhaustive scan for filtered searches
+    pub fn with_exact_filter_fallback(mut self, enabled: bool) -> Self {
+        self.exact_filter_fallback = enabled;
+        self
+    }
+}
+
+/// Size and tuning of a graph index, for monitoring
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GraphStats {
+    /// Nodes in the graph, tombstones included
+    pub nodes: usize,
+    /// Nodes whose vector was deleted but that are still linked in the graph
+    pub tombstones: usize,
+    /// efSearch used by queries that don't override it
+    pub ef_search: usize,
+}
+
+/// Deferred rebuild of an index without its deleted entries, see
+/// [`VectorIndex::compaction`]
+///
+/// Returns the number of deleted entries that were reclaimed.
+pub type CompactionTask = Box<dyn FnOnce() -> Result<usize> + Send>;
+
 /// Trait for vector index implementations
 pub trait VectorIndex: Send + Sync {
     /// Add a vector to the index
@@ -20,12 +69,99 @@ pub trait VectorIndex: Send + Sync {
         Ok(())
     }
 
+    /// Add multiple vectors in batch, reporting `(inserted, total)` as the
+    /// batch progresses
+    fn add_batch_with_progress(
+        &mut self,
+        entries: Vec<(VectorId, Vec<f32>)>,
+        progress: &mut dyn FnMut(usize, usize),
+    ) -> Result<()> {
+        let total = entries.len();
+        self.add_batch(entries)?;
+        progress(total, total);
+        Ok(())
+    }
+
     /// Search for k nearest neighbors
     fn search(&self, query: &[f32], k: usize) -> Result<Vec<SearchResult>>;
 
+    /// Search for k nearest neighbors with per-query parameters
+    fn search_with_params(
+        &self,
+        query: &[f32],
+        k: usize,
+        params: &SearchParams,
+    ) -> Result<Vec<SearchResult>> {
+        let _ = params;
+        self.search(query, k)
+    }
+
+    /// Search for k nearest neighbors among the vectors accepted by `filter`
+    ///
+    /// Implementations should evaluate the predicate during traversal so that
+    /// selective filters still yield k results. The default fetches every
+    /// vector in the index and filters afterwards.
+    fn search_filtered(
+        &self,
+        query: &[f32],
+        k: usize,
+        params: &SearchParams,
+        filter: &(dyn Fn(&VectorId) -> bool + Sync),
+    ) -> Result<Vec<SearchResult>> {
+        let mut results = self.search_with_params(query, self.len(), params)?;
+        results.retain(|r| filter(&r.id));
+        results.truncate(k);
+        Ok(results)
+    }
+
+    /// Whether returned scores are approximate and should be recomputed
+    /// against full-precision vectors before ranking
+    ///
+    /// Quantized indexes return true; callers then over-fetch candidates and
+    /// rescore them.
+    fn needs_rescoring(&self) -> bool {
+        false
+    }
+
+    /// Encode the index structure so it can be reopened without rebuilding
+    ///
+    /// Returns `None` for indexes that are cheap to rebuild from storage.
+    fn export_graph(&self) -> Result<Option<Vec<u8>>> {
+        Ok(None)
+    }
+
+    /// Set the default efSearch used when a query does not override it
+    ///
+    /// Exhaustive indexes have no such knob and ignore the call.
+    fn set_ef_search(&mut self, ef_search: usize) {
+        let _ = ef_search;
+    }
+
+    /// Graph size and tuning, or `None` for indexes without a graph
+    fn graph_stats(&self) -> Option<GraphStats> {
+        None
+    }
+
+    /// Whether enough deleted entries have piled up that a compaction is due
+    fn needs_compaction(&self) -> bool {
+        false
+    }
+
+    /// Rebuild that reclaims deleted entries, or `None` if there are none
+    ///
+    /// The task shares the index's data rather than borrowing it, so it can
+    /// run after the caller's lock on the index is released, or on another
+    /// thread. Searches and writes continue while it rebuilds.
+    fn compaction(&self) -> Option<CompactionTask> {
+        None
+    }
+
     /// Remove a vector from the index
     fn remove(&mut self, id: &VectorId) -> Result<bool>;
 
+    /// Whether the index holds a vector with this id
+    fn contains(&self, id: &VectorId) -> bool;
+
     /// Get the number of vectors in the index
     fn len(&self) -> usize;
 
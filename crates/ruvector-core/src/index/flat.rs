@@ -2,7 +2,7 @@
 
 use crate::distance::distance;
 use crate::error::Result;
-use crate::index::VectorIndex;
+use crate::index::{SearchParams, VectorIndex};
 use crate::types::{DistanceMetric, SearchResult, VectorId};
 use dashmap::DashMap;
 
@@ -34,12 +34,23 @@ impl VectorIndex for FlatIndex {
     }
 
     fn search(&self, query: &[f32], k: usize) -> Result<Vec<SearchResult>> {
+        self.search_filtered(query, k, &SearchParams::default(), &|_| true)
+    }
+
+    fn search_filtered(
+        &self,
+        query: &[f32],
+        k: usize,
+        _params: &SearchParams,
+        filter: &(dyn Fn(&VectorId) -> bool + Sync),
+    ) -> Result<Vec<SearchResult>> {
         // Distance calculation - parallel on native, sequential on WASM
         #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
         let mut results: Vec<_> = self
             .vectors
             .iter()
             .par_bridge()
+            .filter(|entry| filter(entry.key()))
             .map(|entry| {
                 let id = entry.key().clone();
                 let vector = entry.value();
@@ -52,6 +63,7 @@ impl VectorIndex for FlatIndex {
         let mut results: Vec<_> = self
             .vectors
             .iter()
+            .filter(|entry| filter(entry.key()))
             .map(|entry| {
                 let id = entry.key().clone();
                 let vector = entry.value();
@@ -79,6 +91,10 @@ impl VectorIndex for FlatIndex {
         Ok(self.vectors.remove(id).is_some())
     }
 
+    fn contains(&self, id: &VectorId) -> bool {
+        self.vectors.contains_key(id)
+    }
+
     fn len(&self) -> usize {
         self.vectors.len()
     }
@@ -2,14 +2,25 @@
 
 use crate::distance::distance;
 use crate::error::{Result, RuvectorError};
-use crate::index::VectorIndex;
+use crate::index::{CompactionTask, GraphStats, SearchParams, VectorIndex};
 use crate::types::{DistanceMetric, HnswConfig, SearchResult, VectorId};
 use bincode::{Decode, Encode};
 use dashmap::DashMap;
 use hnsw_rs::prelude::*;
 use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::sync::Arc;
 
+/// Default fraction of tombstoned graph nodes above which compaction is due
+pub const DEFAULT_COMPACTION_THRESHOLD: f64 = 0.25;
+
+/// Minimum number of pending deletions before neighbour lists are repaired
+const MIN_REPAIR_BATCH: usize = 64;
+
+/// Points inserted between progress reports during a bulk build
+pub const BULK_INSERT_CHUNK: usize = 4096;
+
 /// Distance function wrapper for hnsw_rs
 struct DistanceFn {
     metric: DistanceMetric,
@@ -33,6 +44,7 @@ pub struct HnswIndex {
     config: HnswConfig,
     metric: DistanceMetric,
     dimensions: usize,
+    compaction_threshold: f64,
 }
 
 struct HnswInner {
@@ -41,6 +53,50 @@ struct HnswInner {
     id_to_idx: DashMap<VectorId, usize>,
     idx_to_id: DashMap<usize, VectorId>,
     next_idx: usize,
+    /// Graph nodes whose vector was deleted; skipped by search, dropped by compaction
+    tombstones: HashSet<usize>,
+    /// Tombstones added since the last neighbour-list repair
+    pending_repair: usize,
+}
+
+impl HnswInner {
+    fn new(hnsw: Hnsw<'static, f32, DistanceFn>) -> Self {
+        Self {
+            hnsw,
+            vectors: DashMap::new(),
+            id_to_idx: DashMap::new(),
+            idx_to_id: DashMap::new(),
+            next_idx: 0,
+            tombstones: HashSet::new(),
+            pending_repair: 0,
+        }
+    }
+
+    /// Drop edges pointing at tombstones and reconnect their live neighbours
+    fn repair(&mut self) {
+        if self.pending_repair == 0 {
+            return;
+        }
+        let tombstones = &self.tombstones;
+        let repaired = self
+            .hnsw
+            .repair_deleted(&|idx: &usize| tombstones.contains(idx));
+        tracing::debug!(
+            "Repaired {} HNSW neighbour lists after {} deletions",
+            repaired,
+            self.pending_repair
+        );
+        self.pending_repair = 0;
+    }
+
+    fn tombstone_ratio(&self) -> f64 {
+        let graph_size = self.vectors.len() + self.tombstones.len();
+        if graph_size == 0 {
+            0.0
+        } else {
+            self.tombstones.len() as f64 / graph_size as f64
+        }
+    }
 }
 
 /// Serializable HNSW index state
@@ -55,6 +111,242 @@ pub struct HnswState {
     metric: SerializableDistanceMetric,
 }
 
+/// Persisted HNSW graph: topology and vectors, so that reopening does not
+/// reinsert every point
+#[derive(Encode, Decode)]
+struct HnswGraphState {
+    config: SerializableHnswConfig,
+    dimensions: usize,
+    metric: SerializableDistanceMetric,
+    next_idx: usize,
+    /// `(layer, rank)` of the entry point
+    entry_point: Option<(u8, i32)>,
+    /// Graph points in layer, then rank order; tombstones included
+    points: Vec<GraphPoint<f32>>,
+    /// Live vectors only
+    ids: Vec<(usize, String)>,
+}
+
+/// One persisted graph node: its data and neighbour lists
+#[derive(Encode, Decode, Serialize, Deserialize)]
+pub(crate) struct GraphPoint<T> {
+    idx: usize,
+    layer: u8,
+    rank: i32,
+    data: Vec<T>,
+    /// Per layer, `(layer, rank, distance)` of each neighbour
+    neighbours: Vec<Vec<(u8, i32, f32)>>,
+}
+
+impl<T> GraphPoint<T> {
+    pub(crate) fn idx(&self) -> usize {
+        self.idx
+    }
+
+    pub(crate) fn data(&self) -> &[T] {
+        &self.data
+    }
+}
+
+/// Insert points into a graph, in parallel when the `parallel` feature is on
+///
+/// Points are inserted in chunks of [`BULK_INSERT_CHUNK`] and `progress` is
+/// called with `(inserted, total)` after each one, and at least once.
+pub(crate) fn insert_points<T, D>(
+    hnsw: &Hnsw<'static, T, D>,
+    points: &[(&[T], usize)],
+    progress: &mut dyn FnMut(usize, usize),
+) where
+    T: Clone + Send + Sync,
+    D: Distance<T> + Send + Sync,
+{
+    let total = points.len();
+    let mut inserted = 0;
+    for chunk in points.chunks(BULK_INSERT_CHUNK) {
+        #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+        {
+            use rayon::prelude::*;
+            chunk.par_iter().for_each(|&point| hnsw.insert_slice(point));
+        }
+        #[cfg(any(not(feature = "parallel"), target_arch = "wasm32"))]
+        {
+            for &point in chunk {
+                hnsw.insert_slice(point);
+            }
+        }
+        inserted += chunk.len();
+        progress(inserted, total);
+    }
+    if total == 0 {
+        progress(0, 0);
+    }
+}
+
+/// Keep only the last entry for each id, as storage does for a batch write
+pub(crate) fn dedup_batch(entries: Vec<(VectorId, Vec<f32>)>) -> Vec<(VectorId, Vec<f32>)> {
+    let mut seen = HashSet::with_capacity(entries.len());
+    let mut unique: Vec<_> = entries
+        .into_iter()
+        .rev()
+        .filter(|(id, _)| seen.insert(id.clone()))
+        .collect();
+    unique.reverse();
+    unique
+}
+
+/// Data and graph index of each live node, copied out for a compaction
+pub(crate) type LiveNodes<T> = Vec<(Vec<T>, usize)>;
+
+/// Copy out the live vectors of `inner` and its next free graph index for a
+/// compaction, or `None` when there are no tombstones to reclaim
+fn live_vectors(inner: &HnswInner) -> Option<(LiveNodes<f32>, usize)> {
+    if inner.tombstones.is_empty() {
+        return None;
+    }
+    let live = inner
+        .id_to_idx
+        .iter()
+        .filter_map(|entry| {
+            inner
+                .vectors
+                .get(entry.key())
+                .map(|vector| (vector.clone(), *entry.value()))
+        })
+        .collect();
+    Some((live, inner.next_idx))
+}
+
+/// Replace `inner`'s graph with one built from a snapshot of its live vectors
+///
+/// The new graph is built without any lock. The write lock is only taken to
+/// catch up with the writes made since the snapshot and swap the graphs:
+/// vectors inserted since are added to the new graph, and vectors deleted
+/// since stay in it as tombstones.
+fn compact_graph(
+    inner: &RwLock<HnswInner>,
+    live: LiveNodes<f32>,
+    next_idx: usize,
+    new_graph: impl FnOnce() -> Hnsw<'static, f32, DistanceFn>,
+) -> Result<usize> {
+    let hnsw = new_graph();
+    for (vector, idx) in &live {
+        hnsw.insert_slice((vector.as_slice(), *idx));
+    }
+
+    let mut inner = inner.write();
+    for idx in next_idx..inner.next_idx {
+        let Some(id) = inner.idx_to_id.get(&idx) else {
+            continue;
+        };
+        if let Some(vector) = inner.vectors.get(id.value()) {
+            hnsw.insert_slice((vector.as_slice(), idx));
+        }
+    }
+    let tombstones: HashSet<usize> = live
+        .iter()
+        .map(|(_, idx)| *idx)
+        .filter(|idx| !inner.idx_to_id.contains_key(idx))
+        .collect();
+    let reclaimed = inner.tombstones.len().saturating_sub(tombstones.len());
+
+    inner.hnsw = hnsw;
+    inner.pending_repair = tombstones.len();
+    inner.tombstones = tombstones;
+
+    tracing::info!(
+        "Compacted HNSW index: reclaimed {} tombstones, {} live vectors",
+        reclaimed,
+        inner.vectors.len()
+    );
+    Ok(reclaimed)
+}
+
+/// Copy every node of a graph, tombstones included, with its neighbour lists
+///
+/// Returns the nodes in layer then rank order along with the `(layer, rank)`
+/// of the entry point.
+pub(crate) fn export_points<T, D>(
+    hnsw: &Hnsw<'static, T, D>,
+) -> (Option<(u8, i32)>, Vec<GraphPoint<T>>)
+where
+    T: Clone + Send + Sync,
+    D: Distance<T> + Send + Sync,
+{
+    let points = hnsw
+        .get_point_indexation()
+        .into_iter()
+        .map(|point| {
+            let p_id = point.get_point_id();
+            let neighbours = point
+                .get_neighborhood_id()
+                .into_iter()
+                .take(p_id.0 as usize + 1)
+                .map(|layer| {
+                    layer
+                        .into_iter()
+                        .map(|n| (n.p_id.0, n.p_id.1, n.distance))
+                        .collect()
+                })
+                .collect();
+            GraphPoint {
+                idx: point.get_origin_id(),
+                layer: p_id.0,
+                rank: p_id.1,
+                data: point.get_v().to_vec(),
+                neighbours,
+            }
+        })
+        .collect();
+    let entry_point = hnsw.get_entry_point_id().map(|p_id| (p_id.0, p_id.1));
+    (entry_point, points)
+}
+
+/// Link exported nodes back into a graph without recomputing distances
+pub(crate) fn import_points<T, D>(
+    config: &HnswConfig,
+    max_layer: usize,
+    distance_fn: D,
+    entry_point: Option<(u8, i32)>,
+    points: Vec<GraphPoint<T>>,
+) -> Result<Hnsw<'static, T, D>>
+where
+    T: Clone + Send + Sync,
+    D: Distance<T> + Send + Sync,
+{
+    let points = points
+        .into_iter()
+        .map(|point| {
+            let neighbours = point
+                .neighbours
+                .into_iter()
+                .map(|layer| {
+                    layer
+                        .into_iter()
+                        .map(|(l, rank, distance)| Neighbour::new(0, distance, PointId(l, rank)))
+                        .collect()
+                })
+                .collect();
+            (
+                point.data,
+                point.idx,
+                PointId(point.layer, point.rank),
+                neighbours,
+            )
+        })
+        .collect();
+
+    Hnsw::from_topology(
+        config.m,
+        config.max_elements,
+        max_layer,
+        config.ef_construction,
+        distance_fn,
+        points,
+        entry_point.map(|(l, rank)| PointId(l, rank)),
+    )
+    .map_err(|e| RuvectorError::SerializationError(format!("Invalid HNSW graph: {}", e)))
+}
+
 #[derive(Encode, Decode, Clone)]
 struct SerializableHnswConfig {
     m: usize,
@@ -108,28 +400,90 @@ impl HnswIndex {
         );
 
         Ok(Self {
-            inner: Arc::new(RwLock::new(HnswInner {
-                hnsw,
-                vectors: DashMap::new(),
-                id_to_idx: DashMap::new(),
-                idx_to_id: DashMap::new(),
-                next_idx: 0,
-            })),
+            inner: Arc::new(RwLock::new(HnswInner::new(hnsw))),
             config,
             metric,
             dimensions,
+            compaction_threshold: DEFAULT_COMPACTION_THRESHOLD,
         })
     }
 
+    /// Build an empty hnsw_rs graph with the given parameters
+    fn new_graph(
+        config: &HnswConfig,
+        dimensions: usize,
+        metric: DistanceMetric,
+    ) -> Hnsw<'static, f32, DistanceFn> {
+        Hnsw::<f32, DistanceFn>::new(
+            config.m,
+            config.max_elements,
+            dimensions,
+            config.ef_construction,
+            DistanceFn::new(metric),
+        )
+    }
+
     /// Get configuration
     pub fn config(&self) -> &HnswConfig {
         &self.config
     }
 
-    /// Set efSearch parameter for query-time accuracy tuning
-    pub fn set_ef_search(&mut self, _ef_search: usize) {
-        // Note: hnsw_rs controls ef_search via the search method's knbn parameter
-        // We store it in config and use it in search_with_ef
+    /// Set the tombstone ratio above which the index reports that it needs compaction
+    ///
+    /// `remove` never compacts by itself; `VectorDB` starts a background
+    /// compaction once the ratio is crossed. A value of `1.0` or more disables
+    /// that, and `compact()` can still be called explicitly.
+    pub fn set_compaction_threshold(&mut self, threshold: f64) {
+        self.compaction_threshold = threshold;
+    }
+
+    /// Number of deleted vectors still present as nodes in the graph
+    pub fn tombstone_count(&self) -> usize {
+        self.inner.read().tombstones.len()
+    }
+
+    /// Fraction of graph nodes that are tombstones
+    pub fn tombstone_ratio(&self) -> f64 {
+        self.inner.read().tombstone_ratio()
+    }
+
+    /// Total number of nodes in the graph, including tombstones
+    pub fn graph_size(&self) -> usize {
+        self.inner.read().hnsw.get_nb_point()
+    }
+
+    /// Rebuild the graph from live vectors only, dropping every tombstone
+    ///
+    /// Internal indices are preserved so id mappings stay valid. The new graph
+    /// is built under a read lock only, so searches and writes continue
+    /// meanwhile. Returns the number of tombstones that were reclaimed.
+    pub fn compact(&self) -> Result<usize> {
+        self.compaction_task().map_or(Ok(0), |task| task())
+    }
+
+    /// Snapshot the live vectors now and rebuild from them when run
+    fn compaction_task(&self) -> Option<CompactionTask> {
+        let (live, next_idx) = live_vectors(&self.inner.read())?;
+        let inner = Arc::clone(&self.inner);
+        let config = self.config.clone();
+        let (dimensions, metric) = (self.dimensions, self.metric);
+        Some(Box::new(move || {
+            compact_graph(&inner, live, next_idx, || {
+                Self::new_graph(&config, dimensions, metric)
+            })
+        }))
+    }
+
+    /// Set the default efSearch parameter for query-time accuracy tuning
+    ///
+    /// Queries that pass their own `ef_search` are unaffected.
+    pub fn set_ef_search(&mut self, ef_search: usize) {
+        self.config.ef_search = ef_search;
+    }
+
+    /// Resolve the efSearch to use for a query
+    fn effective_ef(&self, params: &SearchParams, k: usize) -> usize {
+        params.ef_search.unwrap_or(self.config.ef_search).max(k)
     }
 
     /// Serialize the index to bytes using bincode
@@ -220,10 +574,108 @@ impl HnswIndex {
                 id_to_idx,
                 idx_to_id,
                 next_idx: state.next_idx,
+                tombstones: HashSet::new(),
+                pending_repair: 0,
+            })),
+            config,
+            metric,
+            dimensions,
+            compaction_threshold: DEFAULT_COMPACTION_THRESHOLD,
+        })
+    }
+
+    /// Encode the graph topology, vectors and id mappings
+    ///
+    /// Unlike [`HnswIndex::serialize`], the neighbour lists are kept so that
+    /// [`HnswIndex::from_graph`] restores the index without reinserting.
+    pub fn export_graph(&self) -> Result<Vec<u8>> {
+        let inner = self.inner.read();
+
+        let (entry_point, points) = export_points(&inner.hnsw);
+        let state = HnswGraphState {
+            config: SerializableHnswConfig {
+                m: self.config.m,
+                ef_construction: self.config.ef_construction,
+                ef_search: self.config.ef_search,
+                max_elements: self.config.max_elements,
+            },
+            dimensions: self.dimensions,
+            metric: self.metric.into(),
+            next_idx: inner.next_idx,
+            entry_point,
+            points,
+            ids: inner
+                .idx_to_id
+                .iter()
+                .map(|entry| (*entry.key(), entry.value().clone()))
+                .collect(),
+        };
+
+        bincode::encode_to_vec(&state, bincode::config::standard()).map_err(|e| {
+            RuvectorError::SerializationError(format!("Failed to encode HNSW graph: {}", e))
+        })
+    }
+
+    /// Restore an index from bytes produced by [`HnswIndex::export_graph`]
+    ///
+    /// Neighbour lists are linked back as stored; no distance is recomputed.
+    /// Graph points without a live id come back as tombstones.
+    pub fn from_graph(bytes: &[u8]) -> Result<Self> {
+        let (state, _): (HnswGraphState, usize) =
+            bincode::decode_from_slice(bytes, bincode::config::standard()).map_err(|e| {
+                RuvectorError::SerializationError(format!("Failed to decode HNSW graph: {}", e))
+            })?;
+
+        let config = HnswConfig {
+            m: state.config.m,
+            ef_construction: state.config.ef_construction,
+            ef_search: state.config.ef_search,
+            max_elements: state.config.max_elements,
+        };
+        let metric: DistanceMetric = state.metric.into();
+        let dimensions = state.dimensions;
+
+        let idx_to_id: DashMap<usize, VectorId> = state.ids.into_iter().collect();
+        let vectors = DashMap::new();
+        let mut tombstones = HashSet::new();
+        for point in &state.points {
+            match idx_to_id.get(&point.idx) {
+                Some(id) => {
+                    vectors.insert(id.clone(), point.data.clone());
+                }
+                None => {
+                    tombstones.insert(point.idx);
+                }
+            }
+        }
+        let hnsw = import_points(
+            &config,
+            dimensions,
+            DistanceFn::new(metric),
+            state.entry_point,
+            state.points,
+        )?;
+
+        let id_to_idx = idx_to_id
+            .iter()
+            .map(|entry| (entry.value().clone(), *entry.key()))
+            .collect();
+        let pending_repair = tombstones.len();
+
+        Ok(Self {
+            inner: Arc::new(RwLock::new(HnswInner {
+                hnsw,
+                vectors,
+                id_to_idx,
+                idx_to_id,
+                next_idx: state.next_idx,
+                tombstones,
+                pending_repair,
             })),
             config,
             metric,
             dimensions,
+            compaction_threshold: DEFAULT_COMPACTION_THRESHOLD,
         })
     }
 
@@ -243,8 +695,17 @@ impl HnswIndex {
 
         let inner = self.inner.read();
 
-        // Use HNSW search with custom ef parameter (knbn)
-        let neighbors = inner.hnsw.search(query, k, ef_search);
+        // Use HNSW search with custom ef parameter (knbn). Tombstones are still
+        // traversed for connectivity but never returned.
+        let neighbors = if inner.tombstones.is_empty() {
+            inner.hnsw.search(query, k, ef_search)
+        } else {
+            let tombstones = &inner.tombstones;
+            let live = |idx: &usize| !tombstones.contains(idx);
+            inner
+                .hnsw
+                .search_filter(query, k, ef_search.max(k), Some(&live))
+        };
 
         Ok(neighbors
             .into_iter()
@@ -269,6 +730,9 @@ impl VectorIndex for HnswIndex {
             });
         }
 
+        // Replacing a vector must not leave its old node reachable
+        self.remove(&id)?;
+
         let mut inner = self.inner.write();
         let idx = inner.next_idx;
         inner.next_idx += 1;
@@ -285,6 +749,14 @@ impl VectorIndex for HnswIndex {
     }
 
     fn add_batch(&mut self, entries: Vec<(VectorId, Vec<f32>)>) -> Result<()> {
+        self.add_batch_with_progress(entries, &mut |_, _| {})
+    }
+
+    fn add_batch_with_progress(
+        &mut self,
+        entries: Vec<(VectorId, Vec<f32>)>,
+        progress: &mut dyn FnMut(usize, usize),
+    ) -> Result<()> {
         // Validate all dimensions first
         for (_, vector) in &entries {
             if vector.len() != self.dimensions {
@@ -295,31 +767,27 @@ impl VectorIndex for HnswIndex {
             }
         }
 
+        let entries = dedup_batch(entries);
+        for (id, _) in &entries {
+            self.remove(id)?;
+        }
+
         let mut inner = self.inner.write();
 
-        // Prepare batch data for insertion
-        // First, assign indices and collect vector data
-        let data_with_ids: Vec<_> = entries
+        // Assign graph indices up front so workers can insert in any order
+        let first_idx = inner.next_idx;
+        inner.next_idx += entries.len();
+
+        let points: Vec<_> = entries
             .iter()
             .enumerate()
-            .map(|(i, (id, vector))| {
-                let idx = inner.next_idx + i;
-                (id.clone(), idx, vector.clone())
-            })
+            .map(|(i, (_, vector))| (vector.as_slice(), first_idx + i))
             .collect();
-
-        // Update next_idx
-        inner.next_idx += entries.len();
-
-        // Insert into HNSW sequentially
-        // Note: Using sequential insertion to avoid Send requirements with RwLock guard
-        // For large batches, consider restructuring to use hnsw_rs parallel_insert
-        for (_id, idx, vector) in &data_with_ids {
-            inner.hnsw.insert_data(vector, *idx);
-        }
+        insert_points(&inner.hnsw, &points, progress);
 
         // Store mappings
-        for (id, idx, vector) in data_with_ids {
+        for (i, (id, vector)) in entries.into_iter().enumerate() {
+            let idx = first_idx + i;
             inner.vectors.insert(id.clone(), vector);
             inner.id_to_idx.insert(id.clone(), idx);
             inner.idx_to_id.insert(idx, id);
@@ -333,21 +801,101 @@ impl VectorIndex for HnswIndex {
         self.search_with_ef(query, k, self.config.ef_search)
     }
 
+    fn search_with_params(
+        &self,
+        query: &[f32],
+        k: usize,
+        params: &SearchParams,
+    ) -> Result<Vec<SearchResult>> {
+        self.search_with_ef(query, k, self.effective_ef(params, k))
+    }
+
+    fn search_filtered(
+        &self,
+        query: &[f32],
+        k: usize,
+        params: &SearchParams,
+        filter: &(dyn Fn(&VectorId) -> bool + Sync),
+    ) -> Result<Vec<SearchResult>> {
+        if query.len() != self.dimensions {
+            return Err(RuvectorError::DimensionMismatch {
+                expected: self.dimensions,
+                actual: query.len(),
+            });
+        }
+
+        let inner = self.inner.read();
+
+        // Evaluate the filter during traversal: rejected nodes (including
+        // tombstones, which have no id mapping) still route the search but
+        // never enter the result set.
+        let accept = |idx: &usize| inner.idx_to_id.get(idx).is_some_and(|id| filter(&id));
+        let ef_search = self.effective_ef(params, k);
+        let neighbors = inner.hnsw.search_filter(query, k, ef_search, Some(&accept));
+
+        Ok(neighbors
+            .into_iter()
+            .filter_map(|neighbor| {
+                inner.idx_to_id.get(&neighbor.d_id).map(|id| SearchResult {
+                    id: id.clone(),
+                    score: neighbor.distance,
+                    vector: None,
+                    metadata: None,
+                })
+            })
+            .collect())
+    }
+
+    fn set_ef_search(&mut self, ef_search: usize) {
+        HnswIndex::set_ef_search(self, ef_search);
+    }
+
+    fn export_graph(&self) -> Result<Option<Vec<u8>>> {
+        HnswIndex::export_graph(self).map(Some)
+    }
+
+    fn graph_stats(&self) -> Option<GraphStats> {
+        let inner = self.inner.read();
+        Some(GraphStats {
+            nodes: inner.hnsw.get_nb_point(),
+            tombstones: inner.tombstones.len(),
+            ef_search: self.config.ef_search,
+        })
+    }
+
+    fn needs_compaction(&self) -> bool {
+        self.inner.read().tombstone_ratio() > self.compaction_threshold
+    }
+
+    fn compaction(&self) -> Option<CompactionTask> {
+        self.compaction_task()
+    }
+
     fn remove(&mut self, id: &VectorId) -> Result<bool> {
-        let inner = self.inner.write();
+        let mut inner = self.inner.write();
 
-        // Note: hnsw_rs doesn't support direct deletion
-        // We remove from our mappings but the graph structure remains
-        // This is a known limitation of HNSW
+        // hnsw_rs has no point deletion: the node stays in the graph as a
+        // tombstone that search skips, until the next compaction.
         let removed = inner.vectors.remove(id).is_some();
+        if !removed {
+            return Ok(false);
+        }
 
-        if removed {
-            if let Some((_, idx)) = inner.id_to_idx.remove(id) {
-                inner.idx_to_id.remove(&idx);
-            }
+        if let Some((_, idx)) = inner.id_to_idx.remove(id) {
+            inner.idx_to_id.remove(&idx);
+            inner.tombstones.insert(idx);
+            inner.pending_repair += 1;
+        }
+
+        if inner.pending_repair >= MIN_REPAIR_BATCH.max(inner.vectors.len() / 100) {
+            inner.repair();
         }
 
-        Ok(removed)
+        Ok(true)
+    }
+
+    fn contains(&self, id: &VectorId) -> bool {
+        self.inner.read().vectors.contains_key(id)
     }
 
     fn len(&self) -> usize {
@@ -433,6 +981,38 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_hnsw_batch_insert_reports_progress() -> Result<()> {
+        let config = HnswConfig {
+            m: 16,
+            ef_construction: 100,
+            ef_search: 100,
+            max_elements: 10_000,
+        };
+        let mut index = HnswIndex::new(16, DistanceMetric::Euclidean, config)?;
+
+        let total = BULK_INSERT_CHUNK + 500;
+        let vectors = generate_random_vectors(total, 16);
+        let entries: Vec<_> = vectors
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (format!("vec_{}", i), v.clone()))
+            .collect();
+
+        let mut reports = Vec::new();
+        index.add_batch_with_progress(entries, &mut |inserted, of| reports.push((inserted, of)))?;
+        assert_eq!(reports, vec![(BULK_INSERT_CHUNK, total), (total, total)]);
+        assert_eq!(index.len(), total);
+
+        // Graph built concurrently is still navigable
+        for i in (0..total).step_by(461) {
+            let results = index.search(&vectors[i], 1)?;
+            assert_eq!(results[0].id, format!("vec_{}", i));
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn test_hnsw_serialization() -> Result<()> {
         let config = HnswConfig {
@@ -468,6 +1048,172 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_hnsw_remove_skips_tombstones() -> Result<()> {
+        let config = HnswConfig {
+            m: 16,
+            ef_construction: 100,
+            ef_search: 50,
+            max_elements: 1000,
+        };
+
+        let mut index = HnswIndex::new(32, DistanceMetric::Cosine, config)?;
+        index.set_compaction_threshold(1.0);
+
+        let vectors = generate_random_vectors(200, 32);
+        for (i, vector) in vectors.iter().enumerate() {
+            index.add(format!("vec_{}", i), normalize_vector(vector))?;
+        }
+
+        for i in 0..100 {
+            assert!(index.remove(&format!("vec_{}", i))?);
+        }
+        assert!(!index.remove(&"vec_0".to_string())?);
+
+        assert_eq!(index.len(), 100);
+        assert_eq!(index.tombstone_count(), 100);
+        assert_eq!(index.graph_size(), 200);
+        assert_eq!(
+            index.graph_stats(),
+            Some(GraphStats {
+                nodes: 200,
+                tombstones: 100,
+                ef_search: 50,
+            })
+        );
+
+        let query = normalize_vector(&vectors[0]);
+        let results = index.search(&query, 10)?;
+        assert_eq!(results.len(), 10);
+        for result in &results {
+            let n: usize = result.id.trim_start_matches("vec_").parse().unwrap();
+            assert!(n >= 100, "deleted vector {} returned", result.id);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hnsw_compact() -> Result<()> {
+        let config = HnswConfig::default();
+        let mut index = HnswIndex::new(32, DistanceMetric::Cosine, config)?;
+        index.set_compaction_threshold(1.0);
+
+        let vectors = generate_random_vectors(100, 32);
+        for (i, vector) in vectors.iter().enumerate() {
+            index.add(format!("vec_{}", i), normalize_vector(vector))?;
+        }
+        for i in 0..40 {
+            index.remove(&format!("vec_{}", i))?;
+        }
+
+        assert_eq!(index.compact()?, 40);
+        assert_eq!(index.tombstone_count(), 0);
+        assert_eq!(index.graph_size(), 60);
+
+        let query = normalize_vector(&vectors[50]);
+        let results = index.search(&query, 1)?;
+        assert_eq!(results[0].id, "vec_50");
+
+        // Removing never compacts; the index only reports that it is due
+        index.set_compaction_threshold(0.1);
+        for i in 40..50 {
+            index.remove(&format!("vec_{}", i))?;
+        }
+        assert!(index.needs_compaction());
+        assert_eq!(index.tombstone_count(), 10);
+
+        // Writes made while the task rebuilds are carried over to the new graph
+        let task = index.compaction().unwrap();
+        index.remove(&"vec_60".to_string())?;
+        index.add("vec_new".to_string(), normalize_vector(&vectors[0]))?;
+        assert_eq!(task()?, 10);
+        assert_eq!(index.tombstone_count(), 1);
+        assert_eq!(index.graph_size(), 51);
+        assert_eq!(index.len(), 50);
+        assert!(!index.needs_compaction());
+
+        let results = index.search(&normalize_vector(&vectors[0]), 1)?;
+        assert_eq!(results[0].id, "vec_new");
+        let results = index.search(&normalize_vector(&vectors[60]), 5)?;
+        assert!(results.iter().all(|r| r.id != "vec_60"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hnsw_graph_export_roundtrip() -> Result<()> {
+        let mut index = HnswIndex::new(32, DistanceMetric::Euclidean, HnswConfig::default())?;
+        index.set_compaction_threshold(1.0);
+
+        let vectors = generate_random_vectors(300, 32);
+        for (i, vector) in vectors.iter().enumerate() {
+            index.add(format!("vec_{}", i), vector.clone())?;
+        }
+        for i in 0..20 {
+            index.remove(&format!("vec_{}", i))?;
+        }
+
+        let restored = HnswIndex::from_graph(&index.export_graph()?)?;
+        assert_eq!(restored.len(), 280);
+        assert_eq!(restored.tombstone_count(), 20);
+        assert_eq!(restored.graph_size(), 300);
+
+        // Same topology, same traversal: identical answers
+        for query in vectors.iter().step_by(37) {
+            let original: Vec<_> = index.search(query, 10)?.into_iter().map(|r| r.id).collect();
+            let reloaded: Vec<_> = restored
+                .search(query, 10)?
+                .into_iter()
+                .map(|r| r.id)
+                .collect();
+            assert_eq!(original, reloaded);
+        }
+
+        // The restored graph keeps accepting inserts
+        let mut restored = restored;
+        restored.add("extra".to_string(), vectors[3].clone())?;
+        assert_eq!(restored.search(&vectors[3], 1)?[0].id, "extra");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hnsw_ef_search_params() -> Result<()> {
+        let config = HnswConfig {
+            m: 16,
+            ef_construction: 100,
+            ef_search: 50,
+            max_elements: 1000,
+        };
+
+        let mut index: Box<dyn VectorIndex> =
+            Box::new(HnswIndex::new(32, DistanceMetric::Cosine, config)?);
+
+        let vectors = generate_random_vectors(300, 32);
+        for (i, vector) in vectors.iter().enumerate() {
+            index.add(format!("vec_{}", i), normalize_vector(vector))?;
+        }
+
+        index.set_ef_search(8);
+        let query = normalize_vector(&vectors[7]);
+
+        // Per-query override wins over the default
+        let results = index.search_with_params(&query, 5, &SearchParams::with_ef_search(300))?;
+        assert_eq!(results.len(), 5);
+        assert_eq!(results[0].id, "vec_7");
+
+        // ef is never allowed below k
+        let results = index.search_with_params(&query, 20, &SearchParams::with_ef_search(1))?;
+        assert_eq!(results.len(), 20);
+
+        let mut hnsw = HnswIndex::new(32, DistanceMetric::Cosine, HnswConfig::default())?;
+        hnsw.set_ef_search(42);
+        assert_eq!(hnsw.config().ef_search, 42);
+
+        Ok(())
+    }
+
     #[test]
     fn test_dimension_mismatch() -> Result<()> {
         let config = HnswConfig::default();
@@ -0,0 +1,355 @@
+//! Incremental persistence for graph indexes
+//!
+//! The encoded graph (see [`VectorIndex::export_graph`]) is written to a
+//! snapshot file next to the redb database, `<storage_path>.hnsw`. Every
+//! insert and delete is also journaled to the index WAL inside the same redb
+//! transaction as the vector itself. Opening a database maps the snapshot,
+//! links the graph back without reinserting anything, and replays the WAL
+//! entries written after the snapshot's checkpoint.
+//!
+//! Checkpoints run on a background thread once enough writes have
+//! accumulated. Writers are only held back while the graph is copied out of
+//! the index; searches are never blocked by disk I/O.
+
+use crate::error::{Result, RuvectorError};
+use crate::index::VectorIndex;
+use crate::storage::{IndexWalOp, VectorStorage};
+use memmap2::Mmap;
+use parking_lot::{Mutex, MutexGuard, RwLock};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Journaled writes after which a background checkpoint is started
+pub const DEFAULT_CHECKPOINT_INTERVAL: u64 = 10_000;
+
+const SNAPSHOT_MAGIC: &[u8; 8] = b"RVGRAPH1";
+
+/// Magic, checkpoint sequence and payload length
+const HEADER_LEN: usize = 24;
+
+/// FNV-1a checksum of the payload
+const FOOTER_LEN: usize = 8;
+
+/// Snapshot file holding an encoded index graph
+pub struct GraphFile {
+    path: PathBuf,
+}
+
+/// Memory-mapped, validated snapshot
+pub struct MappedGraph {
+    map: Mmap,
+    checkpoint: u64,
+}
+
+impl MappedGraph {
+    /// Last index WAL sequence number reflected in the graph
+    pub fn checkpoint(&self) -> u64 {
+        self.checkpoint
+    }
+
+    /// Encoded graph, as returned by [`VectorIndex::export_graph`]
+    pub fn graph(&self) -> &[u8] {
+        &self.map[HEADER_LEN..self.map.len() - FOOTER_LEN]
+    }
+}
+
+impl GraphFile {
+    /// Snapshot file belonging to the database at `storage_path`
+    pub fn for_storage_path(storage_path: &str) -> Self {
+        Self {
+            path: PathBuf::from(format!("{}.hnsw", storage_path)),
+        }
+    }
+
+    /// Location of the snapshot file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Atomically replace the snapshot
+    ///
+    /// The data is written to a temporary file and synced before being
+    /// renamed over the previous snapshot, so a crash leaves either the old
+    /// or the new snapshot in place, never a torn one.
+    pub fn write(&self, checkpoint: u64, graph: &[u8]) -> Result<()> {
+        let tmp_path = self.path.with_extension("hnsw.tmp");
+        {
+            let mut file = File::create(&tmp_path)?;
+            file.write_all(SNAPSHOT_MAGIC)?;
+            file.write_all(&checkpoint.to_le_bytes())?;
+            file.write_all(&(graph.len() as u64).to_le_bytes())?;
+            file.write_all(graph)?;
+            file.write_all(&checksum(graph).to_le_bytes())?;
+            file.sync_all()?;
+        }
+        fs::rename(&tmp_path, &self.path)?;
+
+        // Persist the rename itself
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            if let Ok(dir) = File::open(parent) {
+                let _ = dir.sync_all();
+            }
+        }
+        Ok(())
+    }
+
+    /// Map the snapshot into memory
+    ///
+    /// Returns `Ok(None)` when no snapshot exists and an error when the file
+    /// is truncated or fails its checksum.
+    pub fn open(&self) -> Result<Option<MappedGraph>> {
+        let file = match OpenOptions::new().read(true).open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        // SAFETY: snapshots are only ever replaced by rename, never modified
+        // in place, so the mapped file does not change underneath us.
+        let map = unsafe { Mmap::map(&file)? };
+
+        let corrupt = |reason: &str| {
+            RuvectorError::StorageError(format!(
+                "Corrupt graph snapshot {}: {}",
+                self.path.display(),
+                reason
+            ))
+        };
+        if map.len() < HEADER_LEN + FOOTER_LEN || &map[..8] != SNAPSHOT_MAGIC {
+            return Err(corrupt("bad header"));
+        }
+        let checkpoint = u64::from_le_bytes(map[8..16].try_into().unwrap());
+        let len = u64::from_le_bytes(map[16..24].try_into().unwrap()) as usize;
+        if map.len() != HEADER_LEN + len + FOOTER_LEN {
+            return Err(corrupt("truncated"));
+        }
+        let stored = u64::from_le_bytes(map[HEADER_LEN + len..].try_into().unwrap());
+        if checksum(&map[HEADER_LEN..HEADER_LEN + len]) != stored {
+            return Err(corrupt("checksum mismatch"));
+        }
+
+        Ok(Some(MappedGraph { map, checkpoint }))
+    }
+}
+
+/// FNV-1a, enough to catch torn or bit-flipped snapshots
+fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// Checkpointing and recovery of a persisted index graph
+pub struct GraphPersistence {
+    file: GraphFile,
+    /// Held across a storage write and the matching index update, and while
+    /// a checkpoint captures the graph, so the captured graph reflects
+    /// exactly the WAL entries up to its checkpoint
+    write_lock: Mutex<()>,
+    /// Serialises checkpoints so snapshots are written in capture order
+    checkpoint_lock: Mutex<()>,
+    interval: AtomicU64,
+    since_checkpoint: AtomicU64,
+    in_flight: AtomicBool,
+}
+
+impl GraphPersistence {
+    /// Persistence for the database at `storage_path`
+    pub fn new(storage_path: &str) -> Self {
+        Self {
+            file: GraphFile::for_storage_path(storage_path),
+            write_lock: Mutex::new(()),
+            checkpoint_lock: Mutex::new(()),
+            interval: AtomicU64::new(DEFAULT_CHECKPOINT_INTERVAL),
+            since_checkpoint: AtomicU64::new(0),
+            in_flight: AtomicBool::new(false),
+        }
+    }
+
+    /// Snapshot file used by this database
+    pub fn file(&self) -> &GraphFile {
+        &self.file
+    }
+
+    /// Journaled writes after which a background checkpoint starts
+    ///
+    /// `0` disables automatic checkpoints.
+    pub fn set_checkpoint_interval(&self, interval: u64) {
+        self.interval.store(interval, Ordering::Relaxed);
+    }
+
+    /// Guard to hold across a storage write and the matching index update
+    pub fn write_guard(&self) -> MutexGuard<'_, ()> {
+        self.write_lock.lock()
+    }
+
+    /// Record completed writes and start a background checkpoint when due
+    pub fn record_writes(
+        self: &Arc<Self>,
+        count: usize,
+        storage: &Arc<VectorStorage>,
+        index: &Arc<RwLock<Box<dyn VectorIndex>>>,
+    ) {
+        let pending = self
+            .since_checkpoint
+            .fetch_add(count as u64, Ordering::Relaxed)
+            + count as u64;
+        let interval = self.interval.load(Ordering::Relaxed);
+        if interval == 0 || pending < interval || self.in_flight.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let persistence = Arc::clone(self);
+        let storage = Arc::clone(storage);
+        let index = Arc::clone(index);
+        std::thread::spawn(move || {
+            if let Err(e) = persistence.checkpoint(&storage, &index) {
+                tracing::error!("Background graph checkpoint failed: {}", e);
+            }
+            persistence.in_flight.store(false, Ordering::Release);
+        });
+    }
+
+    /// Write a snapshot of the index graph and truncate the WAL it covers
+    ///
+    /// Returns the checkpoint sequence number, or `None` when the index does
+    /// not export a graph.
+    pub fn checkpoint(
+        &self,
+        storage: &VectorStorage,
+        index: &RwLock<Box<dyn VectorIndex>>,
+    ) -> Result<Option<u64>> {
+        let _ordered = self.checkpoint_lock.lock();
+
+        let (checkpoint, graph) = {
+            let _writes = self.write_lock.lock();
+            let checkpoint = storage.index_wal_head()?;
+            let graph = index.read().export_graph()?;
+            self.since_checkpoint.store(0, Ordering::Relaxed);
+            (checkpoint, graph)
+        };
+        let Some(graph) = graph else {
+            return Ok(None);
+        };
+
+        self.file.write(checkpoint, &graph)?;
+        storage.truncate_index_wal(checkpoint)?;
+        tracing::debug!(
+            "Wrote graph checkpoint at WAL sequence {} ({} bytes)",
+            checkpoint,
+            graph.len()
+        );
+        Ok(Some(checkpoint))
+    }
+
+    /// Load the persisted graph and replay the WAL written after it
+    ///
+    /// Returns `None` when there is no usable snapshot, or when the
+    /// recovered index disagrees with storage; the caller then rebuilds the
+    /// index from the stored vectors.
+    pub fn restore(
+        &self,
+        storage: &VectorStorage,
+        load: impl FnOnce(&[u8]) -> Result<Box<dyn VectorIndex>>,
+    ) -> Result<Option<Box<dyn VectorIndex>>> {
+        let mapped = match self.file.open() {
+            Ok(Some(mapped)) => mapped,
+            Ok(None) => return Ok(None),
+            Err(e) => {
+                tracing::warn!("Ignoring graph snapshot: {}", e);
+                return Ok(None);
+            }
+        };
+        let mut index = match load(mapped.graph()) {
+            Ok(index) => index,
+            Err(e) => {
+                tracing::warn!("Ignoring graph snapshot: {}", e);
+                return Ok(None);
+            }
+        };
+
+        let ops = storage.index_wal_since(mapped.checkpoint())?;
+        let replayed = ops.len();
+        for (_, op) in ops {
+            match op {
+                IndexWalOp::Insert(id) => {
+                    index.remove(&id)?;
+                    if let Some(entry) = storage.get(&id)? {
+                        index.add(id, entry.vector)?;
+                    }
+                }
+                IndexWalOp::Delete(id) => {
+                    index.remove(&id)?;
+                }
+            }
+        }
+
+        // Matching counts aren't enough: a lost delete plus a lost insert
+        // would leave the same number of different vectors
+        let ids = storage.all_ids()?;
+        let stored = ids.len();
+        if index.len() != stored {
+            tracing::warn!(
+                "Graph snapshot holds {} vectors after WAL replay but storage has {}; rebuilding",
+                index.len(),
+                stored
+            );
+            return Ok(None);
+        }
+        if let Some(missing) = ids.iter().find(|id| !index.contains(id)) {
+            tracing::warn!(
+                "Graph snapshot is missing vector {} after WAL replay; rebuilding",
+                missing
+            );
+            return Ok(None);
+        }
+
+        tracing::info!(
+            "Restored index graph with {} vectors ({} WAL entries replayed)",
+            stored,
+            replayed
+        );
+        self.since_checkpoint
+            .store(replayed as u64, Ordering::Relaxed);
+        Ok(Some(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_graph_file_roundtrip() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let file = GraphFile::for_storage_path(&dir.path().join("db").to_string_lossy());
+        assert!(file.open()?.is_none());
+
+        file.write(42, b"graph bytes")?;
+        let mapped = file.open()?.unwrap();
+        assert_eq!(mapped.checkpoint(), 42);
+        assert_eq!(mapped.graph(), b"graph bytes");
+        Ok(())
+    }
+
+    #[test]
+    fn test_graph_file_detects_corruption() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let file = GraphFile::for_storage_path(&dir.path().join("db").to_string_lossy());
+        file.write(7, &[1u8; 64])?;
+
+        let mut bytes = fs::read(file.path())?;
+        bytes[HEADER_LEN + 10] ^= 0xff;
+        fs::write(file.path(), &bytes)?;
+        assert!(file.open().is_err());
+
+        bytes.truncate(bytes.len() - 20);
+        fs::write(file.path(), &bytes)?;
+        assert!(file.open().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,985 @@
+//! HNSW index over quantized vector codes
+//!
+//! The graph stores compressed codes (scalar, product or binary) instead of
+//! full-precision vectors. Queries stay in f32 and are compared against the
+//! reconstructed codes on the fly (asymmetric distance), so traversal only
+//! loses the precision of the stored side. Scores are approximate:
+//! [`VectorIndex::needs_rescoring`] tells callers to over-fetch and rescore
+//! the top candidates against the full-precision vectors kept in storage.
+
+use crate::distance::distance;
+use crate::error::{Result, RuvectorError};
+use crate::index::hnsw::{
+    dedup_batch, export_points, import_points, insert_points, GraphPoint, LiveNodes,
+    DEFAULT_COMPACTION_THRESHOLD,
+};
+use crate::index::{CompactionTask, GraphStats, SearchParams, VectorIndex};
+use crate::quantization::{BinaryQuantized, ProductQuantized, QuantizedVector, ScalarQuantized};
+use crate::types::{DistanceMetric, HnswConfig, QuantizationConfig, SearchResult, VectorId};
+use dashmap::DashMap;
+use hnsw_rs::prelude::*;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Minimum number of pending deletions before neighbour lists are repaired
+const MIN_REPAIR_BATCH: usize = 64;
+
+/// Vectors buffered per codebook entry before product quantization is trained
+const PQ_TRAINING_VECTORS_PER_CENTROID: usize = 4;
+
+/// k-means iterations used to train product quantization codebooks
+const PQ_TRAINING_ITERATIONS: usize = 10;
+
+/// Size of the header (`min`, `scale`) prefixed to scalar codes
+const SCALAR_HEADER_BYTES: usize = 8;
+
+/// Encoder/decoder between f32 vectors and the byte codes stored in the graph
+enum Codec {
+    /// `[min: f32 LE][scale: f32 LE][u8; dimensions]`
+    Scalar,
+    /// One sign bit per dimension
+    Binary,
+    /// One centroid index per subspace
+    Product(ProductQuantized),
+}
+
+impl Codec {
+    fn encode(&self, vector: &[f32]) -> Vec<u8> {
+        match self {
+            Codec::Scalar => {
+                let quantized = ScalarQuantized::quantize(vector);
+                let mut code = Vec::with_capacity(SCALAR_HEADER_BYTES + quantized.data.len());
+                code.extend_from_slice(&quantized.min.to_le_bytes());
+                code.extend_from_slice(&quantized.scale.to_le_bytes());
+                code.extend_from_slice(&quantized.data);
+                code
+            }
+            Codec::Binary => BinaryQuantized::quantize(vector).bits,
+            Codec::Product(pq) => pq.encode(vector),
+        }
+    }
+
+    fn decode(&self, code: &[u8], dimensions: usize) -> Vec<f32> {
+        match self {
+            Codec::Scalar => {
+                let min = f32::from_le_bytes([code[0], code[1], code[2], code[3]]);
+                let scale = f32::from_le_bytes([code[4], code[5], code[6], code[7]]);
+                code[SCALAR_HEADER_BYTES..]
+                    .iter()
+                    .map(|&v| min + v as f32 * scale)
+                    .collect()
+            }
+            Codec::Binary => BinaryQuantized::from_bytes(code.to_vec(), dimensions).reconstruct(),
+            Codec::Product(pq) => pq.decode(code),
+        }
+    }
+}
+
+/// Distance between graph points, where a point is either a stored code or
+/// the raw little-endian bytes of a full-precision query
+struct CodeDistance {
+    codec: Arc<Codec>,
+    metric: DistanceMetric,
+    dimensions: usize,
+}
+
+impl CodeDistance {
+    /// Expand a point into f32 values, borrowing when it is a query
+    fn expand<'a>(&self, point: &'a [u8]) -> Cow<'a, [f32]> {
+        if point.len() == self.dimensions * 4 {
+            // SAFETY: every bit pattern is a valid f32; prefix and suffix are
+            // checked to be empty so the slice is aligned and complete.
+            let (prefix, floats, suffix) = unsafe { point.align_to::<f32>() };
+            if prefix.is_empty() && suffix.is_empty() {
+                return Cow::Borrowed(floats);
+            }
+            return Cow::Owned(
+                point
+                    .chunks_exact(4)
+                    .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                    .collect(),
+            );
+        }
+        Cow::Owned(self.codec.decode(point, self.dimensions))
+    }
+}
+
+impl Distance<u8> for CodeDistance {
+    fn eval(&self, a: &[u8], b: &[u8]) -> f32 {
+        distance(&self.expand(a), &self.expand(b), self.metric).unwrap_or(f32::MAX)
+    }
+}
+
+/// View a query as the byte slice understood by [`CodeDistance`]
+fn query_bytes(query: &[f32]) -> &[u8] {
+    // SAFETY: u8 has no alignment requirement and the length covers exactly
+    // the memory of `query`.
+    unsafe { std::slice::from_raw_parts(query.as_ptr().cast::<u8>(), std::mem::size_of_val(query)) }
+}
+
+/// Copy out the live codes of `inner`, its next free graph index and codec
+/// for a compaction
+fn live_codes(inner: &QuantizedInner) -> Option<(LiveNodes<u8>, usize, Arc<Codec>)> {
+    let codec = match &inner.graph {
+        Some(graph) if !inner.tombstones.is_empty() => Arc::clone(&graph.codec),
+        _ => return None,
+    };
+    let live = inner
+        .id_to_idx
+        .iter()
+        .filter_map(|entry| {
+            inner
+                .codes
+                .get(entry.key())
+                .map(|code| (code.clone(), *entry.value()))
+        })
+        .collect();
+    Some((live, inner.next_idx, codec))
+}
+
+/// Replace `inner`'s graph with one built from a snapshot of its live codes
+///
+/// Works like the full-precision compaction: the graph is built without a
+/// lock and the write lock is only held to add codes inserted since the
+/// snapshot and swap the graphs.
+fn compact_codes(
+    inner: &RwLock<QuantizedInner>,
+    live: LiveNodes<u8>,
+    next_idx: usize,
+    codec: Arc<Codec>,
+    new_graph: impl FnOnce(Arc<Codec>) -> CodeGraph,
+) -> Result<usize> {
+    let graph = new_graph(codec);
+    for (code, idx) in &live {
+        graph.hnsw.insert_slice((code.as_slice(), *idx));
+    }
+
+    let mut inner = inner.write();
+    for idx in next_idx..inner.next_idx {
+        let Some(id) = inner.idx_to_id.get(&idx) else {
+            continue;
+        };
+        if let Some(code) = inner.codes.get(id.value()) {
+            graph.hnsw.insert_slice((code.as_slice(), idx));
+        }
+    }
+    let tombstones: HashSet<usize> = live
+        .iter()
+        .map(|(_, idx)| *idx)
+        .filter(|idx| !inner.idx_to_id.contains_key(idx))
+        .collect();
+    let reclaimed = inner.tombstones.len().saturating_sub(tombstones.len());
+
+    inner.graph = Some(graph);
+    inner.pending_repair = tombstones.len();
+    inner.tombstones = tombstones;
+
+    tracing::info!(
+        "Compacted quantized HNSW index: reclaimed {} tombstones, {} live vectors",
+        reclaimed,
+        inner.codes.len()
+    );
+    Ok(reclaimed)
+}
+
+/// HNSW index storing quantized codes in the graph
+pub struct QuantizedHnswIndex {
+    inner: Arc<RwLock<QuantizedInner>>,
+    config: HnswConfig,
+    metric: DistanceMetric,
+    dimensions: usize,
+    quantization: QuantizationConfig,
+    compaction_threshold: f64,
+}
+
+struct QuantizedInner {
+    /// `None` until product quantization codebooks are trained
+    graph: Option<CodeGraph>,
+    codes: DashMap<VectorId, Vec<u8>>,
+    id_to_idx: DashMap<VectorId, usize>,
+    idx_to_id: DashMap<usize, VectorId>,
+    next_idx: usize,
+    /// Graph nodes whose vector was deleted; skipped by search, dropped by compaction
+    tombstones: HashSet<usize>,
+    /// Tombstones added since the last neighbour-list repair
+    pending_repair: usize,
+    /// Full-precision vectors awaiting product quantization training,
+    /// searched exhaustively until then
+    training: Vec<(VectorId, Vec<f32>)>,
+}
+
+/// Persisted quantized graph, see [`QuantizedHnswIndex::export_graph`]
+#[derive(Serialize, Deserialize)]
+struct QuantizedGraphState {
+    config: HnswConfig,
+    dimensions: usize,
+    metric: DistanceMetric,
+    quantization: QuantizationConfig,
+    /// Trained codebooks, for product quantization
+    codebooks: Option<ProductQuantized>,
+    next_idx: usize,
+    entry_point: Option<(u8, i32)>,
+    /// Graph points in layer, then rank order; tombstones included
+    points: Vec<GraphPoint<u8>>,
+    /// Live codes only
+    ids: Vec<(usize, String)>,
+    /// Vectors still waiting for product quantization training
+    training: Vec<(String, Vec<f32>)>,
+}
+
+struct CodeGraph {
+    hnsw: Hnsw<'static, u8, CodeDistance>,
+    codec: Arc<Codec>,
+}
+
+impl CodeGraph {
+    fn new(
+        config: &HnswConfig,
+        dimensions: usize,
+        metric: DistanceMetric,
+        codec: Arc<Codec>,
+    ) -> Self {
+        let distance_fn = CodeDistance {
+            codec: Arc::clone(&codec),
+            metric,
+            dimensions,
+        };
+        Self {
+            hnsw: Hnsw::<u8, CodeDistance>::new(
+                config.m,
+                config.max_elements,
+                dimensions,
+                config.ef_construction,
+                distance_fn,
+            ),
+            codec,
+        }
+    }
+}
+
+impl QuantizedInner {
+    fn tombstone_ratio(&self) -> f64 {
+        let graph_size = self.codes.len() + self.tombstones.len();
+        if graph_size == 0 {
+            0.0
+        } else {
+            self.tombstones.len() as f64 / graph_size as f64
+        }
+    }
+
+    fn repair(&mut self) {
+        if self.pending_repair == 0 {
+            return;
+        }
+        if let Some(graph) = &self.graph {
+            let tombstones = &self.tombstones;
+            let repaired = graph
+                .hnsw
+                .repair_deleted(&|idx: &usize| tombstones.contains(idx));
+            tracing::debug!(
+                "Repaired {} quantized HNSW neighbour lists after {} deletions",
+                repaired,
+                self.pending_repair
+            );
+        }
+        self.pending_repair = 0;
+    }
+}
+
+impl QuantizedHnswIndex {
+    /// Create a new quantized HNSW index
+    ///
+    /// Scalar and binary codes are built immediately. Product quantization
+    /// buffers incoming vectors and searches them exhaustively until enough
+    /// have arrived to train its codebooks.
+    pub fn new(
+        dimensions: usize,
+        metric: DistanceMetric,
+        config: HnswConfig,
+        quantization: QuantizationConfig,
+    ) -> Result<Self> {
+        let codec = match &quantization {
+            QuantizationConfig::Scalar => Some(Codec::Scalar),
+            QuantizationConfig::Binary => Some(Codec::Binary),
+            QuantizationConfig::Product { subspaces, k } => {
+                if *subspaces == 0 || dimensions % subspaces != 0 {
+                    return Err(RuvectorError::InvalidParameter(format!(
+                        "Product quantization subspaces ({}) must divide dimensions ({})",
+                        subspaces, dimensions
+                    )));
+                }
+                if *k == 0 || *k > 256 {
+                    return Err(RuvectorError::InvalidParameter(format!(
+                        "Product quantization codebook size must be in 1..=256, got {}",
+                        k
+                    )));
+                }
+                None
+            }
+            QuantizationConfig::None => {
+                return Err(RuvectorError::InvalidParameter(
+                    "QuantizedHnswIndex requires a quantization scheme".into(),
+                ))
+            }
+        };
+
+        let index = Self {
+            inner: Arc::new(RwLock::new(QuantizedInner {
+                graph: None,
+                codes: DashMap::new(),
+                id_to_idx: DashMap::new(),
+                idx_to_id: DashMap::new(),
+                next_idx: 0,
+                tombstones: HashSet::new(),
+                pending_repair: 0,
+                training: Vec::new(),
+            })),
+            config,
+            metric,
+            dimensions,
+            quantization,
+            compaction_threshold: DEFAULT_COMPACTION_THRESHOLD,
+        };
+        if let Some(codec) = codec {
+            index.inner.write().graph = Some(index.new_graph(Arc::new(codec)));
+        }
+        Ok(index)
+    }
+
+    fn new_graph(&self, codec: Arc<Codec>) -> CodeGraph {
+        CodeGraph::new(&self.config, self.dimensions, self.metric, codec)
+    }
+
+    /// Get configuration
+    pub fn config(&self) -> &HnswConfig {
+        &self.config
+    }
+
+    /// Quantization scheme used for the stored codes
+    pub fn quantization(&self) -> &QuantizationConfig {
+        &self.quantization
+    }
+
+    /// Whether vectors are currently stored as codes in the graph
+    ///
+    /// Always true for scalar and binary quantization; false for product
+    /// quantization until its codebooks have been trained.
+    pub fn is_trained(&self) -> bool {
+        self.inner.read().graph.is_some()
+    }
+
+    /// Set the tombstone ratio above which the index reports that it needs compaction
+    pub fn set_compaction_threshold(&mut self, threshold: f64) {
+        self.compaction_threshold = threshold;
+    }
+
+    /// Number of deleted vectors still present as nodes in the graph
+    pub fn tombstone_count(&self) -> usize {
+        self.inner.read().tombstones.len()
+    }
+
+    /// Bytes used by the stored codes, excluding graph links
+    pub fn code_bytes(&self) -> usize {
+        self.inner
+            .read()
+            .codes
+            .iter()
+            .map(|entry| entry.value().len())
+            .sum()
+    }
+
+    /// Rebuild the graph from live codes only, dropping every tombstone
+    ///
+    /// The new graph is built under a read lock only, so searches and writes
+    /// continue meanwhile. Returns the number of tombstones that were
+    /// reclaimed.
+    pub fn compact(&self) -> Result<usize> {
+        self.compaction_task().map_or(Ok(0), |task| task())
+    }
+
+    /// Snapshot the live codes now and rebuild from them when run
+    fn compaction_task(&self) -> Option<CompactionTask> {
+        let (live, next_idx, codec) = live_codes(&self.inner.read())?;
+        let inner = Arc::clone(&self.inner);
+        let config = self.config.clone();
+        let (dimensions, metric) = (self.dimensions, self.metric);
+        Some(Box::new(move || {
+            compact_codes(&inner, live, next_idx, codec, |codec| {
+                CodeGraph::new(&config, dimensions, metric, codec)
+            })
+        }))
+    }
+
+    /// Encode the graph topology, codes, codebooks and id mappings
+    pub fn export_graph(&self) -> Result<Vec<u8>> {
+        let inner = self.inner.read();
+
+        let (entry_point, points, codebooks) = match &inner.graph {
+            Some(graph) => {
+                let (entry_point, points) = export_points(&graph.hnsw);
+                let codebooks = match graph.codec.as_ref() {
+                    Codec::Product(pq) => Some(pq.clone()),
+                    _ => None,
+                };
+                (entry_point, points, codebooks)
+            }
+            None => (None, Vec::new(), None),
+        };
+
+        let state = QuantizedGraphState {
+            config: self.config.clone(),
+            dimensions: self.dimensions,
+            metric: self.metric,
+            quantization: self.quantization.clone(),
+            codebooks,
+            next_idx: inner.next_idx,
+            entry_point,
+            points,
+            ids: inner
+                .idx_to_id
+                .iter()
+                .map(|entry| (*entry.key(), entry.value().clone()))
+                .collect(),
+            training: inner.training.clone(),
+        };
+
+        bincode::serde::encode_to_vec(&state, bincode::config::standard()).map_err(|e| {
+            RuvectorError::SerializationError(format!("Failed to encode quantized graph: {}", e))
+        })
+    }
+
+    /// Restore an index from bytes produced by [`QuantizedHnswIndex::export_graph`]
+    pub fn from_graph(bytes: &[u8]) -> Result<Self> {
+        let (state, _): (QuantizedGraphState, usize) =
+            bincode::serde::decode_from_slice(bytes, bincode::config::standard()).map_err(|e| {
+                RuvectorError::SerializationError(format!(
+                    "Failed to decode quantized graph: {}",
+                    e
+                ))
+            })?;
+
+        let index = Self::new(
+            state.dimensions,
+            state.metric,
+            state.config,
+            state.quantization,
+        )?;
+        {
+            let mut inner = index.inner.write();
+            let codec = match (state.codebooks, &inner.graph) {
+                (Some(pq), _) => Some(Arc::new(Codec::Product(pq))),
+                (None, Some(graph)) => Some(Arc::clone(&graph.codec)),
+                (None, None) => None,
+            };
+            if let Some(codec) = codec {
+                let idx_to_id: DashMap<usize, VectorId> = state.ids.into_iter().collect();
+                for point in &state.points {
+                    match idx_to_id.get(&point.idx()) {
+                        Some(id) => {
+                            inner.codes.insert(id.clone(), point.data().to_vec());
+                        }
+                        None => {
+                            inner.tombstones.insert(point.idx());
+                        }
+                    }
+                }
+                let hnsw = import_points(
+                    &index.config,
+                    index.dimensions,
+                    CodeDistance {
+                        codec: Arc::clone(&codec),
+                        metric: index.metric,
+                        dimensions: index.dimensions,
+                    },
+                    state.entry_point,
+                    state.points,
+                )?;
+                inner.graph = Some(CodeGraph { hnsw, codec });
+                inner.id_to_idx = idx_to_id
+                    .iter()
+                    .map(|entry| (entry.value().clone(), *entry.key()))
+                    .collect();
+                inner.idx_to_id = idx_to_id;
+                inner.pending_repair = inner.tombstones.len();
+            }
+            inner.next_idx = state.next_idx;
+            inner.training = state.training;
+        }
+        Ok(index)
+    }
+
+    /// Set the default efSearch parameter for query-time accuracy tuning
+    pub fn set_ef_search(&mut self, ef_search: usize) {
+        self.config.ef_search = ef_search;
+    }
+
+    fn check_dimensions(&self, vector: &[f32]) -> Result<()> {
+        if vector.len() != self.dimensions {
+            return Err(RuvectorError::DimensionMismatch {
+                expected: self.dimensions,
+                actual: vector.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Encode and link a vector into a trained graph
+    fn insert_code(inner: &mut QuantizedInner, id: VectorId, vector: &[f32]) {
+        let Some(graph) = &inner.graph else {
+            return;
+        };
+        let code = graph.codec.encode(vector);
+        let idx = inner.next_idx;
+        inner.next_idx += 1;
+
+        graph.hnsw.insert_slice((code.as_slice(), idx));
+        inner.codes.insert(id.clone(), code);
+        inner.id_to_idx.insert(id.clone(), idx);
+        inner.idx_to_id.insert(idx, id);
+    }
+
+    /// Encode and insert many vectors into a graph that already has a codec
+    fn insert_codes(
+        inner: &mut QuantizedInner,
+        entries: Vec<(VectorId, Vec<f32>)>,
+        progress: &mut dyn FnMut(usize, usize),
+    ) {
+        let Some(graph) = &inner.graph else {
+            return;
+        };
+        let codes: Vec<Vec<u8>> = entries
+            .iter()
+            .map(|(_, vector)| graph.codec.encode(vector))
+            .collect();
+        let first_idx = inner.next_idx;
+        inner.next_idx += entries.len();
+
+        let points: Vec<_> = codes
+            .iter()
+            .enumerate()
+            .map(|(i, code)| (code.as_slice(), first_idx + i))
+            .collect();
+        insert_points(&graph.hnsw, &points, progress);
+
+        for (i, ((id, _), code)) in entries.into_iter().zip(codes).enumerate() {
+            let idx = first_idx + i;
+            inner.codes.insert(id.clone(), code);
+            inner.id_to_idx.insert(id.clone(), idx);
+            inner.idx_to_id.insert(idx, id);
+        }
+    }
+
+    /// Train product quantization once enough vectors are buffered and move
+    /// the buffer into the graph
+    fn maybe_train(&self, inner: &mut QuantizedInner) -> Result<()> {
+        let QuantizationConfig::Product { subspaces, k } = self.quantization else {
+            return Ok(());
+        };
+        if inner.graph.is_some() || inner.training.len() < k * PQ_TRAINING_VECTORS_PER_CENTROID {
+            return Ok(());
+        }
+
+        let samples: Vec<Vec<f32>> = inner.training.iter().map(|(_, v)| v.clone()).collect();
+        let pq = ProductQuantized::train(&samples, subspaces, k, PQ_TRAINING_ITERATIONS)?;
+        inner.graph = Some(self.new_graph(Arc::new(Codec::Product(pq))));
+
+        let buffered = std::mem::take(&mut inner.training);
+        tracing::info!(
+            "Trained product quantization on {} vectors ({} subspaces, {} centroids)",
+            buffered.len(),
+            subspaces,
+            k
+        );
+        Self::insert_codes(inner, buffered, &mut |_, _| {});
+        Ok(())
+    }
+}
+
+impl VectorIndex for QuantizedHnswIndex {
+    fn add(&mut self, id: VectorId, vector: Vec<f32>) -> Result<()> {
+        self.check_dimensions(&vector)?;
+        self.remove(&id)?;
+
+        let mut inner = self.inner.write();
+        if inner.graph.is_some() {
+            Self::insert_code(&mut inner, id, &vector);
+            Ok(())
+        } else {
+            inner.training.push((id, vector));
+            self.maybe_train(&mut inner)
+        }
+    }
+
+    fn add_batch(&mut self, entries: Vec<(VectorId, Vec<f32>)>) -> Result<()> {
+        self.add_batch_with_progress(entries, &mut |_, _| {})
+    }
+
+    fn add_batch_with_progress(
+        &mut self,
+        entries: Vec<(VectorId, Vec<f32>)>,
+        progress: &mut dyn FnMut(usize, usize),
+    ) -> Result<()> {
+        for (_, vector) in &entries {
+            self.check_dimensions(vector)?;
+        }
+
+        let entries = dedup_batch(entries);
+        for (id, _) in &entries {
+            self.remove(id)?;
+        }
+
+        let total = entries.len();
+        let mut inner = self.inner.write();
+        let mut entries = entries.into_iter();
+
+        // Buffer for product quantization training until the codebooks exist
+        while inner.graph.is_none() {
+            let Some(entry) = entries.next() else {
+                progress(total, total);
+                return Ok(());
+            };
+            inner.training.push(entry);
+            self.maybe_train(&mut inner)?;
+        }
+
+        let remaining: Vec<_> = entries.collect();
+        let buffered = total - remaining.len();
+        Self::insert_codes(&mut inner, remaining, &mut |inserted, _| {
+            progress(buffered + inserted, total)
+        });
+        Ok(())
+    }
+
+    fn search(&self, query: &[f32], k: usize) -> Result<Vec<SearchResult>> {
+        self.search_filtered(query, k, &SearchParams::default(), &|_| true)
+    }
+
+    fn search_with_params(
+        &self,
+        query: &[f32],
+        k: usize,
+        params: &SearchParams,
+    ) -> Result<Vec<SearchResult>> {
+        self.search_filtered(query, k, params, &|_| true)
+    }
+
+    fn search_filtered(
+        &self,
+        query: &[f32],
+        k: usize,
+        params: &SearchParams,
+        filter: &(dyn Fn(&VectorId) -> bool + Sync),
+    ) -> Result<Vec<SearchResult>> {
+        self.check_dimensions(query)?;
+
+        let inner = self.inner.read();
+        let mut results = Vec::new();
+
+        if let Some(graph) = &inner.graph {
+            let accept = |idx: &usize| inner.idx_to_id.get(idx).is_some_and(|id| filter(&id));
+            let ef_search = params.ef_search.unwrap_or(self.config.ef_search).max(k);
+            let neighbors =
+                graph
+                    .hnsw
+                    .search_filter(query_bytes(query), k, ef_search, Some(&accept));
+            results.extend(neighbors.into_iter().filter_map(|neighbor| {
+                inner.idx_to_id.get(&neighbor.d_id).map(|id| SearchResult {
+                    id: id.clone(),
+                    score: neighbor.distance,
+                    vector: None,
+                    metadata: None,
+                })
+            }));
+        }
+
+        for (id, vector) in inner.training.iter().filter(|(id, _)| filter(id)) {
+            results.push(SearchResult {
+                id: id.clone(),
+                score: distance(query, vector, self.metric)?,
+                vector: None,
+                metadata: None,
+            });
+        }
+
+        results.sort_by(|a, b| {
+            a.score
+                .partial_cmp(&b.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        results.truncate(k);
+        Ok(results)
+    }
+
+    fn needs_rescoring(&self) -> bool {
+        true
+    }
+
+    fn set_ef_search(&mut self, ef_search: usize) {
+        QuantizedHnswIndex::set_ef_search(self, ef_search);
+    }
+
+    fn export_graph(&self) -> Result<Option<Vec<u8>>> {
+        QuantizedHnswIndex::export_graph(self).map(Some)
+    }
+
+    fn graph_stats(&self) -> Option<GraphStats> {
+        let inner = self.inner.read();
+        Some(GraphStats {
+            // Vectors buffered for codebook training aren't in a graph yet
+            nodes: inner.graph.as_ref().map_or(0, |g| g.hnsw.get_nb_point()),
+            tombstones: inner.tombstones.len(),
+            ef_search: self.config.ef_search,
+        })
+    }
+
+    fn needs_compaction(&self) -> bool {
+        self.inner.read().tombstone_ratio() > self.compaction_threshold
+    }
+
+    fn compaction(&self) -> Option<CompactionTask> {
+        self.compaction_task()
+    }
+
+    fn remove(&mut self, id: &VectorId) -> Result<bool> {
+        let mut inner = self.inner.write();
+
+        if let Some(pos) = inner
+            .training
+            .iter()
+            .position(|(existing, _)| existing == id)
+        {
+            inner.training.swap_remove(pos);
+            return Ok(true);
+        }
+
+        if inner.codes.remove(id).is_none() {
+            return Ok(false);
+        }
+        if let Some((_, idx)) = inner.id_to_idx.remove(id) {
+            inner.idx_to_id.remove(&idx);
+            inner.tombstones.insert(idx);
+            inner.pending_repair += 1;
+        }
+
+        if inner.pending_repair >= MIN_REPAIR_BATCH.max(inner.codes.len() / 100) {
+            inner.repair();
+        }
+
+        Ok(true)
+    }
+
+    fn contains(&self, id: &VectorId) -> bool {
+        let inner = self.inner.read();
+        inner.codes.contains_key(id) || inner.training.iter().any(|(existing, _)| existing == id)
+    }
+
+    fn len(&self) -> usize {
+        let inner = self.inner.read();
+        inner.codes.len() + inner.training.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    fn random_vectors(count: usize, dimensions: usize) -> Vec<Vec<f32>> {
+        let mut rng = rand::thread_rng();
+        (0..count)
+            .map(|_| (0..dimensions).map(|_| rng.gen_range(-1.0..1.0)).collect())
+            .collect()
+    }
+
+    fn small_config() -> HnswConfig {
+        HnswConfig {
+            m: 16,
+            ef_construction: 100,
+            ef_search: 64,
+            max_elements: 10_000,
+        }
+    }
+
+    #[test]
+    fn test_scalar_codes_are_compact() -> Result<()> {
+        let dimensions = 64;
+        let mut index = QuantizedHnswIndex::new(
+            dimensions,
+            DistanceMetric::Euclidean,
+            small_config(),
+            QuantizationConfig::Scalar,
+        )?;
+        for (i, vector) in random_vectors(200, dimensions).into_iter().enumerate() {
+            index.add(format!("v{}", i), vector)?;
+        }
+
+        assert_eq!(index.len(), 200);
+        assert_eq!(index.code_bytes(), 200 * (dimensions + SCALAR_HEADER_BYTES));
+        Ok(())
+    }
+
+    #[test]
+    fn test_quantized_compaction_is_explicit() -> Result<()> {
+        let vectors = random_vectors(100, 32);
+        let mut index = QuantizedHnswIndex::new(
+            32,
+            DistanceMetric::Euclidean,
+            small_config(),
+            QuantizationConfig::Scalar,
+        )?;
+        for (i, vector) in vectors.iter().enumerate() {
+            index.add(format!("v{}", i), vector.clone())?;
+        }
+        for i in 0..40 {
+            index.remove(&format!("v{}", i))?;
+        }
+        assert_eq!(index.tombstone_count(), 40);
+        assert!(index.needs_compaction());
+
+        let task = index.compaction().unwrap();
+        index.add("v_new".to_string(), vectors[0].clone())?;
+        assert_eq!(task()?, 40);
+        assert_eq!(index.tombstone_count(), 0);
+        assert_eq!(index.len(), 61);
+        assert_eq!(index.search(&vectors[0], 1)?[0].id, "v_new");
+        assert_eq!(index.search(&vectors[70], 1)?[0].id, "v70");
+        Ok(())
+    }
+
+    #[test]
+    fn test_quantized_search_finds_query_vector() -> Result<()> {
+        for quantization in [QuantizationConfig::Scalar, QuantizationConfig::Binary] {
+            let vectors = random_vectors(300, 32);
+            let mut index = QuantizedHnswIndex::new(
+                32,
+                DistanceMetric::Cosine,
+                small_config(),
+                quantization.clone(),
+            )?;
+            index.add_batch(
+                vectors
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| (format!("v{}", i), v.clone()))
+                    .collect(),
+            )?;
+
+            let results = index.search(&vectors[42], 10)?;
+            assert_eq!(results.len(), 10);
+            assert!(
+                results.iter().any(|r| r.id == "v42"),
+                "{:?} lost the query vector",
+                quantization
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_product_quantization_trains_lazily() -> Result<()> {
+        let quantization = QuantizationConfig::Product {
+            subspaces: 4,
+            k: 16,
+        };
+        let mut index =
+            QuantizedHnswIndex::new(16, DistanceMetric::Euclidean, small_config(), quantization)?;
+        let vectors = random_vectors(100, 16);
+
+        for (i, vector) in vectors.iter().take(63).enumerate() {
+            index.add(format!("v{}", i), vector.clone())?;
+        }
+        assert!(!index.is_trained());
+        assert_eq!(index.search(&vectors[5], 1)?[0].id, "v5");
+
+        for (i, vector) in vectors.iter().enumerate().skip(63) {
+            index.add(format!("v{}", i), vector.clone())?;
+        }
+        assert!(index.is_trained());
+        assert_eq!(index.len(), 100);
+        assert_eq!(index.code_bytes(), 100 * 4);
+
+        assert!(index.remove(&"v5".to_string())?);
+        let results = index.search(&vectors[5], 10)?;
+        assert!(results.iter().all(|r| r.id != "v5"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_batch_insert_trains_and_reports_progress() -> Result<()> {
+        let quantization = QuantizationConfig::Product {
+            subspaces: 4,
+            k: 16,
+        };
+        let mut index =
+            QuantizedHnswIndex::new(16, DistanceMetric::Euclidean, small_config(), quantization)?;
+        let vectors = random_vectors(300, 16);
+        let entries: Vec<_> = vectors
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (format!("v{}", i), v.clone()))
+            .collect();
+
+        let mut last = (0, 0);
+        index.add_batch_with_progress(entries, &mut |inserted, total| {
+            assert!(inserted >= last.0);
+            last = (inserted, total);
+        })?;
+        assert_eq!(last, (300, 300));
+        assert!(index.is_trained());
+        assert_eq!(index.len(), 300);
+        assert_eq!(index.code_bytes(), 300 * 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_quantized_graph_export_roundtrip() -> Result<()> {
+        let quantization = QuantizationConfig::Product {
+            subspaces: 4,
+            k: 16,
+        };
+        let mut index =
+            QuantizedHnswIndex::new(16, DistanceMetric::Euclidean, small_config(), quantization)?;
+        let vectors = random_vectors(120, 16);
+        for (i, vector) in vectors.iter().enumerate() {
+            index.add(format!("v{}", i), vector.clone())?;
+        }
+        index.remove(&"v0".to_string())?;
+
+        let restored = QuantizedHnswIndex::from_graph(&index.export_graph()?)?;
+        assert!(restored.is_trained());
+        assert_eq!(restored.len(), 119);
+        assert_eq!(restored.tombstone_count(), 1);
+        assert_eq!(restored.code_bytes(), index.code_bytes());
+
+        let original: Vec<_> = index
+            .search(&vectors[9], 5)?
+            .into_iter()
+            .map(|r| r.id)
+            .collect();
+        let reloaded: Vec<_> = restored
+            .search(&vectors[9], 5)?
+            .into_iter()
+            .map(|r| r.id)
+            .collect();
+        assert_eq!(original, reloaded);
+        Ok(())
+    }
+
+    #[test]
+    fn test_product_quantization_rejects_uneven_subspaces() {
+        let quantization = QuantizationConfig::Product {
+            subspaces: 5,
+            k: 16,
+        };
+        assert!(QuantizedHnswIndex::new(
+            16,
+            DistanceMetric::Euclidean,
+            small_config(),
+            quantization
+        )
+        .is_err());
+    }
+}
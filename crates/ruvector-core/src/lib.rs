@@ -106,7 +106,10 @@ const _: () = {
 };
 
 pub use error::{Result, RuvectorError};
-pub use types::{DistanceMetric, SearchQuery, SearchResult, VectorEntry, VectorId};
+pub use types::{
+    DistanceMetric, RecommendRequest, ScrollPage, ScrollRequest, SearchQuery, SearchResult,
+    VectorEntry, VectorId,
+};
 pub use vector_db::VectorDB;
 
 // Quantization types (ADR-001)
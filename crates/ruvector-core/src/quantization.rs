@@ -186,6 +186,15 @@ impl ProductQuantized {
 
         codes
     }
+
+    /// Reconstruct an approximate vector from codes produced by `encode`
+    pub fn decode(&self, codes: &[u8]) -> Vec<f32> {
+        codes
+            .iter()
+            .zip(&self.codebooks)
+            .flat_map(|(&code, codebook)| codebook[code as usize].iter().copied())
+            .collect()
+    }
 }
 
 /// Int4 quantization (8x compression)
@@ -6,9 +6,9 @@
 #[cfg(feature = "storage")]
 use crate::error::{Result, RuvectorError};
 #[cfg(feature = "storage")]
-use crate::types::{DbOptions, VectorEntry, VectorId};
+use crate::types::{DbOptions, ScrollPage, ScrollPoint, VectorEntry, VectorId};
 #[cfg(feature = "storage")]
-use bincode::config;
+use bincode::{config, Decode, Encode};
 #[cfg(feature = "storage")]
 use once_cell::sync::Lazy;
 #[cfg(feature = "storage")]
@@ -20,27 +20,48 @@ use serde_json;
 #[cfg(feature = "storage")]
 use std::collections::HashMap;
 #[cfg(feature = "storage")]
+use std::ops::Bound;
+#[cfg(feature = "storage")]
 use std::path::{Path, PathBuf};
 #[cfg(feature = "storage")]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "storage")]
 use std::sync::Arc;
 
 #[cfg(feature = "storage")]
 const VECTORS_TABLE: TableDefinition<&str, &[u8]> = TableDefinition::new("vectors");
 const METADATA_TABLE: TableDefinition<&str, &str> = TableDefinition::new("metadata");
 const CONFIG_TABLE: TableDefinition<&str, &str> = TableDefinition::new("config");
+const INDEX_WAL_TABLE: TableDefinition<u64, &[u8]> = TableDefinition::new("index_wal");
 
 /// Key used to store database configuration in CONFIG_TABLE
 const DB_CONFIG_KEY: &str = "__ruvector_db_config__";
 
+/// Key in CONFIG_TABLE holding the last truncated index WAL sequence, so
+/// sequence numbers keep increasing after the log is emptied
+const INDEX_WAL_FLOOR_KEY: &str = "__ruvector_index_wal_floor__";
+
 // Global database connection pool to allow multiple VectorDB instances
 // to share the same underlying database file
 static DB_POOL: Lazy<Mutex<HashMap<PathBuf, Arc<Database>>>> =
     Lazy::new(|| Mutex::new(HashMap::new()));
 
+/// Index mutation journaled in the same transaction as the vector write, so
+/// that a persisted index graph can be brought up to date when reopened
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub enum IndexWalOp {
+    /// The vector stored under this id was inserted or replaced
+    Insert(VectorId),
+    /// The vector stored under this id was deleted
+    Delete(VectorId),
+}
+
 /// Storage backend for vector database
 pub struct VectorStorage {
     db: Arc<Database>,
     dimensions: usize,
+    /// Whether writes append to the index write-ahead log
+    index_wal: AtomicBool,
 }
 
 impl VectorStorage {
@@ -116,6 +137,7 @@ impl VectorStorage {
                     let _ = write_txn.open_table(VECTORS_TABLE)?;
                     let _ = write_txn.open_table(METADATA_TABLE)?;
                     let _ = write_txn.open_table(CONFIG_TABLE)?;
+                    let _ = write_txn.open_table(INDEX_WAL_TABLE)?;
                 }
                 write_txn.commit()?;
 
@@ -124,7 +146,104 @@ impl VectorStorage {
             }
         };
 
-        Ok(Self { db, dimensions })
+        Ok(Self {
+            db,
+            dimensions,
+            index_wal: AtomicBool::new(false),
+        })
+    }
+
+    /// Start journaling inserts and deletes to the index write-ahead log
+    ///
+    /// Only useful when the index graph is persisted separately; otherwise
+    /// the log would grow without ever being truncated.
+    pub fn enable_index_wal(&self) {
+        self.index_wal.store(true, Ordering::Release);
+    }
+
+    /// Append index operations within an open write transaction
+    fn append_index_wal(
+        &self,
+        txn: &redb::WriteTransaction,
+        ops: impl IntoIterator<Item = IndexWalOp>,
+    ) -> Result<()> {
+        if !self.index_wal.load(Ordering::Acquire) {
+            return Ok(());
+        }
+        let mut seq = Self::index_wal_floor(&txn.open_table(CONFIG_TABLE)?)?;
+        let mut table = txn.open_table(INDEX_WAL_TABLE)?;
+        if let Some((key, _)) = table.last()? {
+            seq = seq.max(key.value());
+        }
+        for op in ops {
+            seq += 1;
+            let bytes = bincode::encode_to_vec(&op, config::standard())
+                .map_err(|e| RuvectorError::SerializationError(e.to_string()))?;
+            table.insert(seq, bytes.as_slice())?;
+        }
+        Ok(())
+    }
+
+    /// Last truncated index WAL sequence number (0 if never truncated)
+    fn index_wal_floor(table: &impl ReadableTable<&'static str, &'static str>) -> Result<u64> {
+        let Some(floor) = table.get(INDEX_WAL_FLOOR_KEY)? else {
+            return Ok(0);
+        };
+        floor
+            .value()
+            .parse()
+            .map_err(|e: std::num::ParseIntError| RuvectorError::SerializationError(e.to_string()))
+    }
+
+    /// Sequence number of the last journaled index operation (0 if none)
+    ///
+    /// Numbers are never reused: after the log is truncated, the head stays
+    /// at the last truncated operation until the next one is appended.
+    pub fn index_wal_head(&self) -> Result<u64> {
+        let read_txn = self.db.begin_read()?;
+        let floor = match read_txn.open_table(CONFIG_TABLE) {
+            Ok(t) => Self::index_wal_floor(&t)?,
+            Err(_) => 0,
+        };
+        let table = match read_txn.open_table(INDEX_WAL_TABLE) {
+            Ok(t) => t,
+            Err(_) => return Ok(floor),
+        };
+        let head = table.last()?.map(|(key, _)| key.value()).unwrap_or(0);
+        Ok(head.max(floor))
+    }
+
+    /// Journaled index operations with a sequence number greater than `after`
+    pub fn index_wal_since(&self, after: u64) -> Result<Vec<(u64, IndexWalOp)>> {
+        let read_txn = self.db.begin_read()?;
+        let table = match read_txn.open_table(INDEX_WAL_TABLE) {
+            Ok(t) => t,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        let mut ops = Vec::new();
+        for item in table.range(after.saturating_add(1)..)? {
+            let (key, value) = item?;
+            let (op, _): (IndexWalOp, usize) =
+                bincode::decode_from_slice(value.value(), config::standard())
+                    .map_err(|e| RuvectorError::SerializationError(e.to_string()))?;
+            ops.push((key.value(), op));
+        }
+        Ok(ops)
+    }
+
+    /// Drop journaled index operations up to and including `through`
+    pub fn truncate_index_wal(&self, through: u64) -> Result<()> {
+        let write_txn = self.db.begin_write()?;
+        {
+            let mut table = write_txn.open_table(INDEX_WAL_TABLE)?;
+            table.retain_in(..=through, |_, _| false)?;
+            let mut config = write_txn.open_table(CONFIG_TABLE)?;
+            let floor = Self::index_wal_floor(&config)?.max(through);
+            config.insert(INDEX_WAL_FLOOR_KEY, floor.to_string().as_str())?;
+        }
+        write_txn.commit()?;
+        Ok(())
     }
 
     /// Insert a vector entry
@@ -159,6 +278,7 @@ impl VectorStorage {
                 meta_table.insert(id.as_str(), metadata_json.as_str())?;
             }
         }
+        self.append_index_wal(&write_txn, [IndexWalOp::Insert(id.clone())])?;
         write_txn.commit()?;
 
         Ok(id)
@@ -202,10 +322,120 @@ impl VectorStorage {
             }
         }
 
+        self.append_index_wal(&write_txn, ids.iter().cloned().map(IndexWalOp::Insert))?;
         write_txn.commit()?;
         Ok(ids)
     }
 
+    /// Insert or replace multiple vectors in a single transaction
+    ///
+    /// Unlike [`VectorStorage::insert_batch`], an entry without metadata
+    /// clears any metadata previously stored under its id. Returns each id
+    /// with the vector it replaced, if any.
+    pub fn upsert_batch(
+        &self,
+        entries: &[VectorEntry],
+    ) -> Result<Vec<(VectorId, Option<Vec<f32>>)>> {
+        let write_txn = self.db.begin_write()?;
+        let mut upserted = Vec::with_capacity(entries.len());
+        let mut changed = Vec::new();
+
+        {
+            let mut table = write_txn.open_table(VECTORS_TABLE)?;
+            let mut meta_table = write_txn.open_table(METADATA_TABLE)?;
+
+            for entry in entries {
+                if entry.vector.len() != self.dimensions {
+                    return Err(RuvectorError::DimensionMismatch {
+                        expected: self.dimensions,
+                        actual: entry.vector.len(),
+                    });
+                }
+
+                let id = entry
+                    .id
+                    .clone()
+                    .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+                let vector_data = bincode::encode_to_vec(&entry.vector, config::standard())
+                    .map_err(|e| RuvectorError::SerializationError(e.to_string()))?;
+                let previous = match table.insert(id.as_str(), vector_data.as_slice())? {
+                    Some(old) => Some(
+                        bincode::decode_from_slice::<Vec<f32>, _>(old.value(), config::standard())
+                            .map_err(|e| RuvectorError::SerializationError(e.to_string()))?
+                            .0,
+                    ),
+                    None => None,
+                };
+
+                match &entry.metadata {
+                    Some(metadata) => {
+                        let metadata_json = serde_json::to_string(metadata)
+                            .map_err(|e| RuvectorError::SerializationError(e.to_string()))?;
+                        meta_table.insert(id.as_str(), metadata_json.as_str())?;
+                    }
+                    None => {
+                        meta_table.remove(id.as_str())?;
+                    }
+                }
+
+                // Payload-only changes leave the index untouched
+                if previous.as_ref() != Some(&entry.vector) {
+                    changed.push(IndexWalOp::Insert(id.clone()));
+                }
+                upserted.push((id, previous));
+            }
+        }
+
+        self.append_index_wal(&write_txn, changed)?;
+        write_txn.commit()?;
+        Ok(upserted)
+    }
+
+    /// Read, modify and write back the metadata of a stored vector in one
+    /// transaction
+    ///
+    /// Returns the updated metadata, or `None` when no vector is stored
+    /// under `id`. Metadata left empty by `update` is removed.
+    pub fn update_metadata<F>(
+        &self,
+        id: &str,
+        update: F,
+    ) -> Result<Option<HashMap<String, serde_json::Value>>>
+    where
+        F: FnOnce(&mut HashMap<String, serde_json::Value>),
+    {
+        let write_txn = self.db.begin_write()?;
+        let metadata;
+
+        {
+            let table = write_txn.open_table(VECTORS_TABLE)?;
+            if table.get(id)?.is_none() {
+                return Ok(None);
+            }
+
+            let mut meta_table = write_txn.open_table(METADATA_TABLE)?;
+            let mut current: HashMap<String, serde_json::Value> = match meta_table.get(id)? {
+                Some(meta_data) => serde_json::from_str(meta_data.value())
+                    .map_err(|e| RuvectorError::SerializationError(e.to_string()))?,
+                None => HashMap::new(),
+            };
+            update(&mut current);
+
+            if current.is_empty() {
+                meta_table.remove(id)?;
+            } else {
+                let metadata_json = serde_json::to_string(&current)
+                    .map_err(|e| RuvectorError::SerializationError(e.to_string()))?;
+                meta_table.insert(id, metadata_json.as_str())?;
+            }
+            metadata = current;
+        }
+
+        write_txn.commit()?;
+        Ok(Some(metadata))
+    }
+
     /// Get a vector by ID
     pub fn get(&self, id: &str) -> Result<Option<VectorEntry>> {
         let read_txn = self.db.begin_read()?;
@@ -251,10 +481,103 @@ impl VectorStorage {
             let _ = meta_table.remove(id)?;
         }
 
+        if deleted {
+            self.append_index_wal(&write_txn, [IndexWalOp::Delete(id.to_string())])?;
+        }
         write_txn.commit()?;
         Ok(deleted)
     }
 
+    /// Delete multiple vectors in a single transaction
+    ///
+    /// Returns the ids that were actually stored.
+    pub fn delete_batch(&self, ids: &[VectorId]) -> Result<Vec<VectorId>> {
+        let write_txn = self.db.begin_write()?;
+        let mut deleted = Vec::new();
+
+        {
+            let mut table = write_txn.open_table(VECTORS_TABLE)?;
+            let mut meta_table = write_txn.open_table(METADATA_TABLE)?;
+            for id in ids {
+                if table.remove(id.as_str())?.is_some() {
+                    deleted.push(id.clone());
+                }
+                let _ = meta_table.remove(id.as_str())?;
+            }
+        }
+
+        self.append_index_wal(&write_txn, deleted.iter().cloned().map(IndexWalOp::Delete))?;
+        write_txn.commit()?;
+        Ok(deleted)
+    }
+
+    /// Page through stored vectors in id order
+    ///
+    /// Scans the vectors stored after `after`, keeping those whose metadata
+    /// `keep` accepts until `limit` have been collected. The page's
+    /// `next_offset` is set whenever the scan stopped before the end.
+    pub fn scroll<F>(
+        &self,
+        after: Option<&str>,
+        limit: usize,
+        with_vectors: bool,
+        mut keep: F,
+    ) -> Result<ScrollPage>
+    where
+        F: FnMut(Option<&HashMap<String, serde_json::Value>>) -> bool,
+    {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(VECTORS_TABLE)?;
+        let meta_table = read_txn.open_table(METADATA_TABLE)?;
+
+        let start = match after {
+            Some(after) => Bound::Excluded(after),
+            None => Bound::Unbounded,
+        };
+        let mut points = Vec::with_capacity(limit.min(1024));
+        let mut next_offset = None;
+
+        for item in table.range::<&str>((start, Bound::Unbounded))? {
+            let (key, vector_data) = item?;
+            let id = key.value();
+            if points.len() == limit {
+                // Stop only once another entry is known to exist
+                next_offset = points.last().map(|p: &ScrollPoint| p.id.clone());
+                break;
+            }
+
+            let metadata: Option<HashMap<String, serde_json::Value>> = match meta_table.get(id)? {
+                Some(meta_data) => Some(
+                    serde_json::from_str(meta_data.value())
+                        .map_err(|e| RuvectorError::SerializationError(e.to_string()))?,
+                ),
+                None => None,
+            };
+            if !keep(metadata.as_ref()) {
+                continue;
+            }
+
+            let vector = if with_vectors {
+                let (vector, _): (Vec<f32>, usize) =
+                    bincode::decode_from_slice(vector_data.value(), config::standard())
+                        .map_err(|e| RuvectorError::SerializationError(e.to_string()))?;
+                Some(vector)
+            } else {
+                None
+            };
+            points.push(ScrollPoint {
+                id: id.to_string(),
+                vector,
+                metadata,
+            });
+        }
+
+        Ok(ScrollPage {
+            points,
+            next_offset,
+        })
+    }
+
     /// Get the number of vectors stored
     pub fn len(&self) -> Result<usize> {
         let read_txn = self.db.begin_read()?;
@@ -4,9 +4,10 @@
 //! making it suitable for WebAssembly environments.
 
 use crate::error::{Result, RuvectorError};
-use crate::types::{VectorEntry, VectorId};
+use crate::types::{ScrollPage, ScrollPoint, VectorEntry, VectorId};
 use dashmap::DashMap;
 use serde_json::Value as JsonValue;
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
 
 /// In-memory storage backend using DashMap for thread-safe concurrent access
@@ -98,6 +99,92 @@ impl MemoryStorage {
         Ok(ids)
     }
 
+    /// Insert or replace multiple vectors
+    ///
+    /// An entry without metadata clears any metadata previously stored under
+    /// its id. Returns each id with the vector it replaced, if any.
+    pub fn upsert_batch(
+        &self,
+        entries: &[VectorEntry],
+    ) -> Result<Vec<(VectorId, Option<Vec<f32>>)>> {
+        if let Some(entry) = entries.iter().find(|e| e.vector.len() != self.dimensions) {
+            return Err(RuvectorError::DimensionMismatch {
+                expected: self.dimensions,
+                actual: entry.vector.len(),
+            });
+        }
+
+        let mut upserted = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let id = entry.id.clone().unwrap_or_else(|| self.generate_id());
+            let previous = self.vectors.insert(id.clone(), entry.vector.clone());
+
+            match &entry.metadata {
+                Some(metadata) => {
+                    self.metadata.insert(
+                        id.clone(),
+                        serde_json::Value::Object(
+                            metadata
+                                .iter()
+                                .map(|(k, v)| (k.clone(), v.clone()))
+                                .collect(),
+                        ),
+                    );
+                }
+                None => {
+                    self.metadata.remove(&id);
+                }
+            }
+
+            upserted.push((id, previous));
+        }
+
+        Ok(upserted)
+    }
+
+    /// Read, modify and write back the metadata of a stored vector
+    ///
+    /// Returns the updated metadata, or `None` when no vector is stored
+    /// under `id`. Metadata left empty by `update` is removed.
+    pub fn update_metadata<F>(
+        &self,
+        id: &str,
+        update: F,
+    ) -> Result<Option<HashMap<String, JsonValue>>>
+    where
+        F: FnOnce(&mut HashMap<String, JsonValue>),
+    {
+        // Hold the vector entry so a concurrent delete can't interleave
+        let Some(_vector) = self.vectors.get(id) else {
+            return Ok(None);
+        };
+
+        let mut current: HashMap<String, JsonValue> = match self.metadata.get(id) {
+            Some(m) => match m.value() {
+                JsonValue::Object(map) => map.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+                _ => HashMap::new(),
+            },
+            None => HashMap::new(),
+        };
+        update(&mut current);
+
+        if current.is_empty() {
+            self.metadata.remove(id);
+        } else {
+            self.metadata.insert(
+                id.to_string(),
+                JsonValue::Object(
+                    current
+                        .iter()
+                        .map(|(k, v)| (k.clone(), v.clone()))
+                        .collect(),
+                ),
+            );
+        }
+
+        Ok(Some(current))
+    }
+
     /// Get a vector by ID
     pub fn get(&self, id: &str) -> Result<Option<VectorEntry>> {
         if let Some(vector_ref) = self.vectors.get(id) {
@@ -127,6 +214,68 @@ impl MemoryStorage {
         Ok(vector_removed)
     }
 
+    /// Delete multiple vectors
+    ///
+    /// Returns the ids that were actually stored.
+    pub fn delete_batch(&self, ids: &[VectorId]) -> Result<Vec<VectorId>> {
+        let mut deleted = Vec::new();
+        for id in ids {
+            if self.delete(id)? {
+                deleted.push(id.clone());
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// Page through stored vectors in id order
+    ///
+    /// Scans the vectors stored after `after`, keeping those whose metadata
+    /// `keep` accepts until `limit` have been collected. The page's
+    /// `next_offset` is set whenever the scan stopped before the end.
+    pub fn scroll<F>(
+        &self,
+        after: Option<&str>,
+        limit: usize,
+        with_vectors: bool,
+        mut keep: F,
+    ) -> Result<ScrollPage>
+    where
+        F: FnMut(Option<&HashMap<String, JsonValue>>) -> bool,
+    {
+        let mut ids: Vec<String> = self
+            .vectors
+            .iter()
+            .map(|entry| entry.key().clone())
+            .filter(|id| after.map_or(true, |after| id.as_str() > after))
+            .collect();
+        ids.sort_unstable();
+
+        let mut points: Vec<ScrollPoint> = Vec::with_capacity(limit.min(ids.len()));
+        let mut next_offset = None;
+        for id in ids {
+            if points.len() == limit {
+                next_offset = points.last().map(|p| p.id.clone());
+                break;
+            }
+            let Some(entry) = self.get(&id)? else {
+                continue;
+            };
+            if !keep(entry.metadata.as_ref()) {
+                continue;
+            }
+            points.push(ScrollPoint {
+                id,
+                vector: with_vectors.then_some(entry.vector),
+                metadata: entry.metadata,
+            });
+        }
+
+        Ok(ScrollPage {
+            points,
+            next_offset,
+        })
+    }
+
     /// Get the number of vectors stored
     pub fn len(&self) -> Result<usize> {
         Ok(self.vectors.len())
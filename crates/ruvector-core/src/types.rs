@@ -1,5 +1,6 @@
 //! Core types and data structures
 
+use ruvector_filter::FilterExpression;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -56,6 +57,92 @@ pub struct SearchResult {
     pub metadata: Option<HashMap<String, serde_json::Value>>,
 }
 
+/// Recommendation query built from stored example vectors
+///
+/// Results are ranked against the mean of the `positive` vectors, pushed
+/// away from the mean of the `negative` ones. The examples themselves are
+/// never returned.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecommendRequest {
+    /// Ids of vectors the results should resemble
+    pub positive: Vec<VectorId>,
+    /// Ids of vectors the results should not resemble
+    #[serde(default)]
+    pub negative: Vec<VectorId>,
+    /// Number of results to return
+    pub k: usize,
+    /// Only recommend vectors whose metadata matches this filter
+    #[serde(default)]
+    pub filter: Option<FilterExpression>,
+    /// Optional HNSW efSearch override for this query
+    #[serde(default)]
+    pub ef_search: Option<usize>,
+}
+
+/// Cursor-based request for one page of stored vectors
+///
+/// Pages are returned in id order; pass the previous page's
+/// [`ScrollPage::next_offset`] as `offset` to continue. Each page is read
+/// separately, so a scroll does not see one snapshot of the whole collection.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrollRequest {
+    /// Only return vectors whose metadata matches this filter
+    #[serde(default)]
+    pub filter: Option<FilterExpression>,
+    /// Maximum number of vectors to return
+    #[serde(default = "default_scroll_limit")]
+    pub limit: usize,
+    /// Resume after this id (`None` starts from the beginning)
+    #[serde(default)]
+    pub offset: Option<VectorId>,
+    /// Include vector data in the page
+    #[serde(default)]
+    pub with_vectors: bool,
+    /// Include metadata in the page
+    #[serde(default = "default_with_payload")]
+    pub with_payload: bool,
+}
+
+fn default_scroll_limit() -> usize {
+    100
+}
+
+fn default_with_payload() -> bool {
+    true
+}
+
+impl Default for ScrollRequest {
+    fn default() -> Self {
+        Self {
+            filter: None,
+            limit: default_scroll_limit(),
+            offset: None,
+            with_vectors: false,
+            with_payload: default_with_payload(),
+        }
+    }
+}
+
+/// Stored vector returned by a scroll
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrollPoint {
+    /// Vector ID
+    pub id: VectorId,
+    /// Vector data (if requested)
+    pub vector: Option<Vec<f32>>,
+    /// Metadata (if requested and present)
+    pub metadata: Option<HashMap<String, serde_json::Value>>,
+}
+
+/// One page of a scroll over stored vectors
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrollPage {
+    /// Vectors in this page, in id order
+    pub points: Vec<ScrollPoint>,
+    /// Offset for the next page, or `None` when the scroll is complete
+    pub next_offset: Option<VectorId>,
+}
+
 /// Database configuration options
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DbOptions {
@@ -1,16 +1,32 @@
 //! Main VectorDB interface
 
-use crate::error::Result;
+use crate::advanced_features::FilterStrategy;
+use crate::distance::distance;
+use crate::error::{Result, RuvectorError};
 use crate::index::flat::FlatIndex;
 
 #[cfg(feature = "hnsw")]
 use crate::index::hnsw::HnswIndex;
+#[cfg(feature = "hnsw")]
+use crate::index::quantized_hnsw::QuantizedHnswIndex;
 
-use crate::index::VectorIndex;
+#[cfg(feature = "storage")]
+use crate::index::persistence::GraphPersistence;
+use crate::index::{GraphStats, SearchParams, VectorIndex};
 use crate::types::*;
-use parking_lot::RwLock;
+use parking_lot::{MutexGuard, RwLock};
+use ruvector_filter::{FilterEvaluator, FilterExpression, IndexType, PayloadIndexManager};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
+/// Candidates fetched per requested result when the index returns
+/// approximate (quantized) scores that are rescored at full precision
+const RESCORE_OVERSAMPLING: usize = 4;
+
+/// Page size used when scanning storage for vectors matching a filter
+const SCAN_PAGE_SIZE: usize = 1024;
+
 // Import appropriate storage backend based on features
 #[cfg(feature = "storage")]
 use crate::storage::VectorStorage;
@@ -22,14 +38,73 @@ use crate::storage_memory::MemoryStorage as VectorStorage;
 pub struct VectorDB {
     storage: Arc<VectorStorage>,
     index: Arc<RwLock<Box<dyn VectorIndex>>>,
+    payload_indexes: Arc<RwLock<PayloadIndexManager>>,
     options: DbOptions,
+    /// Snapshot and WAL bookkeeping for graph indexes
+    #[cfg(feature = "storage")]
+    persistence: Option<Arc<GraphPersistence>>,
+    /// Set while a background index compaction is running
+    compacting: Arc<AtomicBool>,
+}
+
+/// Convert entry metadata into the JSON payload shape used by `ruvector-filter`
+fn metadata_payload(metadata: &HashMap<String, serde_json::Value>) -> serde_json::Value {
+    serde_json::Value::Object(
+        metadata
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect(),
+    )
+}
+
+/// Convert the exact-match filter of a `SearchQuery` into a filter expression
+fn exact_match_filter(filter: &HashMap<String, serde_json::Value>) -> FilterExpression {
+    FilterExpression::and(
+        filter
+            .iter()
+            .map(|(key, value)| FilterExpression::eq(key.clone(), value.clone()))
+            .collect(),
+    )
+}
+
+/// Restore the index selected by `options` from a persisted graph
+#[cfg(feature = "storage")]
+fn load_graph(options: &DbOptions, graph: &[u8]) -> Result<Box<dyn VectorIndex>> {
+    #[cfg(feature = "hnsw")]
+    {
+        match &options.quantization {
+            Some(quantization) if !matches!(quantization, QuantizationConfig::None) => {
+                Ok(Box::new(QuantizedHnswIndex::from_graph(graph)?))
+            }
+            _ => Ok(Box::new(HnswIndex::from_graph(graph)?)),
+        }
+    }
+    #[cfg(not(feature = "hnsw"))]
+    {
+        let _ = (options, graph);
+        Err(crate::error::RuvectorError::IndexError(
+            "Graph persistence requires the hnsw feature".into(),
+        ))
+    }
+}
+
+/// Clears the background compaction flag when dropped, even if the
+/// compaction panics
+struct CompactionGuard(Arc<AtomicBool>);
+
+impl Drop for CompactionGuard {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
 }
 
 impl VectorDB {
     /// Create a new vector database with the given options
     ///
-    /// If a storage path is provided and contains persisted vectors,
-    /// the HNSW index will be automatically rebuilt from storage.
+    /// If a storage path is provided and contains persisted vectors, the
+    /// HNSW graph is mapped from its last snapshot and brought up to date
+    /// from the index WAL; without a usable snapshot the index is rebuilt
+    /// from storage.
     /// If opening an existing database, the stored configuration (dimensions,
     /// distance metric, etc.) will be used instead of the provided options.
     #[allow(unused_mut)] // `options` is mutated only when feature = "storage"
@@ -81,11 +156,21 @@ impl VectorDB {
         let mut index: Box<dyn VectorIndex> = if let Some(hnsw_config) = &options.hnsw_config {
             #[cfg(feature = "hnsw")]
             {
-                Box::new(HnswIndex::new(
-                    options.dimensions,
-                    options.distance_metric,
-                    hnsw_config.clone(),
-                )?)
+                match &options.quantization {
+                    Some(quantization) if !matches!(quantization, QuantizationConfig::None) => {
+                        Box::new(QuantizedHnswIndex::new(
+                            options.dimensions,
+                            options.distance_metric,
+                            hnsw_config.clone(),
+                            quantization.clone(),
+                        )?)
+                    }
+                    _ => Box::new(HnswIndex::new(
+                        options.dimensions,
+                        options.distance_metric,
+                        hnsw_config.clone(),
+                    )?),
+                }
             }
             #[cfg(not(feature = "hnsw"))]
             {
@@ -97,36 +182,62 @@ impl VectorDB {
             Box::new(FlatIndex::new(options.dimensions, options.distance_metric))
         };
 
-        // Rebuild index from persisted vectors if storage is not empty
-        // This fixes the bug where search() returns empty results after restart
+        #[cfg(feature = "storage")]
+        let persistence = if cfg!(feature = "hnsw") && options.hnsw_config.is_some() {
+            Some(Arc::new(GraphPersistence::new(&options.storage_path)))
+        } else {
+            None
+        };
+
         #[cfg(feature = "storage")]
         {
-            let stored_ids = storage.all_ids()?;
-            if !stored_ids.is_empty() {
-                tracing::info!(
-                    "Rebuilding index from {} persisted vectors",
-                    stored_ids.len()
-                );
+            let restored = match &persistence {
+                Some(persistence) => {
+                    persistence.restore(&storage, |graph| load_graph(&options, graph))?
+                }
+                None => None,
+            };
+
+            match restored {
+                Some(restored) => index = restored,
+                None => {
+                    // Rebuild index from persisted vectors if storage is not empty
+                    let stored_ids = storage.all_ids()?;
+                    if !stored_ids.is_empty() {
+                        tracing::info!(
+                            "Rebuilding index from {} persisted vectors",
+                            stored_ids.len()
+                        );
+
+                        // Batch load all vectors for efficient index rebuilding
+                        let mut entries = Vec::with_capacity(stored_ids.len());
+                        for id in stored_ids {
+                            if let Some(entry) = storage.get(&id)? {
+                                entries.push((id, entry.vector));
+                            }
+                        }
 
-                // Batch load all vectors for efficient index rebuilding
-                let mut entries = Vec::with_capacity(stored_ids.len());
-                for id in stored_ids {
-                    if let Some(entry) = storage.get(&id)? {
-                        entries.push((id, entry.vector));
+                        // Add all vectors to index in batch for better performance
+                        index.add_batch(entries)?;
+
+                        tracing::info!("Index rebuilt successfully");
                     }
                 }
+            }
 
-                // Add all vectors to index in batch for better performance
-                index.add_batch(entries)?;
-
-                tracing::info!("Index rebuilt successfully");
+            if persistence.is_some() {
+                storage.enable_index_wal();
             }
         }
 
         Ok(Self {
             storage,
             index: Arc::new(RwLock::new(index)),
+            payload_indexes: Arc::new(RwLock::new(PayloadIndexManager::new())),
             options,
+            #[cfg(feature = "storage")]
+            persistence,
+            compacting: Arc::new(AtomicBool::new(false)),
         })
     }
 
@@ -141,78 +252,598 @@ impl VectorDB {
 
     /// Insert a vector entry
     pub fn insert(&self, entry: VectorEntry) -> Result<VectorId> {
+        let writes = self.write_guard();
         let id = self.storage.insert(&entry)?;
 
+        if let Some(metadata) = &entry.metadata {
+            self.payload_indexes
+                .write()
+                .index_payload(&id, &metadata_payload(metadata))?;
+        }
+
         // Add to index
-        let mut index = self.index.write();
-        index.add(id.clone(), entry.vector)?;
+        self.index.write().add(id.clone(), entry.vector)?;
+        drop(writes);
+        self.record_writes(1);
 
         Ok(id)
     }
 
     /// Insert multiple vectors in a batch
     pub fn insert_batch(&self, entries: Vec<VectorEntry>) -> Result<Vec<VectorId>> {
+        self.insert_batch_with_progress(entries, |_, _| {})
+    }
+
+    /// Insert multiple vectors in a batch, reporting index build progress
+    ///
+    /// `progress` is called with `(inserted, total)` as vectors are linked
+    /// into the index; with the `parallel` feature HNSW graphs are built on
+    /// all cores. The callback runs while the index is locked for writing, so
+    /// it must not call back into the database.
+    pub fn insert_batch_with_progress(
+        &self,
+        entries: Vec<VectorEntry>,
+        mut progress: impl FnMut(usize, usize),
+    ) -> Result<Vec<VectorId>> {
+        let writes = self.write_guard();
         let ids = self.storage.insert_batch(&entries)?;
 
+        {
+            let mut payload_indexes = self.payload_indexes.write();
+            for (id, entry) in ids.iter().zip(entries.iter()) {
+                if let Some(metadata) = &entry.metadata {
+                    payload_indexes.index_payload(id, &metadata_payload(metadata))?;
+                }
+            }
+        }
+
         // Add to index
-        let mut index = self.index.write();
         let index_entries: Vec<_> = ids
             .iter()
             .zip(entries.iter())
             .map(|(id, entry)| (id.clone(), entry.vector.clone()))
             .collect();
 
-        index.add_batch(index_entries)?;
+        self.index
+            .write()
+            .add_batch_with_progress(index_entries, &mut progress)?;
+        drop(writes);
+        self.record_writes(ids.len());
 
         Ok(ids)
     }
 
+    /// Insert a vector, or replace the vector and metadata stored under its id
+    ///
+    /// An entry without metadata clears the stored metadata. The replaced
+    /// vector's graph node is retired rather than left beside the new one,
+    /// and a metadata-only change leaves the index untouched.
+    pub fn upsert(&self, entry: VectorEntry) -> Result<VectorId> {
+        Ok(self.upsert_batch(vec![entry])?.remove(0))
+    }
+
+    /// Insert or replace multiple vectors in a single storage transaction
+    pub fn upsert_batch(&self, entries: Vec<VectorEntry>) -> Result<Vec<VectorId>> {
+        let writes = self.write_guard();
+        let upserted = self.storage.upsert_batch(&entries)?;
+
+        {
+            let mut payload_indexes = self.payload_indexes.write();
+            for ((id, _), entry) in upserted.iter().zip(entries.iter()) {
+                payload_indexes.clear_vector(id);
+                if let Some(metadata) = &entry.metadata {
+                    payload_indexes.index_payload(id, &metadata_payload(metadata))?;
+                }
+            }
+        }
+
+        let index_entries: Vec<_> = upserted
+            .iter()
+            .zip(entries)
+            .filter(|((_, previous), entry)| previous.as_ref() != Some(&entry.vector))
+            .map(|((id, _), entry)| (id.clone(), entry.vector))
+            .collect();
+        let reindexed = index_entries.len();
+        if reindexed > 0 {
+            self.index.write().add_batch(index_entries)?;
+        }
+        drop(writes);
+        self.record_writes(reindexed);
+
+        Ok(upserted.into_iter().map(|(id, _)| id).collect())
+    }
+
+    /// Replace the metadata of a stored vector without re-indexing it
+    ///
+    /// Returns `false` when no vector is stored under `id`.
+    pub fn update_metadata(
+        &self,
+        id: &str,
+        metadata: HashMap<String, serde_json::Value>,
+    ) -> Result<bool> {
+        self.modify_metadata(id, |current| *current = metadata)
+    }
+
+    /// Set payload keys on a stored vector, keeping keys not in `payload`
+    ///
+    /// Returns `false` when no vector is stored under `id`.
+    pub fn set_payload(
+        &self,
+        id: &str,
+        payload: HashMap<String, serde_json::Value>,
+    ) -> Result<bool> {
+        self.modify_metadata(id, |current| current.extend(payload))
+    }
+
+    /// Remove payload keys from a stored vector
+    ///
+    /// Returns `false` when no vector is stored under `id`.
+    pub fn delete_payload_keys(&self, id: &str, keys: &[String]) -> Result<bool> {
+        self.modify_metadata(id, |current| {
+            for key in keys {
+                current.remove(key);
+            }
+        })
+    }
+
+    /// Apply a metadata update in storage and re-index the resulting payload
+    fn modify_metadata(
+        &self,
+        id: &str,
+        update: impl FnOnce(&mut HashMap<String, serde_json::Value>),
+    ) -> Result<bool> {
+        // Held across the storage write so a concurrent delete can't clear
+        // the payload index between the write and the re-index below
+        let mut payload_indexes = self.payload_indexes.write();
+        let Some(metadata) = self.storage.update_metadata(id, update)? else {
+            return Ok(false);
+        };
+
+        payload_indexes.clear_vector(id);
+        payload_indexes.index_payload(id, &metadata_payload(&metadata))?;
+        Ok(true)
+    }
+
     /// Search for similar vectors
+    ///
+    /// The exact-match `filter` of the query, if any, is evaluated during
+    /// traversal (see [`VectorDB::search_with_filter`]). `ef_search` overrides
+    /// the index default for this query only.
     pub fn search(&self, query: SearchQuery) -> Result<Vec<SearchResult>> {
-        let index = self.index.read();
-        let mut results = index.search(&query.vector, query.k)?;
+        self.search_with_params(query, &SearchParams::default())
+    }
+
+    /// Search for similar vectors with explicit search parameters
+    ///
+    /// Like [`search`](Self::search); the query's `ef_search`, when set,
+    /// takes precedence over the one in `params`.
+    pub fn search_with_params(
+        &self,
+        query: SearchQuery,
+        params: &SearchParams,
+    ) -> Result<Vec<SearchResult>> {
+        let params = SearchParams {
+            ef_search: query.ef_search.or(params.ef_search),
+            ..*params
+        };
+
+        if let Some(filter) = &query.filter {
+            let expression = exact_match_filter(filter);
+            return self.search_with_filter(&query.vector, query.k, &expression, &params);
+        }
+
+        let fetch = self.candidate_count(query.k);
+        let mut results = self
+            .index
+            .read()
+            .search_with_params(&query.vector, fetch, &params)?;
+        self.enrich(&mut results);
+        self.rescore(&query.vector, query.k, &mut results)?;
+
+        Ok(results)
+    }
+
+    /// Run several searches, returning one result list per query
+    pub fn search_batch(&self, queries: Vec<SearchQuery>) -> Result<Vec<Vec<SearchResult>>> {
+        queries
+            .into_iter()
+            .map(|query| self.search(query))
+            .collect()
+    }
+
+    /// Recommend vectors similar to the positive examples and unlike the
+    /// negative ones
+    ///
+    /// The query is the mean of the positive vectors, moved away from the
+    /// mean of the negative vectors by the same distance again.
+    pub fn recommend(&self, request: &RecommendRequest) -> Result<Vec<SearchResult>> {
+        if request.positive.is_empty() {
+            return Err(RuvectorError::InvalidParameter(
+                "recommend needs at least one positive example".to_string(),
+            ));
+        }
+
+        let positive = self.mean_vector(&request.positive)?;
+        let query = if request.negative.is_empty() {
+            positive
+        } else {
+            let negative = self.mean_vector(&request.negative)?;
+            positive
+                .iter()
+                .zip(&negative)
+                .map(|(p, n)| p + (p - n))
+                .collect()
+        };
+
+        // Over-fetch so that dropping the examples still leaves k results
+        let examples: HashSet<&VectorId> =
+            request.positive.iter().chain(&request.negative).collect();
+        let k = request.k + examples.len();
+        let params = SearchParams {
+            ef_search: request.ef_search,
+            ..SearchParams::default()
+        };
+        let mut results = match &request.filter {
+            Some(filter) => self.search_with_filter(&query, k, filter, &params)?,
+            None => self.search(SearchQuery {
+                vector: query,
+                k,
+                filter: None,
+                ef_search: request.ef_search,
+            })?,
+        };
+        results.retain(|r| !examples.contains(&r.id));
+        results.truncate(request.k);
+        Ok(results)
+    }
+
+    /// Element-wise mean of stored vectors
+    fn mean_vector(&self, ids: &[VectorId]) -> Result<Vec<f32>> {
+        let mut mean = vec![0.0; self.options.dimensions];
+        for id in ids {
+            let entry = self
+                .storage
+                .get(id)?
+                .ok_or_else(|| RuvectorError::VectorNotFound(id.clone()))?;
+            for (m, v) in mean.iter_mut().zip(&entry.vector) {
+                *m += v;
+            }
+        }
+        for m in &mut mean {
+            *m /= ids.len() as f32;
+        }
+        Ok(mean)
+    }
+
+    /// Search for the k nearest vectors whose metadata matches `filter`
+    ///
+    /// When every field referenced by the filter has a payload index, the
+    /// matching ids are computed up front: highly selective filters are then
+    /// answered by brute force over that set, others by a filter-aware graph
+    /// traversal. Filters on unindexed fields are evaluated against stored
+    /// metadata as nodes are visited.
+    ///
+    /// A traversal that finds fewer than k matches has already visited every
+    /// node reachable in the graph, so a wider efSearch wouldn't find more.
+    /// Matches the graph doesn't reach are only returned with
+    /// [`SearchParams::exact_filter_fallback`], which then scans every match,
+    /// or every stored vector for filters on unindexed fields.
+    pub fn search_with_filter(
+        &self,
+        vector: &[f32],
+        k: usize,
+        filter: &FilterExpression,
+        params: &SearchParams,
+    ) -> Result<Vec<SearchResult>> {
+        let fetch = self.candidate_count(k);
+        let candidates = {
+            let payload_indexes = self.payload_indexes.read();
+            let indexed = filter
+                .get_fields()
+                .iter()
+                .all(|field| payload_indexes.has_index(field));
+            if indexed {
+                FilterEvaluator::new(&payload_indexes).evaluate(filter).ok()
+            } else {
+                None
+            }
+        };
+
+        let mut results = match candidates {
+            Some(ids) => {
+                let total = self.storage.len()?.max(1);
+                let selectivity = ids.len() as f32 / total as f32;
+
+                let prefilter =
+                    FilterStrategy::for_selectivity(selectivity) == FilterStrategy::PreFilter;
+                let mut results = if prefilter {
+                    Vec::new()
+                } else {
+                    self.index
+                        .read()
+                        .search_filtered(vector, fetch, params, &|id| ids.contains(id))?
+                };
+                if results.len() < k.min(ids.len()) && (prefilter || params.exact_filter_fallback) {
+                    results = self.brute_force(vector, k, ids.iter().cloned(), &|_| true)?;
+                }
+                results
+            }
+            None => {
+                let payload_indexes = self.payload_indexes.read();
+                let evaluator = FilterEvaluator::new(&payload_indexes);
+                let matches = |id: &VectorId| self.matches_filter(&evaluator, id, filter);
+                let mut results = self
+                    .index
+                    .read()
+                    .search_filtered(vector, fetch, params, &matches)?;
+                if results.len() < k && params.exact_filter_fallback {
+                    // The graph may not connect every match; scan to be exact
+                    let all_ids = self.storage.all_ids()?;
+                    results = self.brute_force(vector, k, all_ids.into_iter(), &|entry| {
+                        entry
+                            .metadata
+                            .as_ref()
+                            .is_some_and(|m| evaluator.matches(&metadata_payload(m), filter))
+                    })?;
+                }
+                results
+            }
+        };
+
+        self.enrich(&mut results);
+        self.rescore(vector, k, &mut results)?;
+        Ok(results)
+    }
+
+    /// Number of index candidates to fetch for a top-k query
+    fn candidate_count(&self, k: usize) -> usize {
+        if self.index.read().needs_rescoring() {
+            k.saturating_mul(RESCORE_OVERSAMPLING)
+        } else {
+            k
+        }
+    }
+
+    /// Recompute approximate scores against the stored full-precision vectors
+    /// and keep the k best
+    fn rescore(&self, vector: &[f32], k: usize, results: &mut Vec<SearchResult>) -> Result<()> {
+        if self.index.read().needs_rescoring() {
+            for result in results.iter_mut() {
+                if let Some(stored) = &result.vector {
+                    result.score = distance(vector, stored, self.options.distance_metric)?;
+                }
+            }
+            results.sort_by(|a, b| {
+                a.score
+                    .partial_cmp(&b.score)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+        }
+        results.truncate(k);
+        Ok(())
+    }
 
-        // Enrich results with full data if needed
-        for result in &mut results {
+    /// Attach stored vectors and metadata to search results
+    fn enrich(&self, results: &mut [SearchResult]) {
+        for result in results.iter_mut() {
+            if result.vector.is_some() {
+                continue;
+            }
             if let Ok(Some(entry)) = self.storage.get(&result.id) {
                 result.vector = Some(entry.vector);
                 result.metadata = entry.metadata;
             }
         }
+    }
 
-        // Apply metadata filters if specified
-        if let Some(filter) = &query.filter {
-            results.retain(|r| {
-                if let Some(metadata) = &r.metadata {
-                    filter
-                        .iter()
-                        .all(|(key, value)| metadata.get(key).is_some_and(|v| v == value))
-                } else {
-                    false
+    /// Check a stored vector's metadata against a filter
+    fn matches_filter(
+        &self,
+        evaluator: &FilterEvaluator<'_>,
+        id: &str,
+        filter: &FilterExpression,
+    ) -> bool {
+        match self.storage.get(id) {
+            Ok(Some(VectorEntry {
+                metadata: Some(metadata),
+                ..
+            })) => evaluator.matches(&metadata_payload(&metadata), filter),
+            _ => false,
+        }
+    }
+
+    /// Exact top-k over the given ids, reading vectors from storage
+    fn brute_force(
+        &self,
+        vector: &[f32],
+        k: usize,
+        ids: impl Iterator<Item = VectorId>,
+        accept: &dyn Fn(&VectorEntry) -> bool,
+    ) -> Result<Vec<SearchResult>> {
+        let mut results = Vec::new();
+        for id in ids {
+            if let Some(entry) = self.storage.get(&id)? {
+                if !accept(&entry) {
+                    continue;
                 }
-            });
+                let score = distance(vector, &entry.vector, self.options.distance_metric)?;
+                results.push(SearchResult {
+                    id,
+                    score,
+                    vector: Some(entry.vector),
+                    metadata: entry.metadata,
+                });
+            }
         }
 
+        results.sort_by(|a, b| {
+            a.score
+                .partial_cmp(&b.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        results.truncate(k);
         Ok(results)
     }
 
+    /// Create a payload index on a metadata field
+    ///
+    /// Existing vectors are indexed immediately. Payload indexes live in memory
+    /// and must be recreated after reopening the database.
+    pub fn create_payload_index(&self, field: &str, index_type: IndexType) -> Result<()> {
+        let mut payload_indexes = self.payload_indexes.write();
+        payload_indexes.create_index(field, index_type)?;
+
+        for id in self.storage.all_ids()? {
+            if let Some(VectorEntry {
+                metadata: Some(metadata),
+                ..
+            }) = self.storage.get(&id)?
+            {
+                if let Some(value) = metadata.get(field) {
+                    let payload = serde_json::json!({ field: value });
+                    payload_indexes.index_payload(&id, &payload)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Drop the payload index on a metadata field
+    pub fn drop_payload_index(&self, field: &str) -> Result<()> {
+        self.payload_indexes.write().drop_index(field)?;
+        Ok(())
+    }
+
+    /// Fields that currently have a payload index
+    pub fn payload_indexed_fields(&self) -> Vec<String> {
+        self.payload_indexes.read().indexed_fields()
+    }
+
     /// Delete a vector by ID
     pub fn delete(&self, id: &str) -> Result<bool> {
+        let writes = self.write_guard();
         let deleted_storage = self.storage.delete(id)?;
 
         if deleted_storage {
-            let mut index = self.index.write();
-            let _ = index.remove(&id.to_string())?;
+            self.payload_indexes.write().clear_vector(id);
+            let _ = self.index.write().remove(&id.to_string())?;
+            drop(writes);
+            self.record_writes(1);
         }
 
         Ok(deleted_storage)
     }
 
+    /// Delete several vectors by ID in a single storage transaction
+    ///
+    /// Returns the number of vectors that existed and were deleted.
+    pub fn delete_batch(&self, ids: &[VectorId]) -> Result<usize> {
+        let writes = self.write_guard();
+        let deleted = self.storage.delete_batch(ids)?;
+
+        if !deleted.is_empty() {
+            {
+                let mut payload_indexes = self.payload_indexes.write();
+                for id in &deleted {
+                    payload_indexes.clear_vector(id);
+                }
+            }
+            let mut index = self.index.write();
+            for id in &deleted {
+                index.remove(id)?;
+            }
+            drop(index);
+            drop(writes);
+            self.record_writes(deleted.len());
+        }
+
+        Ok(deleted.len())
+    }
+
+    /// Delete every vector whose metadata matches `filter`
+    ///
+    /// Returns the number of vectors deleted.
+    pub fn delete_by_filter(&self, filter: &FilterExpression) -> Result<usize> {
+        self.delete_matching(&[], Some(filter))
+    }
+
+    /// Delete the vectors in `ids` and those whose metadata matches `filter`
+    /// in a single batch, so either all of them are deleted or none
+    ///
+    /// Returns the number of vectors deleted.
+    pub fn delete_matching(
+        &self,
+        ids: &[VectorId],
+        filter: Option<&FilterExpression>,
+    ) -> Result<usize> {
+        let mut ids = ids.to_vec();
+        if let Some(filter) = filter {
+            ids.extend(self.matching_ids(filter)?);
+        }
+        self.delete_batch(&ids)
+    }
+
     /// Get a vector by ID
     pub fn get(&self, id: &str) -> Result<Option<VectorEntry>> {
         self.storage.get(id)
     }
 
+    /// Get several vectors by ID, in request order, skipping missing ids
+    pub fn get_many(&self, ids: &[VectorId]) -> Result<Vec<VectorEntry>> {
+        let mut entries = Vec::with_capacity(ids.len());
+        for id in ids {
+            if let Some(entry) = self.storage.get(id)? {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Count the vectors, or only those whose metadata matches `filter`
+    pub fn count(&self, filter: Option<&FilterExpression>) -> Result<usize> {
+        match filter {
+            Some(filter) => Ok(self.matching_ids(filter)?.len()),
+            None => self.len(),
+        }
+    }
+
+    /// Ids of all vectors whose metadata matches `filter`
+    ///
+    /// Uses the payload indexes when they cover every field of the filter,
+    /// and scans stored metadata otherwise.
+    fn matching_ids(&self, filter: &FilterExpression) -> Result<Vec<VectorId>> {
+        {
+            let payload_indexes = self.payload_indexes.read();
+            let indexed = filter
+                .get_fields()
+                .iter()
+                .all(|field| payload_indexes.has_index(field));
+            if indexed {
+                if let Ok(ids) = FilterEvaluator::new(&payload_indexes).evaluate(filter) {
+                    return Ok(ids.into_iter().collect());
+                }
+            }
+        }
+
+        let mut ids = Vec::new();
+        let mut request = ScrollRequest {
+            filter: Some(filter.clone()),
+            limit: SCAN_PAGE_SIZE,
+            offset: None,
+            with_vectors: false,
+            with_payload: false,
+        };
+        loop {
+            let page = self.scroll(&request)?;
+            ids.extend(page.points.into_iter().map(|point| point.id));
+            match page.next_offset {
+                Some(offset) => request.offset = Some(offset),
+                None => return Ok(ids),
+            }
+        }
+    }
+
     /// Get the number of vectors
     pub fn len(&self) -> Result<usize> {
         self.storage.len()
@@ -223,11 +854,137 @@ impl VectorDB {
         self.storage.is_empty()
     }
 
+    /// Set the default efSearch for queries that don't specify one
+    ///
+    /// Has no effect on flat (exhaustive) indexes.
+    pub fn set_ef_search(&self, ef_search: usize) {
+        self.index.write().set_ef_search(ef_search);
+    }
+
+    /// Graph size and tuning of the index, or `None` for flat indexes
+    pub fn graph_stats(&self) -> Option<GraphStats> {
+        self.index.read().graph_stats()
+    }
+
+    /// Rebuild the index graph without the nodes of deleted vectors
+    ///
+    /// Deleted vectors stay in the graph as tombstones that search skips.
+    /// Once they pass the index's compaction threshold a compaction starts in
+    /// the background after the next write; this runs one now instead. The
+    /// graph is rebuilt without holding the index lock, so searches and
+    /// writes continue meanwhile. Returns the number of tombstones reclaimed,
+    /// always 0 for flat indexes.
+    pub fn compact_index(&self) -> Result<usize> {
+        let task = self.index.read().compaction();
+        task.map_or(Ok(0), |task| task())
+    }
+
+    /// Write a snapshot of the index graph now and truncate the index WAL
+    ///
+    /// Checkpoints also run automatically in the background (see
+    /// [`VectorDB::set_checkpoint_interval`]); calling this before shutdown
+    /// makes the next open replay nothing. No-op for flat indexes.
+    #[cfg(feature = "storage")]
+    pub fn checkpoint(&self) -> Result<()> {
+        if let Some(persistence) = &self.persistence {
+            persistence.checkpoint(&self.storage, &self.index)?;
+        }
+        Ok(())
+    }
+
+    /// Number of writes after which a background checkpoint is started
+    ///
+    /// `0` disables automatic checkpoints.
+    #[cfg(feature = "storage")]
+    pub fn set_checkpoint_interval(&self, interval: u64) {
+        if let Some(persistence) = &self.persistence {
+            persistence.set_checkpoint_interval(interval);
+        }
+    }
+
+    /// Hold off graph checkpoints while a write reaches storage and the index
+    fn write_guard(&self) -> Option<MutexGuard<'_, ()>> {
+        #[cfg(feature = "storage")]
+        {
+            self.persistence.as_ref().map(|p| p.write_guard())
+        }
+        #[cfg(not(feature = "storage"))]
+        {
+            None
+        }
+    }
+
+    /// Count completed writes towards the next background checkpoint, and
+    /// start a background compaction if the index has become due for one
+    fn record_writes(&self, count: usize) {
+        #[cfg(feature = "storage")]
+        if let Some(persistence) = &self.persistence {
+            persistence.record_writes(count, &self.storage, &self.index);
+        }
+        #[cfg(not(feature = "storage"))]
+        let _ = count;
+
+        if !self.index.read().needs_compaction() || self.compacting.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let Some(task) = self.index.read().compaction() else {
+            self.compacting.store(false, Ordering::Release);
+            return;
+        };
+        let compacting = CompactionGuard(Arc::clone(&self.compacting));
+        std::thread::spawn(move || {
+            let _compacting = compacting;
+            if let Err(e) = task() {
+                tracing::error!("Background index compaction failed: {}", e);
+            }
+        });
+    }
+
     /// Get database options
     pub fn options(&self) -> &DbOptions {
         &self.options
     }
 
+    /// Fetch one page of stored vectors in id order
+    ///
+    /// Unlike [`VectorDB::keys`], only a page is materialized at a time, so
+    /// collections of any size can be exported or audited by following
+    /// `next_offset` until it is `None`. Consistency is per page only: with
+    /// the redb backend each page is read in its own transaction, and nothing
+    /// pins a snapshot across pages. Vectors inserted or deleted between
+    /// pages are seen or skipped according to where their id falls relative
+    /// to the cursor, and a vector changed after its page was read is not
+    /// returned again.
+    pub fn scroll(&self, request: &ScrollRequest) -> Result<ScrollPage> {
+        if request.limit == 0 {
+            return Err(RuvectorError::InvalidParameter(
+                "scroll limit must be at least 1".to_string(),
+            ));
+        }
+
+        let payload_indexes = self.payload_indexes.read();
+        let evaluator = FilterEvaluator::new(&payload_indexes);
+        let mut page = self.storage.scroll(
+            request.offset.as_deref(),
+            request.limit,
+            request.with_vectors,
+            |metadata| match &request.filter {
+                Some(filter) => {
+                    metadata.is_some_and(|m| evaluator.matches(&metadata_payload(m), filter))
+                }
+                None => true,
+            },
+        )?;
+        drop(payload_indexes);
+
+        if !request.with_payload {
+            for point in &mut page.points {
+                point.metadata = None;
+            }
+        }
+        Ok(page)
+    }
+
     /// Get all vector IDs (for iteration/serialization)
     pub fn keys(&self) -> Result<Vec<String>> {
         self.storage.all_ids()
@@ -293,6 +1050,16 @@ mod tests {
 
         assert!(results.len() >= 1);
         assert_eq!(results[0].id, "v1", "First result should be exact match");
+
+        // Per-query ef_search is accepted (and ignored) by the flat index
+        let results = db.search(SearchQuery {
+            vector: vec![0.0, 1.0, 0.0],
+            k: 3,
+            filter: None,
+            ef_search: Some(10),
+        })?;
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].id, "v2");
         assert!(
             results[0].score < 0.01,
             "Exact match should have ~0 distance"
@@ -301,6 +1068,496 @@ mod tests {
         Ok(())
     }
 
+    fn insert_categorized(db: &VectorDB, count: usize) -> Result<()> {
+        for i in 0..count {
+            let mut metadata = HashMap::new();
+            // Only one vector in fifty is "rare"
+            let category = if i % 50 == 0 { "rare" } else { "common" };
+            metadata.insert("category".to_string(), serde_json::json!(category));
+            metadata.insert("rank".to_string(), serde_json::json!(i));
+            let angle = i as f32 * 0.01;
+            db.insert(VectorEntry {
+                id: Some(format!("v{}", i)),
+                vector: vec![angle.cos(), angle.sin(), 0.5],
+                metadata: Some(metadata),
+            })?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "hnsw")]
+    fn test_selective_filter_returns_k_results() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let mut options = DbOptions::default();
+        options.storage_path = dir.path().join("test.db").to_string_lossy().to_string();
+        options.dimensions = 3;
+        options.distance_metric = DistanceMetric::Euclidean;
+
+        let db = VectorDB::new(options)?;
+        insert_categorized(&db, 500)?;
+
+        let mut filter = HashMap::new();
+        filter.insert("category".to_string(), serde_json::json!("rare"));
+
+        // Unindexed field: evaluated against stored metadata during traversal,
+        // which only finds the matches the graph reaches
+        let traversed = db.search(SearchQuery {
+            vector: vec![1.0, 0.0, 0.5],
+            k: 5,
+            filter: Some(filter.clone()),
+            ef_search: None,
+        })?;
+        assert!(traversed.len() <= 5);
+        for result in &traversed {
+            let metadata = result.metadata.as_ref().unwrap();
+            assert_eq!(metadata["category"], serde_json::json!("rare"));
+        }
+
+        // The opt-in scan finds the rest
+        let rare = FilterExpression::eq("category", serde_json::json!("rare"));
+        let params = SearchParams::default().with_exact_filter_fallback(true);
+        let results = db.search_with_filter(&[1.0, 0.0, 0.5], 5, &rare, &params)?;
+        assert_eq!(results.len(), 5);
+        assert_eq!(results[0].id, "v0");
+        for result in &results {
+            let metadata = result.metadata.as_ref().unwrap();
+            assert_eq!(metadata["category"], serde_json::json!("rare"));
+        }
+
+        let query = SearchQuery {
+            vector: vec![1.0, 0.0, 0.5],
+            k: 5,
+            filter: Some(filter.clone()),
+            ef_search: None,
+        };
+        let scanned = db.search_with_params(query, &params)?;
+        let ids: Vec<_> = results.iter().map(|r| r.id.clone()).collect();
+        let scanned_ids: Vec<_> = scanned.iter().map(|r| r.id.clone()).collect();
+        assert_eq!(scanned_ids, ids);
+
+        // Indexed field: pre-filtered id set, same answer
+        db.create_payload_index("category", IndexType::Keyword)?;
+        let indexed = db.search(SearchQuery {
+            vector: vec![1.0, 0.0, 0.5],
+            k: 5,
+            filter: Some(filter),
+            ef_search: None,
+        })?;
+        let indexed_ids: Vec<_> = indexed.iter().map(|r| r.id.clone()).collect();
+        assert_eq!(ids, indexed_ids);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_with_filter_expression() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let mut options = DbOptions::default();
+        options.storage_path = dir.path().join("test.db").to_string_lossy().to_string();
+        options.dimensions = 3;
+        options.distance_metric = DistanceMetric::Euclidean;
+        options.hnsw_config = None;
+
+        let db = VectorDB::new(options)?;
+        insert_categorized(&db, 200)?;
+        db.create_payload_index("rank", IndexType::Integer)?;
+
+        let filter = FilterExpression::and(vec![
+            FilterExpression::gte("rank", serde_json::json!(100)),
+            FilterExpression::lt("rank", serde_json::json!(110)),
+        ]);
+        let results =
+            db.search_with_filter(&[1.0, 0.0, 0.5], 20, &filter, &SearchParams::default())?;
+        assert_eq!(results.len(), 10, "only 10 vectors match");
+        assert_eq!(results[0].id, "v100");
+
+        db.delete("v100")?;
+        let results =
+            db.search_with_filter(&[1.0, 0.0, 0.5], 20, &filter, &SearchParams::default())?;
+        assert_eq!(results.len(), 9);
+        assert_eq!(results[0].id, "v101");
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "hnsw")]
+    fn test_upsert_replaces_graph_node() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let mut options = DbOptions::default();
+        options.storage_path = dir.path().join("test.db").to_string_lossy().to_string();
+        options.dimensions = 3;
+        options.distance_metric = DistanceMetric::Euclidean;
+
+        let db = VectorDB::new(options)?;
+        insert_categorized(&db, 50)?;
+
+        let mut metadata = HashMap::new();
+        metadata.insert("category".to_string(), serde_json::json!("moved"));
+        db.upsert(VectorEntry {
+            id: Some("v10".to_string()),
+            vector: vec![-1.0, -1.0, -1.0],
+            metadata: Some(metadata),
+        })?;
+        assert_eq!(db.len()?, 50);
+
+        let results = db.search(SearchQuery {
+            vector: vec![-1.0, -1.0, -1.0],
+            k: 50,
+            filter: None,
+            ef_search: None,
+        })?;
+        assert_eq!(results.len(), 50);
+        assert_eq!(results[0].id, "v10");
+        assert_eq!(results.iter().filter(|r| r.id == "v10").count(), 1);
+
+        // Upserting without metadata clears it
+        db.upsert(VectorEntry {
+            id: Some("v10".to_string()),
+            vector: vec![-1.0, -1.0, -1.0],
+            metadata: None,
+        })?;
+        assert!(db.get("v10")?.unwrap().metadata.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_metadata_updates_keep_vector_and_payload_index() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let mut options = DbOptions::default();
+        options.storage_path = dir.path().join("test.db").to_string_lossy().to_string();
+        options.dimensions = 3;
+        options.distance_metric = DistanceMetric::Euclidean;
+        options.hnsw_config = None;
+
+        let db = VectorDB::new(options)?;
+        insert_categorized(&db, 100)?;
+        db.create_payload_index("category", IndexType::Keyword)?;
+        let before = db.get("v7")?.unwrap();
+
+        let mut payload = HashMap::new();
+        payload.insert("category".to_string(), serde_json::json!("rare"));
+        payload.insert("tag".to_string(), serde_json::json!("promoted"));
+        assert!(db.set_payload("v7", payload)?);
+
+        let entry = db.get("v7")?.unwrap();
+        assert_eq!(entry.vector, before.vector);
+        let metadata = entry.metadata.unwrap();
+        assert_eq!(metadata["rank"], serde_json::json!(7));
+        assert_eq!(metadata["tag"], serde_json::json!("promoted"));
+
+        let rare = FilterExpression::eq("category", serde_json::json!("rare"));
+        let results =
+            db.search_with_filter(&[1.0, 0.0, 0.5], 10, &rare, &SearchParams::default())?;
+        let mut ids: Vec<_> = results.iter().map(|r| r.id.as_str()).collect();
+        ids.sort();
+        assert_eq!(ids, ["v0", "v50", "v7"]);
+
+        assert!(db.delete_payload_keys("v7", &["category".to_string()])?);
+        let metadata = db.get("v7")?.unwrap().metadata.unwrap();
+        assert!(!metadata.contains_key("category"));
+        let results =
+            db.search_with_filter(&[1.0, 0.0, 0.5], 10, &rare, &SearchParams::default())?;
+        assert_eq!(results.len(), 2);
+
+        let mut replacement = HashMap::new();
+        replacement.insert("category".to_string(), serde_json::json!("rare"));
+        assert!(db.update_metadata("v8", replacement)?);
+        let metadata = db.get("v8")?.unwrap().metadata.unwrap();
+        assert_eq!(metadata.len(), 1);
+        let results =
+            db.search_with_filter(&[1.0, 0.0, 0.5], 10, &rare, &SearchParams::default())?;
+        assert_eq!(results.len(), 3);
+
+        assert!(!db.set_payload("missing", HashMap::new())?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_scroll_pages_in_id_order() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let mut options = DbOptions::default();
+        options.storage_path = dir.path().join("test.db").to_string_lossy().to_string();
+        options.dimensions = 3;
+        options.hnsw_config = None;
+
+        let db = VectorDB::new(options)?;
+        insert_categorized(&db, 250)?;
+
+        let mut request = ScrollRequest {
+            limit: 100,
+            ..ScrollRequest::default()
+        };
+        let mut seen = Vec::new();
+        let mut pages = 0;
+        loop {
+            let page = db.scroll(&request)?;
+            pages += 1;
+            assert!(page.points.iter().all(|p| p.vector.is_none()));
+            assert!(page.points.iter().all(|p| p.metadata.is_some()));
+            seen.extend(page.points.into_iter().map(|p| p.id));
+            match page.next_offset {
+                Some(offset) => request.offset = Some(offset),
+                None => break,
+            }
+        }
+        assert_eq!(pages, 3);
+        let mut expected: Vec<_> = (0..250).map(|i| format!("v{}", i)).collect();
+        expected.sort();
+        assert_eq!(seen, expected);
+
+        // Filtered, with vectors and without payload
+        let page = db.scroll(&ScrollRequest {
+            filter: Some(FilterExpression::eq("category", serde_json::json!("rare"))),
+            limit: 3,
+            offset: None,
+            with_vectors: true,
+            with_payload: false,
+        })?;
+        let ids: Vec<_> = page.points.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, ["v0", "v100", "v150"]);
+        assert!(page.points.iter().all(|p| p.metadata.is_none()));
+        assert_eq!(page.points[0].vector, Some(vec![1.0, 0.0, 0.5]));
+
+        let page = db.scroll(&ScrollRequest {
+            filter: Some(FilterExpression::eq("category", serde_json::json!("rare"))),
+            limit: 3,
+            offset: page.next_offset,
+            ..ScrollRequest::default()
+        })?;
+        let ids: Vec<_> = page.points.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, ["v200", "v50"]);
+        assert!(page.next_offset.is_none());
+
+        assert!(db
+            .scroll(&ScrollRequest {
+                limit: 0,
+                ..ScrollRequest::default()
+            })
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_count_and_delete_by_filter() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let mut options = DbOptions::default();
+        options.storage_path = dir.path().join("test.db").to_string_lossy().to_string();
+        options.dimensions = 3;
+        options.hnsw_config = None;
+
+        let db = VectorDB::new(options)?;
+        insert_categorized(&db, 200)?;
+        let rare = FilterExpression::eq("category", serde_json::json!("rare"));
+        let ranked = FilterExpression::lt("rank", serde_json::json!(20));
+
+        assert_eq!(db.count(None)?, 200);
+        assert_eq!(db.count(Some(&rare))?, 4);
+        db.create_payload_index("category", IndexType::Keyword)?;
+        assert_eq!(db.count(Some(&rare))?, 4);
+
+        assert_eq!(db.delete_by_filter(&rare)?, 4);
+        assert_eq!(db.count(Some(&rare))?, 0);
+        assert_eq!(db.len()?, 196);
+
+        // v0 was rare and is already gone
+        assert_eq!(db.delete_by_filter(&ranked)?, 19);
+        assert_eq!(
+            db.delete_batch(&["v20".to_string(), "v20".to_string(), "x".to_string()])?,
+            1
+        );
+        assert_eq!(db.len()?, 176);
+
+        // v21 and v22 match, v23 is listed, v22 is both
+        let below = FilterExpression::lt("rank", serde_json::json!(23));
+        assert_eq!(
+            db.delete_matching(&["v22".to_string(), "v23".to_string()], Some(&below))?,
+            3
+        );
+        assert_eq!(db.len()?, 173);
+
+        let entries = db.get_many(&["v30".to_string(), "v5".to_string(), "v31".to_string()])?;
+        let ids: Vec<_> = entries.iter().map(|e| e.id.clone().unwrap()).collect();
+        assert_eq!(ids, ["v30", "v31"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_compaction_guard_clears_flag_on_panic() {
+        let flag = Arc::new(AtomicBool::new(true));
+        let guard = CompactionGuard(Arc::clone(&flag));
+        let outcome = std::thread::spawn(move || {
+            let _compacting = guard;
+            panic!("compaction failed");
+        })
+        .join();
+        assert!(outcome.is_err());
+        assert!(!flag.load(Ordering::Acquire));
+    }
+
+    #[test]
+    fn test_deletes_compact_index_off_the_write_path() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let mut options = DbOptions::default();
+        options.storage_path = dir.path().join("test.db").to_string_lossy().to_string();
+        options.dimensions = 3;
+
+        let db = VectorDB::new(options)?;
+        insert_categorized(&db, 200)?;
+
+        // Past the compaction threshold, the delete returns with tombstones in
+        // place and a background compaction reclaims them
+        let ids: Vec<VectorId> = (0..100).map(|i| format!("v{}", i)).collect();
+        assert_eq!(db.delete_batch(&ids)?, 100);
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(30);
+        while db.graph_stats().unwrap().tombstones > 0 {
+            assert!(std::time::Instant::now() < deadline, "compaction never ran");
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(db.graph_stats().unwrap().nodes, 100);
+
+        // Below the threshold tombstones stay until compacted explicitly
+        db.delete("v100")?;
+        assert_eq!(db.graph_stats().unwrap().tombstones, 1);
+        assert_eq!(db.compact_index()?, 1);
+        assert_eq!(db.compact_index()?, 0);
+
+        let stats = db.graph_stats().unwrap();
+        assert_eq!((stats.nodes, stats.tombstones), (99, 0));
+        let results = db.search(SearchQuery {
+            vector: db.get("v150")?.unwrap().vector,
+            k: 1,
+            filter: None,
+            ef_search: None,
+        })?;
+        assert_eq!(results[0].id, "v150");
+        Ok(())
+    }
+
+    #[test]
+    fn test_recommend_and_search_batch() -> Result<()> {
+        let dir = tempdir().unwrap();
+        let mut options = DbOptions::default();
+        options.storage_path = dir.path().join("test.db").to_string_lossy().to_string();
+        options.dimensions = 3;
+        options.distance_metric = DistanceMetric::Euclidean;
+        options.hnsw_config = None;
+
+        let db = VectorDB::new(options)?;
+        insert_categorized(&db, 200)?;
+
+        // Examples at angles 0.5 and 0.7; their mean lies on v60
+        let results = db.recommend(&RecommendRequest {
+            positive: vec!["v50".to_string(), "v70".to_string()],
+            negative: vec![],
+            k: 3,
+            filter: None,
+            ef_search: None,
+        })?;
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].id, "v60");
+        assert!(results.iter().all(|r| r.id != "v50" && r.id != "v70"));
+
+        // A negative example behind the positive one pushes results past it
+        let results = db.recommend(&RecommendRequest {
+            positive: vec!["v100".to_string()],
+            negative: vec!["v90".to_string()],
+            k: 1,
+            filter: None,
+            ef_search: None,
+        })?;
+        assert_eq!(results[0].id, "v110");
+
+        let results = db.recommend(&RecommendRequest {
+            positive: vec!["v100".to_string()],
+            negative: vec![],
+            k: 2,
+            filter: Some(FilterExpression::eq("category", serde_json::json!("rare"))),
+            ef_search: None,
+        })?;
+        let ids: Vec<_> = results.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, ["v50", "v150"]);
+
+        assert!(db
+            .recommend(&RecommendRequest {
+                positive: vec!["missing".to_string()],
+                negative: vec![],
+                k: 1,
+                filter: None,
+                ef_search: None,
+            })
+            .is_err());
+
+        let batches = db.search_batch(vec![
+            SearchQuery {
+                vector: vec![1.0, 0.0, 0.5],
+                k: 1,
+                filter: None,
+                ef_search: None,
+            },
+            SearchQuery {
+                vector: vec![0.0, 1.0, 0.0],
+                k: 2,
+                filter: None,
+                ef_search: None,
+            },
+        ])?;
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0][0].id, "v0");
+        assert_eq!(batches[1].len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "hnsw")]
+    fn test_quantized_search_rescores_at_full_precision() -> Result<()> {
+        use rand::Rng;
+
+        let dir = tempdir().unwrap();
+        let mut options = DbOptions::default();
+        options.storage_path = dir.path().join("test.db").to_string_lossy().to_string();
+        options.dimensions = 32;
+        options.distance_metric = DistanceMetric::Euclidean;
+        options.quantization = Some(QuantizationConfig::Scalar);
+
+        let db = VectorDB::new(options)?;
+        let mut rng = rand::thread_rng();
+        let vectors: Vec<Vec<f32>> = (0..300)
+            .map(|_| (0..32).map(|_| rng.gen_range(-1.0..1.0)).collect())
+            .collect();
+        for (i, vector) in vectors.iter().enumerate() {
+            db.insert(VectorEntry {
+                id: Some(format!("v{}", i)),
+                vector: vector.clone(),
+                metadata: None,
+            })?;
+        }
+
+        let results = db.search(SearchQuery {
+            vector: vectors[7].clone(),
+            k: 5,
+            filter: None,
+            ef_search: None,
+        })?;
+        assert_eq!(results.len(), 5);
+        assert_eq!(results[0].id, "v7");
+        // Quantized distances would leave a residual; rescored ones are exact
+        assert_eq!(results[0].score, 0.0);
+        for result in &results {
+            let exact = distance(
+                &vectors[7],
+                result.vector.as_ref().unwrap(),
+                DistanceMetric::Euclidean,
+            )?;
+            assert_eq!(result.score, exact);
+        }
+        assert!(results.windows(2).all(|w| w[0].score <= w[1].score));
+
+        Ok(())
+    }
+
     /// Test that search works after simulated restart (new VectorDB instance)
     /// This verifies the fix for issue #30: HNSW index not rebuilt from storage
     #[test]
@@ -0,0 +1,256 @@
+//! Crash-recovery tests for incremental HNSW graph persistence
+//!
+//! A "crash" leaks the database handle so nothing runs on shutdown; the next
+//! open must recover every committed write from the graph snapshot plus the
+//! index WAL, or fall back to a rebuild when the snapshot is unusable.
+
+use rand::Rng;
+use ruvector_core::index::persistence::GraphFile;
+use ruvector_core::types::{
+    DbOptions, DistanceMetric, HnswConfig, QuantizationConfig, SearchQuery,
+};
+use ruvector_core::{VectorDB, VectorEntry};
+use std::time::{Duration, Instant};
+use tempfile::tempdir;
+
+const DIMENSIONS: usize = 16;
+
+fn options(path: &str, quantization: Option<QuantizationConfig>) -> DbOptions {
+    DbOptions {
+        dimensions: DIMENSIONS,
+        distance_metric: DistanceMetric::Euclidean,
+        storage_path: path.to_string(),
+        hnsw_config: Some(HnswConfig {
+            m: 16,
+            ef_construction: 100,
+            ef_search: 64,
+            max_elements: 10_000,
+        }),
+        quantization,
+    }
+}
+
+fn random_vector(rng: &mut impl Rng) -> Vec<f32> {
+    (0..DIMENSIONS).map(|_| rng.gen_range(-1.0..1.0)).collect()
+}
+
+fn insert(db: &VectorDB, id: String, vector: Vec<f32>) {
+    db.insert(VectorEntry {
+        id: Some(id),
+        vector,
+        metadata: None,
+    })
+    .unwrap();
+}
+
+/// Top-1 id, with `ef_search` above the collection size so recall is exact
+fn nearest(db: &VectorDB, vector: &[f32]) -> String {
+    db.search(SearchQuery {
+        vector: vector.to_vec(),
+        k: 1,
+        filter: None,
+        ef_search: Some(400),
+    })
+    .unwrap()[0]
+        .id
+        .clone()
+}
+
+/// Ids of the `k` nearest neighbours of `vector`
+fn nearest_ids(db: &VectorDB, vector: &[f32], k: usize) -> Vec<String> {
+    db.search(SearchQuery {
+        vector: vector.to_vec(),
+        k,
+        filter: None,
+        ef_search: Some(400),
+    })
+    .unwrap()
+    .into_iter()
+    .map(|r| r.id)
+    .collect()
+}
+
+/// Drop the handle without running any shutdown logic
+fn crash(db: VectorDB) {
+    std::mem::forget(db);
+}
+
+#[test]
+fn test_recovers_writes_after_last_checkpoint() {
+    for quantization in [None, Some(QuantizationConfig::Scalar)] {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("wal.db").to_string_lossy().to_string();
+        let mut rng = rand::thread_rng();
+        let vectors: Vec<Vec<f32>> = (0..300).map(|_| random_vector(&mut rng)).collect();
+
+        {
+            let db = VectorDB::new(options(&path, quantization.clone())).unwrap();
+            db.set_checkpoint_interval(0);
+            for (i, vector) in vectors.iter().enumerate().take(200) {
+                insert(&db, format!("v{}", i), vector.clone());
+            }
+            db.checkpoint().unwrap();
+
+            // Journaled only in the WAL
+            for (i, vector) in vectors.iter().enumerate().skip(200) {
+                insert(&db, format!("v{}", i), vector.clone());
+            }
+            for i in (0..300).step_by(10) {
+                assert!(db.delete(&format!("v{}", i)).unwrap());
+            }
+            insert(&db, "v5".to_string(), vectors[299].clone());
+            crash(db);
+        }
+
+        assert!(GraphFile::for_storage_path(&path).open().unwrap().is_some());
+
+        let db = VectorDB::new(options(&path, quantization.clone())).unwrap();
+        assert_eq!(db.len().unwrap(), 270);
+        // HNSW can leave the odd node unreachable, so require high recall
+        // rather than an exact match for every vector
+        let live: Vec<usize> = (1..300).filter(|i| i % 10 != 0).collect();
+        let found = live
+            .iter()
+            .filter(|&&i| nearest(&db, &vectors[i]) == format!("v{}", i))
+            .count();
+        assert!(
+            found * 100 >= live.len() * 95,
+            "{:?}: {}",
+            quantization,
+            found
+        );
+        for i in [0, 100, 250] {
+            let id = format!("v{}", i);
+            assert!(!nearest_ids(&db, &vectors[i], 20).contains(&id));
+        }
+        // Overwritten after the checkpoint: the new vector is indexed
+        let results = db
+            .search(SearchQuery {
+                vector: vectors[299].clone(),
+                k: 2,
+                filter: None,
+                ef_search: Some(400),
+            })
+            .unwrap();
+        let mut ids: Vec<_> = results.into_iter().map(|r| r.id).collect();
+        ids.sort();
+        assert_eq!(ids, ["v299", "v5"]);
+    }
+}
+
+#[test]
+fn test_checkpoint_truncates_wal_and_survives_reopen() {
+    let dir = tempdir().unwrap();
+    let path = dir
+        .path()
+        .join("checkpoint.db")
+        .to_string_lossy()
+        .to_string();
+    let mut rng = rand::thread_rng();
+    let vectors: Vec<Vec<f32>> = (0..100).map(|_| random_vector(&mut rng)).collect();
+
+    {
+        let db = VectorDB::new(options(&path, None)).unwrap();
+        db.set_checkpoint_interval(0);
+        for (i, vector) in vectors.iter().enumerate() {
+            insert(&db, format!("v{}", i), vector.clone());
+        }
+        db.checkpoint().unwrap();
+        db.checkpoint().unwrap();
+        crash(db);
+    }
+
+    // Reopen twice: the second open restores the graph the first one left
+    for _ in 0..2 {
+        let db = VectorDB::new(options(&path, None)).unwrap();
+        assert_eq!(db.len().unwrap(), 100);
+        assert_eq!(nearest(&db, &vectors[42]), "v42");
+        crash(db);
+    }
+}
+
+#[test]
+fn test_background_checkpoint() {
+    let dir = tempdir().unwrap();
+    let path = dir
+        .path()
+        .join("background.db")
+        .to_string_lossy()
+        .to_string();
+    let mut rng = rand::thread_rng();
+
+    let db = VectorDB::new(options(&path, None)).unwrap();
+    db.set_checkpoint_interval(50);
+    for i in 0..120 {
+        insert(&db, format!("v{}", i), random_vector(&mut rng));
+    }
+
+    let file = GraphFile::for_storage_path(&path);
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while file.open().unwrap().is_none() {
+        assert!(Instant::now() < deadline, "no background checkpoint");
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    assert!(file.open().unwrap().unwrap().checkpoint() >= 50);
+}
+
+#[test]
+fn test_corrupt_snapshot_falls_back_to_rebuild() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("corrupt.db").to_string_lossy().to_string();
+    let mut rng = rand::thread_rng();
+    let vectors: Vec<Vec<f32>> = (0..100).map(|_| random_vector(&mut rng)).collect();
+
+    {
+        let db = VectorDB::new(options(&path, None)).unwrap();
+        db.set_checkpoint_interval(0);
+        for (i, vector) in vectors.iter().enumerate() {
+            insert(&db, format!("v{}", i), vector.clone());
+        }
+        db.checkpoint().unwrap();
+        crash(db);
+    }
+
+    // Torn snapshot plus a leftover temporary file from an interrupted checkpoint
+    let file = GraphFile::for_storage_path(&path);
+    let bytes = std::fs::read(file.path()).unwrap();
+    std::fs::write(file.path(), &bytes[..bytes.len() / 2]).unwrap();
+    std::fs::write(file.path().with_extension("hnsw.tmp"), b"partial").unwrap();
+
+    let db = VectorDB::new(options(&path, None)).unwrap();
+    assert_eq!(db.len().unwrap(), 100);
+    assert_eq!(nearest(&db, &vectors[7]), "v7");
+
+    // The next checkpoint replaces the damaged snapshot
+    db.checkpoint().unwrap();
+    assert!(file.open().unwrap().is_some());
+}
+
+#[test]
+fn test_wal_sequence_survives_truncation() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("floor.db").to_string_lossy().to_string();
+    let mut rng = rand::thread_rng();
+    let vectors: Vec<Vec<f32>> = (0..50).map(|_| random_vector(&mut rng)).collect();
+    let replacement = random_vector(&mut rng);
+
+    {
+        let db = VectorDB::new(options(&path, None)).unwrap();
+        db.set_checkpoint_interval(0);
+        for (i, vector) in vectors.iter().enumerate() {
+            insert(&db, format!("v{}", i), vector.clone());
+        }
+        db.checkpoint().unwrap();
+
+        // Same count as at the checkpoint but different contents; the WAL
+        // is empty, so these must not reuse the checkpoint's sequence
+        assert!(db.delete("v0").unwrap());
+        insert(&db, "new".to_string(), replacement.clone());
+        crash(db);
+    }
+
+    let db = VectorDB::new(options(&path, None)).unwrap();
+    assert_eq!(db.len().unwrap(), 50);
+    assert!(nearest_ids(&db, &replacement, 5).contains(&"new".to_string()));
+    assert!(!nearest_ids(&db, &vectors[0], 50).contains(&"v0".to_string()));
+}
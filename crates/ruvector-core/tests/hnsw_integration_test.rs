@@ -493,3 +493,74 @@ fn test_hnsw_parallel_batch_insert() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_hnsw_recall_after_delete_insert_cycles() -> Result<()> {
+    let dimensions = 64;
+    let k = 10;
+    let batch = 250;
+
+    let config = HnswConfig {
+        m: 16,
+        ef_construction: 200,
+        ef_search: 200,
+        max_elements: 10_000,
+    };
+
+    let mut index = HnswIndex::new(dimensions, DistanceMetric::Cosine, config)?;
+
+    let mut live: Vec<(String, Vec<f32>)> = generate_random_vectors(1000, dimensions, 7)
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (format!("vec_{}", i), normalize_vector(v)))
+        .collect();
+    for (id, vector) in &live {
+        index.add(id.clone(), vector.clone())?;
+    }
+
+    let queries: Vec<_> = generate_random_vectors(20, dimensions, 99)
+        .iter()
+        .map(|v| normalize_vector(v))
+        .collect();
+
+    let mut next_id = live.len();
+    for cycle in 0..4 {
+        // Delete the oldest batch and insert a fresh one
+        for (id, _) in live.drain(..batch) {
+            assert!(index.remove(&id)?);
+        }
+        let fresh = generate_random_vectors(batch, dimensions, 1000 + cycle);
+        for v in fresh {
+            let id = format!("vec_{}", next_id);
+            next_id += 1;
+            let v = normalize_vector(&v);
+            index.add(id.clone(), v.clone())?;
+            live.push((id, v));
+        }
+
+        assert_eq!(index.len(), live.len());
+
+        let mut total_recall = 0.0;
+        for query in &queries {
+            let results = index.search(query, k)?;
+            assert_eq!(results.len(), k, "deletions must not shrink top-k");
+            let result_ids: Vec<_> = results.iter().map(|r| r.id.clone()).collect();
+            let ground_truth = brute_force_search(query, &live, k, DistanceMetric::Cosine);
+            total_recall += calculate_recall(&ground_truth, &result_ids);
+        }
+        let avg_recall = total_recall / queries.len() as f32;
+        println!(
+            "cycle {} - recall@{}: {:.2}%, tombstones: {}",
+            cycle,
+            k,
+            avg_recall * 100.0,
+            index.tombstone_count()
+        );
+        assert!(
+            avg_recall >= 0.85,
+            "Recall should stay above 85% after delete/insert cycles"
+        );
+    }
+
+    Ok(())
+}
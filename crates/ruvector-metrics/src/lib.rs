@@ -63,6 +63,35 @@ lazy_static! {
         Opts::new("ruvector_collections_total", "Total number of collections")
     ).unwrap();
 
+    // HNSW index metrics
+    pub static ref HNSW_GRAPH_NODES: GaugeVec = register_gauge_vec!(
+        Opts::new("ruvector_hnsw_graph_nodes", "HNSW graph nodes, including tombstones"),
+        &["collection"]
+    ).unwrap();
+
+    pub static ref HNSW_TOMBSTONES: GaugeVec = register_gauge_vec!(
+        Opts::new("ruvector_hnsw_tombstones", "Deleted vectors still linked in the HNSW graph"),
+        &["collection"]
+    ).unwrap();
+
+    pub static ref HNSW_EF_SEARCH: GaugeVec = register_gauge_vec!(
+        Opts::new("ruvector_hnsw_ef_search", "Default HNSW efSearch in use"),
+        &["collection"]
+    ).unwrap();
+
+    // HTTP metrics
+    pub static ref HTTP_REQUESTS_TOTAL: CounterVec = register_counter_vec!(
+        Opts::new("ruvector_http_requests_total", "Total HTTP requests"),
+        &["method", "route", "status"]
+    ).unwrap();
+
+    pub static ref HTTP_REQUEST_LATENCY_SECONDS: HistogramVec = register_histogram_vec!(
+        "ruvector_http_request_latency_seconds",
+        "HTTP request latency in seconds",
+        &["method", "route"],
+        vec![0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0]
+    ).unwrap();
+
     // System metrics
     pub static ref MEMORY_USAGE_BYTES: Gauge = register_gauge!(
         Opts::new("ruvector_memory_usage_bytes", "Memory usage in bytes")
@@ -1,7 +1,8 @@
 use crate::{
-    COLLECTIONS_TOTAL, DELETE_REQUESTS_TOTAL, INSERT_LATENCY_SECONDS, INSERT_REQUESTS_TOTAL,
-    MEMORY_USAGE_BYTES, SEARCH_LATENCY_SECONDS, SEARCH_REQUESTS_TOTAL, VECTORS_INSERTED_TOTAL,
-    VECTORS_TOTAL,
+    COLLECTIONS_TOTAL, DELETE_REQUESTS_TOTAL, HNSW_EF_SEARCH, HNSW_GRAPH_NODES, HNSW_TOMBSTONES,
+    HTTP_REQUESTS_TOTAL, HTTP_REQUEST_LATENCY_SECONDS, INSERT_LATENCY_SECONDS,
+    INSERT_REQUESTS_TOTAL, MEMORY_USAGE_BYTES, SEARCH_LATENCY_SECONDS, SEARCH_REQUESTS_TOTAL,
+    VECTORS_INSERTED_TOTAL, VECTORS_TOTAL,
 };
 
 /// Helper struct for recording metrics
@@ -77,6 +78,63 @@ impl MetricsRecorder {
             .set(count as f64);
     }
 
+    /// Update the HNSW graph gauges for a collection
+    ///
+    /// # Arguments
+    /// * `collection` - The collection name
+    /// * `graph_nodes` - Nodes in the graph, including tombstones
+    /// * `tombstones` - Deleted vectors still linked in the graph
+    /// * `ef_search` - The default efSearch in use
+    pub fn set_hnsw_stats(
+        collection: &str,
+        graph_nodes: usize,
+        tombstones: usize,
+        ef_search: usize,
+    ) {
+        HNSW_GRAPH_NODES
+            .with_label_values(&[collection])
+            .set(graph_nodes as f64);
+        HNSW_TOMBSTONES
+            .with_label_values(&[collection])
+            .set(tombstones as f64);
+        HNSW_EF_SEARCH
+            .with_label_values(&[collection])
+            .set(ef_search as f64);
+    }
+
+    /// Drop the per-collection gauges of a deleted collection
+    ///
+    /// # Arguments
+    /// * `collection` - The collection name
+    pub fn remove_collection(collection: &str) {
+        for gauge in [
+            &*VECTORS_TOTAL,
+            &*HNSW_GRAPH_NODES,
+            &*HNSW_TOMBSTONES,
+            &*HNSW_EF_SEARCH,
+        ] {
+            // Missing series are fine: the collection may never have been scraped
+            let _ = gauge.remove_label_values(&[collection]);
+        }
+    }
+
+    /// Record a served HTTP request
+    ///
+    /// # Arguments
+    /// * `method` - The HTTP method
+    /// * `route` - The matched route pattern, e.g. `/collections/:name`
+    /// * `status` - The response status code
+    /// * `latency_secs` - The latency in seconds
+    pub fn record_request(method: &str, route: &str, status: u16, latency_secs: f64) {
+        HTTP_REQUESTS_TOTAL
+            .with_label_values(&[method, route, &status.to_string()])
+            .inc();
+
+        HTTP_REQUEST_LATENCY_SECONDS
+            .with_label_values(&[method, route])
+            .observe(latency_secs);
+    }
+
     /// Update the total number of collections
     ///
     /// # Arguments
@@ -167,6 +225,24 @@ mod tests {
         // Metrics are recorded, no panic
     }
 
+    #[test]
+    fn test_set_hnsw_stats() {
+        MetricsRecorder::set_hnsw_stats("test_hnsw", 120, 20, 64);
+        assert_eq!(
+            HNSW_TOMBSTONES.with_label_values(&["test_hnsw"]).get(),
+            20.0
+        );
+
+        MetricsRecorder::remove_collection("test_hnsw");
+        assert!(!crate::gather_metrics().contains("test_hnsw"));
+    }
+
+    #[test]
+    fn test_record_request() {
+        MetricsRecorder::record_request("GET", "/collections/:name", 200, 0.002);
+        // Metrics are recorded, no panic
+    }
+
     #[test]
     fn test_record_batch() {
         MetricsRecorder::record_batch("test", 100, 50, 10);
@@ -328,6 +328,7 @@ impl VectorDB {
     /// const results = await db.search({
     ///   vector: new Float32Array([1, 2, 3]),
     ///   k: 10,
+    ///   efSearch: 200,
     ///   filter: { category: 'example' }
     /// });
     /// ```
@@ -346,6 +347,21 @@ impl VectorDB {
         .map(|results| results.into_iter().map(Into::into).collect())
     }
 
+    /// Set the default HNSW ef_search for queries that don't pass their own
+    ///
+    /// Higher values improve recall at the cost of latency.
+    ///
+    /// # Example
+    /// ```javascript
+    /// db.setEfSearch(200);
+    /// ```
+    #[napi]
+    pub fn set_ef_search(&self, ef_search: u32) -> Result<()> {
+        let db = self.inner.read().expect("RwLock poisoned");
+        db.set_ef_search(ef_search as usize);
+        Ok(())
+    }
+
     /// Delete a vector by ID
     ///
     /// Returns true if the vector was deleted, false if not found
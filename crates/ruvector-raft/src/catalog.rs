@@ -0,0 +1,380 @@
+//! Replicated collection catalog
+//!
+//! `CatalogStateMachine` applies collection and alias changes from the Raft
+//! log to a local `CollectionManager`, so every node ends up with the same
+//! catalog. `ReplicatedCatalog` is the client side: changes go through
+//! [`RaftNode::propose`], reads through [`RaftNode::read_index`].
+
+use crate::{
+    log::{LogEntry, Snapshot},
+    node::RaftNode,
+    state_machine::StateMachine,
+    storage::write_atomic,
+    LogIndex, RaftError, RaftResult,
+};
+use ruvector_collections::{CollectionConfig, CollectionManager};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tracing::warn;
+
+/// File in the catalog directory recording the last applied log index
+const APPLIED_FILE: &str = "raft_applied";
+
+/// A change to the collection catalog
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum CatalogCommand {
+    CreateCollection {
+        name: String,
+        config: CollectionConfig,
+    },
+    DropCollection {
+        name: String,
+    },
+    CreateAlias {
+        alias: String,
+        collection: String,
+    },
+    DeleteAlias {
+        alias: String,
+    },
+    SwitchAlias {
+        alias: String,
+        collection: String,
+    },
+}
+
+impl CatalogCommand {
+    /// Serialize to bytes
+    pub fn to_bytes(&self) -> RaftResult<Vec<u8>> {
+        serde_json::to_vec(self).map_err(|e| RaftError::Internal(e.to_string()))
+    }
+
+    /// Deserialize from bytes
+    pub fn from_bytes(bytes: &[u8]) -> RaftResult<Self> {
+        serde_json::from_slice(bytes).map_err(|e| RaftError::Internal(e.to_string()))
+    }
+}
+
+/// The catalog as stored in snapshots
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CatalogState {
+    collections: BTreeMap<String, CollectionConfig>,
+    aliases: BTreeMap<String, String>,
+}
+
+/// State machine keeping a `CollectionManager` in step with the log
+///
+/// The manager persists the catalog itself; the index of the last applied
+/// entry is kept next to it so a restarted node resumes where it stopped.
+pub struct CatalogStateMachine {
+    manager: Arc<CollectionManager>,
+    base_path: PathBuf,
+    last_applied: AtomicU64,
+}
+
+impl CatalogStateMachine {
+    /// Open the catalog stored in `base_path`, creating it if needed
+    pub fn open(base_path: impl AsRef<Path>) -> RaftResult<Self> {
+        let base_path = base_path.as_ref().to_path_buf();
+        let manager = CollectionManager::new(base_path.clone())
+            .map_err(|e| RaftError::StateMachine(e.to_string()))?;
+
+        let last_applied = match fs::read(base_path.join(APPLIED_FILE)) {
+            Ok(bytes) => {
+                let bytes: [u8; 8] = bytes.as_slice().try_into().map_err(|_| {
+                    RaftError::Storage(format!("Corrupt {} in {:?}", APPLIED_FILE, base_path))
+                })?;
+                u64::from_le_bytes(bytes)
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => 0,
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            manager: Arc::new(manager),
+            base_path,
+            last_applied: AtomicU64::new(last_applied),
+        })
+    }
+
+    /// The local catalog
+    ///
+    /// Reading it directly may return stale data on followers; use
+    /// [`ReplicatedCatalog`] for linearizable reads.
+    pub fn manager(&self) -> &Arc<CollectionManager> {
+        &self.manager
+    }
+
+    fn execute(&self, command: CatalogCommand) -> Result<(), String> {
+        let result = match command {
+            CatalogCommand::CreateCollection { name, config } => {
+                self.manager.create_collection(&name, config)
+            }
+            CatalogCommand::DropCollection { name } => self.manager.delete_collection(&name),
+            CatalogCommand::CreateAlias { alias, collection } => {
+                self.manager.create_alias(&alias, &collection)
+            }
+            CatalogCommand::DeleteAlias { alias } => self.manager.delete_alias(&alias),
+            CatalogCommand::SwitchAlias { alias, collection } => {
+                self.manager.switch_alias(&alias, &collection)
+            }
+        };
+        result.map_err(|e| e.to_string())
+    }
+
+    fn record_applied(&self, index: LogIndex) -> RaftResult<()> {
+        write_atomic(&self.base_path, APPLIED_FILE, &index.to_le_bytes())?;
+        self.last_applied.store(index, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn state(&self) -> CatalogState {
+        let mut state = CatalogState::default();
+        for name in self.manager.list_collections() {
+            if let Some(collection) = self.manager.get_collection(&name) {
+                state
+                    .collections
+                    .insert(name, collection.read().config.clone());
+            }
+        }
+        state.aliases.extend(self.manager.list_aliases());
+        state
+    }
+
+    fn collection_error(e: ruvector_collections::CollectionError) -> RaftError {
+        RaftError::StateMachine(e.to_string())
+    }
+}
+
+impl StateMachine for CatalogStateMachine {
+    /// Returns a JSON `Result<(), String>`
+    fn apply(&self, entry: &LogEntry) -> Vec<u8> {
+        let result = CatalogCommand::from_bytes(&entry.command)
+            .map_err(|e| e.to_string())
+            .and_then(|command| self.execute(command));
+        if let Err(e) = self.record_applied(entry.index) {
+            // The change itself is durable; replaying it after a restart
+            // fails harmlessly
+            warn!("Failed to record applied index {}: {}", entry.index, e);
+        }
+        serde_json::to_vec(&result).unwrap_or_default()
+    }
+
+    fn snapshot(&self) -> RaftResult<Vec<u8>> {
+        serde_json::to_vec(&self.state()).map_err(|e| RaftError::StateMachine(e.to_string()))
+    }
+
+    /// Reconcile the local catalog with the snapshot
+    ///
+    /// Collections whose configuration matches are kept with their data;
+    /// the rest are dropped or created.
+    fn restore(&self, snapshot: &Snapshot) -> RaftResult<()> {
+        let target: CatalogState = serde_json::from_slice(&snapshot.data)
+            .map_err(|e| RaftError::StateMachine(e.to_string()))?;
+        let current = self.state();
+
+        for alias in current.aliases.keys() {
+            self.manager
+                .delete_alias(alias)
+                .map_err(Self::collection_error)?;
+        }
+        for (name, config) in &current.collections {
+            let keep = target.collections.get(name).is_some_and(|wanted| {
+                serde_json::to_value(wanted).ok() == serde_json::to_value(config).ok()
+            });
+            if !keep {
+                self.manager
+                    .delete_collection(name)
+                    .map_err(Self::collection_error)?;
+            }
+        }
+        for (name, config) in &target.collections {
+            if !self.manager.collection_exists(name) {
+                self.manager
+                    .create_collection(name, config.clone())
+                    .map_err(Self::collection_error)?;
+            }
+        }
+        for (alias, collection) in &target.aliases {
+            self.manager
+                .create_alias(alias, collection)
+                .map_err(Self::collection_error)?;
+        }
+
+        self.record_applied(snapshot.last_included_index)
+    }
+
+    fn last_applied(&self) -> LogIndex {
+        self.last_applied.load(Ordering::SeqCst)
+    }
+}
+
+/// Client for a catalog replicated by a `RaftNode`
+///
+/// Changes and reads must go to the leader; elsewhere they fail with
+/// `NotLeader`.
+pub struct ReplicatedCatalog {
+    node: Arc<RaftNode>,
+    state_machine: Arc<CatalogStateMachine>,
+}
+
+impl ReplicatedCatalog {
+    /// Wrap a node running `state_machine`
+    pub fn new(node: Arc<RaftNode>, state_machine: Arc<CatalogStateMachine>) -> Self {
+        Self {
+            node,
+            state_machine,
+        }
+    }
+
+    /// Create a collection on every node
+    pub async fn create_collection(&self, name: &str, config: CollectionConfig) -> RaftResult<()> {
+        self.execute(CatalogCommand::CreateCollection {
+            name: name.to_string(),
+            config,
+        })
+        .await
+    }
+
+    /// Drop a collection on every node
+    pub async fn drop_collection(&self, name: &str) -> RaftResult<()> {
+        self.execute(CatalogCommand::DropCollection {
+            name: name.to_string(),
+        })
+        .await
+    }
+
+    /// Create an alias on every node
+    pub async fn create_alias(&self, alias: &str, collection: &str) -> RaftResult<()> {
+        self.execute(CatalogCommand::CreateAlias {
+            alias: alias.to_string(),
+            collection: collection.to_string(),
+        })
+        .await
+    }
+
+    /// Delete an alias on every node
+    pub async fn delete_alias(&self, alias: &str) -> RaftResult<()> {
+        self.execute(CatalogCommand::DeleteAlias {
+            alias: alias.to_string(),
+        })
+        .await
+    }
+
+    /// Point an alias at a different collection on every node
+    pub async fn switch_alias(&self, alias: &str, collection: &str) -> RaftResult<()> {
+        self.execute(CatalogCommand::SwitchAlias {
+            alias: alias.to_string(),
+            collection: collection.to_string(),
+        })
+        .await
+    }
+
+    /// List collections, reflecting every completed change
+    pub async fn list_collections(&self) -> RaftResult<Vec<String>> {
+        self.node.read_index().await?;
+        let mut names = self.state_machine.manager().list_collections();
+        names.sort();
+        Ok(names)
+    }
+
+    /// List aliases and their collections, reflecting every completed change
+    pub async fn list_aliases(&self) -> RaftResult<Vec<(String, String)>> {
+        self.node.read_index().await?;
+        let mut aliases = self.state_machine.manager().list_aliases();
+        aliases.sort();
+        Ok(aliases)
+    }
+
+    /// Resolve an alias, reflecting every completed change
+    pub async fn resolve_alias(&self, alias: &str) -> RaftResult<Option<String>> {
+        self.node.read_index().await?;
+        Ok(self.state_machine.manager().resolve_alias(alias))
+    }
+
+    async fn execute(&self, command: CatalogCommand) -> RaftResult<()> {
+        let response = self.node.propose(command.to_bytes()?).await?;
+        let result: Result<(), String> = serde_json::from_slice(&response)
+            .map_err(|e| RaftError::StateMachine(e.to_string()))?;
+        result.map_err(RaftError::StateMachine)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ruvector-raft-catalog-{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn command(index: LogIndex, command: CatalogCommand) -> LogEntry {
+        LogEntry::new(1, index, command.to_bytes().unwrap())
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let source_dir = test_dir("source");
+        let target_dir = test_dir("target");
+        let source = CatalogStateMachine::open(&source_dir).unwrap();
+        let target = CatalogStateMachine::open(&target_dir).unwrap();
+
+        let create = |name: &str, dimensions| CatalogCommand::CreateCollection {
+            name: name.to_string(),
+            config: CollectionConfig::with_dimensions(dimensions),
+        };
+        source.apply(&command(1, create("docs", 4)));
+        source.apply(&command(2, create("images", 8)));
+        source.apply(&command(
+            3,
+            CatalogCommand::CreateAlias {
+                alias: "live".to_string(),
+                collection: "docs".to_string(),
+            },
+        ));
+        // A failed command still advances the applied index
+        let response = source.apply(&command(4, create("docs", 4)));
+        let result: Result<(), String> = serde_json::from_slice(&response).unwrap();
+        assert!(result.is_err());
+        assert_eq!(source.last_applied(), 4);
+
+        // The target has a stale collection and one with a different configuration
+        target.apply(&command(1, create("docs", 16)));
+        target.apply(&command(2, create("old", 4)));
+
+        let snapshot = Snapshot {
+            last_included_index: 4,
+            last_included_term: 1,
+            configuration: Vec::new(),
+            data: source.snapshot().unwrap(),
+        };
+        target.restore(&snapshot).unwrap();
+
+        let mut names = target.manager().list_collections();
+        names.sort();
+        assert_eq!(names, vec!["docs", "images"]);
+        let docs = target.manager().get_collection("docs").unwrap();
+        assert_eq!(docs.read().config.dimensions, 4);
+        assert_eq!(
+            target.manager().resolve_alias("live"),
+            Some("docs".to_string())
+        );
+        assert_eq!(target.last_applied(), 4);
+
+        // The applied index survives a reopen
+        drop(target);
+        let reopened = CatalogStateMachine::open(&target_dir).unwrap();
+        assert_eq!(reopened.last_applied(), 4);
+        assert!(reopened.manager().collection_exists("images"));
+
+        let _ = fs::remove_dir_all(&source_dir);
+        let _ = fs::remove_dir_all(&target_dir);
+    }
+}
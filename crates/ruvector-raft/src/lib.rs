@@ -4,18 +4,29 @@
 //! following the Raft paper specification for managing distributed metadata
 //! in the ruvector vector database.
 
+#[cfg(feature = "collections")]
+pub mod catalog;
 pub mod election;
 pub mod log;
 pub mod node;
 pub mod rpc;
 pub mod state;
+pub mod state_machine;
+pub mod storage;
+pub mod transport;
 
+#[cfg(feature = "collections")]
+pub use catalog::{CatalogCommand, CatalogStateMachine, ReplicatedCatalog};
+pub use log::{EntryKind, LogEntry, Snapshot};
 pub use node::{RaftNode, RaftNodeConfig};
 pub use rpc::{
     AppendEntriesRequest, AppendEntriesResponse, InstallSnapshotRequest, InstallSnapshotResponse,
-    RequestVoteRequest, RequestVoteResponse,
+    RequestVoteRequest, RequestVoteResponse, TimeoutNowRequest,
 };
 pub use state::{LeaderState, PersistentState, RaftState, VolatileState};
+pub use state_machine::StateMachine;
+pub use storage::{FileStorage, InMemoryStorage, RaftStorage, StoredState};
+pub use transport::{InMemoryNetwork, InMemoryTransport, RaftInbox, RaftTransport, TcpTransport};
 
 use thiserror::Error;
 
@@ -55,6 +66,12 @@ pub enum RaftError {
     #[error("Snapshot installation failed: {0}")]
     SnapshotFailed(String),
 
+    #[error("Storage error: {0}")]
+    Storage(String),
+
+    #[error("State machine error: {0}")]
+    StateMachine(String),
+
     #[error("Configuration error: {0}")]
     ConfigError(String),
 
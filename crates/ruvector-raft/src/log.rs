@@ -6,10 +6,24 @@
 //! - Snapshots and compaction
 //! - Persistence
 
-use crate::{LogIndex, RaftError, RaftResult, Term};
+use crate::{LogIndex, NodeId, RaftError, RaftResult, Term};
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 
+/// What a log entry carries
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum EntryKind {
+    /// A state machine command
+    #[default]
+    Command,
+
+    /// The empty entry a new leader appends to commit earlier terms
+    Noop,
+
+    /// A new cluster configuration; the command holds the member list
+    Configuration,
+}
+
 /// A single entry in the Raft log
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct LogEntry {
@@ -19,6 +33,9 @@ pub struct LogEntry {
     /// Index position in the log
     pub index: LogIndex,
 
+    /// What the command holds
+    pub kind: EntryKind,
+
     /// State machine command
     pub command: Vec<u8>,
 }
@@ -29,8 +46,40 @@ impl LogEntry {
         Self {
             term,
             index,
+            kind: EntryKind::Command,
+            command,
+        }
+    }
+
+    /// Create a no-op entry
+    pub fn noop(term: Term, index: LogIndex) -> Self {
+        Self {
+            term,
+            index,
+            kind: EntryKind::Noop,
+            command: Vec::new(),
+        }
+    }
+
+    /// Create an entry that switches the cluster to `members`
+    pub fn configuration(term: Term, index: LogIndex, members: &[NodeId]) -> RaftResult<Self> {
+        let command = bincode::serde::encode_to_vec(members, bincode::config::standard())?;
+        Ok(Self {
+            term,
+            index,
+            kind: EntryKind::Configuration,
             command,
+        })
+    }
+
+    /// The member list of a configuration entry
+    pub fn members(&self) -> Option<Vec<NodeId>> {
+        if self.kind != EntryKind::Configuration {
+            return None;
         }
+        bincode::serde::decode_from_slice(&self.command, bincode::config::standard())
+            .ok()
+            .map(|(members, _)| members)
     }
 }
 
@@ -129,6 +178,17 @@ impl RaftLog {
         self.entries.iter().skip(offset).cloned().collect()
     }
 
+    /// Get at most `max_entries` entries starting from an index
+    pub fn entries_range(&self, start_index: LogIndex, max_entries: usize) -> Vec<LogEntry> {
+        let offset = start_index.saturating_sub(self.base_index + 1) as usize;
+        self.entries
+            .iter()
+            .skip(offset)
+            .take(max_entries)
+            .cloned()
+            .collect()
+    }
+
     /// Append a new entry to the log
     pub fn append(&mut self, term: Term, command: Vec<u8>) -> LogIndex {
         let index = self.last_index() + 1;
@@ -226,6 +286,31 @@ impl RaftLog {
         Ok(snapshot)
     }
 
+    /// The latest configuration in the log and the index it was written at
+    ///
+    /// Configuration entries take effect as soon as they are appended, so
+    /// this includes uncommitted ones. Falls back to the snapshot's
+    /// configuration (at the base index) when the log holds none.
+    pub fn latest_configuration(&self) -> Option<(Vec<NodeId>, LogIndex)> {
+        self.configuration_at(self.last_index())
+    }
+
+    /// The configuration in effect at `index`
+    pub fn configuration_at(&self, index: LogIndex) -> Option<(Vec<NodeId>, LogIndex)> {
+        let found = self
+            .entries
+            .iter()
+            .rev()
+            .filter(|entry| entry.index <= index)
+            .find_map(|entry| entry.members().map(|members| (members, entry.index)));
+        found.or_else(|| {
+            self.snapshot
+                .as_ref()
+                .filter(|snapshot| !snapshot.configuration.is_empty())
+                .map(|snapshot| (snapshot.configuration.clone(), self.base_index))
+        })
+    }
+
     /// Get the current snapshot
     pub fn snapshot(&self) -> Option<&Snapshot> {
         self.snapshot.as_ref()
@@ -346,5 +431,30 @@ mod tests {
         assert_eq!(entries.len(), 2);
         assert_eq!(entries[0].index, 2);
         assert_eq!(entries[1].index, 3);
+
+        let entries = log.entries_range(2, 1);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].index, 2);
+        assert!(log.entries_range(4, 10).is_empty());
+    }
+
+    #[test]
+    fn test_configuration_entries() {
+        let mut log = RaftLog::new();
+        assert!(log.latest_configuration().is_none());
+
+        let members = vec!["node1".to_string(), "node2".to_string()];
+        log.append(1, b"cmd1".to_vec());
+        let entry = LogEntry::configuration(1, 2, &members).unwrap();
+        assert_eq!(entry.members(), Some(members.clone()));
+        log.append_entries(vec![entry, LogEntry::noop(2, 3)])
+            .unwrap();
+        assert!(log.get(3).unwrap().members().is_none());
+        assert_eq!(log.latest_configuration(), Some((members.clone(), 2)));
+        assert!(log.configuration_at(1).is_none());
+
+        // Compacting away the entry keeps the configuration in the snapshot
+        log.create_snapshot(3, Vec::new(), members.clone()).unwrap();
+        assert_eq!(log.latest_configuration(), Some((members, 3)));
     }
 }
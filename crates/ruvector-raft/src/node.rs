@@ -5,22 +5,32 @@
 //! - RPC message handling
 //! - Log replication
 //! - Leader election
-//! - Client request processing
+//! - Snapshot streaming to lagging followers
+//! - Single-server membership changes and leadership transfer
+//! - Applying committed commands to the state machine
+//! - Client request processing and linearizable reads
 
 use crate::{
     election::{ElectionState, VoteValidator},
+    log::{EntryKind, LogEntry, RaftLog, Snapshot},
     rpc::{
         AppendEntriesRequest, AppendEntriesResponse, InstallSnapshotRequest,
         InstallSnapshotResponse, RaftMessage, RequestVoteRequest, RequestVoteResponse,
+        TimeoutNowRequest,
     },
     state::{LeaderState, PersistentState, RaftState, VolatileState},
+    state_machine::StateMachine,
+    storage::{InMemoryStorage, RaftStorage},
+    transport::{RaftInbox, RaftTransport},
     LogIndex, NodeId, RaftError, RaftResult, Term,
 };
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::mpsc;
-use tokio::time::{interval, sleep};
+use tokio::sync::{mpsc, Notify};
+use tokio::time::{interval, Instant};
 use tracing::{debug, error, info, warn};
 
 /// Configuration for a Raft node
@@ -30,6 +40,11 @@ pub struct RaftNodeConfig {
     pub node_id: NodeId,
 
     /// IDs of all cluster members (including self)
+    ///
+    /// This is the initial configuration; configuration entries in the log
+    /// or snapshot take precedence. A node joining a running cluster lists
+    /// the current members and waits to be added with
+    /// [`RaftNode::add_member`].
     pub cluster_members: Vec<NodeId>,
 
     /// Minimum election timeout (milliseconds)
@@ -79,17 +94,86 @@ pub struct CommandResult {
 /// Internal messages for the Raft node
 #[derive(Debug)]
 enum InternalMessage {
-    /// RPC message from another node
-    Rpc { from: NodeId, message: RaftMessage },
     /// Client command to replicate
     ClientCommand {
         command: Command,
         response_tx: mpsc::Sender<RaftResult<CommandResult>>,
     },
+    /// Client command answered once the state machine applies it
+    Proposal {
+        command: Command,
+        response_tx: mpsc::Sender<RaftResult<Vec<u8>>>,
+    },
+    /// Linearizable read
+    ReadIndex {
+        response_tx: mpsc::Sender<RaftResult<LogIndex>>,
+    },
+    /// Snapshot the state machine and compact the log
+    TakeSnapshot {
+        response_tx: mpsc::Sender<RaftResult<Snapshot>>,
+    },
     /// Election timeout fired
     ElectionTimeout,
     /// Heartbeat timeout fired
     HeartbeatTimeout,
+    /// Add or remove a cluster member
+    ChangeMembership {
+        change: MembershipChange,
+        response_tx: mpsc::Sender<RaftResult<CommandResult>>,
+    },
+    /// Hand leadership to another member
+    TransferLeadership {
+        target: NodeId,
+        response_tx: mpsc::Sender<RaftResult<()>>,
+    },
+    /// Stop processing messages
+    Shutdown,
+}
+
+/// A single-server membership change
+#[derive(Debug)]
+enum MembershipChange {
+    Add(NodeId),
+    Remove(NodeId),
+}
+
+/// The configuration in effect and the log index it was written at
+///
+/// Index 0 means the configuration came from [`RaftNodeConfig`].
+#[derive(Debug, Clone)]
+struct Membership {
+    members: Vec<NodeId>,
+    index: LogIndex,
+}
+
+/// A snapshot being received from the leader, chunk by chunk
+#[derive(Debug)]
+struct IncomingSnapshot {
+    last_included_index: LogIndex,
+    last_included_term: Term,
+    data: Vec<u8>,
+}
+
+/// Progress of streaming a snapshot to one follower
+#[derive(Debug)]
+struct OutgoingSnapshot {
+    last_included_index: LogIndex,
+    offset: u64,
+}
+
+/// Channel answering a proposal with the state machine's response
+type ProposalSender = mpsc::Sender<RaftResult<Vec<u8>>>;
+
+/// A read waiting for a quorum to confirm leadership and for the state
+/// machine to reach its read index
+#[derive(Debug)]
+struct PendingRead {
+    read_index: LogIndex,
+    /// Responses to requests with at least this sequence number count
+    seq: u64,
+    acks: HashSet<NodeId>,
+    confirmed: bool,
+    response_tx: mpsc::Sender<RaftResult<LogIndex>>,
 }
 
 /// The Raft consensus node
@@ -115,20 +199,108 @@ pub struct RaftNode {
     /// Current leader ID (if known)
     current_leader: Arc<RwLock<Option<NodeId>>>,
 
+    /// Latest cluster configuration in the log
+    membership: RwLock<Membership>,
+
+    /// When we last heard from the leader
+    last_leader_contact: Mutex<Option<Instant>>,
+
+    /// Snapshot chunks received so far (followers)
+    incoming_snapshot: Mutex<Option<IncomingSnapshot>>,
+
+    /// Snapshots being streamed to followers (leaders)
+    outgoing_snapshots: Mutex<HashMap<NodeId, OutgoingSnapshot>>,
+
+    /// Target and start of an ongoing leadership transfer (leaders)
+    transfer: Mutex<Option<(NodeId, Instant)>>,
+
+    /// State machine committed commands are applied to
+    state_machine: Option<Arc<dyn StateMachine>>,
+
+    /// Wakes the applier when the commit index advances
+    apply_notify: Notify,
+
+    /// Held while the state machine changes, so snapshots see a consistent state
+    apply_lock: Mutex<()>,
+
+    /// Proposals waiting to be applied, by log index
+    apply_waiters: Mutex<HashMap<LogIndex, (Term, ProposalSender)>>,
+
+    /// Sequence number of the last AppendEntries sent (leaders)
+    append_seq: AtomicU64,
+
+    /// Reads waiting to be answered (leaders)
+    pending_reads: Mutex<Vec<PendingRead>>,
+
+    /// Transport to the other cluster members
+    transport: Arc<dyn RaftTransport>,
+
+    /// Stable storage for term, vote and log
+    storage: Mutex<Box<dyn RaftStorage>>,
+
+    /// Set once the node is shut down
+    stopped: AtomicBool,
+
     /// Channel for internal messages
     internal_tx: mpsc::UnboundedSender<InternalMessage>,
-    internal_rx: Arc<RwLock<mpsc::UnboundedReceiver<InternalMessage>>>,
+    internal_rx: Mutex<Option<mpsc::UnboundedReceiver<InternalMessage>>>,
 }
 
 impl RaftNode {
-    /// Create a new Raft node
-    pub fn new(config: RaftNodeConfig) -> Self {
+    /// Create a new Raft node that talks to its peers over `transport`
+    ///
+    /// State is kept in memory only; use [`with_storage`](Self::with_storage)
+    /// for a node that can restart.
+    pub fn new(config: RaftNodeConfig, transport: Arc<dyn RaftTransport>) -> Self {
+        Self::from_parts(
+            config,
+            transport,
+            Box::new(InMemoryStorage::new()),
+            PersistentState::new(),
+        )
+    }
+
+    /// Create a Raft node that persists its state in `storage`
+    ///
+    /// Term, vote, log and snapshot are restored from whatever the storage
+    /// already holds, so a node restarted on the same storage resumes where
+    /// it stopped.
+    pub fn with_storage(
+        config: RaftNodeConfig,
+        transport: Arc<dyn RaftTransport>,
+        mut storage: Box<dyn RaftStorage>,
+    ) -> RaftResult<Self> {
+        let persistent = storage.initial_state()?.into_persistent_state()?;
+        info!(
+            "Restored node {} at term {} with log up to {}",
+            config.node_id,
+            persistent.current_term,
+            persistent.log.last_index()
+        );
+        Ok(Self::from_parts(config, transport, storage, persistent))
+    }
+
+    fn from_parts(
+        config: RaftNodeConfig,
+        transport: Arc<dyn RaftTransport>,
+        storage: Box<dyn RaftStorage>,
+        persistent: PersistentState,
+    ) -> Self {
         let (internal_tx, internal_rx) = mpsc::unbounded_channel();
-        let cluster_size = config.cluster_members.len();
+        let (members, index) = persistent
+            .log
+            .latest_configuration()
+            .unwrap_or_else(|| (config.cluster_members.clone(), 0));
+        let cluster_size = members.len();
+
+        // Everything in the snapshot was committed
+        let mut volatile = VolatileState::new();
+        volatile.update_commit_index(persistent.log.base_index());
+        volatile.apply_entries(persistent.log.base_index());
 
         Self {
-            persistent: Arc::new(RwLock::new(PersistentState::new())),
-            volatile: Arc::new(RwLock::new(VolatileState::new())),
+            persistent: Arc::new(RwLock::new(persistent)),
+            volatile: Arc::new(RwLock::new(volatile)),
             state: Arc::new(RwLock::new(RaftState::Follower)),
             leader_state: Arc::new(RwLock::new(None)),
             election_state: Arc::new(RwLock::new(ElectionState::new(
@@ -137,60 +309,155 @@ impl RaftNode {
                 config.election_timeout_max,
             ))),
             current_leader: Arc::new(RwLock::new(None)),
+            membership: RwLock::new(Membership { members, index }),
+            last_leader_contact: Mutex::new(None),
+            incoming_snapshot: Mutex::new(None),
+            outgoing_snapshots: Mutex::new(HashMap::new()),
+            transfer: Mutex::new(None),
+            state_machine: None,
+            apply_notify: Notify::new(),
+            apply_lock: Mutex::new(()),
+            apply_waiters: Mutex::new(HashMap::new()),
+            append_seq: AtomicU64::new(0),
+            pending_reads: Mutex::new(Vec::new()),
             config,
+            transport,
+            storage: Mutex::new(storage),
+            stopped: AtomicBool::new(false),
             internal_tx,
-            internal_rx: Arc::new(RwLock::new(internal_rx)),
+            internal_rx: Mutex::new(Some(internal_rx)),
+        }
+    }
+
+    /// Apply committed commands to `state_machine`
+    ///
+    /// The state machine is restored from the stored snapshot unless it
+    /// already reflects at least that much of the log. Call before
+    /// [`start`](Self::start).
+    pub fn with_state_machine(mut self, state_machine: Arc<dyn StateMachine>) -> RaftResult<Self> {
+        {
+            let persistent = self.persistent.read();
+            let mut volatile = self.volatile.write();
+            let applied = state_machine.last_applied();
+            if applied >= persistent.log.base_index() {
+                // Whatever it has applied was committed
+                volatile.update_commit_index(applied);
+                volatile.apply_entries(applied);
+            } else if let Some(snapshot) = persistent.log.snapshot() {
+                state_machine.restore(snapshot)?;
+            }
         }
+        self.state_machine = Some(state_machine);
+        Ok(self)
     }
 
     /// Start the Raft node
-    pub async fn start(self: Arc<Self>) {
+    ///
+    /// Runs until [`shutdown`](Self::shutdown) is called or the node's
+    /// transport stops delivering messages. Fails if the node was already
+    /// started or the transport can't listen.
+    pub async fn start(self: Arc<Self>) -> RaftResult<()> {
         info!("Starting Raft node: {}", self.config.node_id);
 
+        let internal_rx = self
+            .internal_rx
+            .lock()
+            .take()
+            .ok_or_else(|| RaftError::Internal("Node already started".to_string()))?;
+        let (inbox, rpc_rx) = RaftInbox::channel();
+        self.transport.listen(inbox)?;
+
         // Spawn election timer task
         self.clone().spawn_election_timer();
 
         // Spawn heartbeat timer task (for leaders)
         self.clone().spawn_heartbeat_timer();
 
+        // Spawn the task applying committed entries
+        self.clone().spawn_applier();
+
         // Main message processing loop
-        self.run().await;
+        self.clone().run(internal_rx, rpc_rx).await;
+        self.stopped.store(true, Ordering::Relaxed);
+        self.apply_notify.notify_one();
+        Ok(())
     }
 
     /// Main message processing loop
-    async fn run(self: Arc<Self>) {
+    async fn run(
+        self: Arc<Self>,
+        mut internal_rx: mpsc::UnboundedReceiver<InternalMessage>,
+        mut rpc_rx: mpsc::UnboundedReceiver<(NodeId, RaftMessage)>,
+    ) {
         loop {
-            let message = {
-                let mut rx = self.internal_rx.write();
-                rx.recv().await
-            };
-
-            match message {
-                Some(InternalMessage::Rpc { from, message }) => {
-                    self.handle_rpc_message(from, message).await;
-                }
-                Some(InternalMessage::ClientCommand {
-                    command,
-                    response_tx,
-                }) => {
-                    self.handle_client_command(command, response_tx).await;
-                }
-                Some(InternalMessage::ElectionTimeout) => {
-                    self.handle_election_timeout().await;
-                }
-                Some(InternalMessage::HeartbeatTimeout) => {
-                    self.handle_heartbeat_timeout().await;
-                }
-                None => {
-                    warn!("Internal channel closed, stopping node");
-                    break;
-                }
+            tokio::select! {
+                rpc = rpc_rx.recv() => match rpc {
+                    Some((from, message)) => self.handle_rpc_message(from, message).await,
+                    None => {
+                        warn!("Transport closed, stopping node");
+                        break;
+                    }
+                },
+                message = internal_rx.recv() => match message {
+                    Some(InternalMessage::ClientCommand {
+                        command,
+                        response_tx,
+                    }) => {
+                        self.handle_client_command(command, response_tx).await;
+                    }
+                    Some(InternalMessage::Proposal {
+                        command,
+                        response_tx,
+                    }) => {
+                        self.handle_proposal(command, response_tx).await;
+                    }
+                    Some(InternalMessage::ReadIndex { response_tx }) => {
+                        self.handle_read_index(response_tx).await;
+                    }
+                    Some(InternalMessage::TakeSnapshot { response_tx }) => {
+                        let _ = response_tx.send(self.snapshot_state_machine()).await;
+                    }
+                    Some(InternalMessage::ElectionTimeout) => {
+                        self.handle_election_timeout().await;
+                    }
+                    Some(InternalMessage::HeartbeatTimeout) => {
+                        self.handle_heartbeat_timeout().await;
+                    }
+                    Some(InternalMessage::ChangeMembership {
+                        change,
+                        response_tx,
+                    }) => {
+                        self.handle_membership_change(change, response_tx).await;
+                    }
+                    Some(InternalMessage::TransferLeadership {
+                        target,
+                        response_tx,
+                    }) => {
+                        self.handle_transfer_leadership(target, response_tx).await;
+                    }
+                    Some(InternalMessage::Shutdown) | None => {
+                        info!("Stopping Raft node: {}", self.config.node_id);
+                        break;
+                    }
+                },
             }
         }
     }
 
     /// Handle RPC message from another node
     async fn handle_rpc_message(&self, from: NodeId, message: RaftMessage) {
+        // While a leader is alive, elections only come from removed servers
+        // that no longer hear from it; ignore them rather than step down
+        if let RaftMessage::RequestVoteRequest(req) = &message {
+            if !req.leadership_transfer && self.leader_is_alive() {
+                debug!(
+                    "Ignoring vote request from {} while the leader is alive",
+                    from
+                );
+                return;
+            }
+        }
+
         // Update term if necessary
         let message_term = message.term();
         let current_term = self.persistent.read().current_term;
@@ -201,51 +468,193 @@ impl RaftNode {
 
         match message {
             RaftMessage::AppendEntriesRequest(req) => {
-                let response = self.handle_append_entries(req).await;
-                // TODO: Send response back to sender
-                debug!("AppendEntries response to {}: {:?}", from, response);
+                let seq = req.seq;
+                let mut response = self.handle_append_entries(req).await;
+                response.seq = seq;
+                self.send(&from, RaftMessage::AppendEntriesResponse(response));
+                self.apply_notify.notify_one();
             }
             RaftMessage::AppendEntriesResponse(resp) => {
                 self.handle_append_entries_response(from, resp).await;
             }
             RaftMessage::RequestVoteRequest(req) => {
                 let response = self.handle_request_vote(req).await;
-                // TODO: Send response back to sender
-                debug!("RequestVote response to {}: {:?}", from, response);
+                self.send(&from, RaftMessage::RequestVoteResponse(response));
             }
             RaftMessage::RequestVoteResponse(resp) => {
                 self.handle_request_vote_response(from, resp).await;
             }
             RaftMessage::InstallSnapshotRequest(req) => {
                 let response = self.handle_install_snapshot(req).await;
-                // TODO: Send response back to sender
-                debug!("InstallSnapshot response to {}: {:?}", from, response);
+                self.send(&from, RaftMessage::InstallSnapshotResponse(response));
             }
             RaftMessage::InstallSnapshotResponse(resp) => {
                 self.handle_install_snapshot_response(from, resp).await;
             }
+            RaftMessage::TimeoutNow(req) => {
+                self.handle_timeout_now(from, req).await;
+            }
+        }
+    }
+
+    /// Whether we are the leader or heard from one within the minimum election timeout
+    fn leader_is_alive(&self) -> bool {
+        if self.state.read().is_leader() {
+            return true;
+        }
+        let min_timeout = Duration::from_millis(self.config.election_timeout_min);
+        self.last_leader_contact
+            .lock()
+            .is_some_and(|contact| contact.elapsed() < min_timeout)
+    }
+
+    /// Note a message from the leader of the current term
+    fn follow(&self, leader_id: &NodeId) {
+        // A candidate or deposed leader loses to the sender
+        if !self.state.read().is_follower() {
+            self.become_follower();
+        }
+
+        self.election_state.write().reset_timer();
+        *self.current_leader.write() = Some(leader_id.clone());
+        *self.last_leader_contact.lock() = Some(Instant::now());
+    }
+
+    /// Drop to follower, abandoning everything only a leader does
+    fn become_follower(&self) {
+        *self.state.write() = RaftState::Follower;
+        *self.leader_state.write() = None;
+        *self.transfer.lock() = None;
+        for read in self.pending_reads.lock().drain(..) {
+            let _ = read.response_tx.try_send(Err(RaftError::NotLeader));
+        }
+    }
+
+    /// Get the current cluster members
+    pub fn members(&self) -> Vec<NodeId> {
+        self.membership.read().members.clone()
+    }
+
+    /// The members other than this node
+    fn peers(&self) -> Vec<NodeId> {
+        self.membership
+            .read()
+            .members
+            .iter()
+            .filter(|member| **member != self.config.node_id)
+            .cloned()
+            .collect()
+    }
+
+    fn is_member(&self) -> bool {
+        self.membership
+            .read()
+            .members
+            .contains(&self.config.node_id)
+    }
+
+    /// Adopt the latest configuration in `log`
+    ///
+    /// Called whenever configuration entries are appended or truncated, or
+    /// a snapshot is installed.
+    fn refresh_membership(&self, log: &RaftLog) {
+        let (members, index) = log
+            .latest_configuration()
+            .unwrap_or_else(|| (self.config.cluster_members.clone(), 0));
+        {
+            let mut membership = self.membership.write();
+            if membership.members == members {
+                membership.index = index;
+                return;
+            }
+            info!("Cluster configuration at {} is now {:?}", index, members);
+            *membership = Membership {
+                members: members.clone(),
+                index,
+            };
         }
+
+        self.election_state
+            .write()
+            .update_cluster_size(members.len());
+
+        // Start replicating to new members and forget removed ones
+        if let Some(leader_state) = self.leader_state.write().as_mut() {
+            let last_index = log.last_index();
+            for member in &members {
+                if *member != self.config.node_id && !leader_state.next_index.contains_key(member) {
+                    leader_state
+                        .next_index
+                        .insert(member.clone(), last_index + 1);
+                    leader_state.match_index.insert(member.clone(), 0);
+                }
+            }
+            leader_state
+                .next_index
+                .retain(|member, _| members.contains(member));
+            leader_state
+                .match_index
+                .retain(|member, _| members.contains(member));
+        }
+        self.outgoing_snapshots
+            .lock()
+            .retain(|member, _| members.contains(member));
+    }
+
+    /// Send a message to another cluster member
+    fn send(&self, to: &NodeId, message: RaftMessage) {
+        self.transport.send(to, message);
+    }
+
+    /// Durably record the term and vote in `persistent`
+    fn persist_hard_state(&self, persistent: &PersistentState) -> RaftResult<()> {
+        self.storage
+            .lock()
+            .save_hard_state(persistent.current_term, persistent.voted_for.as_ref())
+    }
+
+    /// Durably append a new entry from the current term to the local log
+    fn append_local(
+        &self,
+        persistent: &mut PersistentState,
+        make_entry: impl FnOnce(Term, LogIndex) -> RaftResult<LogEntry>,
+    ) -> RaftResult<LogIndex> {
+        let index = persistent.log.last_index() + 1;
+        let entry = make_entry(persistent.current_term, index)?;
+        self.storage.lock().append(std::slice::from_ref(&entry))?;
+        persistent.log.append_entries(vec![entry])?;
+        Ok(index)
     }
 
     /// Handle AppendEntries RPC
     async fn handle_append_entries(&self, req: AppendEntriesRequest) -> AppendEntriesResponse {
         let mut persistent = self.persistent.write();
-        let mut volatile = self.volatile.write();
 
         // Reply false if term < currentTerm
         if req.term < persistent.current_term {
             return AppendEntriesResponse::failure(persistent.current_term, None, None);
         }
 
-        // Reset election timer
-        self.election_state.write().reset_timer();
-        *self.current_leader.write() = Some(req.leader_id.clone());
+        // The sender is the leader of our term
+        self.follow(&req.leader_id);
 
-        // Reply false if log doesn't contain an entry at prevLogIndex with prevLogTerm
-        if !persistent
-            .log
-            .matches(req.prev_log_index, req.prev_log_term)
+        // Reply false if log doesn't contain an entry at prevLogIndex with prevLogTerm.
+        // Entries up to the snapshot base are committed and match by definition.
+        let base_index = persistent.log.base_index();
+        if req.prev_log_index >= base_index
+            && !persistent
+                .log
+                .matches(req.prev_log_index, req.prev_log_term)
         {
+            let last_index = persistent.log.last_index();
+            if req.prev_log_index > last_index {
+                // Our log is too short; the leader can skip straight past it
+                return AppendEntriesResponse::failure(
+                    persistent.current_term,
+                    Some(last_index + 1),
+                    None,
+                );
+            }
             let conflict_index = req.prev_log_index;
             let conflict_term = persistent.log.term_at(conflict_index);
             return AppendEntriesResponse::failure(
@@ -255,38 +664,66 @@ impl RaftNode {
             );
         }
 
-        // Append new entries
-        if !req.entries.is_empty() {
-            // Delete conflicting entries and append new ones
-            let mut index = req.prev_log_index + 1;
-            for entry in &req.entries {
-                if let Some(existing_term) = persistent.log.term_at(index) {
-                    if existing_term != entry.term {
-                        // Conflict found, truncate from here
-                        let _ = persistent.log.truncate_from(index);
+        // Skip entries we already have, truncate at the first conflict and
+        // append the rest. Retransmitted entries must not truncate anything.
+        let mut new_entries: &[LogEntry] = &[];
+        let mut truncated = false;
+        for (i, entry) in req.entries.iter().enumerate() {
+            if entry.index <= base_index {
+                continue;
+            }
+            match persistent.log.term_at(entry.index) {
+                Some(term) if term == entry.term => continue,
+                Some(_) => {
+                    // Conflict found, truncate from here
+                    if let Err(e) = self.storage.lock().truncate_from(entry.index) {
+                        error!("Failed to truncate log: {}", e);
+                        return AppendEntriesResponse::failure(persistent.current_term, None, None);
                     }
+                    let _ = persistent.log.truncate_from(entry.index);
+                    truncated = true;
+                    new_entries = &req.entries[i..];
                 }
-                index += 1;
+                None => new_entries = &req.entries[i..],
             }
+            break;
+        }
 
-            // Append entries
-            if let Err(e) = persistent.log.append_entries(req.entries.clone()) {
+        if !new_entries.is_empty() {
+            // Entries must be durable before we acknowledge them
+            let appended = self
+                .storage
+                .lock()
+                .append(new_entries)
+                .and_then(|_| persistent.log.append_entries(new_entries.to_vec()));
+            if let Err(e) = appended {
                 error!("Failed to append entries: {}", e);
                 return AppendEntriesResponse::failure(persistent.current_term, None, None);
             }
         }
 
+        // Configurations take effect as soon as they are in the log
+        if truncated
+            || new_entries
+                .iter()
+                .any(|entry| entry.kind == EntryKind::Configuration)
+        {
+            self.refresh_membership(&persistent.log);
+        }
+
+        // Only the entries covered by this request are known to match the leader
+        let last_new_entry = req
+            .entries
+            .last()
+            .map_or(req.prev_log_index, |entry| entry.index);
+
         // Update commit index
+        let mut volatile = self.volatile.write();
         if req.leader_commit > volatile.commit_index {
-            let last_new_entry = if req.entries.is_empty() {
-                req.prev_log_index
-            } else {
-                req.entries.last().unwrap().index
-            };
             volatile.update_commit_index(std::cmp::min(req.leader_commit, last_new_entry));
         }
 
-        AppendEntriesResponse::success(persistent.current_term, persistent.log.last_index())
+        AppendEntriesResponse::success(persistent.current_term, last_new_entry)
     }
 
     /// Handle AppendEntries response
@@ -295,33 +732,179 @@ impl RaftNode {
             return;
         }
 
-        let persistent = self.persistent.write();
-        let mut leader_state_guard = self.leader_state.write();
+        let catch_up = {
+            let persistent = self.persistent.read();
+            if resp.term != persistent.current_term {
+                // Stale response from an earlier term
+                return;
+            }
+            // Any answer in our term shows the follower still follows us
+            self.acknowledge_reads(Some(&from), resp.seq);
+
+            let mut leader_state_guard = self.leader_state.write();
+            let Some(leader_state) = leader_state_guard.as_mut() else {
+                return;
+            };
+            if leader_state.get_next_index(&from).is_none() {
+                // No longer a member
+                return;
+            }
 
-        if let Some(leader_state) = leader_state_guard.as_mut() {
             if resp.success {
-                // Update next_index and match_index
+                // Update next_index and match_index; responses may arrive out of order
                 if let Some(match_index) = resp.match_index {
-                    leader_state.update_replication(&from, match_index);
-
-                    // Update commit index
-                    let new_commit = leader_state.calculate_commit_index();
-                    let mut volatile = self.volatile.write();
-                    if new_commit > volatile.commit_index {
-                        // Verify the entry is from current term
-                        if let Some(term) = persistent.log.term_at(new_commit) {
-                            if term == persistent.current_term {
-                                volatile.update_commit_index(new_commit);
-                                info!("Updated commit index to {}", new_commit);
-                            }
-                        }
+                    if match_index > leader_state.get_match_index(&from).unwrap_or(0) {
+                        leader_state.update_replication(&from, match_index);
                     }
                 }
             } else {
-                // Decrement next_index and retry
-                leader_state.decrement_next_index(&from);
-                debug!("Replication failed for {}, decrementing next_index", from);
+                // Back up next_index, skipping to the follower's hint if it has one
+                let next_index = leader_state.get_next_index(&from).unwrap_or(1);
+                let retry_from = match resp.conflict_index {
+                    Some(hint) => hint.min(next_index.saturating_sub(1)),
+                    None => next_index.saturating_sub(1),
+                };
+                leader_state
+                    .next_index
+                    .insert(from.clone(), retry_from.max(1));
+                debug!(
+                    "Replication failed for {}, retrying from {}",
+                    from,
+                    retry_from.max(1)
+                );
+            }
+
+            leader_state.get_next_index(&from).unwrap_or(0) <= persistent.log.last_index()
+        };
+
+        // Keep a lagging follower busy instead of waiting for the next heartbeat
+        if catch_up {
+            self.replicate_to(&from);
+        } else if resp.success {
+            self.send_timeout_now_if_ready(&from);
+        }
+        self.advance_commit_index();
+    }
+
+    /// Commit up to the highest index a majority of the members have stored
+    fn advance_commit_index(&self) {
+        let advanced = {
+            let persistent = self.persistent.read();
+            let leader_state = self.leader_state.read();
+            let Some(leader_state) = leader_state.as_ref() else {
+                return;
+            };
+
+            let new_commit = self.quorum_match_index(leader_state, persistent.log.last_index());
+            let mut volatile = self.volatile.write();
+            // Only entries from the current term commit by counting replicas
+            if new_commit > volatile.commit_index
+                && persistent.log.term_at(new_commit) == Some(persistent.current_term)
+            {
+                volatile.update_commit_index(new_commit);
+                debug!("Updated commit index to {}", new_commit);
+                true
+            } else {
+                false
+            }
+        };
+
+        if advanced {
+            self.apply_notify.notify_one();
+            self.step_down_if_removed();
+        }
+    }
+
+    /// Apply newly committed entries to the state machine, in order
+    ///
+    /// Only command entries reach the state machine. Proposals waiting on
+    /// an applied index get the state machine's response, or `NotLeader`
+    /// if a different entry ended up at their index.
+    fn apply_committed(&self) {
+        loop {
+            let entries = {
+                let persistent = self.persistent.read();
+                let volatile = self.volatile.read();
+                if volatile.last_applied >= volatile.commit_index {
+                    break;
+                }
+                let mut entries = persistent.log.entries_range(
+                    volatile.last_applied + 1,
+                    self.config.max_entries_per_message,
+                );
+                entries.retain(|entry| entry.index <= volatile.commit_index);
+                entries
+            };
+            if entries.is_empty() {
+                break;
             }
+
+            for entry in entries {
+                let response = {
+                    let _applying = self.apply_lock.lock();
+                    // An installed snapshot may have overtaken the batch
+                    if entry.index != self.volatile.read().last_applied + 1 {
+                        continue;
+                    }
+                    let response = match (&self.state_machine, entry.kind) {
+                        (Some(state_machine), EntryKind::Command) => state_machine.apply(&entry),
+                        _ => Vec::new(),
+                    };
+                    self.volatile.write().apply_entries(entry.index);
+                    response
+                };
+
+                if let Some((term, response_tx)) = self.apply_waiters.lock().remove(&entry.index) {
+                    let result = if term == entry.term {
+                        Ok(response)
+                    } else {
+                        Err(RaftError::NotLeader)
+                    };
+                    let _ = response_tx.try_send(result);
+                }
+            }
+        }
+
+        self.answer_reads();
+    }
+
+    /// Highest log index stored on a majority of the members
+    ///
+    /// The leader counts its own log only while it is a member itself.
+    fn quorum_match_index(&self, leader_state: &LeaderState, last_index: LogIndex) -> LogIndex {
+        let mut indices: Vec<LogIndex> = self
+            .membership
+            .read()
+            .members
+            .iter()
+            .map(|member| {
+                if *member == self.config.node_id {
+                    last_index
+                } else {
+                    leader_state.get_match_index(member).unwrap_or(0)
+                }
+            })
+            .collect();
+        if indices.is_empty() {
+            return 0;
+        }
+        indices.sort_unstable_by(|a, b| b.cmp(a));
+
+        let quorum = indices.len() / 2 + 1;
+        indices[quorum - 1]
+    }
+
+    /// A leader that committed its own removal hands over to the rest
+    fn step_down_if_removed(&self) {
+        let removed = {
+            let membership = self.membership.read();
+            !membership.members.contains(&self.config.node_id)
+                && membership.index <= self.volatile.read().commit_index
+        };
+        if removed && self.state.read().is_leader() {
+            info!("Removed from the cluster, stepping down");
+            self.become_follower();
+            *self.current_leader.write() = None;
         }
     }
 
@@ -350,7 +933,13 @@ impl RaftNode {
         );
 
         if should_grant {
+            // The vote must be durable before it is granted
             persistent.vote_for(req.candidate_id.clone());
+            if let Err(e) = self.persist_hard_state(&persistent) {
+                error!("Failed to persist vote: {}", e);
+                persistent.voted_for = None;
+                return RequestVoteResponse::denied(persistent.current_term);
+            }
             self.election_state.write().reset_timer();
             info!("Granted vote to {} for term {}", req.candidate_id, req.term);
             RequestVoteResponse::granted(persistent.current_term)
@@ -371,7 +960,7 @@ impl RaftNode {
             return;
         }
 
-        if resp.vote_granted {
+        if resp.vote_granted && self.membership.read().members.contains(&from) {
             let won_election = self.election_state.write().record_vote(from.clone());
             if won_election {
                 info!("Won election for term {}", current_term);
@@ -381,28 +970,170 @@ impl RaftNode {
     }
 
     /// Handle InstallSnapshot RPC
+    ///
+    /// Chunks must arrive in order; anything else is answered with the
+    /// offset we expect next. The last chunk installs the snapshot.
     async fn handle_install_snapshot(
         &self,
         req: InstallSnapshotRequest,
     ) -> InstallSnapshotResponse {
-        let persistent = self.persistent.write();
+        let mut persistent = self.persistent.write();
+        let term = persistent.current_term;
 
-        if req.term < persistent.current_term {
-            return InstallSnapshotResponse::failure(persistent.current_term);
+        if req.term < term {
+            return InstallSnapshotResponse::failure(term);
+        }
+        self.follow(&req.leader_id);
+
+        // Everything the snapshot covers is already committed here
+        if req.last_included_index <= self.volatile.read().commit_index {
+            *self.incoming_snapshot.lock() = None;
+            return InstallSnapshotResponse::success(term, None);
+        }
+
+        let mut incoming = self.incoming_snapshot.lock();
+        if req.offset == 0 {
+            *incoming = Some(IncomingSnapshot {
+                last_included_index: req.last_included_index,
+                last_included_term: req.last_included_term,
+                data: Vec::new(),
+            });
+        }
+        let Some(pending) = incoming.as_mut().filter(|pending| {
+            pending.last_included_index == req.last_included_index
+                && pending.last_included_term == req.last_included_term
+        }) else {
+            return InstallSnapshotResponse::resume(term, 0);
+        };
+        if req.offset != pending.data.len() as u64 {
+            return InstallSnapshotResponse::resume(term, pending.data.len() as u64);
+        }
+
+        pending.data.extend_from_slice(&req.data);
+        if !req.done {
+            return InstallSnapshotResponse::success(term, Some(pending.data.len() as u64));
         }
 
-        // TODO: Implement snapshot installation
-        // For now, just acknowledge
-        InstallSnapshotResponse::success(persistent.current_term, None)
+        let pending = incoming.take().expect("checked above");
+        let snapshot = Snapshot {
+            last_included_index: pending.last_included_index,
+            last_included_term: pending.last_included_term,
+            data: pending.data,
+            configuration: req.configuration,
+        };
+        match self.install_snapshot(&mut persistent, snapshot) {
+            Ok(()) => {
+                info!(
+                    "Installed snapshot up to {} from {}",
+                    req.last_included_index, req.leader_id
+                );
+                InstallSnapshotResponse::success(term, None)
+            }
+            Err(e) => {
+                error!("Failed to install snapshot: {}", e);
+                InstallSnapshotResponse::failure(term)
+            }
+        }
     }
 
-    /// Handle InstallSnapshot response
-    async fn handle_install_snapshot_response(
+    /// Replace the log up to the snapshot and store it
+    fn install_snapshot(
         &self,
-        _from: NodeId,
-        _resp: InstallSnapshotResponse,
-    ) {
-        // TODO: Implement snapshot response handling
+        persistent: &mut PersistentState,
+        snapshot: Snapshot,
+    ) -> RaftResult<()> {
+        let index = snapshot.last_included_index;
+
+        // Entries past the snapshot survive only if they follow on from it
+        let keep_suffix = persistent.log.matches(index, snapshot.last_included_term);
+        {
+            let mut storage = self.storage.lock();
+            if !keep_suffix && persistent.log.last_index() > index {
+                storage.truncate_from(index + 1)?;
+            }
+            storage.save_snapshot(&snapshot)?;
+        }
+
+        let _applying = self.apply_lock.lock();
+        if let Some(state_machine) = &self.state_machine {
+            // A snapshot older than what's applied adds nothing
+            if index > self.volatile.read().last_applied {
+                state_machine.restore(&snapshot)?;
+            }
+        }
+        persistent.log.install_snapshot(snapshot)?;
+        if !keep_suffix {
+            persistent.log.truncate_from(index + 1)?;
+        }
+        self.refresh_membership(&persistent.log);
+
+        {
+            let mut volatile = self.volatile.write();
+            volatile.update_commit_index(index);
+            volatile.apply_entries(index);
+        }
+        // Proposals the snapshot skipped over have lost track of their outcome
+        self.apply_waiters
+            .lock()
+            .retain(|&waiting, (_, response_tx)| {
+                if waiting > index {
+                    return true;
+                }
+                let _ = response_tx.try_send(Err(RaftError::NotLeader));
+                false
+            });
+        Ok(())
+    }
+
+    /// Handle InstallSnapshot response
+    async fn handle_install_snapshot_response(&self, from: NodeId, resp: InstallSnapshotResponse) {
+        if !self.state.read().is_leader() {
+            return;
+        }
+
+        {
+            let persistent = self.persistent.read();
+            if resp.term != persistent.current_term {
+                return;
+            }
+
+            let mut leader_state_guard = self.leader_state.write();
+            let Some(leader_state) = leader_state_guard.as_mut() else {
+                return;
+            };
+            let mut outgoing = self.outgoing_snapshots.lock();
+            let Some(transfer) = outgoing.get_mut(&from) else {
+                return;
+            };
+
+            match (resp.success, resp.next_offset) {
+                (true, None) => {
+                    // Installed; continue with the entries after it
+                    let index = transfer.last_included_index;
+                    outgoing.remove(&from);
+                    if index > leader_state.get_match_index(&from).unwrap_or(0) {
+                        leader_state.update_replication(&from, index);
+                    }
+                    info!("{} installed the snapshot up to {}", from, index);
+                }
+                (_, Some(offset)) => transfer.offset = offset,
+                (false, None) => return,
+            }
+        }
+
+        self.replicate_to(&from);
+        self.advance_commit_index();
+    }
+
+    /// Handle TimeoutNow from a leader handing over leadership
+    async fn handle_timeout_now(&self, from: NodeId, req: TimeoutNowRequest) {
+        let current_term = self.persistent.read().current_term;
+        if req.term != current_term || !self.state.read().is_follower() || !self.is_member() {
+            return;
+        }
+
+        info!("{} is handing over leadership", from);
+        self.start_election(true).await;
     }
 
     /// Handle client command
@@ -411,22 +1142,262 @@ impl RaftNode {
         command: Command,
         response_tx: mpsc::Sender<RaftResult<CommandResult>>,
     ) {
-        // Only leader can handle client commands
-        if !self.state.read().is_leader() {
-            let _ = response_tx.send(Err(RaftError::NotLeader)).await;
+        let result = self.append_command(command);
+        let appended = result.is_ok();
+        let _ = response_tx.send(result).await;
+        if !appended {
             return;
         }
 
+        // Trigger immediate replication
+        self.replicate_to_all();
+        self.advance_commit_index();
+    }
+
+    /// Handle a command whose state machine response the client awaits
+    async fn handle_proposal(
+        &self,
+        command: Command,
+        response_tx: mpsc::Sender<RaftResult<Vec<u8>>>,
+    ) {
+        match self.append_command(command) {
+            Ok(result) => {
+                self.apply_waiters
+                    .lock()
+                    .insert(result.index, (result.term, response_tx));
+                self.replicate_to_all();
+                self.advance_commit_index();
+            }
+            Err(e) => {
+                let _ = response_tx.send(Err(e)).await;
+            }
+        }
+    }
+
+    /// Append a client command to the leader's log
+    fn append_command(&self, command: Command) -> RaftResult<CommandResult> {
+        // Only leader can handle client commands, and not while handing over
+        if !self.state.read().is_leader() || self.transfer.lock().is_some() {
+            return Err(RaftError::NotLeader);
+        }
+
         let mut persistent = self.persistent.write();
         let term = persistent.current_term;
-        let index = persistent.log.append(term, command.data);
+        self.append_local(&mut persistent, |term, index| {
+            Ok(LogEntry::new(term, index, command.data))
+        })
+        .map(|index| CommandResult { index, term })
+    }
 
-        let result = CommandResult { index, term };
-        let _ = response_tx.send(Ok(result)).await;
+    /// Handle a linearizable read
+    ///
+    /// The read index is the commit index, or the leader's first entry of
+    /// its term if that hasn't committed yet. The read is answered once a
+    /// quorum has answered a heartbeat sent after it arrived and the state
+    /// machine has applied the read index.
+    async fn handle_read_index(&self, response_tx: mpsc::Sender<RaftResult<LogIndex>>) {
+        if !self.state.read().is_leader() || self.transfer.lock().is_some() {
+            let _ = response_tx.send(Err(RaftError::NotLeader)).await;
+            return;
+        }
 
-        // Trigger immediate replication
-        drop(persistent);
-        let _ = self.internal_tx.send(InternalMessage::HeartbeatTimeout);
+        let read_index = {
+            let persistent = self.persistent.read();
+            let commit_index = self.volatile.read().commit_index;
+            let term = persistent.current_term;
+            if persistent.log.term_at(commit_index) == Some(term) {
+                commit_index
+            } else {
+                (commit_index + 1..=persistent.log.last_index())
+                    .find(|&index| persistent.log.term_at(index) == Some(term))
+                    .unwrap_or(commit_index)
+            }
+        };
+
+        self.pending_reads.lock().push(PendingRead {
+            read_index,
+            seq: self.append_seq.load(Ordering::Relaxed) + 1,
+            acks: HashSet::new(),
+            confirmed: false,
+            response_tx,
+        });
+        self.replicate_to_all();
+        // A single-node cluster is its own quorum
+        self.acknowledge_reads(None, 0);
+    }
+
+    /// Count an answer from `from` towards reads registered before request `seq` went out
+    fn acknowledge_reads(&self, from: Option<&NodeId>, seq: u64) {
+        let members = self.members();
+        let quorum = members.len() / 2 + 1;
+        let own_vote = usize::from(members.contains(&self.config.node_id));
+        {
+            let mut reads = self.pending_reads.lock();
+            for read in reads.iter_mut().filter(|read| !read.confirmed) {
+                if let Some(from) = from.filter(|from| seq >= read.seq && members.contains(from)) {
+                    read.acks.insert(from.clone());
+                }
+                read.confirmed = read.acks.len() + own_vote >= quorum;
+            }
+        }
+        self.answer_reads();
+    }
+
+    /// Answer confirmed reads the state machine has caught up with
+    fn answer_reads(&self) {
+        let last_applied = self.volatile.read().last_applied;
+        self.pending_reads.lock().retain(|read| {
+            if read.confirmed && read.read_index <= last_applied {
+                let _ = read.response_tx.try_send(Ok(read.read_index));
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    /// Snapshot the state machine at the last applied entry and compact the log
+    fn snapshot_state_machine(&self) -> RaftResult<Snapshot> {
+        let state_machine = self
+            .state_machine
+            .as_ref()
+            .ok_or_else(|| RaftError::ConfigError("No state machine attached".to_string()))?;
+        let (last_applied, data) = {
+            let _applying = self.apply_lock.lock();
+            (self.volatile.read().last_applied, state_machine.snapshot()?)
+        };
+        self.compact_log(last_applied, data)
+    }
+
+    /// Handle a membership change
+    ///
+    /// One server is added or removed at a time, and only once the previous
+    /// change and an entry from the leader's term have committed, so any two
+    /// consecutive configurations share a majority.
+    async fn handle_membership_change(
+        &self,
+        change: MembershipChange,
+        response_tx: mpsc::Sender<RaftResult<CommandResult>>,
+    ) {
+        if !self.state.read().is_leader() || self.transfer.lock().is_some() {
+            let _ = response_tx.send(Err(RaftError::NotLeader)).await;
+            return;
+        }
+
+        let result = {
+            let mut persistent = self.persistent.write();
+            let term = persistent.current_term;
+            let commit_index = self.volatile.read().commit_index;
+            let mut members = self.membership.read().clone();
+
+            if members.index > commit_index {
+                Err(RaftError::ConfigError(
+                    "A membership change is already in progress".to_string(),
+                ))
+            } else if persistent.log.term_at(commit_index) != Some(term) {
+                Err(RaftError::ConfigError(
+                    "The leader has not committed an entry in its term yet".to_string(),
+                ))
+            } else {
+                let changed = match change {
+                    MembershipChange::Add(node_id) if !members.members.contains(&node_id) => {
+                        members.members.push(node_id);
+                        Ok(())
+                    }
+                    MembershipChange::Add(node_id) => Err(RaftError::ConfigError(format!(
+                        "{} is already a member",
+                        node_id
+                    ))),
+                    MembershipChange::Remove(node_id) if members.members.len() == 1 => {
+                        Err(RaftError::ConfigError(format!(
+                            "Can't remove {}, the last member",
+                            node_id
+                        )))
+                    }
+                    MembershipChange::Remove(node_id) => {
+                        let before = members.members.len();
+                        members.members.retain(|member| *member != node_id);
+                        if members.members.len() == before {
+                            Err(RaftError::ConfigError(format!(
+                                "{} is not a member",
+                                node_id
+                            )))
+                        } else {
+                            Ok(())
+                        }
+                    }
+                };
+                changed
+                    .and_then(|_| {
+                        self.append_local(&mut persistent, |term, index| {
+                            LogEntry::configuration(term, index, &members.members)
+                        })
+                    })
+                    .map(|index| {
+                        self.refresh_membership(&persistent.log);
+                        CommandResult { index, term }
+                    })
+            }
+        };
+        let appended = result.is_ok();
+        let _ = response_tx.send(result).await;
+        if appended {
+            self.replicate_to_all();
+            self.advance_commit_index();
+        }
+    }
+
+    /// Handle a leadership transfer request
+    ///
+    /// Client commands are refused until the transfer completes or times
+    /// out after an election timeout, so the target can catch up.
+    async fn handle_transfer_leadership(
+        &self,
+        target: NodeId,
+        response_tx: mpsc::Sender<RaftResult<()>>,
+    ) {
+        let result = if !self.state.read().is_leader() {
+            Err(RaftError::NotLeader)
+        } else if target == self.config.node_id {
+            Ok(())
+        } else if !self.peers().contains(&target) {
+            Err(RaftError::ConfigError(format!(
+                "{} is not a member",
+                target
+            )))
+        } else {
+            info!("Transferring leadership to {}", target);
+            *self.transfer.lock() = Some((target.clone(), Instant::now()));
+            Ok(())
+        };
+        let started = result.is_ok() && target != self.config.node_id;
+        let _ = response_tx.send(result).await;
+        if started {
+            self.replicate_to(&target);
+            self.send_timeout_now_if_ready(&target);
+        }
+    }
+
+    /// Tell the transfer target to start an election once it has our whole log
+    fn send_timeout_now_if_ready(&self, member: &NodeId) {
+        if self.transfer.lock().as_ref().map(|(target, _)| target) != Some(member) {
+            return;
+        }
+
+        let request = {
+            let persistent = self.persistent.read();
+            let leader_state = self.leader_state.read();
+            let caught_up = leader_state
+                .as_ref()
+                .and_then(|ls| ls.get_match_index(member))
+                == Some(persistent.log.last_index());
+            if !caught_up {
+                return;
+            }
+            TimeoutNowRequest::new(persistent.current_term, self.config.node_id.clone())
+        };
+        debug!("Sending TimeoutNow to {}", member);
+        self.send(member, RaftMessage::TimeoutNow(request));
     }
 
     /// Handle election timeout
@@ -439,84 +1410,124 @@ impl RaftNode {
             return;
         }
 
+        // Servers outside the configuration never campaign
+        if !self.is_member() {
+            self.election_state.write().reset_timer();
+            return;
+        }
+
         info!("Election timeout, starting election");
-        self.start_election().await;
+        self.start_election(false).await;
     }
 
     /// Start a new election
-    async fn start_election(&self) {
+    ///
+    /// `leadership_transfer` marks an election requested by the leader,
+    /// which voters must not ignore while they still hear from it.
+    async fn start_election(&self, leadership_transfer: bool) {
         // Transition to candidate
         *self.state.write() = RaftState::Candidate;
+        *self.current_leader.write() = None;
+        *self.last_leader_contact.lock() = None;
 
         // Increment term and vote for self
-        let mut persistent = self.persistent.write();
-        persistent.increment_term();
-        persistent.vote_for(self.config.node_id.clone());
-        let term = persistent.current_term;
+        let (term, last_log_index, last_log_term) = {
+            let mut persistent = self.persistent.write();
+            persistent.increment_term();
+            persistent.vote_for(self.config.node_id.clone());
+            if let Err(e) = self.persist_hard_state(&persistent) {
+                // Don't ask for votes in a term we might forget
+                error!("Failed to persist term: {}", e);
+                return;
+            }
+            (
+                persistent.current_term,
+                persistent.log.last_index(),
+                persistent.log.last_term(),
+            )
+        };
 
         // Initialize election state
-        self.election_state
-            .write()
-            .start_election(term, &self.config.node_id);
-
-        let last_log_index = persistent.log.last_index();
-        let last_log_term = persistent.log.last_term();
+        let won_election = {
+            let mut election_state = self.election_state.write();
+            election_state.start_election(term, &self.config.node_id);
+            election_state.votes.has_quorum()
+        };
 
         info!(
             "Starting election for term {} as {}",
             term, self.config.node_id
         );
 
+        // A single-node cluster elects itself
+        if won_election {
+            self.become_leader().await;
+            return;
+        }
+
         // Send RequestVote RPCs to all other nodes
-        for member in &self.config.cluster_members {
-            if member != &self.config.node_id {
-                let _request = RequestVoteRequest::new(
-                    term,
-                    self.config.node_id.clone(),
-                    last_log_index,
-                    last_log_term,
-                );
-                // TODO: Send request to member
-                debug!("Would send RequestVote to {}", member);
-            }
+        let mut request = RequestVoteRequest::new(
+            term,
+            self.config.node_id.clone(),
+            last_log_index,
+            last_log_term,
+        );
+        request.leadership_transfer = leadership_transfer;
+        for member in self.peers() {
+            self.send(&member, RaftMessage::RequestVoteRequest(request.clone()));
         }
     }
 
     /// Become leader after winning election
     async fn become_leader(&self) {
-        info!(
-            "Becoming leader for term {}",
-            self.persistent.read().current_term
-        );
+        let term = self.persistent.read().current_term;
+        info!("Becoming leader for term {}", term);
 
         *self.state.write() = RaftState::Leader;
         *self.current_leader.write() = Some(self.config.node_id.clone());
+        self.outgoing_snapshots.lock().clear();
+        *self.transfer.lock() = None;
+
+        // Commit a no-op so entries from earlier terms commit without
+        // waiting for the next client command
+        let noop_index = {
+            let mut persistent = self.persistent.write();
+            match self.append_local(&mut persistent, |term, index| {
+                Ok(LogEntry::noop(term, index))
+            }) {
+                Ok(index) => index,
+                Err(e) => {
+                    error!("Failed to append no-op entry, stepping down: {}", e);
+                    self.become_follower();
+                    *self.current_leader.write() = None;
+                    return;
+                }
+            }
+        };
 
-        let last_log_index = self.persistent.read().log.last_index();
-        let other_members: Vec<_> = self
-            .config
-            .cluster_members
-            .iter()
-            .filter(|m| *m != &self.config.node_id)
-            .cloned()
-            .collect();
-
-        *self.leader_state.write() = Some(LeaderState::new(&other_members, last_log_index));
+        *self.leader_state.write() = Some(LeaderState::new(&self.peers(), noop_index - 1));
 
         // Send initial heartbeats
-        let _ = self.internal_tx.send(InternalMessage::HeartbeatTimeout);
+        self.replicate_to_all();
+        self.advance_commit_index();
     }
 
     /// Step down to follower (when discovering higher term)
     async fn step_down(&self, term: Term) {
         info!("Stepping down to follower for term {}", term);
 
-        *self.state.write() = RaftState::Follower;
-        *self.leader_state.write() = None;
+        self.become_follower();
         *self.current_leader.write() = None;
 
         let mut persistent = self.persistent.write();
-        persistent.update_term(term);
+        if persistent.update_term(term) {
+            if let Err(e) = self.persist_hard_state(&persistent) {
+                error!("Failed to persist term {}: {}", term, e);
+            }
+        }
+
+        // Give the new leader a full timeout to reach us before competing with it
+        self.election_state.write().reset_timer();
     }
 
     /// Handle heartbeat timeout (for leaders)
@@ -525,42 +1536,158 @@ impl RaftNode {
             return;
         }
 
+        // Give up on a transfer the target couldn't complete in time
+        {
+            let transfer_timeout = Duration::from_millis(self.config.election_timeout_max);
+            let mut transfer = self.transfer.lock();
+            if let Some((target, started)) = transfer.as_ref() {
+                if started.elapsed() >= transfer_timeout {
+                    warn!("Leadership transfer to {} timed out", target);
+                    *transfer = None;
+                }
+            }
+        }
+
         self.send_heartbeats().await;
     }
 
     /// Send heartbeats to all followers
+    ///
+    /// Heartbeats carry any entries a follower is missing, so they also
+    /// retry replication that was lost in the network.
     async fn send_heartbeats(&self) {
-        let persistent = self.persistent.read();
-        let term = persistent.current_term;
-        let commit_index = self.volatile.read().commit_index;
-
-        for member in &self.config.cluster_members {
-            if member != &self.config.node_id {
-                let request = AppendEntriesRequest::heartbeat(
-                    term,
-                    self.config.node_id.clone(),
-                    commit_index,
-                );
-                // TODO: Send heartbeat to member
-                debug!("Would send heartbeat to {}", member);
+        self.replicate_to_all();
+    }
+
+    /// Send AppendEntries to every follower
+    fn replicate_to_all(&self) {
+        for member in self.peers() {
+            self.replicate_to(&member);
+        }
+    }
+
+    /// Send AppendEntries with the entries `member` is missing
+    ///
+    /// A follower that needs entries already compacted away gets the next
+    /// chunk of the snapshot instead.
+    fn replicate_to(&self, member: &NodeId) {
+        let message = {
+            let persistent = self.persistent.read();
+            let leader_state = self.leader_state.read();
+            let Some(leader_state) = leader_state.as_ref() else {
+                return;
+            };
+
+            let last_index = persistent.log.last_index();
+            let next_index = leader_state
+                .get_next_index(member)
+                .unwrap_or(last_index + 1);
+            if next_index <= persistent.log.base_index() {
+                if let Some(snapshot) = persistent.log.snapshot() {
+                    let request = self.snapshot_chunk(member, snapshot, persistent.current_term);
+                    self.send(member, RaftMessage::InstallSnapshotRequest(request));
+                    return;
+                }
             }
+
+            let next_index = next_index.max(persistent.log.base_index() + 1);
+            let prev_log_index = next_index - 1;
+            let prev_log_term = persistent.log.term_at(prev_log_index).unwrap_or(0);
+            let entries = persistent
+                .log
+                .entries_range(next_index, self.config.max_entries_per_message);
+
+            let mut request = AppendEntriesRequest::new(
+                persistent.current_term,
+                self.config.node_id.clone(),
+                prev_log_index,
+                prev_log_term,
+                entries,
+                self.volatile.read().commit_index,
+            );
+            request.seq = self.append_seq.fetch_add(1, Ordering::Relaxed) + 1;
+            RaftMessage::AppendEntriesRequest(request)
+        };
+
+        self.send(member, message);
+    }
+
+    /// The chunk of `snapshot` to send `member` next
+    ///
+    /// The transfer restarts from the beginning whenever the leader has
+    /// taken a newer snapshot.
+    fn snapshot_chunk(
+        &self,
+        member: &NodeId,
+        snapshot: &Snapshot,
+        term: Term,
+    ) -> InstallSnapshotRequest {
+        let mut outgoing = self.outgoing_snapshots.lock();
+        let transfer = outgoing.entry(member.clone()).or_insert(OutgoingSnapshot {
+            last_included_index: snapshot.last_included_index,
+            offset: 0,
+        });
+        if transfer.last_included_index != snapshot.last_included_index {
+            *transfer = OutgoingSnapshot {
+                last_included_index: snapshot.last_included_index,
+                offset: 0,
+            };
         }
+
+        InstallSnapshotRequest::new(
+            term,
+            self.config.node_id.clone(),
+            snapshot,
+            transfer.offset,
+            self.config.snapshot_chunk_size,
+        )
     }
 
     /// Spawn election timer task
     fn spawn_election_timer(self: Arc<Self>) {
         let node = self.clone();
         tokio::spawn(async move {
-            let mut interval = interval(Duration::from_millis(50));
+            let mut interval = interval(Duration::from_millis(10));
             loop {
                 interval.tick().await;
-                if node.election_state.read().should_start_election() {
-                    let _ = node.internal_tx.send(InternalMessage::ElectionTimeout);
+                if node.stopped.load(Ordering::Relaxed) {
+                    break;
+                }
+                if node.election_state.read().should_start_election()
+                    && node
+                        .internal_tx
+                        .send(InternalMessage::ElectionTimeout)
+                        .is_err()
+                {
+                    break;
                 }
             }
         });
     }
 
+    /// Spawn the task applying committed entries
+    ///
+    /// Applying runs off the main loop so a slow state machine doesn't hold
+    /// up heartbeats and elections.
+    fn spawn_applier(self: Arc<Self>) {
+        tokio::spawn(async move {
+            loop {
+                if self.stopped.load(Ordering::Relaxed) {
+                    break;
+                }
+                let node = self.clone();
+                if tokio::task::spawn_blocking(move || node.apply_committed())
+                    .await
+                    .is_err()
+                {
+                    error!("Applying committed entries panicked, stopping the applier");
+                    break;
+                }
+                self.apply_notify.notified().await;
+            }
+        });
+    }
+
     /// Spawn heartbeat timer task
     fn spawn_heartbeat_timer(self: Arc<Self>) {
         let node = self.clone();
@@ -569,23 +1696,113 @@ impl RaftNode {
             let mut interval = interval(Duration::from_millis(interval_ms));
             loop {
                 interval.tick().await;
-                if node.state.read().is_leader() {
-                    let _ = node.internal_tx.send(InternalMessage::HeartbeatTimeout);
+                if node.stopped.load(Ordering::Relaxed) {
+                    break;
+                }
+                if node.state.read().is_leader()
+                    && node
+                        .internal_tx
+                        .send(InternalMessage::HeartbeatTimeout)
+                        .is_err()
+                {
+                    break;
                 }
             }
         });
     }
 
     /// Submit a command to the Raft cluster
+    ///
+    /// Returns once the command is appended to the leader's log; it is
+    /// committed when [`commit_index`](Self::commit_index) reaches its index.
     pub async fn submit_command(&self, data: Vec<u8>) -> RaftResult<CommandResult> {
-        let (tx, mut rx) = mpsc::channel(1);
         let command = Command { data };
+        self.request(|response_tx| InternalMessage::ClientCommand {
+            command,
+            response_tx,
+        })
+        .await
+    }
+
+    /// Submit a command and wait for the state machine's response
+    ///
+    /// Returns once the command is committed and applied. Fails with
+    /// `NotLeader` on followers, or if leadership changed and a different
+    /// entry took the command's place.
+    pub async fn propose(&self, data: Vec<u8>) -> RaftResult<Vec<u8>> {
+        let command = Command { data };
+        self.request(|response_tx| InternalMessage::Proposal {
+            command,
+            response_tx,
+        })
+        .await
+    }
+
+    /// Wait until reading the local state machine is linearizable
+    ///
+    /// Only the leader serves reads. Returns the read index once the
+    /// leader has confirmed with a quorum that it still leads and the state
+    /// machine has applied everything committed before the call, so state
+    /// read afterwards reflects every completed write.
+    pub async fn read_index(&self) -> RaftResult<LogIndex> {
+        self.request(|response_tx| InternalMessage::ReadIndex { response_tx })
+            .await
+    }
 
+    /// Snapshot the state machine and compact the log up to the last applied entry
+    pub async fn take_snapshot(&self) -> RaftResult<Snapshot> {
+        self.request(|response_tx| InternalMessage::TakeSnapshot { response_tx })
+            .await
+    }
+
+    /// Add `node_id` to the cluster
+    ///
+    /// Only the leader accepts membership changes, one at a time. Returns
+    /// once the new configuration is appended; it is in effect immediately
+    /// and final once committed. The new node catches up through log
+    /// replication or a snapshot.
+    pub async fn add_member(&self, node_id: NodeId) -> RaftResult<CommandResult> {
+        self.request(|response_tx| InternalMessage::ChangeMembership {
+            change: MembershipChange::Add(node_id),
+            response_tx,
+        })
+        .await
+    }
+
+    /// Remove `node_id` from the cluster
+    ///
+    /// A leader can remove itself: it keeps leading until the change
+    /// commits and then steps down.
+    pub async fn remove_member(&self, node_id: &NodeId) -> RaftResult<CommandResult> {
+        self.request(|response_tx| InternalMessage::ChangeMembership {
+            change: MembershipChange::Remove(node_id.clone()),
+            response_tx,
+        })
+        .await
+    }
+
+    /// Hand leadership to `target`
+    ///
+    /// Returns once the transfer has started. The leader stops accepting
+    /// commands, brings `target` up to date and tells it to start an
+    /// election; if that doesn't happen within an election timeout, the
+    /// leader carries on.
+    pub async fn transfer_leadership(&self, target: &NodeId) -> RaftResult<()> {
+        self.request(|response_tx| InternalMessage::TransferLeadership {
+            target: target.clone(),
+            response_tx,
+        })
+        .await
+    }
+
+    /// Send a request to the message loop and wait for its answer
+    async fn request<T>(
+        &self,
+        message: impl FnOnce(mpsc::Sender<RaftResult<T>>) -> InternalMessage,
+    ) -> RaftResult<T> {
+        let (tx, mut rx) = mpsc::channel(1);
         self.internal_tx
-            .send(InternalMessage::ClientCommand {
-                command,
-                response_tx: tx,
-            })
+            .send(message(tx))
             .map_err(|_| RaftError::Internal("Node stopped".to_string()))?;
 
         rx.recv()
@@ -593,6 +1810,57 @@ impl RaftNode {
             .ok_or_else(|| RaftError::Internal("Response channel closed".to_string()))?
     }
 
+    /// Compact the log into a snapshot of the state machine
+    ///
+    /// `data` must be the state machine after applying every entry up to
+    /// `up_to_index`, which has to be committed (and applied, with an
+    /// attached state machine; see [`take_snapshot`](Self::take_snapshot)).
+    /// The snapshot is stored before the covered entries are discarded.
+    pub fn compact_log(&self, up_to_index: LogIndex, data: Vec<u8>) -> RaftResult<Snapshot> {
+        let mut persistent = self.persistent.write();
+        let volatile = self.volatile.read();
+        let limit = if self.state_machine.is_some() {
+            volatile.last_applied
+        } else {
+            volatile.commit_index
+        };
+        drop(volatile);
+        if up_to_index > limit {
+            return Err(RaftError::InvalidLogIndex(up_to_index));
+        }
+
+        if up_to_index <= persistent.log.base_index() {
+            return Err(RaftError::InvalidLogIndex(up_to_index));
+        }
+        let configuration = persistent.log.configuration_at(up_to_index).map_or_else(
+            || self.config.cluster_members.clone(),
+            |(members, _)| members,
+        );
+        let snapshot = Snapshot {
+            last_included_index: up_to_index,
+            last_included_term: persistent
+                .log
+                .term_at(up_to_index)
+                .ok_or(RaftError::InvalidLogIndex(up_to_index))?,
+            data,
+            configuration,
+        };
+        self.storage.lock().save_snapshot(&snapshot)?;
+        persistent.log.install_snapshot(snapshot.clone())?;
+        info!("Compacted log up to {}", up_to_index);
+        Ok(snapshot)
+    }
+
+    /// Stop the node
+    ///
+    /// The message loop and timers exit and the node stops answering its
+    /// peers. A node with durable storage can be recreated from it.
+    pub fn shutdown(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        self.apply_notify.notify_one();
+        let _ = self.internal_tx.send(InternalMessage::Shutdown);
+    }
+
     /// Get current state
     pub fn current_state(&self) -> RaftState {
         *self.state.read()
@@ -607,11 +1875,42 @@ impl RaftNode {
     pub fn current_leader(&self) -> Option<NodeId> {
         self.current_leader.read().clone()
     }
+
+    /// Get this node's ID
+    pub fn node_id(&self) -> &NodeId {
+        &self.config.node_id
+    }
+
+    /// Get the index of the highest entry known to be committed
+    pub fn commit_index(&self) -> LogIndex {
+        self.volatile.read().commit_index
+    }
+
+    /// Get the index of the last entry applied to the state machine
+    pub fn last_applied(&self) -> LogIndex {
+        self.volatile.read().last_applied
+    }
+
+    /// Get the index of the last entry in this node's log
+    pub fn last_log_index(&self) -> LogIndex {
+        self.persistent.read().log.last_index()
+    }
+
+    /// Get the log entry at `index`, if this node has it
+    pub fn log_entry(&self, index: LogIndex) -> Option<LogEntry> {
+        self.persistent.read().log.get(index).cloned()
+    }
+
+    /// Get the latest snapshot, if the log was compacted or one was installed
+    pub fn snapshot(&self) -> Option<Snapshot> {
+        self.persistent.read().log.snapshot().cloned()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::transport::InMemoryNetwork;
 
     #[test]
     fn test_node_creation() {
@@ -624,8 +1923,25 @@ mod tests {
             ],
         );
 
-        let node = RaftNode::new(config);
+        let network = InMemoryNetwork::new();
+        let node = RaftNode::new(config, Arc::new(network.transport("node1")));
         assert_eq!(node.current_state(), RaftState::Follower);
         assert_eq!(node.current_term(), 0);
     }
+
+    #[tokio::test]
+    async fn test_single_node_elects_itself_and_commits() {
+        let config = RaftNodeConfig::new("solo".to_string(), vec!["solo".to_string()]);
+        let network = InMemoryNetwork::new();
+        let node = Arc::new(RaftNode::new(config, Arc::new(network.transport("solo"))));
+        tokio::spawn(node.clone().start());
+
+        while !node.current_state().is_leader() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let result = node.submit_command(b"cmd".to_vec()).await.unwrap();
+        assert_eq!(node.commit_index(), result.index);
+        assert_eq!(node.log_entry(result.index).unwrap().command, b"cmd");
+        assert!(node.clone().start().await.is_err());
+    }
 }
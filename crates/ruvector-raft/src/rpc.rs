@@ -4,6 +4,7 @@
 //! - AppendEntries (log replication and heartbeat)
 //! - RequestVote (leader election)
 //! - InstallSnapshot (snapshot transfer)
+//! - TimeoutNow (leadership transfer)
 
 use crate::{log::LogEntry, log::Snapshot, LogIndex, NodeId, Term};
 use serde::{Deserialize, Serialize};
@@ -30,6 +31,10 @@ pub struct AppendEntriesRequest {
 
     /// Leader's commitIndex
     pub leader_commit: LogIndex,
+
+    /// Sequence number echoed in the response, so the leader knows which
+    /// round of requests a follower has answered
+    pub seq: u64,
 }
 
 impl AppendEntriesRequest {
@@ -49,6 +54,7 @@ impl AppendEntriesRequest {
             prev_log_term,
             entries,
             leader_commit,
+            seq: 0,
         }
     }
 
@@ -61,6 +67,7 @@ impl AppendEntriesRequest {
             prev_log_term: 0,
             entries: Vec::new(),
             leader_commit,
+            seq: 0,
         }
     }
 
@@ -99,6 +106,9 @@ pub struct AppendEntriesResponse {
     /// Conflict information for faster log backtracking
     pub conflict_index: Option<LogIndex>,
     pub conflict_term: Option<Term>,
+
+    /// Sequence number of the request this answers
+    pub seq: u64,
 }
 
 impl AppendEntriesResponse {
@@ -110,6 +120,7 @@ impl AppendEntriesResponse {
             match_index: Some(match_index),
             conflict_index: None,
             conflict_term: None,
+            seq: 0,
         }
     }
 
@@ -125,6 +136,7 @@ impl AppendEntriesResponse {
             match_index: None,
             conflict_index,
             conflict_term,
+            seq: 0,
         }
     }
 
@@ -159,6 +171,10 @@ pub struct RequestVoteRequest {
 
     /// Term of candidate's last log entry
     pub last_log_term: Term,
+
+    /// The election was requested by the leader through TimeoutNow, so
+    /// voters must not ignore it while they still hear from that leader
+    pub leadership_transfer: bool,
 }
 
 impl RequestVoteRequest {
@@ -174,6 +190,7 @@ impl RequestVoteRequest {
             candidate_id,
             last_log_index,
             last_log_term,
+            leadership_transfer: false,
         }
     }
 
@@ -251,6 +268,9 @@ pub struct InstallSnapshotRequest {
     /// Term of lastIncludedIndex
     pub last_included_term: Term,
 
+    /// Cluster configuration stored with the snapshot
+    pub configuration: Vec<NodeId>,
+
     /// Byte offset where chunk is positioned in the snapshot file
     pub offset: u64,
 
@@ -262,17 +282,18 @@ pub struct InstallSnapshotRequest {
 }
 
 impl InstallSnapshotRequest {
-    /// Create a new InstallSnapshot request
+    /// Create the request carrying the chunk of `snapshot` that starts at `offset`
     pub fn new(
         term: Term,
         leader_id: NodeId,
-        snapshot: Snapshot,
+        snapshot: &Snapshot,
         offset: u64,
         chunk_size: usize,
     ) -> Self {
         let data_len = snapshot.data.len();
-        let chunk_end = std::cmp::min(offset as usize + chunk_size, data_len);
-        let chunk = snapshot.data[offset as usize..chunk_end].to_vec();
+        let chunk_start = std::cmp::min(offset as usize, data_len);
+        let chunk_end = std::cmp::min(chunk_start + chunk_size, data_len);
+        let chunk = snapshot.data[chunk_start..chunk_end].to_vec();
         let done = chunk_end >= data_len;
 
         Self {
@@ -280,7 +301,8 @@ impl InstallSnapshotRequest {
             leader_id,
             last_included_index: snapshot.last_included_index,
             last_included_term: snapshot.last_included_term,
-            offset,
+            configuration: snapshot.configuration.clone(),
+            offset: chunk_start as u64,
             data: chunk,
             done,
         }
@@ -333,6 +355,49 @@ impl InstallSnapshotResponse {
         }
     }
 
+    /// Reject an out-of-order chunk and ask for the one at `next_offset`
+    pub fn resume(term: Term, next_offset: u64) -> Self {
+        Self {
+            term,
+            success: false,
+            next_offset: Some(next_offset),
+        }
+    }
+
+    /// Serialize to bytes
+    pub fn to_bytes(&self) -> Result<Vec<u8>, bincode::error::EncodeError> {
+        use bincode::config;
+        bincode::encode_to_vec(bincode::serde::Compat(self), config::standard())
+    }
+
+    /// Deserialize from bytes
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, bincode::error::DecodeError> {
+        use bincode::config;
+        let (compat, _): (bincode::serde::Compat<Self>, _) =
+            bincode::decode_from_slice(bytes, config::standard())?;
+        Ok(compat.0)
+    }
+}
+
+/// TimeoutNow RPC request
+///
+/// Sent by a leader handing over leadership; the target starts an election
+/// immediately instead of waiting for its election timeout
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeoutNowRequest {
+    /// Leader's term
+    pub term: Term,
+
+    /// Leader's ID
+    pub leader_id: NodeId,
+}
+
+impl TimeoutNowRequest {
+    /// Create a new TimeoutNow request
+    pub fn new(term: Term, leader_id: NodeId) -> Self {
+        Self { term, leader_id }
+    }
+
     /// Serialize to bytes
     pub fn to_bytes(&self) -> Result<Vec<u8>, bincode::error::EncodeError> {
         use bincode::config;
@@ -357,6 +422,7 @@ pub enum RaftMessage {
     RequestVoteResponse(RequestVoteResponse),
     InstallSnapshotRequest(InstallSnapshotRequest),
     InstallSnapshotResponse(InstallSnapshotResponse),
+    TimeoutNow(TimeoutNowRequest),
 }
 
 impl RaftMessage {
@@ -369,6 +435,7 @@ impl RaftMessage {
             RaftMessage::RequestVoteResponse(resp) => resp.term,
             RaftMessage::InstallSnapshotRequest(req) => req.term,
             RaftMessage::InstallSnapshotResponse(resp) => resp.term,
+            RaftMessage::TimeoutNow(req) => req.term,
         }
     }
 
@@ -431,6 +498,37 @@ mod tests {
         assert_eq!(failure.conflict_index, Some(5));
     }
 
+    #[test]
+    fn test_install_snapshot_chunks() {
+        let snapshot = Snapshot {
+            last_included_index: 7,
+            last_included_term: 2,
+            data: (0..10).collect(),
+            configuration: vec!["node1".to_string()],
+        };
+
+        let first = InstallSnapshotRequest::new(3, "leader".to_string(), &snapshot, 0, 4);
+        assert_eq!(first.data, vec![0, 1, 2, 3]);
+        assert!(!first.done);
+        assert_eq!(first.configuration, snapshot.configuration);
+
+        let last = InstallSnapshotRequest::new(3, "leader".to_string(), &snapshot, 8, 4);
+        assert_eq!(last.data, vec![8, 9]);
+        assert!(last.done);
+
+        // An empty snapshot still takes one (final) chunk
+        let empty = Snapshot {
+            data: Vec::new(),
+            ..snapshot
+        };
+        let only = InstallSnapshotRequest::new(3, "leader".to_string(), &empty, 0, 4);
+        assert!(only.data.is_empty() && only.done);
+
+        let message = RaftMessage::InstallSnapshotRequest(last);
+        let decoded = RaftMessage::from_bytes(&message.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.term(), 3);
+    }
+
     #[test]
     fn test_vote_responses() {
         let granted = RequestVoteResponse::granted(1);
@@ -0,0 +1,38 @@
+//! Replicated state machine interface
+//!
+//! A `RaftNode` applies every committed command entry, in log order, to its
+//! state machine. Snapshots of the state machine let the log be compacted
+//! and bring lagging followers up to date.
+
+use crate::{log::LogEntry, log::Snapshot, LogIndex, RaftResult};
+
+/// A deterministic state machine driven by the Raft log
+///
+/// Every node applies the same commands in the same order, so `apply` must
+/// depend only on the current state and the entry. Methods take `&self`
+/// so callers can keep a handle and read the state; implementations use
+/// interior mutability.
+pub trait StateMachine: Send + Sync {
+    /// Apply a committed command entry and return the response for the
+    /// client that proposed it
+    ///
+    /// Failures of the command itself (such as creating something that
+    /// exists) belong in the response: every node reaches the same
+    /// outcome, and the log moves on.
+    fn apply(&self, entry: &LogEntry) -> Vec<u8>;
+
+    /// Serialize everything applied so far
+    fn snapshot(&self) -> RaftResult<Vec<u8>>;
+
+    /// Replace the state with a snapshot taken by [`snapshot`](Self::snapshot)
+    fn restore(&self, snapshot: &Snapshot) -> RaftResult<()>;
+
+    /// Index of the last entry reflected in the state
+    ///
+    /// State machines that persist their own state report how far it goes,
+    /// so a restarted node doesn't apply those entries twice. In-memory ones
+    /// start empty and keep the default of 0.
+    fn last_applied(&self) -> LogIndex {
+        0
+    }
+}
@@ -0,0 +1,801 @@
+//! Durable storage for Raft state
+//!
+//! Raft requires the current term, the vote and the log entries to be on
+//! stable storage before a node answers an RPC that depends on them.
+//! [`RaftNode`](crate::RaftNode) calls into a [`RaftStorage`] before every
+//! such answer:
+//! - [`InMemoryStorage`] keeps everything in memory, for tests and
+//!   throwaway clusters
+//! - [`FileStorage`] writes a segmented, checksummed write-ahead log and
+//!   fsyncs every change
+
+use crate::{
+    log::{LogEntry, RaftLog, Snapshot},
+    state::PersistentState,
+    LogIndex, NodeId, RaftError, RaftResult, Term,
+};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Default size at which [`FileStorage`] starts a new log segment (bytes)
+pub const DEFAULT_SEGMENT_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Largest log record [`FileStorage`] will read back (bytes)
+const MAX_RECORD_LEN: usize = 256 * 1024 * 1024;
+
+/// Size of a record header: payload length and CRC32, both little-endian u32
+const RECORD_HEADER_LEN: usize = 8;
+
+const HARD_STATE_FILE: &str = "hard_state.bin";
+const SNAPSHOT_FILE: &str = "snapshot.bin";
+const SEGMENT_EXTENSION: &str = "wal";
+
+/// Everything a node needs from storage to resume after a restart
+#[derive(Debug, Clone, Default)]
+pub struct StoredState {
+    /// Latest term the node has seen
+    pub current_term: Term,
+
+    /// Candidate voted for in the current term
+    pub voted_for: Option<NodeId>,
+
+    /// Latest snapshot, covering every entry up to its last included index
+    pub snapshot: Option<Snapshot>,
+
+    /// Log entries after the snapshot, in index order
+    pub entries: Vec<LogEntry>,
+}
+
+impl StoredState {
+    /// Rebuild the node's persistent state
+    pub fn into_persistent_state(self) -> RaftResult<PersistentState> {
+        let mut log = RaftLog::new();
+        if let Some(snapshot) = self.snapshot {
+            log.install_snapshot(snapshot)?;
+        }
+        log.append_entries(self.entries)?;
+
+        Ok(PersistentState {
+            current_term: self.current_term,
+            voted_for: self.voted_for,
+            log,
+        })
+    }
+}
+
+/// Stable storage for a node's term, vote, log and snapshot
+///
+/// Every method must only return once the change is durable: the node
+/// acknowledges votes and entries to its peers right after.
+pub trait RaftStorage: Send {
+    /// Read back everything stored; called once when the node is created
+    fn initial_state(&mut self) -> RaftResult<StoredState>;
+
+    /// Store the current term and vote
+    fn save_hard_state(&mut self, term: Term, voted_for: Option<&NodeId>) -> RaftResult<()>;
+
+    /// Append entries directly following the last stored entry
+    fn append(&mut self, entries: &[LogEntry]) -> RaftResult<()>;
+
+    /// Delete the entry at `index` and every entry after it
+    fn truncate_from(&mut self, index: LogIndex) -> RaftResult<()>;
+
+    /// Store a snapshot and discard the entries it covers
+    fn save_snapshot(&mut self, snapshot: &Snapshot) -> RaftResult<()>;
+}
+
+/// Storage that keeps everything in memory
+///
+/// Nothing survives a restart, so a cluster using it is only safe as long
+/// as a majority of its nodes never restarts.
+#[derive(Debug, Default)]
+pub struct InMemoryStorage {
+    state: StoredState,
+}
+
+impl InMemoryStorage {
+    /// Create empty storage
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl RaftStorage for InMemoryStorage {
+    fn initial_state(&mut self) -> RaftResult<StoredState> {
+        Ok(self.state.clone())
+    }
+
+    fn save_hard_state(&mut self, term: Term, voted_for: Option<&NodeId>) -> RaftResult<()> {
+        self.state.current_term = term;
+        self.state.voted_for = voted_for.cloned();
+        Ok(())
+    }
+
+    fn append(&mut self, entries: &[LogEntry]) -> RaftResult<()> {
+        self.state.entries.extend_from_slice(entries);
+        Ok(())
+    }
+
+    fn truncate_from(&mut self, index: LogIndex) -> RaftResult<()> {
+        self.state.entries.retain(|entry| entry.index < index);
+        Ok(())
+    }
+
+    fn save_snapshot(&mut self, snapshot: &Snapshot) -> RaftResult<()> {
+        self.state
+            .entries
+            .retain(|entry| entry.index > snapshot.last_included_index);
+        self.state.snapshot = Some(snapshot.clone());
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct HardState {
+    current_term: Term,
+    voted_for: Option<NodeId>,
+}
+
+/// One write-ahead log file
+#[derive(Debug)]
+struct Segment {
+    /// Index of the first entry the segment holds (or will hold)
+    first_index: LogIndex,
+    path: PathBuf,
+    /// Byte offset of each record; record `i` holds entry `first_index + i`
+    offsets: Vec<u64>,
+    len: u64,
+}
+
+impl Segment {
+    fn last_index(&self) -> Option<LogIndex> {
+        (!self.offsets.is_empty()).then(|| self.first_index + self.offsets.len() as u64 - 1)
+    }
+}
+
+/// File-backed storage with a segmented write-ahead log
+///
+/// The directory holds:
+/// - `hard_state.bin`: term and vote, replaced atomically
+/// - `snapshot.bin`: the latest snapshot, replaced atomically
+/// - `{first_index}.wal`: log segments of length-prefixed, CRC32-checked
+///   records, one per entry
+///
+/// Appends go to the newest segment, and a new segment is started once it
+/// exceeds the segment size. Every change is fsynced before returning.
+/// Compaction deletes segments whose entries are all covered by the
+/// snapshot. On open, a partially written record at the end of the newest
+/// segment (a crash during an append) is cut off; damage anywhere else is
+/// reported as an error rather than silently dropping entries.
+#[derive(Debug)]
+pub struct FileStorage {
+    dir: PathBuf,
+    segment_size: u64,
+    segments: Vec<Segment>,
+    /// Append handle for the newest segment
+    active: Option<File>,
+    snapshot_index: LogIndex,
+    last_index: LogIndex,
+    /// State read while opening, handed out by the first `initial_state`
+    recovered: Option<StoredState>,
+}
+
+impl FileStorage {
+    /// Open or create storage in `dir` with the default segment size
+    pub fn open(dir: impl AsRef<Path>) -> RaftResult<Self> {
+        Self::with_segment_size(dir, DEFAULT_SEGMENT_SIZE)
+    }
+
+    /// Open or create storage in `dir`, rolling segments at `segment_size` bytes
+    pub fn with_segment_size(dir: impl AsRef<Path>, segment_size: u64) -> RaftResult<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let mut storage = Self {
+            dir,
+            segment_size: segment_size.max(1),
+            segments: Vec::new(),
+            active: None,
+            snapshot_index: 0,
+            last_index: 0,
+            recovered: None,
+        };
+        storage.recovered = Some(storage.recover()?);
+        Ok(storage)
+    }
+
+    /// Number of log segment files
+    pub fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    fn segment_path(&self, first_index: LogIndex) -> PathBuf {
+        self.dir
+            .join(format!("{:020}.{}", first_index, SEGMENT_EXTENSION))
+    }
+
+    /// Read all files back, cutting off a torn record at the end of the log
+    fn recover(&mut self) -> RaftResult<StoredState> {
+        let hard_state: Option<HardState> = read_file(&self.dir.join(HARD_STATE_FILE))?;
+        let snapshot: Option<Snapshot> = read_file(&self.dir.join(SNAPSHOT_FILE))?;
+        self.snapshot_index = snapshot.as_ref().map_or(0, |s| s.last_included_index);
+
+        let mut first_indices = Vec::new();
+        for dir_entry in fs::read_dir(&self.dir)? {
+            let path = dir_entry?.path();
+            if path.extension().and_then(|e| e.to_str()) == Some("tmp") {
+                // Left behind by a crash during an atomic replace
+                let _ = fs::remove_file(&path);
+                continue;
+            }
+            if path.extension().and_then(|e| e.to_str()) != Some(SEGMENT_EXTENSION) {
+                continue;
+            }
+            let first_index = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .and_then(|s| s.parse::<LogIndex>().ok())
+                .ok_or_else(|| {
+                    RaftError::Storage(format!("Unexpected log file {}", path.display()))
+                })?;
+            first_indices.push(first_index);
+        }
+        first_indices.sort_unstable();
+
+        let mut entries = Vec::new();
+        self.segments.clear();
+        let segment_count = first_indices.len();
+        for (i, first_index) in first_indices.into_iter().enumerate() {
+            let is_last = i + 1 == segment_count;
+            let path = self.segment_path(first_index);
+            let (segment_entries, segment) = read_segment(&path, first_index, is_last)?;
+
+            if let Some(previous) = self.segments.last() {
+                let expected = previous.last_index().unwrap_or(previous.first_index - 1) + 1;
+                if first_index != expected {
+                    return Err(RaftError::Storage(format!(
+                        "Log segment {} should start at index {}",
+                        path.display(),
+                        expected
+                    )));
+                }
+            } else if first_index > self.snapshot_index + 1 {
+                return Err(RaftError::Storage(format!(
+                    "Log starts at index {} but the snapshot ends at {}",
+                    first_index, self.snapshot_index
+                )));
+            }
+
+            entries.extend(
+                segment_entries
+                    .into_iter()
+                    .filter(|entry| entry.index > self.snapshot_index),
+            );
+            self.segments.push(segment);
+        }
+
+        if entries.is_empty() && !self.segments.is_empty() {
+            // A crash between storing a snapshot and deleting the segments it covers
+            self.remove_segments_from(0)?;
+            sync_dir(&self.dir)?;
+        }
+
+        self.last_index = entries.last().map_or(self.snapshot_index, |e| e.index);
+        self.active = match self.segments.last() {
+            Some(segment) => Some(OpenOptions::new().append(true).open(&segment.path)?),
+            None => None,
+        };
+
+        let hard_state = hard_state.unwrap_or(HardState {
+            current_term: 0,
+            voted_for: None,
+        });
+        Ok(StoredState {
+            current_term: hard_state.current_term,
+            voted_for: hard_state.voted_for,
+            snapshot,
+            entries,
+        })
+    }
+
+    /// Start a new segment whose first entry will be `first_index`
+    fn roll_segment(&mut self, first_index: LogIndex) -> RaftResult<()> {
+        if let Some(active) = self.active.take() {
+            active.sync_data()?;
+        }
+        let path = self.segment_path(first_index);
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(true)
+            .write(true)
+            .open(&path)?;
+        sync_dir(&self.dir)?;
+
+        self.segments.push(Segment {
+            first_index,
+            path,
+            offsets: Vec::new(),
+            len: 0,
+        });
+        self.active = Some(file);
+        Ok(())
+    }
+
+    /// Delete segment files from `position` on
+    fn remove_segments_from(&mut self, position: usize) -> RaftResult<()> {
+        for segment in self.segments.drain(position..) {
+            fs::remove_file(&segment.path)?;
+        }
+        Ok(())
+    }
+}
+
+impl RaftStorage for FileStorage {
+    fn initial_state(&mut self) -> RaftResult<StoredState> {
+        match self.recovered.take() {
+            Some(state) => Ok(state),
+            None => self.recover(),
+        }
+    }
+
+    fn save_hard_state(&mut self, term: Term, voted_for: Option<&NodeId>) -> RaftResult<()> {
+        let hard_state = HardState {
+            current_term: term,
+            voted_for: voted_for.cloned(),
+        };
+        write_atomic(&self.dir, HARD_STATE_FILE, &encode(&hard_state)?)
+    }
+
+    fn append(&mut self, entries: &[LogEntry]) -> RaftResult<()> {
+        // The whole batch is checked before the log changes, so a bad batch
+        // leaves it untouched
+        let contiguous = entries
+            .iter()
+            .zip(self.last_index + 1..)
+            .all(|(entry, index)| entry.index == index);
+        if !contiguous {
+            return Err(RaftError::LogInconsistency);
+        }
+        let payloads = entries.iter().map(encode).collect::<RaftResult<Vec<_>>>()?;
+
+        let mut pending = Vec::new();
+        for (entry, payload) in entries.iter().zip(payloads) {
+            let needs_roll = match self.segments.last() {
+                None => true,
+                Some(segment) => segment.len >= self.segment_size,
+            };
+            if needs_roll {
+                if let Some(active) = self.active.as_mut() {
+                    active.write_all(&pending)?;
+                }
+                pending.clear();
+                self.roll_segment(entry.index)?;
+            }
+
+            let segment = self.segments.last_mut().expect("segment was just rolled");
+            segment.offsets.push(segment.len);
+            segment.len += (RECORD_HEADER_LEN + payload.len()) as u64;
+            pending.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+            pending.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+            pending.extend_from_slice(&payload);
+            self.last_index = entry.index;
+        }
+
+        if let Some(active) = self.active.as_mut() {
+            active.write_all(&pending)?;
+            active.sync_data()?;
+        }
+        Ok(())
+    }
+
+    fn truncate_from(&mut self, index: LogIndex) -> RaftResult<()> {
+        if index <= self.snapshot_index {
+            return Err(RaftError::InvalidLogIndex(index));
+        }
+        if index > self.last_index {
+            return Ok(());
+        }
+
+        // Segments starting at or after the cut go entirely
+        let keep = self
+            .segments
+            .iter()
+            .position(|segment| segment.first_index >= index)
+            .unwrap_or(self.segments.len());
+        self.active = None;
+        self.remove_segments_from(keep)?;
+
+        if let Some(segment) = self.segments.last_mut() {
+            let position = (index - segment.first_index) as usize;
+            if position < segment.offsets.len() {
+                segment.len = segment.offsets[position];
+                segment.offsets.truncate(position);
+            }
+            let file = OpenOptions::new().append(true).open(&segment.path)?;
+            file.set_len(segment.len)?;
+            file.sync_all()?;
+            self.active = Some(file);
+        }
+        sync_dir(&self.dir)?;
+
+        self.last_index = index - 1;
+        Ok(())
+    }
+
+    fn save_snapshot(&mut self, snapshot: &Snapshot) -> RaftResult<()> {
+        write_atomic(&self.dir, SNAPSHOT_FILE, &encode(snapshot)?)?;
+        let snapshot_index = snapshot.last_included_index;
+        self.snapshot_index = snapshot_index;
+
+        // A segment can go once the next one starts within the snapshot
+        let mut covered = 0;
+        while covered + 1 < self.segments.len()
+            && self.segments[covered + 1].first_index <= snapshot_index + 1
+        {
+            covered += 1;
+        }
+        if self.last_index <= snapshot_index {
+            // The snapshot covers the whole log; the next append starts fresh
+            covered = self.segments.len();
+            self.active = None;
+            self.last_index = snapshot_index;
+        }
+        for segment in self.segments.drain(..covered) {
+            fs::remove_file(&segment.path)?;
+        }
+        sync_dir(&self.dir)
+    }
+}
+
+/// Read every record of a segment
+///
+/// A damaged record at the end of the newest segment is a write cut short by
+/// a crash: the file is truncated there. Anywhere else it is an error.
+fn read_segment(
+    path: &Path,
+    first_index: LogIndex,
+    is_last: bool,
+) -> RaftResult<(Vec<LogEntry>, Segment)> {
+    let mut data = Vec::new();
+    File::open(path)?.read_to_end(&mut data)?;
+
+    let mut entries = Vec::new();
+    let mut offsets = Vec::new();
+    let mut offset = 0usize;
+    let mut damaged = None;
+
+    while offset < data.len() {
+        match read_record(&data[offset..]) {
+            Some((entry, record_len)) if entry.index == first_index + entries.len() as u64 => {
+                offsets.push(offset as u64);
+                entries.push(entry);
+                offset += record_len;
+            }
+            Some((entry, _)) => {
+                damaged = Some(format!(
+                    "entry {} out of order at offset {}",
+                    entry.index, offset
+                ));
+                break;
+            }
+            None => {
+                damaged = Some(format!("damaged record at offset {}", offset));
+                break;
+            }
+        }
+    }
+
+    if let Some(reason) = damaged {
+        if !is_last {
+            return Err(RaftError::Storage(format!(
+                "Log segment {}: {}",
+                path.display(),
+                reason
+            )));
+        }
+        warn!(
+            "Truncating log segment {} after a torn write: {}",
+            path.display(),
+            reason
+        );
+        let file = OpenOptions::new().write(true).open(path)?;
+        file.set_len(offset as u64)?;
+        file.sync_all()?;
+    }
+
+    let segment = Segment {
+        first_index,
+        path: path.to_path_buf(),
+        offsets,
+        len: offset as u64,
+    };
+    Ok((entries, segment))
+}
+
+/// Decode the record at the start of `data`, with its total length
+fn read_record(data: &[u8]) -> Option<(LogEntry, usize)> {
+    if data.len() < RECORD_HEADER_LEN {
+        return None;
+    }
+    let len = u32::from_le_bytes(data[0..4].try_into().ok()?) as usize;
+    let crc = u32::from_le_bytes(data[4..8].try_into().ok()?);
+    if len > MAX_RECORD_LEN || data.len() < RECORD_HEADER_LEN + len {
+        return None;
+    }
+
+    let payload = &data[RECORD_HEADER_LEN..RECORD_HEADER_LEN + len];
+    if crc32fast::hash(payload) != crc {
+        return None;
+    }
+    let entry = decode(payload).ok()?;
+    Some((entry, RECORD_HEADER_LEN + len))
+}
+
+fn encode<T: Serialize>(value: &T) -> RaftResult<Vec<u8>> {
+    use bincode::config;
+    Ok(bincode::encode_to_vec(
+        bincode::serde::Compat(value),
+        config::standard(),
+    )?)
+}
+
+fn decode<T: serde::de::DeserializeOwned>(bytes: &[u8]) -> RaftResult<T> {
+    use bincode::config;
+    let (compat, _): (bincode::serde::Compat<T>, _) =
+        bincode::decode_from_slice(bytes, config::standard())?;
+    Ok(compat.0)
+}
+
+/// Read and decode a whole file, if it exists
+fn read_file<T: serde::de::DeserializeOwned>(path: &Path) -> RaftResult<Option<T>> {
+    match fs::read(path) {
+        Ok(bytes) => Ok(Some(decode(&bytes)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Replace `dir/name` with `data` so that a crash leaves the old or the new contents
+pub(crate) fn write_atomic(dir: &Path, name: &str, data: &[u8]) -> RaftResult<()> {
+    let tmp_path = dir.join(format!("{}.tmp", name));
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, dir.join(name))?;
+    sync_dir(dir)
+}
+
+/// Make file creations, renames and deletions in `dir` durable
+fn sync_dir(dir: &Path) -> RaftResult<()> {
+    #[cfg(unix)]
+    File::open(dir)?.sync_all()?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ruvector-raft-storage-{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn entries(range: std::ops::RangeInclusive<u64>, term: Term) -> Vec<LogEntry> {
+        range
+            .map(|i| LogEntry::new(term, i, format!("cmd{}", i).into_bytes()))
+            .collect()
+    }
+
+    fn snapshot(index: LogIndex, term: Term) -> Snapshot {
+        Snapshot {
+            last_included_index: index,
+            last_included_term: term,
+            data: b"state".to_vec(),
+            configuration: vec!["node1".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_file_storage_reopen() {
+        let dir = test_dir("reopen");
+        {
+            let mut storage = FileStorage::with_segment_size(&dir, 256).unwrap();
+            let state = storage.initial_state().unwrap();
+            assert_eq!(state.current_term, 0);
+            assert!(state.entries.is_empty());
+
+            storage
+                .save_hard_state(3, Some(&"node2".to_string()))
+                .unwrap();
+            storage.append(&entries(1..=10, 1)).unwrap();
+            storage.append(&entries(11..=20, 3)).unwrap();
+            assert!(storage.segment_count() > 1);
+
+            // Entries must follow the last stored one
+            assert!(storage.append(&entries(22..=22, 3)).is_err());
+        }
+
+        let mut storage = FileStorage::with_segment_size(&dir, 256).unwrap();
+        let state = storage.initial_state().unwrap();
+        assert_eq!(state.current_term, 3);
+        assert_eq!(state.voted_for.as_deref(), Some("node2"));
+        assert_eq!(state.entries.len(), 20);
+        assert_eq!(state.entries[14], entries(15..=15, 3)[0]);
+
+        let persistent = state.into_persistent_state().unwrap();
+        assert_eq!(persistent.log.last_index(), 20);
+        assert_eq!(persistent.log.last_term(), 3);
+
+        storage.append(&entries(21..=21, 3)).unwrap();
+        let state = FileStorage::open(&dir).unwrap().initial_state().unwrap();
+        assert_eq!(state.entries.last().unwrap().index, 21);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_file_storage_rejects_gapped_batch() {
+        let dir = test_dir("gapped");
+        let mut storage = FileStorage::with_segment_size(&dir, 128).unwrap();
+        storage.initial_state().unwrap();
+        storage.append(&entries(1..=5, 1)).unwrap();
+
+        // A gap after valid entries rejects the whole batch
+        let mut batch = entries(6..=8, 1);
+        batch.extend(entries(10..=10, 1));
+        assert!(matches!(
+            storage.append(&batch),
+            Err(RaftError::LogInconsistency)
+        ));
+
+        storage.append(&entries(6..=7, 1)).unwrap();
+        let state = FileStorage::open(&dir).unwrap().initial_state().unwrap();
+        assert_eq!(state.entries, entries(1..=7, 1));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_file_storage_truncate_across_segments() {
+        let dir = test_dir("truncate");
+        let mut storage = FileStorage::with_segment_size(&dir, 128).unwrap();
+        storage.append(&entries(1..=30, 1)).unwrap();
+        let segments = storage.segment_count();
+        assert!(segments > 3);
+
+        storage.truncate_from(12).unwrap();
+        assert!(storage.segment_count() < segments);
+        storage.append(&entries(12..=15, 2)).unwrap();
+        storage.truncate_from(40).unwrap();
+
+        let state = FileStorage::with_segment_size(&dir, 128)
+            .unwrap()
+            .initial_state()
+            .unwrap();
+        assert_eq!(state.entries.len(), 15);
+        assert_eq!(state.entries[10].term, 1);
+        assert_eq!(state.entries[11].term, 2);
+        assert_eq!(state.entries[14].index, 15);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_file_storage_snapshot_compaction() {
+        let dir = test_dir("compaction");
+        let mut storage = FileStorage::with_segment_size(&dir, 128).unwrap();
+        storage.append(&entries(1..=30, 1)).unwrap();
+        let segments = storage.segment_count();
+
+        storage.save_snapshot(&snapshot(20, 1)).unwrap();
+        assert!(storage.segment_count() < segments);
+        assert!(storage.truncate_from(20).is_err());
+        storage.append(&entries(31..=32, 2)).unwrap();
+
+        let state = FileStorage::with_segment_size(&dir, 128)
+            .unwrap()
+            .initial_state()
+            .unwrap();
+        assert_eq!(state.snapshot.as_ref().unwrap().last_included_index, 20);
+        assert_eq!(state.entries.first().unwrap().index, 21);
+        assert_eq!(state.entries.last().unwrap().index, 32);
+
+        // A snapshot past the end of the log replaces it entirely
+        let mut storage = FileStorage::with_segment_size(&dir, 128).unwrap();
+        storage.save_snapshot(&snapshot(50, 4)).unwrap();
+        assert_eq!(storage.segment_count(), 0);
+        assert!(storage.append(&entries(52..=52, 4)).is_err());
+        storage.append(&entries(51..=52, 4)).unwrap();
+
+        let state = FileStorage::open(&dir).unwrap().initial_state().unwrap();
+        let persistent = state.into_persistent_state().unwrap();
+        assert_eq!(persistent.log.base_index(), 50);
+        assert_eq!(persistent.log.last_index(), 52);
+
+        // A crash after storing a snapshot but before deleting the segments it covers
+        write_atomic(&dir, SNAPSHOT_FILE, &encode(&snapshot(60, 4)).unwrap()).unwrap();
+        let mut storage = FileStorage::open(&dir).unwrap();
+        assert!(storage.initial_state().unwrap().entries.is_empty());
+        assert_eq!(storage.segment_count(), 0);
+        storage.append(&entries(61..=61, 5)).unwrap();
+        let state = FileStorage::open(&dir).unwrap().initial_state().unwrap();
+        assert_eq!(state.entries[0].index, 61);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_file_storage_recovers_from_torn_write() {
+        let dir = test_dir("torn");
+        let last_segment = {
+            let mut storage = FileStorage::with_segment_size(&dir, 128).unwrap();
+            storage.append(&entries(1..=10, 1)).unwrap();
+            storage.segments.last().unwrap().path.clone()
+        };
+
+        // A crash halfway through writing entry 11
+        let record = {
+            let payload = encode(&entries(11..=11, 1)[0]).unwrap();
+            let mut record = (payload.len() as u32).to_le_bytes().to_vec();
+            record.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+            record.extend_from_slice(&payload);
+            record
+        };
+        let mut file = OpenOptions::new().append(true).open(&last_segment).unwrap();
+        file.write_all(&record[..record.len() / 2]).unwrap();
+        drop(file);
+
+        let mut storage = FileStorage::with_segment_size(&dir, 128).unwrap();
+        let state = storage.initial_state().unwrap();
+        assert_eq!(state.entries.len(), 10);
+        storage.append(&entries(11..=12, 1)).unwrap();
+
+        let state = FileStorage::with_segment_size(&dir, 128)
+            .unwrap()
+            .initial_state()
+            .unwrap();
+        assert_eq!(state.entries.len(), 12);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_file_storage_rejects_corruption_before_tail() {
+        let dir = test_dir("corrupt");
+        let first_segment = {
+            let mut storage = FileStorage::with_segment_size(&dir, 128).unwrap();
+            storage.append(&entries(1..=30, 1)).unwrap();
+            storage.segments[0].path.clone()
+        };
+
+        let mut data = fs::read(&first_segment).unwrap();
+        data[RECORD_HEADER_LEN + 1] ^= 0xff;
+        fs::write(&first_segment, data).unwrap();
+
+        let error = FileStorage::with_segment_size(&dir, 128).unwrap_err();
+        assert!(matches!(error, RaftError::Storage(_)));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_in_memory_storage() {
+        let mut storage = InMemoryStorage::new();
+        storage.append(&entries(1..=10, 1)).unwrap();
+        storage.truncate_from(8).unwrap();
+        storage.save_snapshot(&snapshot(5, 1)).unwrap();
+        storage.save_hard_state(2, None).unwrap();
+
+        let state = storage.initial_state().unwrap();
+        assert_eq!(state.current_term, 2);
+        assert_eq!(state.entries.len(), 2);
+        assert_eq!(state.entries[0].index, 6);
+    }
+}
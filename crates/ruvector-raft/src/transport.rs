@@ -0,0 +1,545 @@
+//! Network transports for Raft messages
+//!
+//! Raft only needs one-way, unreliable delivery: every RPC response is just
+//! another message back to the sender, and lost messages are recovered by
+//! the next heartbeat or election. [`RaftTransport::send`] therefore queues a
+//! message and returns immediately, and incoming messages are pushed into
+//! the node's [`RaftInbox`].
+//!
+//! - [`TcpTransport`]: length-prefixed [`RaftMessage::to_bytes`] frames over
+//!   one persistent TCP connection per peer
+//! - [`InMemoryNetwork`]: nodes in one process, with partitions and delays
+//!   for testing
+
+use crate::{rpc::RaftMessage, NodeId, RaftError, RaftResult};
+use parking_lot::{Mutex, RwLock};
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+/// Largest frame accepted from a peer (bytes)
+const MAX_FRAME_LEN: usize = 64 * 1024 * 1024;
+
+/// Time allowed for connecting to a peer
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Pause after a failed connection before trying the peer again
+const RECONNECT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Receiving end of a node's transport
+///
+/// Transports hand each incoming message to [`RaftInbox::deliver`] along with
+/// the ID of the node that sent it.
+#[derive(Debug, Clone)]
+pub struct RaftInbox {
+    tx: mpsc::UnboundedSender<(NodeId, RaftMessage)>,
+}
+
+impl RaftInbox {
+    /// Create an inbox and the receiver its messages arrive on
+    pub fn channel() -> (Self, mpsc::UnboundedReceiver<(NodeId, RaftMessage)>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (Self { tx }, rx)
+    }
+
+    /// Deliver a message; returns `false` once the receiver is gone
+    pub fn deliver(&self, from: NodeId, message: RaftMessage) -> bool {
+        self.tx.send((from, message)).is_ok()
+    }
+}
+
+/// Moves Raft messages between nodes
+///
+/// Delivery may be lossy, duplicated or reordered between connections; the
+/// Raft protocol tolerates all three.
+pub trait RaftTransport: Send + Sync {
+    /// Start delivering messages addressed to this node into `inbox`
+    fn listen(&self, inbox: RaftInbox) -> RaftResult<()>;
+
+    /// Queue `message` for delivery to `to` without waiting for the network
+    fn send(&self, to: &NodeId, message: RaftMessage);
+}
+
+/// TCP transport
+///
+/// Each node listens on one address. Outgoing messages to a peer are written
+/// by a dedicated task over a persistent connection that is re-established
+/// on failure; messages queued while a peer is unreachable are dropped. A
+/// connection starts with a frame holding the sender's node ID, followed by
+/// one frame per message, each prefixed with its length as a big-endian u32.
+///
+/// Connections from node IDs that aren't configured peers are dropped, but
+/// the ID is not authenticated: anyone who can reach the listening address
+/// can claim to be a peer and rewrite followers' logs. Run the transport on
+/// a trusted network or behind TLS.
+pub struct TcpTransport {
+    node_id: NodeId,
+    local_addr: SocketAddr,
+    listener: Mutex<Option<TcpListener>>,
+    peers: Arc<RwLock<HashMap<NodeId, SocketAddr>>>,
+    outbound: Mutex<HashMap<NodeId, mpsc::UnboundedSender<RaftMessage>>>,
+}
+
+impl TcpTransport {
+    /// Bind the listening socket for `node_id`
+    ///
+    /// Messages are not accepted until the node starts and calls
+    /// [`RaftTransport::listen`].
+    pub async fn bind(node_id: impl Into<NodeId>, addr: impl ToSocketAddrs) -> RaftResult<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        Ok(Self {
+            node_id: node_id.into(),
+            local_addr: listener.local_addr()?,
+            listener: Mutex::new(Some(listener)),
+            peers: Arc::new(RwLock::new(HashMap::new())),
+            outbound: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// The address this transport is listening on
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Set the address of a peer, replacing any previous one
+    ///
+    /// An existing connection is kept until it fails.
+    pub fn add_peer(&self, node_id: impl Into<NodeId>, addr: SocketAddr) {
+        self.peers.write().insert(node_id.into(), addr);
+    }
+
+    /// Forget a peer and close the connection to it
+    pub fn remove_peer(&self, node_id: &NodeId) {
+        self.peers.write().remove(node_id);
+        self.outbound.lock().remove(node_id);
+    }
+}
+
+impl RaftTransport for TcpTransport {
+    fn listen(&self, inbox: RaftInbox) -> RaftResult<()> {
+        let listener = self
+            .listener
+            .lock()
+            .take()
+            .ok_or_else(|| RaftError::Internal("Transport is already listening".to_string()))?;
+
+        let peers = self.peers.clone();
+        tokio::spawn(async move {
+            loop {
+                let (stream, addr) = match listener.accept().await {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        warn!("Failed to accept Raft connection: {}", e);
+                        continue;
+                    }
+                };
+                let inbox = inbox.clone();
+                let peers = peers.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = read_messages(stream, &peers, inbox).await {
+                        debug!("Raft connection from {} closed: {}", addr, e);
+                    }
+                });
+            }
+        });
+        Ok(())
+    }
+
+    fn send(&self, to: &NodeId, message: RaftMessage) {
+        let mut outbound = self.outbound.lock();
+        let sender = outbound.entry(to.clone()).or_insert_with(|| {
+            let (tx, rx) = mpsc::unbounded_channel();
+            tokio::spawn(write_messages(
+                self.node_id.clone(),
+                to.clone(),
+                self.peers.clone(),
+                rx,
+            ));
+            tx
+        });
+        let _ = sender.send(message);
+    }
+}
+
+/// Read one length-prefixed frame
+async fn read_frame(stream: &mut TcpStream) -> RaftResult<Vec<u8>> {
+    let len = stream.read_u32().await? as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(RaftError::Internal(format!(
+            "Frame of {} bytes exceeds the limit of {}",
+            len, MAX_FRAME_LEN
+        )));
+    }
+    let mut frame = vec![0; len];
+    stream.read_exact(&mut frame).await?;
+    Ok(frame)
+}
+
+/// Write one length-prefixed frame
+async fn write_frame(stream: &mut TcpStream, frame: &[u8]) -> RaftResult<()> {
+    let mut buf = Vec::with_capacity(4 + frame.len());
+    buf.extend_from_slice(&(frame.len() as u32).to_be_bytes());
+    buf.extend_from_slice(frame);
+    stream.write_all(&buf).await?;
+    Ok(())
+}
+
+/// Deliver the messages of one incoming connection
+///
+/// The connection is closed unless the sender is a configured peer, and
+/// again once it stops being one.
+async fn read_messages(
+    mut stream: TcpStream,
+    peers: &RwLock<HashMap<NodeId, SocketAddr>>,
+    inbox: RaftInbox,
+) -> RaftResult<()> {
+    let from = String::from_utf8(read_frame(&mut stream).await?)
+        .map_err(|_| RaftError::Internal("Invalid node ID in handshake".to_string()))?;
+    let unknown = || RaftError::Internal(format!("{} is not a configured peer", from));
+    if !peers.read().contains_key(&from) {
+        return Err(unknown());
+    }
+
+    loop {
+        let frame = read_frame(&mut stream).await?;
+        if !peers.read().contains_key(&from) {
+            return Err(unknown());
+        }
+        let message = RaftMessage::from_bytes(&frame)?;
+        if !inbox.deliver(from.clone(), message) {
+            return Ok(());
+        }
+    }
+}
+
+/// Write queued messages to one peer until the transport forgets it
+async fn write_messages(
+    node_id: NodeId,
+    peer: NodeId,
+    peers: Arc<RwLock<HashMap<NodeId, SocketAddr>>>,
+    mut rx: mpsc::UnboundedReceiver<RaftMessage>,
+) {
+    let mut stream: Option<TcpStream> = None;
+
+    while let Some(message) = rx.recv().await {
+        if stream.is_none() {
+            stream = connect(&node_id, &peer, &peers).await;
+            if stream.is_none() {
+                // Drop what piled up while connecting; Raft will resend
+                while rx.try_recv().is_ok() {}
+                tokio::time::sleep(RECONNECT_BACKOFF).await;
+                continue;
+            }
+        }
+
+        let frame = match message.to_bytes() {
+            Ok(frame) => frame,
+            Err(e) => {
+                warn!("Failed to encode message for {}: {}", peer, e);
+                continue;
+            }
+        };
+        if let Some(conn) = stream.as_mut() {
+            if let Err(e) = write_frame(conn, &frame).await {
+                debug!("Connection to {} lost: {}", peer, e);
+                stream = None;
+            }
+        }
+    }
+}
+
+/// Connect to a peer and send the handshake
+async fn connect(
+    node_id: &NodeId,
+    peer: &NodeId,
+    peers: &RwLock<HashMap<NodeId, SocketAddr>>,
+) -> Option<TcpStream> {
+    let Some(addr) = peers.read().get(peer).copied() else {
+        debug!("No address for peer {}", peer);
+        return None;
+    };
+
+    let mut stream = match tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(addr)).await {
+        Ok(Ok(stream)) => stream,
+        Ok(Err(e)) => {
+            debug!("Failed to connect to {} at {}: {}", peer, addr, e);
+            return None;
+        }
+        Err(_) => {
+            debug!("Timed out connecting to {} at {}", peer, addr);
+            return None;
+        }
+    };
+    let _ = stream.set_nodelay(true);
+    write_frame(&mut stream, node_id.as_bytes()).await.ok()?;
+    Some(stream)
+}
+
+/// An in-process network of Raft nodes with fault injection
+///
+/// Every node gets an [`InMemoryTransport`] from [`InMemoryNetwork::transport`].
+/// Links can be cut with [`isolate`](Self::isolate) and
+/// [`partition`](Self::partition), restored with [`heal`](Self::heal), and
+/// slowed down with [`set_delay`](Self::set_delay). Messages on a cut link
+/// are dropped, including ones already in flight when the link is cut.
+#[derive(Clone, Default)]
+pub struct InMemoryNetwork {
+    inner: Arc<NetworkState>,
+}
+
+#[derive(Default)]
+struct NetworkState {
+    inboxes: RwLock<HashMap<NodeId, RaftInbox>>,
+    isolated: RwLock<HashSet<NodeId>>,
+    blocked: RwLock<HashSet<(NodeId, NodeId)>>,
+    delay: RwLock<Duration>,
+    link_delays: RwLock<HashMap<(NodeId, NodeId), Duration>>,
+    delivered: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl InMemoryNetwork {
+    /// Create an empty network
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create the transport for `node_id`
+    pub fn transport(&self, node_id: impl Into<NodeId>) -> InMemoryTransport {
+        InMemoryTransport {
+            node_id: node_id.into(),
+            network: self.clone(),
+        }
+    }
+
+    /// Cut all links to and from a node
+    pub fn isolate(&self, node_id: &str) {
+        self.inner.isolated.write().insert(node_id.to_string());
+    }
+
+    /// Cut all links between nodes in different groups
+    ///
+    /// Nodes in the same group, and nodes not listed in any group, can still
+    /// reach each other.
+    pub fn partition(&self, groups: &[&[NodeId]]) {
+        let mut blocked = self.inner.blocked.write();
+        for (i, group) in groups.iter().enumerate() {
+            for other in groups.iter().skip(i + 1) {
+                for a in group.iter() {
+                    for b in other.iter() {
+                        blocked.insert((a.clone(), b.clone()));
+                        blocked.insert((b.clone(), a.clone()));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Restore every link cut by `isolate` or `partition`
+    pub fn heal(&self) {
+        self.inner.isolated.write().clear();
+        self.inner.blocked.write().clear();
+    }
+
+    /// Delay delivery on every link without a link-specific delay
+    pub fn set_delay(&self, delay: Duration) {
+        *self.inner.delay.write() = delay;
+    }
+
+    /// Delay delivery from `from` to `to`
+    pub fn set_link_delay(&self, from: &str, to: &str, delay: Duration) {
+        self.inner
+            .link_delays
+            .write()
+            .insert((from.to_string(), to.to_string()), delay);
+    }
+
+    /// Number of messages delivered to a node so far
+    pub fn messages_delivered(&self) -> u64 {
+        self.inner.delivered.load(Ordering::Relaxed)
+    }
+
+    /// Number of messages dropped on cut links or to unknown nodes so far
+    pub fn messages_dropped(&self) -> u64 {
+        self.inner.dropped.load(Ordering::Relaxed)
+    }
+
+    fn is_connected(&self, from: &NodeId, to: &NodeId) -> bool {
+        let isolated = self.inner.isolated.read();
+        !isolated.contains(from)
+            && !isolated.contains(to)
+            && !self
+                .inner
+                .blocked
+                .read()
+                .contains(&(from.clone(), to.clone()))
+    }
+
+    fn link_delay(&self, from: &NodeId, to: &NodeId) -> Duration {
+        self.inner
+            .link_delays
+            .read()
+            .get(&(from.clone(), to.clone()))
+            .copied()
+            .unwrap_or_else(|| *self.inner.delay.read())
+    }
+
+    fn deliver(&self, from: &NodeId, to: &NodeId, message: RaftMessage) {
+        if !self.is_connected(from, to) {
+            self.inner.dropped.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        let Some(inbox) = self.inner.inboxes.read().get(to).cloned() else {
+            self.inner.dropped.fetch_add(1, Ordering::Relaxed);
+            return;
+        };
+
+        let delay = self.link_delay(from, to);
+        if delay.is_zero() {
+            self.inner.delivered.fetch_add(1, Ordering::Relaxed);
+            inbox.deliver(from.clone(), message);
+            return;
+        }
+
+        let network = self.clone();
+        let (from, to) = (from.clone(), to.clone());
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            if network.is_connected(&from, &to) {
+                network.inner.delivered.fetch_add(1, Ordering::Relaxed);
+                inbox.deliver(from, message);
+            } else {
+                network.inner.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+    }
+}
+
+/// A node's endpoint on an [`InMemoryNetwork`]
+pub struct InMemoryTransport {
+    node_id: NodeId,
+    network: InMemoryNetwork,
+}
+
+impl RaftTransport for InMemoryTransport {
+    fn listen(&self, inbox: RaftInbox) -> RaftResult<()> {
+        self.network
+            .inner
+            .inboxes
+            .write()
+            .insert(self.node_id.clone(), inbox);
+        Ok(())
+    }
+
+    fn send(&self, to: &NodeId, message: RaftMessage) {
+        self.network.deliver(&self.node_id, to, message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::RequestVoteRequest;
+
+    fn vote_request(term: u64) -> RaftMessage {
+        RaftMessage::RequestVoteRequest(RequestVoteRequest::new(term, "a".to_string(), 0, 0))
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_partition_and_heal() {
+        let network = InMemoryNetwork::new();
+        let a = network.transport("a");
+        let (inbox, mut rx) = RaftInbox::channel();
+        network.transport("b").listen(inbox).unwrap();
+
+        a.send(&"b".to_string(), vote_request(1));
+        assert_eq!(rx.recv().await.unwrap().0, "a");
+
+        network.partition(&[&["a".to_string()], &["b".to_string()]]);
+        a.send(&"b".to_string(), vote_request(2));
+        network.heal();
+        network.isolate("b");
+        a.send(&"b".to_string(), vote_request(3));
+        network.heal();
+        a.send(&"b".to_string(), vote_request(4));
+
+        let (_, message) = rx.recv().await.unwrap();
+        assert_eq!(message.term(), 4);
+        assert_eq!(network.messages_delivered(), 2);
+        assert_eq!(network.messages_dropped(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_delay_drops_in_flight_on_partition() {
+        let network = InMemoryNetwork::new();
+        let a = network.transport("a");
+        let (inbox, mut rx) = RaftInbox::channel();
+        network.transport("b").listen(inbox).unwrap();
+        network.set_link_delay("a", "b", Duration::from_millis(50));
+
+        a.send(&"b".to_string(), vote_request(1));
+        network.isolate("a");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(rx.try_recv().is_err());
+        assert_eq!(network.messages_dropped(), 1);
+
+        network.heal();
+        a.send(&"b".to_string(), vote_request(2));
+        let (_, message) = rx.recv().await.unwrap();
+        assert_eq!(message.term(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_tcp_roundtrip_and_reconnect() {
+        let a = TcpTransport::bind("a", "127.0.0.1:0").await.unwrap();
+        let b = TcpTransport::bind("b", "127.0.0.1:0").await.unwrap();
+        a.add_peer("b", b.local_addr());
+        b.add_peer("a", a.local_addr());
+        let (inbox, mut rx) = RaftInbox::channel();
+        b.listen(inbox).unwrap();
+        assert!(b.listen(RaftInbox::channel().0).is_err());
+
+        for term in 1..=3 {
+            a.send(&"b".to_string(), vote_request(term));
+        }
+        for term in 1..=3 {
+            let (from, message) = rx.recv().await.unwrap();
+            assert_eq!(from, "a");
+            assert_eq!(message.term(), term);
+        }
+
+        // Messages to an unknown peer are dropped without affecting others
+        a.send(&"c".to_string(), vote_request(4));
+        a.send(&"b".to_string(), vote_request(5));
+        assert_eq!(rx.recv().await.unwrap().1.term(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_tcp_drops_unknown_senders() {
+        let b = TcpTransport::bind("b", "127.0.0.1:0").await.unwrap();
+        let (inbox, mut rx) = RaftInbox::channel();
+        b.listen(inbox).unwrap();
+
+        // Claims to be a member, but b was never told about it
+        let intruder = TcpTransport::bind("a", "127.0.0.1:0").await.unwrap();
+        intruder.add_peer("b", b.local_addr());
+        intruder.send(&"b".to_string(), vote_request(7));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(rx.try_recv().is_err());
+
+        // Once configured, the same ID gets through on a new connection
+        b.add_peer("a", intruder.local_addr());
+        let a = TcpTransport::bind("a", "127.0.0.1:0").await.unwrap();
+        a.add_peer("b", b.local_addr());
+        a.send(&"b".to_string(), vote_request(8));
+        let (from, message) = rx.recv().await.unwrap();
+        assert_eq!(from, "a");
+        assert_eq!(message.term(), 8);
+    }
+}
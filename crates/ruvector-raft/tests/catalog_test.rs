@@ -0,0 +1,188 @@
+//! The collection catalog replicated through Raft
+//!
+//! Each node keeps its log in `FileStorage` and its catalog in a
+//! `CollectionManager` directory, so nodes can be restarted from disk.
+
+#![cfg(feature = "collections")]
+
+use ruvector_collections::CollectionConfig;
+use ruvector_raft::{
+    CatalogStateMachine, FileStorage, InMemoryNetwork, LogIndex, NodeId, RaftError, RaftNode,
+    RaftNodeConfig, ReplicatedCatalog, StateMachine,
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, Instant};
+
+const WAIT: Duration = Duration::from_secs(10);
+
+async fn wait_until(what: &str, mut condition: impl FnMut() -> bool) {
+    let deadline = Instant::now() + WAIT;
+    while !condition() {
+        assert!(Instant::now() < deadline, "timed out waiting for {}", what);
+        sleep(Duration::from_millis(10)).await;
+    }
+}
+
+async fn wait_for_leader(nodes: &[Arc<RaftNode>]) -> usize {
+    let mut leader = None;
+    wait_until("a leader", || {
+        leader = (0..nodes.len())
+            .filter(|&i| nodes[i].current_state().is_leader())
+            .max_by_key(|&i| nodes[i].current_term());
+        leader.is_some()
+    })
+    .await;
+    leader.unwrap()
+}
+
+fn ids(size: usize) -> Vec<NodeId> {
+    (1..=size).map(|i| format!("node{}", i)).collect()
+}
+
+struct CatalogCluster {
+    network: InMemoryNetwork,
+    dir: PathBuf,
+    ids: Vec<NodeId>,
+    nodes: Vec<Arc<RaftNode>>,
+    catalogs: Vec<Arc<CatalogStateMachine>>,
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl CatalogCluster {
+    fn new(size: usize) -> Self {
+        let dir = std::env::temp_dir().join("ruvector-raft-catalog-cluster");
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut cluster = Self {
+            network: InMemoryNetwork::new(),
+            dir,
+            ids: ids(size),
+            nodes: Vec::new(),
+            catalogs: Vec::new(),
+            handles: Vec::new(),
+        };
+        for i in 0..size {
+            let (node, catalog, handle) = cluster.open(i);
+            cluster.nodes.push(node);
+            cluster.catalogs.push(catalog);
+            cluster.handles.push(handle);
+        }
+        cluster
+    }
+
+    /// Open node `i`'s log and catalog from disk and start it
+    fn open(&self, i: usize) -> (Arc<RaftNode>, Arc<CatalogStateMachine>, JoinHandle<()>) {
+        let dir = self.dir.join(&self.ids[i]);
+        let catalog = Arc::new(CatalogStateMachine::open(dir.join("catalog")).unwrap());
+        let node = RaftNode::with_storage(
+            RaftNodeConfig::new(self.ids[i].clone(), self.ids.clone()),
+            Arc::new(self.network.transport(self.ids[i].clone())),
+            Box::new(FileStorage::open(dir.join("raft")).unwrap()),
+        )
+        .unwrap()
+        .with_state_machine(catalog.clone())
+        .unwrap();
+        let node = Arc::new(node);
+        let handle = tokio::spawn({
+            let node = node.clone();
+            async move { node.start().await.unwrap() }
+        });
+        (node, catalog, handle)
+    }
+
+    fn client(&self, i: usize) -> ReplicatedCatalog {
+        ReplicatedCatalog::new(self.nodes[i].clone(), self.catalogs[i].clone())
+    }
+
+    fn names(&self, i: usize) -> Vec<String> {
+        let mut names = self.catalogs[i].manager().list_collections();
+        names.sort();
+        names
+    }
+}
+
+impl Drop for CatalogCluster {
+    fn drop(&mut self) {
+        for node in &self.nodes {
+            node.shutdown();
+        }
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_replicated_catalog() {
+    let mut cluster = CatalogCluster::new(3);
+    let leader = wait_for_leader(&cluster.nodes).await;
+    let catalog = cluster.client(leader);
+
+    catalog
+        .create_collection("docs", CollectionConfig::with_dimensions(4))
+        .await
+        .unwrap();
+    catalog
+        .create_collection("docs_v2", CollectionConfig::with_dimensions(4))
+        .await
+        .unwrap();
+    assert!(matches!(
+        catalog
+            .create_collection("docs", CollectionConfig::with_dimensions(8))
+            .await,
+        Err(RaftError::StateMachine(_))
+    ));
+    catalog.create_alias("live", "docs").await.unwrap();
+    catalog.switch_alias("live", "docs_v2").await.unwrap();
+    catalog.drop_collection("docs").await.unwrap();
+
+    assert_eq!(catalog.list_collections().await.unwrap(), vec!["docs_v2"]);
+    assert_eq!(
+        catalog.resolve_alias("live").await.unwrap(),
+        Some("docs_v2".to_string())
+    );
+    assert!(matches!(
+        cluster.client((leader + 1) % 3).list_collections().await,
+        Err(RaftError::NotLeader)
+    ));
+    wait_until("every node to apply the catalog", || {
+        (0..3).all(|i| cluster.names(i) == ["docs_v2"])
+    })
+    .await;
+    for i in 0..3 {
+        assert_eq!(
+            cluster.catalogs[i].manager().resolve_alias("live"),
+            Some("docs_v2".to_string())
+        );
+    }
+
+    // A follower restarted from disk resumes from its applied index
+    let follower = (leader + 1) % 3;
+    cluster.nodes[follower].shutdown();
+    (&mut cluster.handles[follower]).await.unwrap();
+    let applied = cluster.catalogs[follower].last_applied();
+    assert!(applied > 0);
+    catalog
+        .create_collection("images", CollectionConfig::with_dimensions(8))
+        .await
+        .unwrap();
+    catalog.delete_alias("live").await.unwrap();
+
+    let (node, state_machine, handle) = cluster.open(follower);
+    assert_eq!(state_machine.last_applied(), applied);
+    assert_eq!(node.last_applied(), applied);
+    cluster.nodes[follower] = node;
+    cluster.catalogs[follower] = state_machine;
+    cluster.handles[follower] = handle;
+
+    let last: LogIndex = cluster.nodes[leader].last_applied();
+    wait_until("the restarted follower to catch up", || {
+        cluster.nodes[follower].last_applied() >= last
+    })
+    .await;
+    assert_eq!(cluster.names(follower), ["docs_v2", "images"]);
+    assert!(cluster.catalogs[follower]
+        .manager()
+        .list_aliases()
+        .is_empty());
+}
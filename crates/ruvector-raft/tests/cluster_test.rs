@@ -0,0 +1,257 @@
+//! Multi-node Raft clusters over the in-memory and TCP transports
+//!
+//! Each test starts real `RaftNode`s, injects partitions or delays and
+//! checks leader election, log replication and log convergence.
+
+use ruvector_raft::{
+    InMemoryNetwork, NodeId, RaftNode, RaftNodeConfig, RaftTransport, TcpTransport,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{sleep, Instant};
+
+const WAIT: Duration = Duration::from_secs(10);
+
+struct Cluster {
+    network: InMemoryNetwork,
+    nodes: Vec<Arc<RaftNode>>,
+}
+
+fn node_ids(size: usize) -> Vec<NodeId> {
+    (1..=size).map(|i| format!("node{}", i)).collect()
+}
+
+fn start_nodes(ids: &[NodeId], transports: Vec<Arc<dyn RaftTransport>>) -> Vec<Arc<RaftNode>> {
+    ids.iter()
+        .zip(transports)
+        .map(|(id, transport)| {
+            let node = Arc::new(RaftNode::new(
+                RaftNodeConfig::new(id.clone(), ids.to_vec()),
+                transport,
+            ));
+            tokio::spawn(node.clone().start());
+            node
+        })
+        .collect()
+}
+
+impl Cluster {
+    fn start(size: usize) -> Self {
+        let network = InMemoryNetwork::new();
+        let ids = node_ids(size);
+        let transports = ids
+            .iter()
+            .map(|id| Arc::new(network.transport(id.clone())) as Arc<dyn RaftTransport>)
+            .collect();
+        Self {
+            network,
+            nodes: start_nodes(&ids, transports),
+        }
+    }
+
+    fn ids(&self, indices: &[usize]) -> Vec<NodeId> {
+        indices
+            .iter()
+            .map(|&i| self.nodes[i].node_id().clone())
+            .collect()
+    }
+}
+
+async fn wait_until(what: &str, mut condition: impl FnMut() -> bool) {
+    let deadline = Instant::now() + WAIT;
+    while !condition() {
+        assert!(Instant::now() < deadline, "timed out waiting for {}", what);
+        sleep(Duration::from_millis(10)).await;
+    }
+}
+
+/// The single leader among `members` with the highest term, once there is one
+async fn wait_for_leader(nodes: &[Arc<RaftNode>], members: &[usize]) -> usize {
+    let mut leader = None;
+    wait_until("a leader", || {
+        leader = members
+            .iter()
+            .copied()
+            .filter(|&i| nodes[i].current_state().is_leader())
+            .max_by_key(|&i| nodes[i].current_term());
+        leader.is_some()
+    })
+    .await;
+    leader.unwrap()
+}
+
+/// Leaders never share a term
+fn assert_election_safety(nodes: &[Arc<RaftNode>]) {
+    let mut leaders: HashMap<u64, &NodeId> = HashMap::new();
+    for node in nodes {
+        if node.current_state().is_leader() {
+            if let Some(other) = leaders.insert(node.current_term(), node.node_id()) {
+                panic!(
+                    "{} and {} both lead term {}",
+                    other,
+                    node.node_id(),
+                    node.current_term()
+                );
+            }
+        }
+    }
+}
+
+async fn submit(node: &RaftNode, commands: impl IntoIterator<Item = String>) -> u64 {
+    let mut last = 0;
+    for command in commands {
+        last = node
+            .submit_command(command.into_bytes())
+            .await
+            .unwrap()
+            .index;
+    }
+    last
+}
+
+/// Wait until `members` have committed `index` and hold identical logs up to it
+async fn wait_for_replication(nodes: &[Arc<RaftNode>], members: &[usize], index: u64) {
+    wait_until("replication", || {
+        members.iter().all(|&i| nodes[i].commit_index() >= index)
+    })
+    .await;
+
+    for i in 1..=index {
+        let expected = nodes[members[0]].log_entry(i).unwrap();
+        for &member in &members[1..] {
+            assert_eq!(nodes[member].log_entry(i).as_ref(), Some(&expected));
+        }
+    }
+}
+
+fn commands(name: &str, count: usize) -> Vec<String> {
+    (0..count).map(|i| format!("{}-{}", name, i)).collect()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_three_node_election_and_replication() {
+    let cluster = Cluster::start(3);
+    let all = [0, 1, 2];
+
+    let leader = wait_for_leader(&cluster.nodes, &all).await;
+    assert_election_safety(&cluster.nodes);
+    let leader_id = cluster.nodes[leader].node_id().clone();
+    wait_until("followers to learn the leader", || {
+        cluster
+            .nodes
+            .iter()
+            .all(|n| n.current_leader().as_ref() == Some(&leader_id))
+    })
+    .await;
+
+    let last = submit(&cluster.nodes[leader], commands("a", 20)).await;
+    wait_for_replication(&cluster.nodes, &all, last).await;
+    let entry = cluster.nodes[(leader + 1) % 3].log_entry(last).unwrap();
+    assert_eq!(entry.command, b"a-19");
+
+    // Followers refuse client commands
+    let follower = &cluster.nodes[(leader + 1) % 3];
+    assert!(follower.submit_command(b"x".to_vec()).await.is_err());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_three_node_leader_failover() {
+    let cluster = Cluster::start(3);
+    let all = [0, 1, 2];
+
+    let old_leader = wait_for_leader(&cluster.nodes, &all).await;
+    let old_term = cluster.nodes[old_leader].current_term();
+    let last = submit(&cluster.nodes[old_leader], commands("before", 5)).await;
+    wait_for_replication(&cluster.nodes, &all, last).await;
+
+    // Cut the leader off; the other two elect a new one
+    cluster.network.isolate(cluster.nodes[old_leader].node_id());
+    let rest: Vec<usize> = all.iter().copied().filter(|&i| i != old_leader).collect();
+    let new_leader = wait_for_leader(&cluster.nodes, &rest).await;
+    assert!(cluster.nodes[new_leader].current_term() > old_term);
+    assert_election_safety(&cluster.nodes);
+
+    // The isolated leader still accepts writes but can't commit them
+    let stale = submit(&cluster.nodes[old_leader], commands("stale", 3)).await;
+    let last = submit(&cluster.nodes[new_leader], commands("after", 5)).await;
+    wait_for_replication(&cluster.nodes, &rest, last).await;
+    assert!(cluster.nodes[old_leader].commit_index() < stale);
+
+    // After healing, the old leader steps down and its stale entries are replaced
+    cluster.network.heal();
+    wait_for_replication(&cluster.nodes, &all, last).await;
+    assert!(!cluster.nodes[old_leader].current_state().is_leader());
+    for i in 1..=last {
+        let entry = cluster.nodes[old_leader].log_entry(i).unwrap();
+        assert!(!entry.command.starts_with(b"stale"));
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_five_node_partition_with_delays() {
+    let cluster = Cluster::start(5);
+    cluster.network.set_delay(Duration::from_millis(5));
+    let all = [0, 1, 2, 3, 4];
+
+    let leader = wait_for_leader(&cluster.nodes, &all).await;
+    let last = submit(&cluster.nodes[leader], commands("a", 10)).await;
+    wait_for_replication(&cluster.nodes, &all, last).await;
+
+    // Put the leader in a minority of two
+    let minority = [leader, (leader + 1) % 5];
+    let majority: Vec<usize> = all
+        .iter()
+        .copied()
+        .filter(|i| !minority.contains(i))
+        .collect();
+    cluster
+        .network
+        .partition(&[&cluster.ids(&minority), &cluster.ids(&majority)]);
+
+    let stale = submit(&cluster.nodes[leader], commands("minority", 3)).await;
+    let new_leader = wait_for_leader(&cluster.nodes, &majority).await;
+    let last = submit(&cluster.nodes[new_leader], commands("majority", 10)).await;
+    wait_for_replication(&cluster.nodes, &majority, last).await;
+    assert!(minority
+        .iter()
+        .all(|&i| cluster.nodes[i].commit_index() < stale));
+    assert_election_safety(&cluster.nodes);
+
+    cluster.network.heal();
+    wait_for_replication(&cluster.nodes, &all, last).await;
+    for i in 1..=last {
+        let entry = cluster.nodes[leader].log_entry(i).unwrap();
+        assert!(!entry.command.starts_with(b"minority"));
+    }
+    assert!(cluster.network.messages_dropped() > 0);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_three_node_cluster_over_tcp() {
+    let ids = node_ids(3);
+    let mut transports = Vec::new();
+    for id in &ids {
+        transports.push(Arc::new(
+            TcpTransport::bind(id.clone(), "127.0.0.1:0").await.unwrap(),
+        ));
+    }
+    for transport in &transports {
+        for (id, peer) in ids.iter().zip(&transports) {
+            transport.add_peer(id.clone(), peer.local_addr());
+        }
+    }
+    let nodes = start_nodes(
+        &ids,
+        transports
+            .into_iter()
+            .map(|t| t as Arc<dyn RaftTransport>)
+            .collect(),
+    );
+    let all = [0, 1, 2];
+
+    let leader = wait_for_leader(&nodes, &all).await;
+    let last = submit(&nodes[leader], commands("tcp", 50)).await;
+    wait_for_replication(&nodes, &all, last).await;
+    assert_election_safety(&nodes);
+}
@@ -0,0 +1,266 @@
+//! Snapshot streaming, membership changes and leadership transfer
+//!
+//! Clusters run over the in-memory transport; nodes that join later are
+//! started on the same network before being added.
+
+use ruvector_raft::{InMemoryNetwork, NodeId, RaftError, RaftNode, RaftNodeConfig};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{sleep, Instant};
+
+const WAIT: Duration = Duration::from_secs(10);
+
+struct Cluster {
+    network: InMemoryNetwork,
+    nodes: Vec<Arc<RaftNode>>,
+}
+
+impl Cluster {
+    /// Start `size` nodes that form the initial configuration
+    fn start(size: usize) -> Self {
+        let mut cluster = Self {
+            network: InMemoryNetwork::new(),
+            nodes: Vec::new(),
+        };
+        let ids: Vec<NodeId> = (1..=size).map(|i| format!("node{}", i)).collect();
+        for id in &ids {
+            cluster.spawn(id, ids.clone());
+        }
+        cluster
+    }
+
+    /// Start a node that knows `members` as the initial configuration
+    fn spawn(&mut self, id: &str, members: Vec<NodeId>) -> usize {
+        let mut config = RaftNodeConfig::new(id.to_string(), members);
+        config.snapshot_chunk_size = 64;
+        let node = Arc::new(RaftNode::new(
+            config,
+            Arc::new(self.network.transport(id.to_string())),
+        ));
+        tokio::spawn(node.clone().start());
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    fn id(&self, i: usize) -> NodeId {
+        self.nodes[i].node_id().clone()
+    }
+}
+
+impl Drop for Cluster {
+    fn drop(&mut self) {
+        for node in &self.nodes {
+            node.shutdown();
+        }
+    }
+}
+
+async fn wait_until(what: &str, mut condition: impl FnMut() -> bool) {
+    let deadline = Instant::now() + WAIT;
+    while !condition() {
+        assert!(Instant::now() < deadline, "timed out waiting for {}", what);
+        sleep(Duration::from_millis(10)).await;
+    }
+}
+
+async fn wait_for_leader(nodes: &[Arc<RaftNode>], members: &[usize]) -> usize {
+    let mut leader = None;
+    wait_until("a leader", || {
+        leader = members
+            .iter()
+            .copied()
+            .filter(|&i| nodes[i].current_state().is_leader())
+            .max_by_key(|&i| nodes[i].current_term());
+        leader.is_some()
+    })
+    .await;
+    leader.unwrap()
+}
+
+async fn submit(node: &RaftNode, name: &str, count: usize) -> u64 {
+    let mut last = 0;
+    for i in 0..count {
+        let command = format!("{}-{}", name, i).into_bytes();
+        last = node.submit_command(command).await.unwrap().index;
+    }
+    last
+}
+
+async fn wait_for_commit(nodes: &[Arc<RaftNode>], members: &[usize], index: u64) {
+    wait_until("replication", || {
+        members.iter().all(|&i| nodes[i].commit_index() >= index)
+    })
+    .await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_lagging_follower_installs_snapshot() {
+    let cluster = Cluster::start(3);
+    cluster.network.set_delay(Duration::from_millis(2));
+    let all = [0, 1, 2];
+
+    let leader = wait_for_leader(&cluster.nodes, &all).await;
+    let last = submit(&cluster.nodes[leader], "a", 10).await;
+    wait_for_commit(&cluster.nodes, &all, last).await;
+
+    // The follower misses entries that are then compacted away
+    let lagging = (leader + 1) % 3;
+    let other = (leader + 2) % 3;
+    cluster.network.isolate(&cluster.id(lagging));
+    let last = submit(&cluster.nodes[leader], "b", 40).await;
+    wait_for_commit(&cluster.nodes, &[leader, other], last).await;
+    let data: Vec<u8> = (0..1000u32).map(|i| (i % 251) as u8).collect();
+    let snapshot = cluster.nodes[leader]
+        .compact_log(last, data.clone())
+        .unwrap();
+
+    // Back online, it can only catch up from the snapshot, streamed in 64-byte chunks
+    cluster.network.heal();
+    wait_for_commit(&cluster.nodes, &all, last).await;
+    let installed = cluster.nodes[lagging].snapshot().unwrap();
+    assert_eq!(installed.last_included_index, snapshot.last_included_index);
+    assert_eq!(installed.last_included_term, snapshot.last_included_term);
+    assert_eq!(installed.data, data);
+    assert_eq!(installed.configuration, cluster.nodes[leader].members());
+
+    // Replication carries on from the snapshot
+    let last = submit(&cluster.nodes[leader], "c", 5).await;
+    wait_for_commit(&cluster.nodes, &all, last).await;
+    assert_eq!(
+        cluster.nodes[lagging].log_entry(last),
+        cluster.nodes[leader].log_entry(last)
+    );
+    assert!(cluster.nodes[lagging]
+        .log_entry(snapshot.last_included_index)
+        .is_none());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_add_and_remove_members() {
+    let mut cluster = Cluster::start(3);
+    let initial = cluster.nodes[0].members();
+
+    let leader = wait_for_leader(&cluster.nodes, &[0, 1, 2]).await;
+    let last = submit(&cluster.nodes[leader], "a", 20).await;
+    wait_for_commit(&cluster.nodes, &[0, 1, 2], last).await;
+    cluster.nodes[leader]
+        .compact_log(last, b"state".to_vec())
+        .unwrap();
+
+    // A new node waits quietly until it is added, then catches up from the snapshot
+    let joining = cluster.spawn("node4", initial);
+    sleep(Duration::from_millis(400)).await;
+    assert!(!cluster.nodes[joining].current_state().is_leader());
+    assert_eq!(cluster.nodes[joining].current_term(), 0);
+
+    cluster.network.set_delay(Duration::from_millis(5));
+    let change = cluster.nodes[leader]
+        .add_member("node4".to_string())
+        .await
+        .unwrap();
+    // One change at a time
+    assert!(matches!(
+        cluster.nodes[leader].add_member("node5".to_string()).await,
+        Err(RaftError::ConfigError(_))
+    ));
+    let all = [0, 1, 2, joining];
+    wait_for_commit(&cluster.nodes, &all, change.index).await;
+    for &i in &all {
+        assert_eq!(cluster.nodes[i].members().len(), 4);
+    }
+    assert_eq!(cluster.nodes[joining].snapshot().unwrap().data, b"state");
+    let last = submit(&cluster.nodes[leader], "b", 10).await;
+    wait_for_commit(&cluster.nodes, &all, last).await;
+
+    // A removed follower keeps running but can't disrupt the cluster
+    let removed = (leader + 1) % 3;
+    let change = cluster.nodes[leader]
+        .remove_member(&cluster.id(removed))
+        .await
+        .unwrap();
+    let rest: Vec<usize> = all.iter().copied().filter(|&i| i != removed).collect();
+    wait_for_commit(&cluster.nodes, &rest, change.index).await;
+    let term = cluster.nodes[leader].current_term();
+    sleep(Duration::from_millis(1000)).await;
+    assert!(cluster.nodes[leader].current_state().is_leader());
+    assert_eq!(cluster.nodes[leader].current_term(), term);
+    let last = submit(&cluster.nodes[leader], "c", 10).await;
+    wait_for_commit(&cluster.nodes, &rest, last).await;
+
+    // The leader removes itself and steps down once that commits
+    let leader_id = cluster.id(leader);
+    cluster.nodes[leader]
+        .remove_member(&leader_id)
+        .await
+        .unwrap();
+    let remaining: Vec<usize> = rest.iter().copied().filter(|&i| i != leader).collect();
+    let new_leader = wait_for_leader(&cluster.nodes, &remaining).await;
+    wait_until("the old leader to step down", || {
+        !cluster.nodes[leader].current_state().is_leader()
+    })
+    .await;
+    assert!(!cluster.nodes[new_leader].members().contains(&leader_id));
+    let last = submit(&cluster.nodes[new_leader], "d", 10).await;
+    wait_for_commit(&cluster.nodes, &remaining, last).await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_leadership_transfer() {
+    let cluster = Cluster::start(3);
+    let all = [0, 1, 2];
+
+    let leader = wait_for_leader(&cluster.nodes, &all).await;
+    let last = submit(&cluster.nodes[leader], "a", 20).await;
+    wait_for_commit(&cluster.nodes, &all, last).await;
+
+    assert!(cluster.nodes[leader]
+        .transfer_leadership(&cluster.id(leader))
+        .await
+        .is_ok());
+    assert!(cluster.nodes[leader]
+        .transfer_leadership(&"node9".to_string())
+        .await
+        .is_err());
+
+    let target = (leader + 1) % 3;
+    let term = cluster.nodes[leader].current_term();
+    cluster.nodes[leader]
+        .transfer_leadership(&cluster.id(target))
+        .await
+        .unwrap();
+    wait_until("the target to take over", || {
+        cluster.nodes[target].current_state().is_leader()
+    })
+    .await;
+    assert_eq!(cluster.nodes[target].current_term(), term + 1);
+    wait_until("the old leader to follow", || {
+        cluster.nodes[leader].current_leader().as_ref() == Some(&cluster.id(target))
+    })
+    .await;
+    let last = submit(&cluster.nodes[target], "b", 10).await;
+    wait_for_commit(&cluster.nodes, &all, last).await;
+
+    // A transfer to an unreachable node times out and the leader carries on
+    let leader = target;
+    let unreachable = (leader + 1) % 3;
+    cluster.network.isolate(&cluster.id(unreachable));
+    cluster.nodes[leader]
+        .transfer_leadership(&cluster.id(unreachable))
+        .await
+        .unwrap();
+    assert!(matches!(
+        cluster.nodes[leader].submit_command(b"x".to_vec()).await,
+        Err(RaftError::NotLeader)
+    ));
+    let deadline = Instant::now() + WAIT;
+    while cluster.nodes[leader]
+        .submit_command(b"y".to_vec())
+        .await
+        .is_err()
+    {
+        assert!(Instant::now() < deadline, "leader never resumed");
+        sleep(Duration::from_millis(20)).await;
+    }
+    assert!(cluster.nodes[leader].current_state().is_leader());
+    cluster.network.heal();
+}
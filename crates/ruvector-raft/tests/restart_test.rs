@@ -0,0 +1,288 @@
+//! Crash-restart tests for nodes backed by `FileStorage`
+//!
+//! Nodes are shut down and recreated from their storage directories, and
+//! must come back with their term, vote and log intact.
+
+use ruvector_raft::rpc::{RaftMessage, RequestVoteRequest};
+use ruvector_raft::{
+    FileStorage, InMemoryNetwork, InMemoryTransport, NodeId, RaftInbox, RaftNode, RaftNodeConfig,
+    RaftTransport,
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout, Instant};
+
+const WAIT: Duration = Duration::from_secs(10);
+
+struct DurableCluster {
+    network: InMemoryNetwork,
+    dir: PathBuf,
+    ids: Vec<NodeId>,
+    nodes: Vec<Arc<RaftNode>>,
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl DurableCluster {
+    fn new(name: &str, size: usize) -> Self {
+        let dir = std::env::temp_dir().join(format!("ruvector-raft-restart-{}", name));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut cluster = Self {
+            network: InMemoryNetwork::new(),
+            dir,
+            ids: (1..=size).map(|i| format!("node{}", i)).collect(),
+            nodes: Vec::new(),
+            handles: Vec::new(),
+        };
+        for i in 0..size {
+            let (node, handle) = cluster.open(i);
+            cluster.nodes.push(node);
+            cluster.handles.push(handle);
+        }
+        cluster
+    }
+
+    /// Create node `i` from its storage directory and start it
+    fn open(&self, i: usize) -> (Arc<RaftNode>, JoinHandle<()>) {
+        let storage = FileStorage::with_segment_size(self.dir.join(&self.ids[i]), 512).unwrap();
+        let node = Arc::new(
+            RaftNode::with_storage(
+                RaftNodeConfig::new(self.ids[i].clone(), self.ids.clone()),
+                Arc::new(self.network.transport(self.ids[i].clone())),
+                Box::new(storage),
+            )
+            .unwrap(),
+        );
+        let handle = tokio::spawn({
+            let node = node.clone();
+            async move { node.start().await.unwrap() }
+        });
+        (node, handle)
+    }
+
+    async fn crash(&mut self, i: usize) {
+        self.nodes[i].shutdown();
+        (&mut self.handles[i]).await.unwrap();
+    }
+
+    fn restart(&mut self, i: usize) {
+        let (node, handle) = self.open(i);
+        self.nodes[i] = node;
+        self.handles[i] = handle;
+    }
+}
+
+impl Drop for DurableCluster {
+    fn drop(&mut self) {
+        for node in &self.nodes {
+            node.shutdown();
+        }
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+async fn wait_until(what: &str, mut condition: impl FnMut() -> bool) {
+    let deadline = Instant::now() + WAIT;
+    while !condition() {
+        assert!(Instant::now() < deadline, "timed out waiting for {}", what);
+        sleep(Duration::from_millis(10)).await;
+    }
+}
+
+async fn wait_for_leader(nodes: &[Arc<RaftNode>], members: &[usize]) -> usize {
+    let mut leader = None;
+    wait_until("a leader", || {
+        leader = members
+            .iter()
+            .copied()
+            .filter(|&i| nodes[i].current_state().is_leader())
+            .max_by_key(|&i| nodes[i].current_term());
+        leader.is_some()
+    })
+    .await;
+    leader.unwrap()
+}
+
+async fn submit(node: &RaftNode, name: &str, count: usize) -> u64 {
+    let mut last = 0;
+    for i in 0..count {
+        let command = format!("{}-{}", name, i).into_bytes();
+        last = node.submit_command(command).await.unwrap().index;
+    }
+    last
+}
+
+async fn wait_for_commit(nodes: &[Arc<RaftNode>], members: &[usize], index: u64) {
+    wait_until("replication", || {
+        members.iter().all(|&i| nodes[i].commit_index() >= index)
+    })
+    .await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_whole_cluster_restart_keeps_log() {
+    let mut cluster = DurableCluster::new("whole", 3);
+    let all = [0, 1, 2];
+
+    let leader = wait_for_leader(&cluster.nodes, &all).await;
+    let last = submit(&cluster.nodes[leader], "a", 30).await;
+    wait_for_commit(&cluster.nodes, &all, last).await;
+    let term = cluster.nodes[leader].current_term();
+    let log: Vec<_> = (1..=last)
+        .map(|i| cluster.nodes[leader].log_entry(i).unwrap())
+        .collect();
+
+    for i in all {
+        cluster.crash(i).await;
+    }
+    for i in all {
+        cluster.restart(i);
+        let node = &cluster.nodes[i];
+        assert!(node.current_term() >= term);
+        assert!(node.last_log_index() >= last);
+        for (index, entry) in (1..=last).zip(&log) {
+            assert_eq!(node.log_entry(index).as_ref(), Some(entry));
+        }
+    }
+
+    // The restarted cluster elects a leader in a later term and keeps committing
+    let leader = wait_for_leader(&cluster.nodes, &all).await;
+    assert!(cluster.nodes[leader].current_term() > term);
+    let before_restart = last;
+    let last = submit(&cluster.nodes[leader], "b", 10).await;
+    wait_for_commit(&cluster.nodes, &all, last).await;
+    for node in &cluster.nodes {
+        assert_eq!(node.log_entry(before_restart).unwrap().command, b"a-29");
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_follower_restart_catches_up() {
+    let mut cluster = DurableCluster::new("follower", 3);
+    let all = [0, 1, 2];
+
+    let leader = wait_for_leader(&cluster.nodes, &all).await;
+    let last = submit(&cluster.nodes[leader], "a", 10).await;
+    wait_for_commit(&cluster.nodes, &all, last).await;
+
+    let follower = (leader + 1) % 3;
+    cluster.crash(follower).await;
+    let last = submit(&cluster.nodes[leader], "b", 20).await;
+    let others = [leader, (leader + 2) % 3];
+    wait_for_commit(&cluster.nodes, &others, last).await;
+
+    cluster.restart(follower);
+    assert!(cluster.nodes[follower].last_log_index() < last);
+    wait_for_commit(&cluster.nodes, &all, last).await;
+    assert_eq!(
+        cluster.nodes[follower].log_entry(last),
+        cluster.nodes[leader].log_entry(last)
+    );
+}
+
+/// Ask node1 for its vote in term 5 until it answers
+///
+/// Requests sent before node1 starts listening are dropped, so keep asking.
+async fn request_vote(
+    candidate: &InMemoryTransport,
+    responses: &mut mpsc::UnboundedReceiver<(NodeId, RaftMessage)>,
+    candidate_id: &str,
+) -> bool {
+    let request = RequestVoteRequest::new(5, candidate_id.to_string(), 0, 0);
+    loop {
+        candidate.send(
+            &"node1".to_string(),
+            RaftMessage::RequestVoteRequest(request.clone()),
+        );
+        match timeout(Duration::from_millis(100), responses.recv()).await {
+            Ok(Some((_, RaftMessage::RequestVoteResponse(response)))) => {
+                return response.vote_granted
+            }
+            Ok(other) => panic!("unexpected message {:?}", other),
+            Err(_) => continue,
+        }
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_vote_survives_restart() {
+    let dir = std::env::temp_dir().join("ruvector-raft-restart-vote");
+    let _ = std::fs::remove_dir_all(&dir);
+    let ids: Vec<NodeId> = vec!["node1".into(), "node2".into(), "node3".into()];
+    let network = InMemoryNetwork::new();
+
+    // node2 and node3 are played by the test
+    let (inbox, mut responses) = RaftInbox::channel();
+    let node2 = network.transport("node2");
+    let node3 = network.transport("node3");
+    node3.listen(inbox.clone()).unwrap();
+    node2.listen(inbox).unwrap();
+
+    let start = |ids: Vec<NodeId>| {
+        let storage = FileStorage::open(&dir).unwrap();
+        let mut config = RaftNodeConfig::new("node1".to_string(), ids);
+        // Keep node1 from running its own elections
+        config.election_timeout_min = 60_000;
+        config.election_timeout_max = 60_000;
+        let node = Arc::new(
+            RaftNode::with_storage(
+                config,
+                Arc::new(network.transport("node1")),
+                Box::new(storage),
+            )
+            .unwrap(),
+        );
+        tokio::spawn(node.clone().start());
+        node
+    };
+    let node = start(ids.clone());
+    assert!(request_vote(&node2, &mut responses, "node2").await);
+
+    node.shutdown();
+    sleep(Duration::from_millis(50)).await;
+    let node = start(ids);
+    assert_eq!(node.current_term(), 5);
+
+    // Same term, different candidate: the vote was already cast
+    assert!(!request_vote(&node3, &mut responses, "node3").await);
+
+    // The original candidate can ask again
+    assert!(request_vote(&node2, &mut responses, "node2").await);
+
+    node.shutdown();
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_compacted_log_restart() {
+    let mut cluster = DurableCluster::new("compaction", 1);
+
+    wait_for_leader(&cluster.nodes, &[0]).await;
+    let last = submit(&cluster.nodes[0], "a", 40).await;
+    wait_for_commit(&cluster.nodes, &[0], last).await;
+    let snapshot = cluster.nodes[0]
+        .compact_log(last - 5, b"state".to_vec())
+        .unwrap();
+    assert!(cluster.nodes[0].compact_log(last + 1, Vec::new()).is_err());
+    assert!(cluster.nodes[0].log_entry(last - 5).is_none());
+    let last = submit(&cluster.nodes[0], "b", 3).await;
+
+    cluster.crash(0).await;
+    cluster.restart(0);
+    let node = &cluster.nodes[0];
+    assert!(node.commit_index() >= snapshot.last_included_index);
+    assert!(node.log_entry(snapshot.last_included_index).is_none());
+    assert_eq!(
+        node.log_entry(snapshot.last_included_index + 1)
+            .unwrap()
+            .command,
+        b"a-35"
+    );
+    assert_eq!(node.log_entry(last).unwrap().command, b"b-2");
+
+    wait_for_leader(&cluster.nodes, &[0]).await;
+    let last = submit(&cluster.nodes[0], "c", 1).await;
+    wait_for_commit(&cluster.nodes, &[0], last).await;
+}
@@ -0,0 +1,149 @@
+//! Applying committed entries to a state machine and linearizable reads
+
+use parking_lot::Mutex;
+use ruvector_raft::{
+    InMemoryNetwork, LogEntry, NodeId, RaftError, RaftNode, RaftNodeConfig, RaftResult, Snapshot,
+    StateMachine,
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{sleep, Instant};
+
+const WAIT: Duration = Duration::from_secs(10);
+
+/// Appends every command to a list and answers with the list's length
+#[derive(Default)]
+struct ListMachine {
+    items: Mutex<Vec<String>>,
+}
+
+impl StateMachine for ListMachine {
+    fn apply(&self, entry: &LogEntry) -> Vec<u8> {
+        let mut items = self.items.lock();
+        items.push(String::from_utf8_lossy(&entry.command).into_owned());
+        (items.len() as u64).to_le_bytes().to_vec()
+    }
+
+    fn snapshot(&self) -> RaftResult<Vec<u8>> {
+        Ok(self.items.lock().join("\n").into_bytes())
+    }
+
+    fn restore(&self, snapshot: &Snapshot) -> RaftResult<()> {
+        *self.items.lock() = String::from_utf8_lossy(&snapshot.data)
+            .split('\n')
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect();
+        Ok(())
+    }
+}
+
+async fn wait_until(what: &str, mut condition: impl FnMut() -> bool) {
+    let deadline = Instant::now() + WAIT;
+    while !condition() {
+        assert!(Instant::now() < deadline, "timed out waiting for {}", what);
+        sleep(Duration::from_millis(10)).await;
+    }
+}
+
+async fn wait_for_leader(nodes: &[Arc<RaftNode>]) -> usize {
+    let mut leader = None;
+    wait_until("a leader", || {
+        leader = (0..nodes.len())
+            .filter(|&i| nodes[i].current_state().is_leader())
+            .max_by_key(|&i| nodes[i].current_term());
+        leader.is_some()
+    })
+    .await;
+    leader.unwrap()
+}
+
+fn ids(size: usize) -> Vec<NodeId> {
+    (1..=size).map(|i| format!("node{}", i)).collect()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_state_machine_replication() {
+    let network = InMemoryNetwork::new();
+    let ids = ids(3);
+    let machines: Vec<Arc<ListMachine>> = ids.iter().map(|_| Arc::default()).collect();
+    let nodes: Vec<Arc<RaftNode>> = ids
+        .iter()
+        .zip(&machines)
+        .map(|(id, machine)| {
+            let node = RaftNode::new(
+                RaftNodeConfig::new(id.clone(), ids.clone()),
+                Arc::new(network.transport(id.clone())),
+            )
+            .with_state_machine(machine.clone())
+            .unwrap();
+            let node = Arc::new(node);
+            tokio::spawn(node.clone().start());
+            node
+        })
+        .collect();
+
+    let leader = wait_for_leader(&nodes).await;
+    for i in 1..=5u64 {
+        let response = nodes[leader]
+            .propose(format!("item{}", i).into_bytes())
+            .await
+            .unwrap();
+        assert_eq!(response, i.to_le_bytes());
+    }
+
+    // The leader has applied its writes before the read returns
+    let read_index = nodes[leader].read_index().await.unwrap();
+    assert!(nodes[leader].last_applied() >= read_index);
+    assert_eq!(machines[leader].items.lock().len(), 5);
+
+    let follower = (leader + 1) % 3;
+    assert!(matches!(
+        nodes[follower].propose(b"x".to_vec()).await,
+        Err(RaftError::NotLeader)
+    ));
+    assert!(matches!(
+        nodes[follower].read_index().await,
+        Err(RaftError::NotLeader)
+    ));
+    wait_until("followers to apply", || {
+        machines
+            .iter()
+            .all(|machine| machine.items.lock().len() == 5)
+    })
+    .await;
+    let expected = machines[leader].items.lock().clone();
+    for machine in &machines {
+        assert_eq!(*machine.items.lock(), expected);
+    }
+
+    // A follower that misses entries compacted into a snapshot is restored from it
+    let lagging = (leader + 2) % 3;
+    network.isolate(&ids[lagging]);
+    for i in 6..=10 {
+        nodes[leader]
+            .propose(format!("item{}", i).into_bytes())
+            .await
+            .unwrap();
+    }
+    let snapshot = nodes[leader].take_snapshot().await.unwrap();
+    assert_eq!(snapshot.last_included_index, nodes[leader].last_applied());
+    assert!(nodes[lagging].propose(b"x".to_vec()).await.is_err());
+    network.heal();
+    nodes[leader].propose(b"item11".to_vec()).await.unwrap();
+    wait_until("the lagging follower to catch up", || {
+        machines[lagging].items.lock().len() == 11
+    })
+    .await;
+    assert_eq!(
+        *machines[lagging].items.lock(),
+        *machines[leader].items.lock()
+    );
+    assert!(nodes[lagging]
+        .snapshot()
+        .is_some_and(|installed| installed.last_included_index >= snapshot.last_included_index));
+
+    for node in &nodes {
+        node.shutdown();
+    }
+}
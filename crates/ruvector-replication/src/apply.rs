@@ -0,0 +1,274 @@
+//! Applying replicated changes on a secondary
+//!
+//! A [`ReplicationClient`](crate::ReplicationClient) hands every change it
+//! receives to an [`ApplyHook`]; [`VectorDbApplier`] replays them into a
+//! local `VectorDB`, and [`CrdtApplier`] merges them into one when several
+//! primaries take writes.
+
+use crate::payload::{Crdt, CrdtPayload, PayloadSchema};
+use crate::{ChangeEvent, ChangeOperation, ReplicationError, Result};
+use chrono::{DateTime, Duration, Utc};
+use parking_lot::Mutex;
+use redb::{Database, ReadableTable, TableDefinition};
+use ruvector_core::types::VectorEntry;
+use ruvector_core::VectorDB;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Database holding the merge state of every document
+const STATES_FILE: &str = "crdt.redb";
+
+const STATES_TABLE: TableDefinition<&str, &[u8]> = TableDefinition::new("states");
+
+/// Applies replicated changes, in log order
+///
+/// A change may be applied again after a restart or reconnect, so
+/// applying must be idempotent.
+pub trait ApplyHook: Send + Sync {
+    /// Apply one change
+    fn apply(&self, event: &ChangeEvent) -> Result<()>;
+}
+
+/// Replays changes into a `VectorDB`
+///
+/// Inserts and updates carry a JSON `VectorEntry` and are upserted; bulk
+/// changes carry a JSON list of entries. Changes to every collection go to
+/// the one database.
+pub struct VectorDbApplier {
+    db: Arc<VectorDB>,
+}
+
+impl VectorDbApplier {
+    /// Apply changes to `db`
+    pub fn new(db: Arc<VectorDB>) -> Self {
+        Self { db }
+    }
+
+    /// The database changes are applied to
+    pub fn db(&self) -> &Arc<VectorDB> {
+        &self.db
+    }
+}
+
+impl ApplyHook for VectorDbApplier {
+    fn apply(&self, event: &ChangeEvent) -> Result<()> {
+        match event.operation {
+            ChangeOperation::Insert | ChangeOperation::Update => {
+                let mut entry: VectorEntry = serde_json::from_slice(&event.data)?;
+                entry.id = Some(event.document_id.clone());
+                self.db.upsert(entry).map_err(storage_error)?;
+            }
+            ChangeOperation::Delete => {
+                self.db.delete(&event.document_id).map_err(storage_error)?;
+            }
+            ChangeOperation::Bulk => {
+                let entries: Vec<VectorEntry> = serde_json::from_slice(&event.data)?;
+                self.db.upsert_batch(entries).map_err(storage_error)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Merges changes from several primaries into a `VectorDB`
+///
+/// For multi-primary replication: local writes go through
+/// [`write`](Self::write) and [`delete`](Self::delete), which return the
+/// change to replicate, and the changes other primaries replicate are
+/// merged in as they are applied. Every primary that has seen the same
+/// changes holds the same documents, whatever order they arrived in.
+///
+/// Each document's [`CrdtPayload`] is kept in a database of its own in
+/// `dir`, one record per document, so entries in the `VectorDB` carry only
+/// the user's metadata. Deleted documents keep their record as a tombstone
+/// until [`purge_tombstones`](Self::purge_tombstones) drops it. Every
+/// change applied must come from a `CrdtApplier`.
+pub struct CrdtApplier {
+    db: Arc<VectorDB>,
+    replica_id: String,
+    schema: PayloadSchema,
+    /// Merge state by document ID
+    states: Database,
+    /// Held while a document changes
+    lock: Mutex<()>,
+}
+
+/// Merge state of a document as stored
+#[derive(Serialize, Deserialize)]
+struct StoredState {
+    payload: CrdtPayload,
+    /// When the document was last seen deleted, for tombstones
+    deleted_at: Option<DateTime<Utc>>,
+}
+
+impl CrdtApplier {
+    /// Merge changes into `db`, writing locally as `replica_id`
+    ///
+    /// `dir` holds the merge state of every document. Every primary must use
+    /// the same `schema`.
+    pub fn open(
+        db: Arc<VectorDB>,
+        dir: impl AsRef<Path>,
+        replica_id: impl Into<String>,
+        schema: PayloadSchema,
+    ) -> Result<Self> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+        let states = Database::create(dir.join(STATES_FILE)).map_err(state_error)?;
+        let txn = states.begin_write().map_err(state_error)?;
+        txn.open_table(STATES_TABLE).map_err(state_error)?;
+        txn.commit().map_err(state_error)?;
+
+        Ok(Self {
+            db,
+            replica_id: replica_id.into(),
+            schema,
+            states,
+            lock: Mutex::new(()),
+        })
+    }
+
+    /// The database changes are merged into
+    pub fn db(&self) -> &Arc<VectorDB> {
+        &self.db
+    }
+
+    /// ID local writes are made as
+    pub fn replica_id(&self) -> &str {
+        &self.replica_id
+    }
+
+    /// Merge state of document `id`, `None` if it was never written
+    pub fn payload(&self, id: &str) -> Result<Option<CrdtPayload>> {
+        Ok(self.load(id)?.map(|state| state.payload))
+    }
+
+    /// Write `entry` locally, returning the change to replicate to
+    /// `collection`
+    ///
+    /// Entries without an id get a generated one.
+    pub fn write(&self, collection: impl Into<String>, entry: &VectorEntry) -> Result<ChangeEvent> {
+        let mut entry = entry.clone();
+        let id = entry
+            .id
+            .get_or_insert_with(|| Uuid::new_v4().to_string())
+            .clone();
+
+        let _changing = self.lock.lock();
+        let mut payload = self.payload(&id)?.unwrap_or_default();
+        payload.write(&self.replica_id, &entry, &self.schema)?;
+        self.store(&id, &payload)?;
+        ChangeEvent::payload(collection, id, &payload)
+    }
+
+    /// Delete document `id` locally, returning the change to replicate to
+    /// `collection`
+    pub fn delete(&self, collection: impl Into<String>, id: &str) -> Result<ChangeEvent> {
+        let _changing = self.lock.lock();
+        let mut payload = self.payload(id)?.unwrap_or_default();
+        payload.delete(&self.replica_id);
+        self.store(id, &payload)?;
+        ChangeEvent::payload(collection, id, &payload)
+    }
+
+    /// Drop the state of documents deleted more than `retention` ago
+    ///
+    /// A dropped tombstone no longer stops a change made before the delete
+    /// from recreating the document, so `retention` must cover the longest
+    /// time a change can take to reach this primary from any other.
+    /// Returns the number of tombstones dropped.
+    pub fn purge_tombstones(&self, retention: Duration) -> Result<usize> {
+        let cutoff = Utc::now() - retention;
+        let _changing = self.lock.lock();
+
+        let txn = self.states.begin_write().map_err(state_error)?;
+        let purged = {
+            let mut table = txn.open_table(STATES_TABLE).map_err(state_error)?;
+            let mut expired = Vec::new();
+            for item in table.iter().map_err(state_error)? {
+                let (id, bytes) = item.map_err(state_error)?;
+                let state: StoredState = serde_json::from_slice(bytes.value())?;
+                if state.deleted_at.is_some_and(|at| at < cutoff) {
+                    expired.push(id.value().to_string());
+                }
+            }
+            for id in &expired {
+                table.remove(id.as_str()).map_err(state_error)?;
+            }
+            expired.len()
+        };
+        txn.commit().map_err(state_error)?;
+        Ok(purged)
+    }
+
+    fn load(&self, id: &str) -> Result<Option<StoredState>> {
+        let txn = self.states.begin_read().map_err(state_error)?;
+        let table = txn.open_table(STATES_TABLE).map_err(state_error)?;
+        match table.get(id).map_err(state_error)? {
+            Some(bytes) => Ok(Some(serde_json::from_slice(bytes.value())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Save `payload` as the state of document `id`
+    ///
+    /// The document is written to the database before its state, so after
+    /// a crash in between, replaying the change finds the state unchanged
+    /// from before and writes both again.
+    fn store(&self, id: &str, payload: &CrdtPayload) -> Result<()> {
+        let deleted_at = match payload.to_entry(id) {
+            Some(entry) => {
+                self.db.upsert(entry).map_err(storage_error)?;
+                None
+            }
+            None => {
+                self.db.delete(id).map_err(storage_error)?;
+                Some(Utc::now())
+            }
+        };
+
+        let state = StoredState {
+            payload: payload.clone(),
+            deleted_at,
+        };
+        let txn = self.states.begin_write().map_err(state_error)?;
+        {
+            let mut table = txn.open_table(STATES_TABLE).map_err(state_error)?;
+            table
+                .insert(id, serde_json::to_vec(&state)?.as_slice())
+                .map_err(state_error)?;
+        }
+        txn.commit().map_err(state_error)?;
+        Ok(())
+    }
+}
+
+impl ApplyHook for CrdtApplier {
+    fn apply(&self, event: &ChangeEvent) -> Result<()> {
+        if event.operation == ChangeOperation::Bulk {
+            return Err(ReplicationError::SyncFailed(
+                "Bulk changes can't be merged".to_string(),
+            ));
+        }
+        let incoming: CrdtPayload = serde_json::from_slice(&event.data)?;
+
+        let _changing = self.lock.lock();
+        let mut payload = self.payload(&event.document_id)?.unwrap_or_default();
+        let before = payload.clone();
+        payload.merge(&incoming);
+        if payload != before {
+            self.store(&event.document_id, &payload)?;
+        }
+        Ok(())
+    }
+}
+
+fn storage_error(e: ruvector_core::RuvectorError) -> ReplicationError {
+    ReplicationError::SyncFailed(format!("Failed to apply change: {}", e))
+}
+
+fn state_error(e: impl std::fmt::Display) -> ReplicationError {
+    ReplicationError::SyncFailed(format!("CRDT state store failed: {}", e))
+}
@@ -1,14 +1,25 @@
 //! Automatic failover and high availability
 //!
 //! Provides failover management with health monitoring,
-//! quorum-based decision making, and split-brain prevention.
-
-use crate::{Replica, ReplicaRole, ReplicaSet, ReplicationError, Result};
+//! quorum-based decision making, and split-brain prevention
+//! through epoch fencing. A promotion needs the votes of a majority of
+//! replicas, and with a [`ClusterKey`] epoch announcements are
+//! authenticated.
+
+use crate::sync::write_atomic;
+use crate::transport::{request_status, request_vote};
+use crate::{Replica, ReplicaRole, ReplicaSet, ReplicaStatus, ReplicationError, Result};
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use parking_lot::RwLock;
+use futures::future::join_all;
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
 use tokio::time::interval;
 
 /// Health status of a replica
@@ -104,7 +115,347 @@ impl Default for FailoverPolicy {
     }
 }
 
+/// File holding a node's view of the current epoch
+const EPOCH_FILE: &str = "epoch.json";
+
+/// File holding the last promotion a node voted for
+const VOTE_FILE: &str = "vote.json";
+
+/// Health checks kept in the history
+const MAX_HEALTH_HISTORY: usize = 1000;
+
+/// The primary of a failover epoch
+///
+/// Every promotion starts a new epoch. Nodes only ever move to a higher
+/// epoch, so an epoch number works as a fencing token: a primary from an
+/// older epoch is refused by everyone who has seen a newer one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrimaryInfo {
+    /// Epoch number, increased by every promotion
+    pub epoch: u64,
+    /// ID of the primary replica
+    pub replica_id: String,
+    /// Address the primary serves replication on
+    pub address: String,
+    /// Tag made with the [`ClusterKey`], if the cluster uses one
+    #[serde(default)]
+    pub tag: Option<[u8; 32]>,
+}
+
+/// Secret shared by the nodes and failover managers of a cluster
+///
+/// Keyed fences and managers only honour a [`PrimaryInfo`] tagged with the
+/// same key, so a peer without it can't fence the cluster by announcing a
+/// made-up epoch or vote in a promotion.
+#[derive(Clone)]
+pub struct ClusterKey([u8; 32]);
+
+impl ClusterKey {
+    /// Use `key` as the shared secret
+    pub fn new(key: [u8; 32]) -> Self {
+        Self(key)
+    }
+
+    /// Derive the shared secret from a passphrase
+    pub fn from_passphrase(passphrase: &str) -> Self {
+        Self(blake3::derive_key(
+            "ruvector-replication epoch announcement key",
+            passphrase.as_bytes(),
+        ))
+    }
+
+    /// `info` tagged with this key
+    pub fn sign(&self, mut info: PrimaryInfo) -> PrimaryInfo {
+        info.tag = Some(*self.mac(&info).as_bytes());
+        info
+    }
+
+    /// Whether `info` carries a valid tag for this key
+    pub fn verify(&self, info: &PrimaryInfo) -> bool {
+        // Hash equality is constant time
+        info.tag
+            .is_some_and(|tag| self.mac(info) == blake3::Hash::from(tag))
+    }
+
+    fn mac(&self, info: &PrimaryInfo) -> blake3::Hash {
+        let mut hasher = blake3::Hasher::new_keyed(&self.0);
+        hasher.update(&info.epoch.to_le_bytes());
+        for field in [&info.replica_id, &info.address] {
+            hasher.update(&(field.len() as u64).to_le_bytes());
+            hasher.update(field.as_bytes());
+        }
+        hasher.finalize()
+    }
+}
+
+impl fmt::Debug for ClusterKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ClusterKey(..)")
+    }
+}
+
+/// What a replica reports when probed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusReport {
+    /// ID of the replica
+    pub replica_id: String,
+    /// Last sequence in the replica's log
+    pub position: u64,
+    /// The primary as the replica knows it
+    pub primary: Option<PrimaryInfo>,
+}
+
+/// What a replica answers when asked to vote for a promotion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ballot {
+    /// Whether the replica voted for the proposal
+    pub granted: bool,
+    /// Highest epoch the replica has voted in or moved to
+    pub epoch: u64,
+    /// The replica's status
+    pub report: StatusReport,
+}
+
+/// One node's view of the current epoch, used to fence writes
+///
+/// Shared by the node's [`SyncManager`](crate::SyncManager), which only
+/// takes writes while the node is primary, its
+/// [`ReplicationServer`](crate::ReplicationServer), which learns newer
+/// epochs from probes and subscribers, and its
+/// [`ReplicationClient`](crate::ReplicationClient), which follows the
+/// primary of the current epoch.
+///
+/// A fence votes for at most one promotion per epoch, so two managers
+/// promoting different replicas in the same epoch can't both gather a
+/// majority.
+pub struct EpochFence {
+    replica_id: String,
+    /// Directory the epoch is persisted in, if durable
+    dir: Option<PathBuf>,
+    current: watch::Sender<Option<PrimaryInfo>>,
+    /// The last promotion voted for
+    voted: Mutex<Option<PrimaryInfo>>,
+    /// Announcements must be tagged with this key, if set
+    key: Option<ClusterKey>,
+    /// Serializes changes so the persisted epoch never goes back
+    update_lock: Mutex<()>,
+}
+
+impl EpochFence {
+    /// Create an in-memory fence for `replica_id`
+    pub fn new(replica_id: impl Into<String>) -> Self {
+        Self {
+            replica_id: replica_id.into(),
+            dir: None,
+            current: watch::channel(None).0,
+            voted: Mutex::new(None),
+            key: None,
+            update_lock: Mutex::new(()),
+        }
+    }
+
+    /// Open the fence persisted in `dir`, so a restarted node remembers
+    /// the latest epoch it has seen
+    pub fn open(dir: impl AsRef<Path>, replica_id: impl Into<String>) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)?;
+        let current = read_json(&dir.join(EPOCH_FILE))?;
+        let voted = read_json(&dir.join(VOTE_FILE))?;
+        Ok(Self {
+            replica_id: replica_id.into(),
+            dir: Some(dir),
+            current: watch::channel(current).0,
+            voted: Mutex::new(voted),
+            key: None,
+            update_lock: Mutex::new(()),
+        })
+    }
+
+    /// Only honour announcements and proposals tagged with `key`
+    pub fn with_key(mut self, key: ClusterKey) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    /// ID of the node this fence belongs to
+    pub fn replica_id(&self) -> &str {
+        &self.replica_id
+    }
+
+    /// The primary of the latest epoch seen
+    pub fn current(&self) -> Option<PrimaryInfo> {
+        self.current.borrow().clone()
+    }
+
+    /// The latest epoch seen, 0 before any
+    pub fn epoch(&self) -> u64 {
+        self.current.borrow().as_ref().map_or(0, |info| info.epoch)
+    }
+
+    /// The highest epoch this node has voted in or moved to
+    pub fn highest_epoch(&self) -> u64 {
+        let voted = self.voted.lock().as_ref().map_or(0, |info| info.epoch);
+        voted.max(self.epoch())
+    }
+
+    /// Whether this node is the primary of the latest epoch
+    pub fn is_primary(&self) -> bool {
+        self.current
+            .borrow()
+            .as_ref()
+            .is_some_and(|info| info.replica_id == self.replica_id)
+    }
+
+    /// Move to `info`'s epoch if it is newer than the current one,
+    /// returning whether it was
+    ///
+    /// The new epoch takes effect even if persisting it fails. A keyed
+    /// fence refuses announcements without a valid tag.
+    pub fn observe(&self, info: &PrimaryInfo) -> Result<bool> {
+        self.authenticate(info)?;
+        let _guard = self.update_lock.lock();
+        let newer = self
+            .current
+            .borrow()
+            .as_ref()
+            .map_or(true, |current| info.epoch > current.epoch);
+        if !newer {
+            return Ok(false);
+        }
+
+        // Persisted first, so the epoch is durable once anyone sees it
+        let persisted = match &self.dir {
+            Some(dir) => serde_json::to_vec(&Some(info))
+                .map_err(ReplicationError::from)
+                .and_then(|bytes| write_atomic(dir, EPOCH_FILE, &bytes)),
+            None => Ok(()),
+        };
+        self.current.send_replace(Some(info.clone()));
+        if info.replica_id == self.replica_id {
+            tracing::info!("{} is primary in epoch {}", self.replica_id, info.epoch);
+        } else {
+            tracing::info!(
+                "{} follows {} in epoch {}",
+                self.replica_id,
+                info.replica_id,
+                info.epoch
+            );
+        }
+        persisted.map(|()| true)
+    }
+
+    /// Vote for `proposal` to become primary, returning whether the vote
+    /// was granted
+    ///
+    /// Votes only go to epochs newer than any this node has voted in or
+    /// moved to, and are persisted before they are granted.
+    pub fn vote(&self, proposal: &PrimaryInfo) -> Result<bool> {
+        self.authenticate(proposal)?;
+        let _guard = self.update_lock.lock();
+        let mut voted = self.voted.lock();
+        if voted.as_ref() == Some(proposal) {
+            return Ok(true);
+        }
+        let highest = voted
+            .as_ref()
+            .map_or(0, |info| info.epoch)
+            .max(self.epoch());
+        if proposal.epoch <= highest {
+            return Ok(false);
+        }
+
+        if let Some(dir) = &self.dir {
+            write_atomic(dir, VOTE_FILE, &serde_json::to_vec(proposal)?)?;
+        }
+        *voted = Some(proposal.clone());
+        tracing::info!(
+            "{} votes for {} in epoch {}",
+            self.replica_id,
+            proposal.replica_id,
+            proposal.epoch
+        );
+        Ok(true)
+    }
+
+    fn authenticate(&self, info: &PrimaryInfo) -> Result<()> {
+        match &self.key {
+            Some(key) if !key.verify(info) => Err(ReplicationError::Unauthenticated(format!(
+                "{} in epoch {} is not tagged with the cluster key",
+                info.replica_id, info.epoch
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// The current epoch if this node may take writes in it
+    pub fn check_writable(&self) -> Result<u64> {
+        match self.current.borrow().as_ref() {
+            Some(info) if info.replica_id == self.replica_id => Ok(info.epoch),
+            info => Err(ReplicationError::NotPrimary {
+                epoch: info.map_or(0, |info| info.epoch),
+                primary: info.map(|info| info.replica_id.clone()),
+            }),
+        }
+    }
+
+    /// Watch the primary of the latest epoch
+    pub fn subscribe(&self) -> watch::Receiver<Option<PrimaryInfo>> {
+        self.current.subscribe()
+    }
+}
+
+/// Read a JSON file, `None` if it doesn't exist
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<Option<T>> {
+    match std::fs::read(path) {
+        Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Reaches replicas to check their health
+///
+/// Each probe announces the primary the prober knows, so a replica that
+/// missed a promotion, such as a returning old primary, fences itself.
+#[async_trait]
+pub trait HealthProbe: Send + Sync {
+    /// Get the status of `replica`, announcing `primary`
+    async fn probe(&self, replica: &Replica, primary: Option<&PrimaryInfo>)
+        -> Result<StatusReport>;
+
+    /// Ask `replica` to vote for `proposal` becoming primary
+    async fn request_vote(&self, replica: &Replica, proposal: &PrimaryInfo) -> Result<Ballot>;
+}
+
+/// Probes replicas through the [`ReplicationServer`](crate::ReplicationServer)
+/// listening on their address
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TcpHealthProbe;
+
+#[async_trait]
+impl HealthProbe for TcpHealthProbe {
+    async fn probe(
+        &self,
+        replica: &Replica,
+        primary: Option<&PrimaryInfo>,
+    ) -> Result<StatusReport> {
+        request_status(&replica.address, primary).await
+    }
+
+    async fn request_vote(&self, replica: &Replica, proposal: &PrimaryInfo) -> Result<Ballot> {
+        request_vote(&replica.address, proposal).await
+    }
+}
+
 /// Manages automatic failover and health monitoring
+///
+/// With a [`HealthProbe`], the monitoring loop probes every replica, marks
+/// unreachable ones offline and, once the primary has failed
+/// `failure_threshold` checks in a row, promotes the secondary furthest
+/// along the log. Promotion starts a new epoch once a quorum of replicas
+/// has voted for it, and the epoch is then announced to every replica the
+/// manager can reach. Without a probe, health is judged from the heartbeats
+/// recorded in the replica set and only the local view changes.
 pub struct FailoverManager {
     /// The replica set
     replica_set: Arc<RwLock<ReplicaSet>>,
@@ -116,18 +467,22 @@ pub struct FailoverManager {
     failure_counts: Arc<RwLock<std::collections::HashMap<String, usize>>>,
     /// Whether failover is in progress
     failover_in_progress: Arc<RwLock<bool>>,
+    /// How replicas are reached, if at all
+    probe: Option<Arc<dyn HealthProbe>>,
+    /// The primary of the latest epoch known
+    primary: watch::Sender<Option<PrimaryInfo>>,
+    /// Highest epoch proposed or voted in by a replica, as far as known
+    proposed: Mutex<u64>,
+    /// Key proposals are tagged with and reports checked against, if set
+    key: Option<ClusterKey>,
+    /// Monitoring task, while running
+    monitor: Mutex<Option<JoinHandle<()>>>,
 }
 
 impl FailoverManager {
     /// Create a new failover manager
     pub fn new(replica_set: Arc<RwLock<ReplicaSet>>) -> Self {
-        Self {
-            replica_set,
-            policy: Arc::new(RwLock::new(FailoverPolicy::default())),
-            health_history: Arc::new(RwLock::new(Vec::new())),
-            failure_counts: Arc::new(RwLock::new(std::collections::HashMap::new())),
-            failover_in_progress: Arc::new(RwLock::new(false)),
-        }
+        Self::with_policy(replica_set, FailoverPolicy::default())
     }
 
     /// Create with custom policy
@@ -138,9 +493,26 @@ impl FailoverManager {
             health_history: Arc::new(RwLock::new(Vec::new())),
             failure_counts: Arc::new(RwLock::new(std::collections::HashMap::new())),
             failover_in_progress: Arc::new(RwLock::new(false)),
+            probe: None,
+            primary: watch::channel(None).0,
+            proposed: Mutex::new(0),
+            key: None,
+            monitor: Mutex::new(None),
         }
     }
 
+    /// Reach replicas through `probe`
+    pub fn with_probe(mut self, probe: Arc<dyn HealthProbe>) -> Self {
+        self.probe = Some(probe);
+        self
+    }
+
+    /// Tag proposals with `key` and ignore primaries reported without it
+    pub fn with_key(mut self, key: ClusterKey) -> Self {
+        self.key = Some(key);
+        self
+    }
+
     /// Set the failover policy
     pub fn set_policy(&self, policy: FailoverPolicy) {
         *self.policy.write() = policy;
@@ -151,163 +523,373 @@ impl FailoverManager {
         self.policy.read().clone()
     }
 
-    /// Start health monitoring
-    pub async fn start_monitoring(&self) {
-        let policy = self.policy.read().clone();
-        let replica_set = self.replica_set.clone();
-        let health_history = self.health_history.clone();
-        let failure_counts = self.failure_counts.clone();
-        let failover_in_progress = self.failover_in_progress.clone();
-        let manager_policy = self.policy.clone();
+    /// The primary clients should write to, as of the latest epoch known
+    ///
+    /// Learned from the replicas' reports and from this manager's own
+    /// promotions.
+    pub fn current_primary(&self) -> Option<PrimaryInfo> {
+        self.primary.borrow().clone()
+    }
 
-        tokio::spawn(async move {
-            let mut interval_timer = interval(policy.health_check_interval);
+    /// Watch the primary clients should write to
+    pub fn watch_primary(&self) -> watch::Receiver<Option<PrimaryInfo>> {
+        self.primary.subscribe()
+    }
 
+    /// Start health monitoring
+    ///
+    /// Runs a round of [`run_health_checks`](Self::run_health_checks) every
+    /// `health_check_interval` until stopped or the manager is dropped.
+    pub async fn start_monitoring(self: &Arc<Self>) {
+        let interval_duration = self.policy.read().health_check_interval;
+        let manager = Arc::downgrade(self);
+
+        let task = tokio::spawn(async move {
+            let mut interval_timer = interval(interval_duration);
             loop {
                 interval_timer.tick().await;
+                match manager.upgrade() {
+                    Some(manager) => manager.run_health_checks().await,
+                    None => break,
+                }
+            }
+        });
 
-                let replica_ids = {
-                    let set = replica_set.read();
-                    set.replica_ids()
-                };
+        if let Some(previous) = self.monitor.lock().replace(task) {
+            previous.abort();
+        }
+    }
 
-                for replica_id in replica_ids {
-                    let health = Self::check_replica_health(
-                        &replica_set,
-                        &replica_id,
-                        policy.health_check_timeout,
-                    )
-                    .await;
-
-                    // Record health check
-                    health_history.write().push(health.clone());
-
-                    // Update failure count and check if failover is needed
-                    // Use a scope to ensure lock is dropped before any await
-                    let should_failover = {
-                        let mut counts = failure_counts.write();
-                        let count = counts.entry(replica_id.clone()).or_insert(0);
-
-                        match health.status {
-                            HealthStatus::Healthy => {
-                                *count = 0;
-                                false
-                            }
-                            HealthStatus::Degraded => {
-                                // Don't increment for degraded
-                                false
-                            }
-                            HealthStatus::Unhealthy | HealthStatus::Unresponsive => {
-                                *count += 1;
-
-                                // Check if failover is needed
-                                let current_policy = manager_policy.read();
-                                *count >= current_policy.failure_threshold
-                                    && current_policy.auto_failover
-                            }
-                        }
-                    }; // Lock is dropped here
-
-                    // Trigger failover if needed (after lock is dropped)
-                    if should_failover {
-                        if let Err(e) =
-                            Self::trigger_failover(&replica_set, &failover_in_progress).await
-                        {
-                            tracing::error!("Failover failed: {}", e);
-                        }
+    /// Stop health monitoring
+    pub fn stop_monitoring(&self) {
+        if let Some(task) = self.monitor.lock().take() {
+            task.abort();
+        }
+    }
+
+    /// Check every replica once, failing over if the primary is down
+    pub async fn run_health_checks(&self) {
+        let policy = self.policy();
+        let replicas: Vec<Replica> = {
+            let set = self.replica_set.read();
+            set.replica_ids()
+                .iter()
+                .filter_map(|id| set.get_replica(id))
+                .collect()
+        };
+
+        let announced = self.current_primary();
+        let checks = join_all(replicas.iter().map(|replica| {
+            self.check_replica_health(replica, announced.as_ref(), policy.health_check_timeout)
+        }))
+        .await;
+
+        for (health, report) in checks {
+            // Record health check
+            self.health_history.write().push(health.clone());
+
+            if self.probe.is_some() {
+                let set = self.replica_set.read();
+                set.update_replica(&health.replica_id, |replica| match &report {
+                    Some(report) => {
+                        replica.heartbeat();
+                        replica.status = ReplicaStatus::Healthy;
+                        replica.update_position(report.position);
                     }
-                }
+                    None => replica.status = ReplicaStatus::Offline,
+                });
+            }
+            if let Some(primary) = report.and_then(|report| report.primary) {
+                self.adopt(primary);
+            }
 
-                // Trim health history to last 1000 entries
-                let mut history = health_history.write();
-                let len = history.len();
-                if len > 1000 {
-                    history.drain(0..len - 1000);
-                }
+            let mut counts = self.failure_counts.write();
+            let count = counts.entry(health.replica_id.clone()).or_insert(0);
+            match health.status {
+                HealthStatus::Healthy => *count = 0,
+                // Don't increment for degraded
+                HealthStatus::Degraded => {}
+                HealthStatus::Unhealthy | HealthStatus::Unresponsive => *count += 1,
             }
+        }
+
+        // Trim health history
+        {
+            let mut history = self.health_history.write();
+            let len = history.len();
+            if len > MAX_HEALTH_HISTORY {
+                history.drain(0..len - MAX_HEALTH_HISTORY);
+            }
+        }
+
+        let primary_id = self
+            .current_primary()
+            .map(|primary| primary.replica_id)
+            .or_else(|| self.replica_set.read().get_primary().map(|r| r.id));
+        let should_failover = primary_id.is_some_and(|id| {
+            policy.auto_failover && self.failure_count(&id) >= policy.failure_threshold
         });
+        if should_failover {
+            if let Err(e) = self.trigger_failover(None).await {
+                tracing::error!("Failover failed: {}", e);
+            }
+        }
     }
 
-    /// Check health of a specific replica
+    /// Check health of a specific replica, returning its report if probed
     async fn check_replica_health(
-        replica_set: &Arc<RwLock<ReplicaSet>>,
-        replica_id: &str,
+        &self,
+        replica: &Replica,
+        announced: Option<&PrimaryInfo>,
         timeout: Duration,
-    ) -> HealthCheck {
-        // In a real implementation, this would make a network call
-        // For now, we simulate health checks based on replica status
-
-        let replica = {
-            let set = replica_set.read();
-            set.get_replica(replica_id)
-        };
-
-        match replica {
-            Some(replica) => {
-                if replica.is_timed_out(timeout) {
-                    HealthCheck::unresponsive(replica_id.to_string())
+    ) -> (HealthCheck, Option<StatusReport>) {
+        let id = replica.id.clone();
+        let probe = match &self.probe {
+            Some(probe) => probe,
+            None => {
+                // Judge from the heartbeats recorded in the replica set
+                let check = if replica.is_timed_out(timeout) {
+                    HealthCheck::unresponsive(id)
                 } else if replica.is_healthy() {
-                    HealthCheck::healthy(replica_id.to_string(), 10)
+                    HealthCheck::healthy(id, 10)
                 } else {
-                    HealthCheck::unhealthy(replica_id.to_string(), "Replica is lagging".to_string())
-                }
+                    HealthCheck::unhealthy(id, "Replica is lagging".to_string())
+                };
+                return (check, None);
+            }
+        };
+
+        let started = std::time::Instant::now();
+        match tokio::time::timeout(timeout, probe.probe(replica, announced)).await {
+            Ok(Ok(report)) => {
+                let elapsed = started.elapsed().as_millis() as u64;
+                (HealthCheck::healthy(id, elapsed), Some(report))
             }
-            None => HealthCheck::unhealthy(replica_id.to_string(), "Replica not found".to_string()),
+            Ok(Err(e)) => (HealthCheck::unhealthy(id, e.to_string()), None),
+            Err(_) => (HealthCheck::unresponsive(id), None),
         }
     }
 
-    /// Trigger failover to a healthy secondary
-    async fn trigger_failover(
-        replica_set: &Arc<RwLock<ReplicaSet>>,
-        failover_in_progress: &Arc<RwLock<bool>>,
-    ) -> Result<()> {
+    /// Take on a primary reported by a replica if its epoch is newer
+    fn adopt(&self, info: PrimaryInfo) {
+        if self.key.as_ref().is_some_and(|key| !key.verify(&info)) {
+            tracing::warn!(
+                "Ignoring primary {} in epoch {} without a valid tag",
+                info.replica_id,
+                info.epoch
+            );
+            return;
+        }
+        let newer = self.primary.send_if_modified(|current| {
+            if current
+                .as_ref()
+                .map_or(true, |current| info.epoch > current.epoch)
+            {
+                *current = Some(info.clone());
+                true
+            } else {
+                false
+            }
+        });
+        if newer {
+            tracing::info!("Primary is {} in epoch {}", info.replica_id, info.epoch);
+            let _ = self
+                .replica_set
+                .write()
+                .promote_to_primary(&info.replica_id);
+        }
+    }
+
+    /// Fail over to `target`, or to the best secondary
+    async fn trigger_failover(&self, target: Option<String>) -> Result<PrimaryInfo> {
         // Check if failover is already in progress
         {
-            let mut in_progress = failover_in_progress.write();
+            let mut in_progress = self.failover_in_progress.write();
             if *in_progress {
-                return Ok(());
+                return Err(ReplicationError::FailoverFailed(
+                    "Failover already in progress".to_string(),
+                ));
             }
             *in_progress = true;
         }
 
         tracing::warn!("Initiating failover");
+        let result = self.promote(target).await;
+        match &result {
+            Ok(info) => tracing::info!(
+                "Failover completed: promoted {} to primary in epoch {}",
+                info.replica_id,
+                info.epoch
+            ),
+            Err(e) => tracing::error!("Failover failed: {}", e),
+        }
 
-        // Find candidate within a scope to drop the lock before await
-        let candidate_id = {
-            let set = replica_set.read();
+        // Clear failover flag
+        *self.failover_in_progress.write() = false;
+        result
+    }
+
+    async fn promote(&self, target: Option<String>) -> Result<PrimaryInfo> {
+        let policy = self.policy();
+
+        // Choose within a scope to drop the lock before awaiting
+        let (candidate, voters, needed) = {
+            let set = self.replica_set.read();
 
             // Check quorum
-            if !set.has_quorum() {
-                *failover_in_progress.write() = false;
+            let needed = set.get_quorum_size().max(policy.min_quorum);
+            let reachable = set
+                .get_healthy_replicas()
+                .into_iter()
+                .filter(|r| r.role != ReplicaRole::Witness)
+                .count();
+            if policy.prevent_split_brain && reachable < needed {
                 return Err(ReplicationError::QuorumNotMet {
-                    needed: set.get_quorum_size(),
-                    available: set.get_healthy_replicas().len(),
+                    needed,
+                    available: reachable,
                 });
             }
 
-            // Find best candidate for promotion
-            let candidate = Self::select_failover_candidate(&set)?;
-            candidate.id.clone()
-        }; // Lock is dropped here
+            let candidate = match target {
+                Some(id) => set
+                    .get_replica(&id)
+                    .ok_or(ReplicationError::ReplicaNotFound(id))?,
+                None => Self::select_failover_candidate(&set)?,
+            };
+            let voters: Vec<Replica> = set
+                .replica_ids()
+                .iter()
+                .filter_map(|id| set.get_replica(id))
+                .filter(|r| r.role != ReplicaRole::Witness)
+                .collect();
+            (candidate, voters, needed)
+        };
+
+        let epoch = {
+            let mut proposed = self.proposed.lock();
+            *proposed = (*proposed).max(self.epoch()) + 1;
+            *proposed
+        };
+        let info = self.sign(PrimaryInfo {
+            epoch,
+            replica_id: candidate.id.clone(),
+            address: candidate.address.clone(),
+            tag: None,
+        });
 
-        // Promote the candidate (lock re-acquired inside promote_to_primary)
-        let result = {
-            let mut set = replica_set.write();
-            set.promote_to_primary(&candidate_id)
+        let probe = match &self.probe {
+            Some(probe) => probe,
+            None => {
+                // No way to reach the replicas: only the local view changes
+                self.replica_set.write().promote_to_primary(&candidate.id)?;
+                self.primary.send_replace(Some(info.clone()));
+                return Ok(info);
+            }
         };
 
-        match &result {
-            Ok(()) => tracing::info!("Failover completed: promoted {} to primary", candidate_id),
-            Err(e) => tracing::error!("Failover failed: {}", e),
+        // A quorum must vote for the epoch before anyone hears of it. Each
+        // replica votes once per epoch, so a concurrent promotion can't
+        // win the same epoch too
+        let ballots = join_all(voters.iter().map(|replica| async {
+            let ballot = tokio::time::timeout(
+                policy.health_check_timeout,
+                probe.request_vote(replica, &info),
+            )
+            .await;
+            (replica.id.clone(), ballot)
+        }))
+        .await;
+        let mut granted = Vec::new();
+        for (id, ballot) in ballots {
+            let ballot = match ballot {
+                Ok(Ok(ballot)) => ballot,
+                Ok(Err(e)) => {
+                    tracing::debug!("No vote from {}: {}", id, e);
+                    continue;
+                }
+                Err(_) => continue,
+            };
+            {
+                let mut proposed = self.proposed.lock();
+                *proposed = (*proposed).max(ballot.epoch);
+            }
+            if let Some(primary) = ballot.report.primary {
+                self.adopt(primary);
+            }
+            if ballot.granted {
+                granted.push(id);
+            }
+        }
+        if !granted.contains(&candidate.id) {
+            return Err(ReplicationError::FailoverFailed(format!(
+                "{} did not vote for itself in epoch {}",
+                candidate.id, info.epoch
+            )));
+        }
+        if policy.prevent_split_brain && granted.len() < needed {
+            return Err(ReplicationError::QuorumNotMet {
+                needed,
+                available: granted.len(),
+            });
         }
 
-        // Clear failover flag
-        *failover_in_progress.write() = false;
+        // The candidate must take the new epoch before anyone else hears of it
+        let report = tokio::time::timeout(
+            policy.health_check_timeout,
+            probe.probe(&candidate, Some(&info)),
+        )
+        .await
+        .map_err(|_| {
+            ReplicationError::FailoverFailed(format!("{} did not answer", candidate.id))
+        })??;
+        if report.primary.as_ref() != Some(&info) {
+            if let Some(primary) = report.primary {
+                self.adopt(primary);
+            }
+            return Err(ReplicationError::FailoverFailed(format!(
+                "{} did not take epoch {}",
+                candidate.id, info.epoch
+            )));
+        }
 
-        result
+        self.replica_set.write().promote_to_primary(&candidate.id)?;
+        self.primary.send_replace(Some(info.clone()));
+        self.failure_counts.write().remove(&candidate.id);
+
+        // Point the other replicas at the new primary
+        join_all(
+            voters
+                .iter()
+                .filter(|replica| replica.id != candidate.id)
+                .map(|replica| {
+                    tokio::time::timeout(
+                        policy.health_check_timeout,
+                        probe.probe(replica, Some(&info)),
+                    )
+                }),
+        )
+        .await;
+
+        Ok(info)
+    }
+
+    /// `info` tagged with the manager's key, if it has one
+    fn sign(&self, info: PrimaryInfo) -> PrimaryInfo {
+        match &self.key {
+            Some(key) => key.sign(info),
+            None => info,
+        }
+    }
+
+    /// Epoch of the latest primary known, 0 before any
+    fn epoch(&self) -> u64 {
+        self.primary.borrow().as_ref().map_or(0, |info| info.epoch)
     }
 
     /// Select the best candidate for failover
+    ///
+    /// The healthy secondary furthest along the log wins, so the fewest
+    /// writes are lost; ties go to the highest priority, then the lowest lag.
     fn select_failover_candidate(replica_set: &ReplicaSet) -> Result<Replica> {
         let mut candidates: Vec<Replica> = replica_set
             .get_healthy_replicas()
@@ -321,36 +903,22 @@ impl FailoverManager {
             ));
         }
 
-        // Sort by priority (highest first), then by lowest lag
-        candidates.sort_by(|a, b| b.priority.cmp(&a.priority).then(a.lag_ms.cmp(&b.lag_ms)));
+        candidates.sort_by(|a, b| {
+            b.log_position
+                .cmp(&a.log_position)
+                .then(b.priority.cmp(&a.priority))
+                .then(a.lag_ms.cmp(&b.lag_ms))
+        });
 
         Ok(candidates[0].clone())
     }
 
     /// Manually trigger failover
     pub async fn manual_failover(&self, target_replica_id: Option<String>) -> Result<()> {
-        let mut set = self.replica_set.write();
-
-        // Check quorum
-        if !set.has_quorum() {
-            return Err(ReplicationError::QuorumNotMet {
-                needed: set.get_quorum_size(),
-                available: set.get_healthy_replicas().len(),
-            });
-        }
-
-        let target = if let Some(id) = target_replica_id {
-            set.get_replica(&id)
-                .ok_or_else(|| ReplicationError::ReplicaNotFound(id))?
-        } else {
-            Self::select_failover_candidate(&set)?
-        };
-
-        set.promote_to_primary(&target.id)?;
-
+        let info = self.trigger_failover(target_replica_id).await?;
         tracing::info!(
             "Manual failover completed: promoted {} to primary",
-            target.id
+            info.replica_id
         );
         Ok(())
     }
@@ -387,6 +955,12 @@ impl FailoverManager {
     }
 }
 
+impl Drop for FailoverManager {
+    fn drop(&mut self) {
+        self.stop_monitoring();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -4,7 +4,9 @@
 //! - Multi-node replica management
 //! - Synchronous, asynchronous, and semi-synchronous replication modes
 //! - Conflict resolution with vector clocks and CRDTs
+//! - Conflict-free vector payloads for multi-primary writes
 //! - Change data capture and streaming
+//! - Durable log streaming to secondaries over TCP
 //! - Automatic failover and split-brain prevention
 //!
 //! # Examples
@@ -29,17 +31,32 @@
 //! }
 //! ```
 
+pub mod apply;
 pub mod conflict;
 pub mod failover;
+pub mod payload;
 pub mod replica;
 pub mod stream;
 pub mod sync;
+pub mod transport;
+
+pub use apply::{ApplyHook, CrdtApplier, VectorDbApplier};
 
 pub use conflict::{ConflictResolver, LastWriteWins, MergeFunction, VectorClock};
-pub use failover::{FailoverManager, FailoverPolicy, HealthStatus};
+pub use failover::{
+    Ballot, ClusterKey, EpochFence, FailoverManager, FailoverPolicy, HealthProbe, HealthStatus,
+    PrimaryInfo, StatusReport, TcpHealthProbe,
+};
+pub use payload::{
+    Crdt, CrdtPayload, Dot, FieldKind, FieldState, LwwRegister, OrSet, PayloadSchema, PnCounter,
+    Stamp,
+};
 pub use replica::{Replica, ReplicaRole, ReplicaSet, ReplicaStatus};
-pub use stream::{ChangeEvent, ChangeOperation, ReplicationStream};
-pub use sync::{LogEntry, ReplicationLog, SyncManager, SyncMode};
+pub use stream::{ChangeEvent, ChangeOperation, Checkpoint, ReplicationStream};
+pub use sync::{AckTracker, LogEntry, ReplicationLog, SyncManager, SyncMode};
+pub use transport::{
+    request_status, request_vote, ReplicationClient, ReplicationMessage, ReplicationServer,
+};
 
 use thiserror::Error;
 
@@ -73,6 +90,12 @@ pub enum ReplicationError {
     #[error("Quorum not met: needed {needed}, got {available}")]
     QuorumNotMet { needed: usize, available: usize },
 
+    #[error("Not the primary in epoch {epoch}")]
+    NotPrimary { epoch: u64, primary: Option<String> },
+
+    #[error("Unauthenticated announcement: {0}")]
+    Unauthenticated(String),
+
     #[error("Split-brain detected")]
     SplitBrain,
 
@@ -85,6 +108,9 @@ pub enum ReplicationError {
     #[error("Serialization decode error: {0}")]
     SerializationDecode(#[from] bincode::error::DecodeError),
 
+    #[error("JSON serialization error: {0}")]
+    SerializationJson(#[from] serde_json::Error),
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 }
@@ -0,0 +1,653 @@
+//! Conflict-free vector payloads for multi-primary replication
+//!
+//! When several primaries take writes to the same documents, every write
+//! ships the document's whole [`CrdtPayload`] and replicas merge what they
+//! receive into what they hold. Merging is commutative, associative and
+//! idempotent, so replicas that have seen the same writes hold the same
+//! document whatever order the writes arrived in, and replaying a change
+//! after a reconnect is harmless.
+//!
+//! Each metadata field is one of three CRDTs, chosen by a [`PayloadSchema`]:
+//! a last-writer-wins [`LwwRegister`], an observed-remove [`OrSet`] for tag
+//! arrays, or a [`PnCounter`]. The vector itself is a register too.
+
+use crate::{ReplicationError, Result, VectorClock};
+use ruvector_core::types::VectorEntry;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// A state-based CRDT
+pub trait Crdt {
+    /// Merge `other` into this state
+    ///
+    /// Must be commutative, associative and idempotent.
+    fn merge(&mut self, other: &Self);
+}
+
+/// Orders writes to a document
+///
+/// `time` is a Lamport time: a write is stamped one past the latest time in
+/// the document as the writer saw it, so a write that has seen another
+/// always orders after it. Concurrent writes are ordered by time, then by
+/// replica ID, which is the deterministic rule every replica applies to
+/// concurrent vector updates. A replica never reuses a stamp, as each of
+/// its writes is past everything it has seen.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Stamp {
+    /// Lamport time of the write
+    pub time: u64,
+    /// Replica that made the write
+    pub replica_id: String,
+}
+
+/// A value whose latest write wins
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LwwRegister<T> {
+    value: T,
+    stamp: Stamp,
+}
+
+impl<T: Clone> LwwRegister<T> {
+    /// Create a register holding `value`, written at `stamp`
+    pub fn new(value: T, stamp: Stamp) -> Self {
+        Self { value, stamp }
+    }
+
+    /// The current value
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    /// Stamp of the write holding the current value
+    pub fn stamp(&self) -> &Stamp {
+        &self.stamp
+    }
+
+    /// Write `value` at `stamp`, unless a later write is already held
+    pub fn set(&mut self, value: T, stamp: Stamp) {
+        if stamp > self.stamp {
+            self.value = value;
+            self.stamp = stamp;
+        }
+    }
+}
+
+impl<T: Clone> Crdt for LwwRegister<T> {
+    fn merge(&mut self, other: &Self) {
+        self.set(other.value.clone(), other.stamp.clone());
+    }
+}
+
+/// Unique tag of one addition to an [`OrSet`]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Dot {
+    /// Replica that made the addition
+    pub replica_id: String,
+    /// The replica's addition counter
+    pub counter: u64,
+}
+
+/// An observed-remove set of strings
+///
+/// A removal only removes the additions the remover has seen, so an
+/// element added concurrently with its removal stays in the set. Removed
+/// additions are remembered in a vector clock rather than as tombstones.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OrSet {
+    /// Live additions of each element
+    entries: BTreeMap<String, BTreeSet<Dot>>,
+    /// Every addition seen, live or removed
+    context: VectorClock,
+}
+
+impl OrSet {
+    /// Create an empty set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `element` on behalf of `replica_id`
+    pub fn add(&mut self, replica_id: &str, element: impl Into<String>) {
+        self.context.increment(replica_id);
+        let dot = Dot {
+            replica_id: replica_id.to_string(),
+            counter: self.context.get(replica_id),
+        };
+        // The new addition supersedes the ones seen so far
+        self.entries.insert(element.into(), BTreeSet::from([dot]));
+    }
+
+    /// Remove `element` as far as this replica has seen it
+    pub fn remove(&mut self, element: &str) {
+        self.entries.remove(element);
+    }
+
+    /// Whether `element` is in the set
+    pub fn contains(&self, element: &str) -> bool {
+        self.entries.contains_key(element)
+    }
+
+    /// The elements, in order
+    pub fn elements(&self) -> Vec<String> {
+        self.entries.keys().cloned().collect()
+    }
+
+    /// Whether `context` has seen the addition `dot`
+    fn seen(context: &VectorClock, dot: &Dot) -> bool {
+        context.get(&dot.replica_id) >= dot.counter
+    }
+}
+
+impl Crdt for OrSet {
+    fn merge(&mut self, other: &Self) {
+        let elements: BTreeSet<String> = self
+            .entries
+            .keys()
+            .chain(other.entries.keys())
+            .cloned()
+            .collect();
+        let empty = BTreeSet::new();
+        for element in elements {
+            let ours = self.entries.get(&element).unwrap_or(&empty);
+            let theirs = other.entries.get(&element).unwrap_or(&empty);
+            // An addition survives if both sides have it, or if the side
+            // without it never saw it and so can't have removed it
+            let dots: BTreeSet<Dot> = ours
+                .iter()
+                .filter(|dot| theirs.contains(dot) || !Self::seen(&other.context, dot))
+                .chain(theirs.iter().filter(|dot| !Self::seen(&self.context, dot)))
+                .cloned()
+                .collect();
+            if dots.is_empty() {
+                self.entries.remove(&element);
+            } else {
+                self.entries.insert(element, dots);
+            }
+        }
+        self.context.merge(&other.context);
+    }
+}
+
+/// A counter that can go up and down
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PnCounter {
+    /// Total increments by replica
+    increments: BTreeMap<String, u64>,
+    /// Total decrements by replica
+    decrements: BTreeMap<String, u64>,
+}
+
+impl PnCounter {
+    /// Create a counter at zero
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `delta` on behalf of `replica_id`
+    pub fn add(&mut self, replica_id: &str, delta: i64) {
+        let totals = if delta >= 0 {
+            &mut self.increments
+        } else {
+            &mut self.decrements
+        };
+        *totals.entry(replica_id.to_string()).or_insert(0) += delta.unsigned_abs();
+    }
+
+    /// The current value
+    pub fn value(&self) -> i64 {
+        let increments: u64 = self.increments.values().sum();
+        let decrements: u64 = self.decrements.values().sum();
+        increments as i64 - decrements as i64
+    }
+}
+
+impl Crdt for PnCounter {
+    fn merge(&mut self, other: &Self) {
+        for (totals, other_totals) in [
+            (&mut self.increments, &other.increments),
+            (&mut self.decrements, &other.decrements),
+        ] {
+            for (replica_id, &total) in other_totals {
+                let current = totals.entry(replica_id.clone()).or_insert(0);
+                *current = (*current).max(total);
+            }
+        }
+    }
+}
+
+/// How a metadata field is merged
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum FieldKind {
+    /// Any JSON value; the latest write wins
+    #[default]
+    Register,
+    /// An array of strings; concurrent additions and removals both apply
+    TagSet,
+    /// An integer; concurrent changes add up
+    Counter,
+}
+
+/// The [`FieldKind`] of each metadata field
+///
+/// Fields not listed are registers. Every primary must use the same schema.
+#[derive(Debug, Clone, Default)]
+pub struct PayloadSchema {
+    fields: HashMap<String, FieldKind>,
+}
+
+impl PayloadSchema {
+    /// Create a schema where every field is a register
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Merge `field` as a set of tags
+    pub fn tag_set(mut self, field: impl Into<String>) -> Self {
+        self.fields.insert(field.into(), FieldKind::TagSet);
+        self
+    }
+
+    /// Merge `field` as a counter
+    pub fn counter(mut self, field: impl Into<String>) -> Self {
+        self.fields.insert(field.into(), FieldKind::Counter);
+        self
+    }
+
+    /// How `field` is merged
+    pub fn kind(&self, field: &str) -> FieldKind {
+        self.fields.get(field).copied().unwrap_or_default()
+    }
+}
+
+/// Merge state of one metadata field
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FieldState {
+    /// A last-writer-wins value; `Null` once the field is removed
+    Register(LwwRegister<serde_json::Value>),
+    /// A set of tags
+    TagSet(OrSet),
+    /// A counter
+    Counter(PnCounter),
+}
+
+impl FieldState {
+    fn kind(&self) -> FieldKind {
+        match self {
+            FieldState::Register(_) => FieldKind::Register,
+            FieldState::TagSet(_) => FieldKind::TagSet,
+            FieldState::Counter(_) => FieldKind::Counter,
+        }
+    }
+
+    /// The field's value, `None` if it is absent
+    fn value(&self) -> Option<serde_json::Value> {
+        match self {
+            FieldState::Register(register) if register.value().is_null() => None,
+            FieldState::Register(register) => Some(register.value().clone()),
+            FieldState::TagSet(set) if set.entries.is_empty() => None,
+            FieldState::TagSet(set) => Some(set.elements().into()),
+            FieldState::Counter(counter) => Some(counter.value().into()),
+        }
+    }
+}
+
+impl Crdt for FieldState {
+    fn merge(&mut self, other: &Self) {
+        match (&mut *self, other) {
+            (FieldState::Register(ours), FieldState::Register(theirs)) => ours.merge(theirs),
+            (FieldState::TagSet(ours), FieldState::TagSet(theirs)) => ours.merge(theirs),
+            (FieldState::Counter(ours), FieldState::Counter(theirs)) => ours.merge(theirs),
+            // Primaries disagree on the schema; keep the same kind everywhere
+            _ => {
+                if other.kind() > self.kind() {
+                    *self = other.clone();
+                }
+            }
+        }
+    }
+}
+
+/// Merge state of one document
+///
+/// Local writes go through [`write`](Self::write) and
+/// [`delete`](Self::delete), which stamp the change for this replica;
+/// states from other primaries are folded in with [`Crdt::merge`]. A
+/// deleted document keeps its state, so a write the deleting replica hadn't
+/// seen can't bring back the old payload, but a later write does recreate
+/// the document.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CrdtPayload {
+    vector: LwwRegister<Vec<f32>>,
+    fields: BTreeMap<String, FieldState>,
+    /// Whether the document exists
+    live: LwwRegister<bool>,
+    /// Latest Lamport time seen
+    time: u64,
+}
+
+impl CrdtPayload {
+    /// Create the state of a document never written
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the document exists
+    pub fn is_live(&self) -> bool {
+        *self.live.value()
+    }
+
+    /// Stamp of the write that set the vector
+    pub fn vector_stamp(&self) -> &Stamp {
+        self.vector.stamp()
+    }
+
+    /// Record `entry` as written on `replica_id`
+    ///
+    /// Only what differs from the current state is written, so fields this
+    /// write leaves alone don't override concurrent writes to them.
+    /// Metadata fields missing from `entry` are removed.
+    pub fn write(
+        &mut self,
+        replica_id: &str,
+        entry: &VectorEntry,
+        schema: &PayloadSchema,
+    ) -> Result<()> {
+        let stamp = self.next_stamp(replica_id);
+        if !self.is_live() || self.vector.value() != &entry.vector {
+            self.vector.set(entry.vector.clone(), stamp.clone());
+        }
+        if !self.is_live() {
+            self.live.set(true, stamp.clone());
+        }
+
+        let empty = HashMap::new();
+        let metadata = entry.metadata.as_ref().unwrap_or(&empty);
+        for (field, value) in metadata {
+            self.write_field(replica_id, &stamp, field, value, schema.kind(field))?;
+        }
+        let removed: Vec<String> = self
+            .fields
+            .keys()
+            .filter(|field| !metadata.contains_key(*field))
+            .cloned()
+            .collect();
+        for field in removed {
+            self.clear_field(replica_id, &stamp, &field);
+        }
+        Ok(())
+    }
+
+    /// Record the document as deleted on `replica_id`
+    ///
+    /// Tags and counters are cleared as far as this replica has seen them.
+    pub fn delete(&mut self, replica_id: &str) {
+        let stamp = self.next_stamp(replica_id);
+        self.live.set(false, stamp.clone());
+        let fields: Vec<String> = self.fields.keys().cloned().collect();
+        for field in fields {
+            self.clear_field(replica_id, &stamp, &field);
+        }
+    }
+
+    /// The document as an entry with ID `id`, `None` if it is deleted
+    pub fn to_entry(&self, id: impl Into<String>) -> Option<VectorEntry> {
+        if !self.is_live() {
+            return None;
+        }
+        let metadata: HashMap<String, serde_json::Value> = self
+            .fields
+            .iter()
+            .filter_map(|(field, state)| Some((field.clone(), state.value()?)))
+            .collect();
+        Some(VectorEntry {
+            id: Some(id.into()),
+            vector: self.vector.value().clone(),
+            metadata: (!metadata.is_empty()).then_some(metadata),
+        })
+    }
+
+    fn next_stamp(&mut self, replica_id: &str) -> Stamp {
+        self.time += 1;
+        Stamp {
+            time: self.time,
+            replica_id: replica_id.to_string(),
+        }
+    }
+
+    fn write_field(
+        &mut self,
+        replica_id: &str,
+        stamp: &Stamp,
+        field: &str,
+        value: &serde_json::Value,
+        kind: FieldKind,
+    ) -> Result<()> {
+        let state = self
+            .fields
+            .entry(field.to_string())
+            .or_insert_with(|| match kind {
+                FieldKind::Register => FieldState::Register(LwwRegister::default()),
+                FieldKind::TagSet => FieldState::TagSet(OrSet::new()),
+                FieldKind::Counter => FieldState::Counter(PnCounter::new()),
+            });
+        match state {
+            FieldState::Register(register) => {
+                if register.value() != value {
+                    register.set(value.clone(), stamp.clone());
+                }
+            }
+            FieldState::TagSet(set) => {
+                let tags: BTreeSet<&str> = value
+                    .as_array()
+                    .and_then(|tags| tags.iter().map(|tag| tag.as_str()).collect())
+                    .ok_or_else(|| invalid_field(field, "an array of strings"))?;
+                for tag in set.elements() {
+                    if !tags.contains(tag.as_str()) {
+                        set.remove(&tag);
+                    }
+                }
+                for tag in tags {
+                    if !set.contains(tag) {
+                        set.add(replica_id, tag);
+                    }
+                }
+            }
+            FieldState::Counter(counter) => {
+                let target = value
+                    .as_i64()
+                    .ok_or_else(|| invalid_field(field, "an integer"))?;
+                let delta = target - counter.value();
+                if delta != 0 {
+                    counter.add(replica_id, delta);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn clear_field(&mut self, replica_id: &str, stamp: &Stamp, field: &str) {
+        match self.fields.get_mut(field) {
+            Some(FieldState::Register(register)) if !register.value().is_null() => {
+                register.set(serde_json::Value::Null, stamp.clone());
+            }
+            Some(FieldState::TagSet(set)) => {
+                for tag in set.elements() {
+                    set.remove(&tag);
+                }
+            }
+            Some(FieldState::Counter(counter)) => {
+                let value = counter.value();
+                if value != 0 {
+                    counter.add(replica_id, -value);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Crdt for CrdtPayload {
+    fn merge(&mut self, other: &Self) {
+        self.vector.merge(&other.vector);
+        self.live.merge(&other.live);
+        for (field, state) in &other.fields {
+            match self.fields.get_mut(field) {
+                Some(ours) => ours.merge(state),
+                None => {
+                    self.fields.insert(field.clone(), state.clone());
+                }
+            }
+        }
+        self.time = self.time.max(other.time);
+    }
+}
+
+fn invalid_field(field: &str, expected: &str) -> ReplicationError {
+    ReplicationError::InvalidState(format!("Field {} must be {}", field, expected))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entry(vector: Vec<f32>, metadata: serde_json::Value) -> VectorEntry {
+        VectorEntry {
+            id: Some("doc".to_string()),
+            vector,
+            metadata: serde_json::from_value(metadata).unwrap(),
+        }
+    }
+
+    fn schema() -> PayloadSchema {
+        PayloadSchema::new().tag_set("tags").counter("views")
+    }
+
+    /// Two replicas that start from the same written document
+    fn forked(metadata: serde_json::Value) -> (CrdtPayload, CrdtPayload) {
+        let mut a = CrdtPayload::new();
+        a.write("a", &entry(vec![1.0], metadata), &schema())
+            .unwrap();
+        (a.clone(), a)
+    }
+
+    fn merged(a: &CrdtPayload, b: &CrdtPayload) -> VectorEntry {
+        let mut ab = a.clone();
+        ab.merge(b);
+        let mut ba = b.clone();
+        ba.merge(a);
+        assert_eq!(
+            serde_json::to_value(ab.to_entry("doc")).unwrap(),
+            serde_json::to_value(ba.to_entry("doc")).unwrap()
+        );
+        ab.to_entry("doc").unwrap()
+    }
+
+    #[test]
+    fn test_concurrent_vector_updates() {
+        let (mut a, mut b) = forked(json!({}));
+        a.write("a", &entry(vec![2.0], json!({})), &schema())
+            .unwrap();
+        b.write("b", &entry(vec![3.0], json!({})), &schema())
+            .unwrap();
+
+        // Same Lamport time, so the higher replica ID wins
+        assert_eq!(merged(&a, &b).vector, vec![3.0]);
+
+        // A write that has seen the other wins regardless of replica ID
+        a.merge(&b);
+        a.write("a", &entry(vec![4.0], json!({})), &schema())
+            .unwrap();
+        assert_eq!(merged(&a, &b).vector, vec![4.0]);
+    }
+
+    #[test]
+    fn test_registers_merge_per_field() {
+        let (mut a, mut b) = forked(json!({"title": "x", "color": "red"}));
+        a.write(
+            "a",
+            &entry(vec![1.0], json!({"title": "y", "color": "red"})),
+            &schema(),
+        )
+        .unwrap();
+        b.write(
+            "b",
+            &entry(vec![1.0], json!({"title": "x", "color": "blue"})),
+            &schema(),
+        )
+        .unwrap();
+
+        let entry = merged(&a, &b);
+        let metadata = entry.metadata.unwrap();
+        assert_eq!(metadata["title"], json!("y"));
+        assert_eq!(metadata["color"], json!("blue"));
+    }
+
+    #[test]
+    fn test_tag_set_add_wins() {
+        let (mut a, mut b) = forked(json!({"tags": ["x", "y"]}));
+        a.write("a", &entry(vec![1.0], json!({"tags": ["y"]})), &schema())
+            .unwrap();
+        b.write(
+            "b",
+            &entry(vec![1.0], json!({"tags": ["x", "y", "z"]})),
+            &schema(),
+        )
+        .unwrap();
+        // Removing a tag b never saw doesn't cancel b's addition
+        let mut c = b.clone();
+        c.write(
+            "b",
+            &entry(vec![1.0], json!({"tags": ["y", "z"]})),
+            &schema(),
+        )
+        .unwrap();
+        c.write(
+            "b",
+            &entry(vec![1.0], json!({"tags": ["x", "y", "z"]})),
+            &schema(),
+        )
+        .unwrap();
+
+        assert_eq!(merged(&a, &b).metadata.unwrap()["tags"], json!(["y", "z"]));
+        assert_eq!(
+            merged(&a, &c).metadata.unwrap()["tags"],
+            json!(["x", "y", "z"])
+        );
+    }
+
+    #[test]
+    fn test_counters_add_up() {
+        let (mut a, mut b) = forked(json!({"views": 10}));
+        a.write("a", &entry(vec![1.0], json!({"views": 13})), &schema())
+            .unwrap();
+        b.write("b", &entry(vec![1.0], json!({"views": 8})), &schema())
+            .unwrap();
+        assert_eq!(merged(&a, &b).metadata.unwrap()["views"], json!(11));
+
+        let error = a
+            .write("a", &entry(vec![1.0], json!({"views": "many"})), &schema())
+            .unwrap_err();
+        assert!(matches!(error, ReplicationError::InvalidState(_)));
+    }
+
+    #[test]
+    fn test_delete_and_recreate() {
+        let (mut a, mut b) = forked(json!({"title": "x"}));
+        a.delete("a");
+        assert!(a.to_entry("doc").is_none());
+
+        // An older write arriving later doesn't resurrect the document
+        let stale = b.clone();
+        a.merge(&stale);
+        assert!(a.to_entry("doc").is_none());
+
+        // A write made after seeing the delete brings it back
+        b.merge(&a);
+        b.write("b", &entry(vec![5.0], json!({})), &schema())
+            .unwrap();
+        a.merge(&b);
+        assert_eq!(a.to_entry("doc").unwrap().vector, vec![5.0]);
+        assert!(a.to_entry("doc").unwrap().metadata.is_none());
+    }
+}
@@ -189,6 +189,17 @@ impl ReplicaSet {
         self.replicas.get(id).map(|r| r.clone())
     }
 
+    /// Update a replica in place, returning whether it exists
+    pub fn update_replica(&self, id: &str, update: impl FnOnce(&mut Replica)) -> bool {
+        match self.replicas.get_mut(id) {
+            Some(mut replica) => {
+                update(&mut replica);
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Get the current primary replica
     pub fn get_primary(&self) -> Option<Replica> {
         let primary_id = self.primary_id.read();
@@ -217,17 +228,19 @@ impl ReplicaSet {
 
     /// Promote a secondary to primary
     pub fn promote_to_primary(&mut self, id: &str) -> Result<()> {
-        // Get the replica and verify it exists
-        let mut replica = self
+        // Get the replica and verify it exists. No entry guard is held while
+        // another entry is locked, as both may live in the same shard.
+        let role = self
             .replicas
-            .get_mut(id)
+            .get(id)
+            .map(|replica| replica.role)
             .ok_or_else(|| ReplicationError::ReplicaNotFound(id.to_string()))?;
 
-        if replica.role == ReplicaRole::Primary {
+        if role == ReplicaRole::Primary {
             return Ok(());
         }
 
-        if replica.role == ReplicaRole::Witness {
+        if role == ReplicaRole::Witness {
             return Err(ReplicationError::InvalidState(
                 "Cannot promote witness to primary".to_string(),
             ));
@@ -246,7 +259,9 @@ impl ReplicaSet {
         }
 
         // Promote new primary
-        replica.role = ReplicaRole::Primary;
+        if let Some(mut replica) = self.replicas.get_mut(id) {
+            replica.role = ReplicaRole::Primary;
+        }
         let mut primary = self.primary_id.write();
         *primary = Some(id.to_string());
 
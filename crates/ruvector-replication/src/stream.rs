@@ -3,9 +3,10 @@
 //! Provides mechanisms for streaming changes from the replication log
 //! with support for checkpointing, resumption, and backpressure handling.
 
-use crate::{LogEntry, ReplicationError, ReplicationLog, Result};
+use crate::{CrdtPayload, LogEntry, ReplicationError, ReplicationLog, Result};
 use chrono::{DateTime, Utc};
 use parking_lot::RwLock;
+use ruvector_core::types::VectorEntry;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::mpsc;
@@ -72,6 +73,73 @@ impl ChangeEvent {
         self
     }
 
+    /// Event writing `entry` to `collection`
+    ///
+    /// The entry is carried as JSON in `data`; entries without an id get a
+    /// generated one.
+    pub fn insert(collection: impl Into<String>, entry: &VectorEntry) -> Result<Self> {
+        let mut entry = entry.clone();
+        let document_id = entry
+            .id
+            .get_or_insert_with(|| Uuid::new_v4().to_string())
+            .clone();
+        Ok(Self::new(
+            0,
+            ChangeOperation::Insert,
+            collection.into(),
+            document_id,
+            serde_json::to_vec(&entry)?,
+        ))
+    }
+
+    /// Event deleting `document_id` from `collection`
+    pub fn delete(collection: impl Into<String>, document_id: impl Into<String>) -> Self {
+        Self::new(
+            0,
+            ChangeOperation::Delete,
+            collection.into(),
+            document_id.into(),
+            Vec::new(),
+        )
+    }
+
+    /// Event carrying the merge state of `document_id` after a write on
+    /// one of several primaries
+    ///
+    /// Applied with a [`CrdtApplier`](crate::CrdtApplier), which merges the
+    /// state into its own.
+    pub fn payload(
+        collection: impl Into<String>,
+        document_id: impl Into<String>,
+        payload: &CrdtPayload,
+    ) -> Result<Self> {
+        let operation = if payload.is_live() {
+            ChangeOperation::Update
+        } else {
+            ChangeOperation::Delete
+        };
+        Ok(Self::new(
+            0,
+            operation,
+            collection.into(),
+            document_id.into(),
+            serde_json::to_vec(payload)?,
+        ))
+    }
+
+    /// Encode the event as the data of a log entry
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    /// Decode the event carried by a log entry, numbered by the entry's
+    /// sequence
+    pub fn decode(entry: &LogEntry) -> Result<Self> {
+        let mut event: Self = serde_json::from_slice(&entry.data)?;
+        event.sequence = entry.sequence;
+        Ok(event)
+    }
+
     /// Convert from a log entry
     pub fn from_log_entry(
         entry: &LogEntry,
@@ -219,14 +287,16 @@ impl ReplicationStream {
                 // Convert to change events
                 let mut events = Vec::new();
                 for entry in &entries {
-                    // In a real implementation, we would decode the operation type
-                    // from the entry data. For now, we use a placeholder.
-                    let event = ChangeEvent::from_log_entry(
-                        entry,
-                        ChangeOperation::Update,
-                        "default".to_string(),
-                        Uuid::new_v4().to_string(),
-                    );
+                    // Entries that don't carry an encoded event are passed
+                    // on as opaque updates
+                    let event = ChangeEvent::decode(entry).unwrap_or_else(|_| {
+                        ChangeEvent::from_log_entry(
+                            entry,
+                            ChangeOperation::Update,
+                            "default".to_string(),
+                            Uuid::new_v4().to_string(),
+                        )
+                    });
                     events.push(event);
                 }
 
@@ -363,9 +433,9 @@ mod tests {
         let log = Arc::new(ReplicationLog::new("replica-1"));
 
         // Add some entries
-        log.append(b"data1".to_vec());
-        log.append(b"data2".to_vec());
-        log.append(b"data3".to_vec());
+        log.append(b"data1".to_vec()).unwrap();
+        log.append(b"data2".to_vec()).unwrap();
+        log.append(b"data3".to_vec()).unwrap();
 
         let stream = ReplicationStream::new(log.clone(), "consumer-1");
         let mut rx = stream.stream_from(0).await.unwrap();
@@ -3,14 +3,18 @@
 //! Provides different replication modes (sync, async, semi-sync)
 //! and manages the replication log for tracking changes.
 
-use crate::{ReplicaSet, ReplicationError, Result};
+use crate::{EpochFence, ReplicaRole, ReplicaSet, ReplicationError, Result};
 use chrono::{DateTime, Utc};
 use dashmap::DashMap;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::time::timeout;
+use tokio::sync::watch;
 use uuid::Uuid;
 
 /// Synchronization mode for replication
@@ -70,7 +74,27 @@ impl LogEntry {
     }
 }
 
+/// Name of the file holding a durable log's entries
+const LOG_FILE: &str = "replication.log";
+
+/// Bytes before each record's payload: length and CRC32
+const RECORD_HEADER_LEN: usize = 8;
+
+/// Backing file of a durable [`ReplicationLog`]
+struct LogFile {
+    dir: PathBuf,
+    file: File,
+    /// Length of the intact records in the file
+    len: u64,
+    /// Set when a failed append couldn't be cut off again
+    broken: bool,
+}
+
 /// Manages the replication log
+///
+/// Logs created with [`ReplicationLog::new`] live in memory. Logs opened
+/// with [`ReplicationLog::open`] also write every entry to disk, fsynced
+/// before `append` returns, and are read back when reopened.
 pub struct ReplicationLog {
     /// Log entries indexed by sequence number
     entries: Arc<DashMap<u64, LogEntry>>,
@@ -78,6 +102,10 @@ pub struct ReplicationLog {
     sequence: Arc<RwLock<u64>>,
     /// Replica ID
     replica_id: String,
+    /// Backing file, for durable logs
+    file: Option<Mutex<LogFile>>,
+    /// Latest sequence, for streams waiting on new entries
+    appended: watch::Sender<u64>,
 }
 
 impl ReplicationLog {
@@ -87,16 +115,117 @@ impl ReplicationLog {
             entries: Arc::new(DashMap::new()),
             sequence: Arc::new(RwLock::new(0)),
             replica_id: replica_id.into(),
+            file: None,
+            appended: watch::channel(0).0,
+        }
+    }
+
+    /// Open the durable log kept in `dir`, creating it if needed
+    ///
+    /// A record torn by a crash at the end of the file is cut off;
+    /// corruption anywhere else, including a damaged length that hides the
+    /// records after it, fails the open.
+    pub fn open(dir: impl AsRef<Path>, replica_id: impl Into<String>) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(LOG_FILE);
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let entries = DashMap::new();
+        let mut sequence = 0;
+        let mut offset = 0;
+        while offset < bytes.len() {
+            match read_record(&bytes[offset..]) {
+                Some((entry, len)) => {
+                    sequence = entry.sequence;
+                    entries.insert(entry.sequence, entry);
+                    offset += len;
+                }
+                None if is_torn_tail(&bytes[offset..]) => break,
+                None => {
+                    return Err(ReplicationError::InvalidState(format!(
+                        "Corrupt replication log record at offset {} of {}",
+                        offset,
+                        path.display()
+                    )))
+                }
+            }
         }
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        if offset < bytes.len() {
+            tracing::warn!(
+                "Cutting {} bytes of a torn record from {}",
+                bytes.len() - offset,
+                path.display()
+            );
+            file.set_len(offset as u64)?;
+            file.sync_all()?;
+        }
+        sync_dir(&dir)?;
+
+        Ok(Self {
+            entries: Arc::new(entries),
+            sequence: Arc::new(RwLock::new(sequence)),
+            replica_id: replica_id.into(),
+            file: Some(Mutex::new(LogFile {
+                dir,
+                file,
+                len: offset as u64,
+                broken: false,
+            })),
+            appended: watch::channel(sequence).0,
+        })
     }
 
     /// Append an entry to the log
-    pub fn append(&self, data: Vec<u8>) -> LogEntry {
+    pub fn append(&self, data: Vec<u8>) -> Result<LogEntry> {
         let mut seq = self.sequence.write();
+        let entry = LogEntry::new(*seq + 1, data, self.replica_id.clone());
+        self.persist(&entry)?;
         *seq += 1;
-        let entry = LogEntry::new(*seq, data, self.replica_id.clone());
         self.entries.insert(*seq, entry.clone());
-        entry
+        self.appended.send_replace(*seq);
+        Ok(entry)
+    }
+
+    /// Append an entry received from another replica, keeping its sequence
+    ///
+    /// Returns `false` for an entry the log already has. Entries must
+    /// follow the last one without gaps.
+    pub fn append_entry(&self, entry: LogEntry) -> Result<bool> {
+        let mut seq = self.sequence.write();
+        if entry.sequence <= *seq {
+            return match self.entries.get(&entry.sequence) {
+                Some(existing) if existing.id != entry.id => Err(ReplicationError::InvalidState(
+                    format!("Entry {} differs from the local log", entry.sequence),
+                )),
+                _ => Ok(false),
+            };
+        }
+        if entry.sequence != *seq + 1 {
+            return Err(ReplicationError::InvalidState(format!(
+                "Expected entry {}, got {}",
+                *seq + 1,
+                entry.sequence
+            )));
+        }
+        if !entry.verify() {
+            return Err(ReplicationError::SyncFailed(format!(
+                "Checksum mismatch in entry {}",
+                entry.sequence
+            )));
+        }
+
+        self.persist(&entry)?;
+        *seq = entry.sequence;
+        self.entries.insert(entry.sequence, entry);
+        self.appended.send_replace(*seq);
+        Ok(true)
     }
 
     /// Get an entry by sequence number
@@ -115,6 +244,11 @@ impl ReplicationLog {
         entries
     }
 
+    /// ID of the replica this log belongs to
+    pub fn replica_id(&self) -> &str {
+        &self.replica_id
+    }
+
     /// Get the current sequence number
     pub fn current_sequence(&self) -> u64 {
         *self.sequence.read()
@@ -126,27 +260,243 @@ impl ReplicationLog {
         self.get_range(since + 1, current)
     }
 
+    /// Watch the current sequence, which changes after every append
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.appended.subscribe()
+    }
+
     /// Truncate log before a given sequence
-    pub fn truncate_before(&self, before: u64) {
+    ///
+    /// The latest entry is always kept, so a reopened log carries on from
+    /// the same sequence.
+    pub fn truncate_before(&self, before: u64) -> Result<()> {
+        let seq = self.sequence.write();
+        let before = before.min(*seq);
+
+        if let Some(file) = &self.file {
+            let mut file = file.lock();
+            let mut data = Vec::new();
+            for entry in self.get_range(before, *seq) {
+                data.extend_from_slice(&encode_record(&entry)?);
+            }
+            write_atomic(&file.dir, LOG_FILE, &data)?;
+            file.file = OpenOptions::new()
+                .append(true)
+                .open(file.dir.join(LOG_FILE))?;
+            file.len = data.len() as u64;
+            file.broken = false;
+        }
+
         self.entries.retain(|seq, _| *seq >= before);
+        Ok(())
     }
 
     /// Get log size
     pub fn size(&self) -> usize {
         self.entries.len()
     }
+
+    /// Write `entry` to the backing file, if any
+    ///
+    /// A failed write is cut off again, so later records don't end up
+    /// behind a partial one.
+    fn persist(&self, entry: &LogEntry) -> Result<()> {
+        if let Some(file) = &self.file {
+            let mut file = file.lock();
+            if file.broken {
+                return Err(ReplicationError::InvalidState(format!(
+                    "Replication log in {} holds a partial record",
+                    file.dir.display()
+                )));
+            }
+            let record = encode_record(entry)?;
+            let written = file
+                .file
+                .write_all(&record)
+                .and_then(|()| file.file.sync_data());
+            if let Err(e) = written {
+                let good = file.len;
+                if let Err(cut) = file.file.set_len(good) {
+                    tracing::error!(
+                        "Failed to cut a partial record from the replication log: {}",
+                        cut
+                    );
+                    file.broken = true;
+                }
+                return Err(e.into());
+            }
+            file.len += record.len() as u64;
+        }
+        Ok(())
+    }
+}
+
+/// Encode `entry` as a `[len][crc32][entry]` record
+fn encode_record(entry: &LogEntry) -> Result<Vec<u8>> {
+    let payload = encode(entry)?;
+    let mut record = Vec::with_capacity(RECORD_HEADER_LEN + payload.len());
+    record.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    record.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+    record.extend_from_slice(&payload);
+    Ok(record)
+}
+
+/// Decode the record at the start of `bytes` and its length, if it is intact
+fn read_record(bytes: &[u8]) -> Option<(LogEntry, usize)> {
+    let len = record_len(bytes)?;
+    if bytes.len() < len {
+        return None;
+    }
+    let crc = u32::from_le_bytes(bytes[4..8].try_into().ok()?);
+    let payload = &bytes[RECORD_HEADER_LEN..len];
+    if crc32fast::hash(payload) != crc {
+        return None;
+    }
+    let entry = decode(payload).ok()?;
+    Some((entry, len))
+}
+
+/// Whether the unreadable record at the start of `bytes` is a prefix of
+/// an append torn by a crash
+///
+/// Only the final record can be torn: it must reach the end of the file,
+/// and no intact record may follow, as one would behind a damaged length.
+fn is_torn_tail(bytes: &[u8]) -> bool {
+    let reaches_end = record_len(bytes).map_or(true, |len| len >= bytes.len());
+    reaches_end && !(1..bytes.len()).any(|offset| read_record(&bytes[offset..]).is_some())
+}
+
+fn record_len(bytes: &[u8]) -> Option<usize> {
+    let header: [u8; 4] = bytes.get(..4)?.try_into().ok()?;
+    Some(RECORD_HEADER_LEN + u32::from_le_bytes(header) as usize)
+}
+
+pub(crate) fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    use bincode::config;
+    Ok(bincode::encode_to_vec(
+        bincode::serde::Compat(value),
+        config::standard(),
+    )?)
+}
+
+pub(crate) fn decode<T: serde::de::DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    use bincode::config;
+    let (compat, _): (bincode::serde::Compat<T>, _) =
+        bincode::decode_from_slice(bytes, config::standard())?;
+    Ok(compat.0)
+}
+
+/// Replace `dir/name` with `data` so that a crash leaves the old or the new contents
+pub(crate) fn write_atomic(dir: &Path, name: &str, data: &[u8]) -> Result<()> {
+    let tmp_path = dir.join(format!("{}.tmp", name));
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, dir.join(name))?;
+    sync_dir(dir)
+}
+
+/// Make file creations and renames in `dir` durable
+fn sync_dir(dir: &Path) -> Result<()> {
+    #[cfg(unix)]
+    File::open(dir)?.sync_all()?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+/// Highest log sequence each replica has acknowledged
+///
+/// Fed by the [`ReplicationServer`](crate::ReplicationServer) as replicas
+/// report what they have applied, and read by [`SyncManager`] to decide
+/// when a write is replicated enough.
+#[derive(Debug)]
+pub struct AckTracker {
+    acked: watch::Sender<HashMap<String, u64>>,
+}
+
+impl Default for AckTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AckTracker {
+    /// Create a tracker with no acknowledgements
+    pub fn new() -> Self {
+        Self {
+            acked: watch::channel(HashMap::new()).0,
+        }
+    }
+
+    /// Record that `replica_id` has every entry up to `sequence`
+    pub fn record(&self, replica_id: &str, sequence: u64) {
+        self.acked.send_if_modified(|acked| {
+            let position = acked.entry(replica_id.to_string()).or_insert(0);
+            if sequence > *position {
+                *position = sequence;
+                true
+            } else {
+                false
+            }
+        });
+    }
+
+    /// Highest sequence acknowledged by `replica_id`
+    pub fn acknowledged(&self, replica_id: &str) -> u64 {
+        self.acked.borrow().get(replica_id).copied().unwrap_or(0)
+    }
+
+    /// Number of `replicas` that have acknowledged `sequence`
+    pub fn count(&self, sequence: u64, replicas: &[String]) -> usize {
+        count_acked(&self.acked.borrow(), sequence, replicas)
+    }
+
+    /// Wait until `needed` of `replicas` have acknowledged `sequence`, or
+    /// `timeout` passes, and return how many have
+    pub async fn wait_for(
+        &self,
+        sequence: u64,
+        replicas: &[String],
+        needed: usize,
+        timeout: Duration,
+    ) -> usize {
+        let mut acked = self.acked.subscribe();
+        let _ = tokio::time::timeout(
+            timeout,
+            acked.wait_for(|acked| count_acked(acked, sequence, replicas) >= needed),
+        )
+        .await;
+        self.count(sequence, replicas)
+    }
+}
+
+fn count_acked(acked: &HashMap<String, u64>, sequence: u64, replicas: &[String]) -> usize {
+    replicas
+        .iter()
+        .filter(|id| acked.get(*id).is_some_and(|&position| position >= sequence))
+        .count()
 }
 
 /// Manages synchronization across replicas
+///
+/// Entries reach the replicas through a
+/// [`ReplicationServer`](crate::ReplicationServer) streaming the log; the
+/// sync mode decides how many of them must acknowledge an entry before
+/// `replicate` returns.
 pub struct SyncManager {
     /// The replica set
     replica_set: Arc<ReplicaSet>,
     /// Replication log
     log: Arc<ReplicationLog>,
+    /// Positions acknowledged by the replicas
+    acks: Arc<AckTracker>,
     /// Synchronization mode
     sync_mode: Arc<RwLock<SyncMode>>,
     /// Timeout for synchronous operations
     sync_timeout: Duration,
+    /// Epoch fence; writes are refused unless this node is primary
+    fence: Option<Arc<EpochFence>>,
 }
 
 impl SyncManager {
@@ -155,11 +505,28 @@ impl SyncManager {
         Self {
             replica_set,
             log,
+            acks: Arc::new(AckTracker::new()),
             sync_mode: Arc::new(RwLock::new(SyncMode::Async)),
             sync_timeout: Duration::from_secs(5),
+            fence: None,
         }
     }
 
+    /// Only take writes while `fence` says this node is primary
+    ///
+    /// Acknowledgements are then awaited from every other non-witness
+    /// replica, since the roles in the replica set go stale on failover.
+    pub fn with_fence(mut self, fence: Arc<EpochFence>) -> Self {
+        self.fence = Some(fence);
+        self
+    }
+
+    /// Acknowledgements counted by this manager, to be fed by the server
+    /// streaming the log to replicas
+    pub fn acks(&self) -> Arc<AckTracker> {
+        self.acks.clone()
+    }
+
     /// Set the synchronization mode
     pub fn set_sync_mode(&self, mode: SyncMode) {
         *self.sync_mode.write() = mode;
@@ -176,9 +543,18 @@ impl SyncManager {
     }
 
     /// Replicate data to all replicas according to sync mode
+    ///
+    /// The entry is in the local log once appended, so replicas receive it
+    /// even if this fails; an error only means too few of them acknowledged
+    /// it within the sync timeout. With a fence, writes on a node that isn't
+    /// the primary fail with `NotPrimary` before anything is appended.
     pub async fn replicate(&self, data: Vec<u8>) -> Result<LogEntry> {
-        // Append to local log
-        let entry = self.log.append(data);
+        if let Some(fence) = &self.fence {
+            fence.check_writable()?;
+        }
+
+        // Append to local log; replication streams pick it up from there
+        let entry = self.log.append(data)?;
 
         // Get sync mode
         let mode = self.sync_mode();
@@ -187,16 +563,7 @@ impl SyncManager {
             SyncMode::Sync => {
                 self.replicate_sync(&entry).await?;
             }
-            SyncMode::Async => {
-                // Fire and forget
-                let entry_clone = entry.clone();
-                let replica_set = self.replica_set.clone();
-                tokio::spawn(async move {
-                    if let Err(e) = Self::send_to_replicas(&replica_set, &entry_clone).await {
-                        tracing::error!("Async replication failed: {}", e);
-                    }
-                });
-            }
+            SyncMode::Async => {}
             SyncMode::SemiSync { min_replicas } => {
                 self.replicate_semi_sync(&entry, min_replicas).await?;
             }
@@ -207,17 +574,30 @@ impl SyncManager {
 
     /// Synchronous replication - wait for all replicas
     async fn replicate_sync(&self, entry: &LogEntry) -> Result<()> {
-        timeout(
-            self.sync_timeout,
-            Self::send_to_replicas(&self.replica_set, entry),
-        )
-        .await
-        .map_err(|_| ReplicationError::Timeout("Sync replication timed out".to_string()))?
+        let secondaries = self.secondary_ids();
+        let acked = self
+            .acks
+            .wait_for(
+                entry.sequence,
+                &secondaries,
+                secondaries.len(),
+                self.sync_timeout,
+            )
+            .await;
+        if acked < secondaries.len() {
+            return Err(ReplicationError::Timeout(format!(
+                "Entry {} acknowledged by {} of {} replicas",
+                entry.sequence,
+                acked,
+                secondaries.len()
+            )));
+        }
+        Ok(())
     }
 
     /// Semi-synchronous replication - wait for minimum replicas
     async fn replicate_semi_sync(&self, entry: &LogEntry, min_replicas: usize) -> Result<()> {
-        let secondaries = self.replica_set.get_secondaries();
+        let secondaries = self.secondary_ids();
         if secondaries.len() < min_replicas {
             return Err(ReplicationError::QuorumNotMet {
                 needed: min_replicas,
@@ -225,41 +605,45 @@ impl SyncManager {
             });
         }
 
-        // Send to all and wait for min_replicas to respond
-        let entry_clone = entry.clone();
-        let replica_set = self.replica_set.clone();
-        let min = min_replicas;
-
-        timeout(self.sync_timeout, async move {
-            // Simulate sending to replicas and waiting for acknowledgments
-            // In a real implementation, this would use network calls
-            let acks = secondaries.len().min(min);
-            if acks >= min {
-                Ok(())
-            } else {
-                Err(ReplicationError::QuorumNotMet {
-                    needed: min,
-                    available: acks,
-                })
-            }
-        })
-        .await
-        .map_err(|_| ReplicationError::Timeout("Semi-sync replication timed out".to_string()))?
+        let acked = self
+            .acks
+            .wait_for(
+                entry.sequence,
+                &secondaries,
+                min_replicas,
+                self.sync_timeout,
+            )
+            .await;
+        if acked < min_replicas {
+            return Err(ReplicationError::QuorumNotMet {
+                needed: min_replicas,
+                available: acked,
+            });
+        }
+        Ok(())
     }
 
-    /// Send log entry to all replicas
-    async fn send_to_replicas(replica_set: &ReplicaSet, entry: &LogEntry) -> Result<()> {
-        let secondaries = replica_set.get_secondaries();
-
-        // In a real implementation, this would send over the network
-        // For now, we simulate successful replication
-        for replica in secondaries {
-            if replica.is_healthy() {
-                tracing::debug!("Replicating entry {} to {}", entry.sequence, replica.id);
-            }
+    fn secondary_ids(&self) -> Vec<String> {
+        match &self.fence {
+            // Every other replica follows the fenced primary
+            Some(fence) => self
+                .replica_set
+                .replica_ids()
+                .into_iter()
+                .filter(|id| id != fence.replica_id())
+                .filter(|id| {
+                    self.replica_set
+                        .get_replica(id)
+                        .is_some_and(|replica| replica.role != ReplicaRole::Witness)
+                })
+                .collect(),
+            None => self
+                .replica_set
+                .get_secondaries()
+                .into_iter()
+                .map(|replica| replica.id)
+                .collect(),
         }
-
-        Ok(())
     }
 
     /// Catch up a lagging replica
@@ -320,8 +704,8 @@ mod tests {
     fn test_replication_log() {
         let log = ReplicationLog::new("replica-1");
 
-        let entry1 = log.append(b"data1".to_vec());
-        let entry2 = log.append(b"data2".to_vec());
+        let entry1 = log.append(b"data1".to_vec()).unwrap();
+        let entry2 = log.append(b"data2".to_vec()).unwrap();
 
         assert_eq!(entry1.sequence, 1);
         assert_eq!(entry2.sequence, 2);
@@ -363,12 +747,129 @@ mod tests {
         let manager = SyncManager::new(Arc::new(replica_set), log.clone());
 
         // Add some entries
-        log.append(b"data1".to_vec());
-        log.append(b"data2".to_vec());
-        log.append(b"data3".to_vec());
+        log.append(b"data1".to_vec()).unwrap();
+        log.append(b"data2".to_vec()).unwrap();
+        log.append(b"data3".to_vec()).unwrap();
 
         // Catchup from position 1
         let entries = manager.catchup("r2", 1).await.unwrap();
         assert_eq!(entries.len(), 2); // Entries 2 and 3
     }
+
+    #[test]
+    fn test_durable_log() {
+        let dir = std::env::temp_dir().join("ruvector-replication-durable-log");
+        let _ = fs::remove_dir_all(&dir);
+
+        let log = ReplicationLog::open(&dir, "r1").unwrap();
+        for i in 0..5u8 {
+            log.append(vec![i]).unwrap();
+        }
+        drop(log);
+
+        // Half a record, as left by a crash mid-append
+        let record = encode_record(&LogEntry::new(6, vec![6], "r1".to_string())).unwrap();
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(dir.join(LOG_FILE))
+            .unwrap();
+        file.write_all(&record[..record.len() / 2]).unwrap();
+        drop(file);
+
+        let log = ReplicationLog::open(&dir, "r1").unwrap();
+        assert_eq!(log.current_sequence(), 5);
+        assert_eq!(log.get(3).unwrap().data, vec![2]);
+        assert_eq!(log.append(vec![5]).unwrap().sequence, 6);
+
+        // Entries from elsewhere keep their sequence and must not leave gaps
+        let existing = log.get(6).unwrap();
+        assert!(!log.append_entry(existing).unwrap());
+        let conflicting = LogEntry::new(6, vec![9], "r2".to_string());
+        assert!(log.append_entry(conflicting).is_err());
+        let gap = LogEntry::new(8, vec![8], "r2".to_string());
+        assert!(log.append_entry(gap).is_err());
+        let next = LogEntry::new(7, vec![7], "r2".to_string());
+        assert!(log.append_entry(next.clone()).unwrap());
+
+        log.truncate_before(4).unwrap();
+        drop(log);
+        let log = ReplicationLog::open(&dir, "r1").unwrap();
+        assert_eq!(log.current_sequence(), 7);
+        assert_eq!(log.size(), 4);
+        assert!(log.get(3).is_none());
+        assert_eq!(log.get(7).unwrap().id, next.id);
+
+        // A damaged length in the middle doesn't pass for a torn tail
+        drop(log);
+        let mut bytes = fs::read(dir.join(LOG_FILE)).unwrap();
+        let intact = bytes.clone();
+        bytes[..4].copy_from_slice(&u32::MAX.to_le_bytes());
+        fs::write(dir.join(LOG_FILE), &bytes).unwrap();
+        assert!(ReplicationLog::open(&dir, "r1").is_err());
+        fs::write(dir.join(LOG_FILE), &intact).unwrap();
+        let log = ReplicationLog::open(&dir, "r1").unwrap();
+        assert_eq!(log.current_sequence(), 7);
+
+        // The latest entry survives truncating everything
+        log.truncate_before(100).unwrap();
+        drop(log);
+        let log = ReplicationLog::open(&dir, "r1").unwrap();
+        assert_eq!(log.current_sequence(), 7);
+        assert_eq!(log.append(vec![8]).unwrap().sequence, 8);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_acknowledgements() {
+        let mut replica_set = ReplicaSet::new("cluster-1");
+        replica_set
+            .add_replica("r1", "127.0.0.1:9001", ReplicaRole::Primary)
+            .unwrap();
+        replica_set
+            .add_replica("r2", "127.0.0.1:9002", ReplicaRole::Secondary)
+            .unwrap();
+        replica_set
+            .add_replica("r3", "127.0.0.1:9003", ReplicaRole::Secondary)
+            .unwrap();
+
+        let log = Arc::new(ReplicationLog::new("r1"));
+        let mut manager = SyncManager::new(Arc::new(replica_set), log);
+        manager.set_sync_timeout(Duration::from_millis(100));
+        let manager = Arc::new(manager);
+        let acks = manager.acks();
+
+        // Nobody acknowledges
+        manager.set_sync_mode(SyncMode::SemiSync { min_replicas: 1 });
+        match manager.replicate(b"a".to_vec()).await {
+            Err(ReplicationError::QuorumNotMet { needed, available }) => {
+                assert_eq!((needed, available), (1, 0));
+            }
+            other => panic!("unexpected result {:?}", other.map(|e| e.sequence)),
+        }
+
+        // One acknowledgement completes a semi-sync write
+        let writer = tokio::spawn({
+            let manager = manager.clone();
+            async move { manager.replicate(b"b".to_vec()).await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        acks.record("r2", 2);
+        assert_eq!(writer.await.unwrap().unwrap().sequence, 2);
+
+        // Acknowledgements from unknown replicas don't count, and sync
+        // writes need every secondary
+        manager.set_sync_mode(SyncMode::Sync);
+        acks.record("consumer", 3);
+        acks.record("r2", 3);
+        assert!(matches!(
+            manager.replicate(b"c".to_vec()).await,
+            Err(ReplicationError::Timeout(_))
+        ));
+        acks.record("r3", 4);
+        acks.record("r2", 4);
+        assert_eq!(manager.replicate(b"d".to_vec()).await.unwrap().sequence, 4);
+        assert_eq!(acks.acknowledged("r3"), 4);
+        assert_eq!(acks.count(3, &["r2".to_string(), "r3".to_string()]), 2);
+    }
 }
@@ -0,0 +1,740 @@
+//! Streaming the replication log over TCP
+//!
+//! A primary serves its log with a [`ReplicationServer`]. Each secondary
+//! runs a [`ReplicationClient`], which subscribes from its checkpoint,
+//! appends the entries it receives to its own durable log, applies them
+//! through an [`ApplyHook`] and acknowledges what it has applied. A client
+//! whose connection drops reconnects and resumes after its checkpoint.
+//!
+//! Messages are length-prefixed [`ReplicationMessage::to_bytes`] frames:
+//!
+//! ```text
+//! secondary                                       primary
+//!   Subscribe { replica_id, after, primary }  ──▶
+//!                                             ◀──  Entries [after + 1, ...]
+//!   Ack { sequence }                          ──▶
+//!                                             ◀──  Entries [...]
+//!
+//! prober                                          any node
+//!   Status { primary }                        ──▶
+//!                                             ◀──  StatusReport
+//!
+//! failover manager                                any node
+//!   RequestVote { proposal }                  ──▶
+//!                                             ◀──  Ballot
+//! ```
+//!
+//! Servers and clients given an [`EpochFence`] take part in failover: they
+//! adopt newer epochs announced by subscribers and probes, a server only
+//! streams while its node is primary, and a client follows the primary of
+//! the latest epoch.
+
+use crate::stream::Checkpoint;
+use crate::sync::{decode, encode, write_atomic};
+use crate::{
+    AckTracker, ApplyHook, Ballot, ChangeEvent, EpochFence, LogEntry, PrimaryInfo,
+    ReplicationError, ReplicationLog, Result, StatusReport,
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::watch;
+use tokio::task::{JoinHandle, JoinSet};
+use tracing::{debug, info, warn};
+
+/// Largest frame accepted from a peer
+const MAX_FRAME_LEN: usize = 64 * 1024 * 1024;
+
+/// Most entries shipped in one frame
+const MAX_BATCH_ENTRIES: u64 = 256;
+
+/// Time allowed for connecting to the primary
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Pause after a lost connection before connecting again
+const RECONNECT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// File holding a secondary's checkpoint
+const CHECKPOINT_FILE: &str = "checkpoint.json";
+
+/// Message exchanged between a primary and a secondary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ReplicationMessage {
+    /// Start streaming the entries after `after`, from the primary the
+    /// secondary knows
+    Subscribe {
+        replica_id: String,
+        after: u64,
+        primary: Option<PrimaryInfo>,
+    },
+    /// Consecutive log entries
+    Entries(Vec<LogEntry>),
+    /// The secondary has applied every entry up to `sequence`
+    Ack { sequence: u64 },
+    /// The primary can't serve the subscription
+    Error(String),
+    /// Ask for a node's status, announcing the primary the sender knows
+    Status { primary: Option<PrimaryInfo> },
+    /// Answer to `Status`
+    StatusReport(StatusReport),
+    /// Ask a node to vote for a promotion
+    RequestVote { proposal: PrimaryInfo },
+    /// Answer to `RequestVote`
+    Ballot(Ballot),
+}
+
+impl ReplicationMessage {
+    /// Encode the message for the wire
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        encode(self)
+    }
+
+    /// Decode a message produced by [`ReplicationMessage::to_bytes`]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        decode(bytes)
+    }
+}
+
+/// Serves a primary's replication log to secondaries
+///
+/// Acknowledgements from the secondaries are recorded in the
+/// [`AckTracker`] the server was bound with, usually
+/// [`SyncManager::acks`](crate::SyncManager::acks). The server also answers
+/// status requests from probes and clients. Dropping the server closes the
+/// listener and every stream.
+pub struct ReplicationServer {
+    local_addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl ReplicationServer {
+    /// Listen on `addr` and stream `log` to every secondary that connects
+    pub async fn bind(
+        addr: impl ToSocketAddrs,
+        log: Arc<ReplicationLog>,
+        acks: Arc<AckTracker>,
+    ) -> Result<Self> {
+        Self::listen(addr, log, acks, None).await
+    }
+
+    /// Like [`bind`](Self::bind), for a node that takes part in failover
+    ///
+    /// The server only streams while `fence` says its node is primary, and
+    /// ends its streams as soon as the node learns of a newer primary.
+    pub async fn bind_with_fence(
+        addr: impl ToSocketAddrs,
+        log: Arc<ReplicationLog>,
+        acks: Arc<AckTracker>,
+        fence: Arc<EpochFence>,
+    ) -> Result<Self> {
+        Self::listen(addr, log, acks, Some(fence)).await
+    }
+
+    async fn listen(
+        addr: impl ToSocketAddrs,
+        log: Arc<ReplicationLog>,
+        acks: Arc<AckTracker>,
+        fence: Option<Arc<EpochFence>>,
+    ) -> Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        let task = tokio::spawn(accept_connections(listener, log, acks, fence));
+        Ok(Self { local_addr, task })
+    }
+
+    /// Address the server listens on
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Stop listening and close every stream
+    pub fn shutdown(&self) {
+        self.task.abort();
+    }
+}
+
+impl Drop for ReplicationServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn accept_connections(
+    listener: TcpListener,
+    log: Arc<ReplicationLog>,
+    acks: Arc<AckTracker>,
+    fence: Option<Arc<EpochFence>>,
+) {
+    // Dropped with the task, which aborts every stream
+    let mut streams = JoinSet::new();
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, peer)) => {
+                    let log = log.clone();
+                    let acks = acks.clone();
+                    let fence = fence.clone();
+                    streams.spawn(async move {
+                        if let Err(e) = serve_connection(stream, &log, &acks, fence.as_deref()).await {
+                            debug!("Connection from {} closed: {}", peer, e);
+                        }
+                    });
+                }
+                Err(e) => warn!("Failed to accept a replica: {}", e),
+            },
+            Some(_) = streams.join_next() => {}
+        }
+    }
+}
+
+/// Answer a status request, or stream `log` to a secondary until the
+/// connection fails
+async fn serve_connection(
+    stream: TcpStream,
+    log: &ReplicationLog,
+    acks: &AckTracker,
+    fence: Option<&EpochFence>,
+) -> Result<()> {
+    stream.set_nodelay(true)?;
+    let (mut reader, mut writer) = stream.into_split();
+    let (replica_id, after, primary) = match read_message(&mut reader).await? {
+        ReplicationMessage::Subscribe {
+            replica_id,
+            after,
+            primary,
+        } => (replica_id, after, primary),
+        ReplicationMessage::Status { primary } => {
+            if let (Some(fence), Some(primary)) = (fence, &primary) {
+                observe(fence, primary);
+            }
+            let report = status_report(log, fence);
+            return write_message(&mut writer, &ReplicationMessage::StatusReport(report)).await;
+        }
+        ReplicationMessage::RequestVote { proposal } => {
+            // A node outside failover has no say in it
+            let granted = fence.is_some_and(|fence| {
+                fence.vote(&proposal).unwrap_or_else(|e| {
+                    warn!(
+                        "Refusing to vote for {} in epoch {}: {}",
+                        proposal.replica_id, proposal.epoch, e
+                    );
+                    false
+                })
+            });
+            let ballot = Ballot {
+                granted,
+                epoch: fence.map_or(0, |fence| fence.highest_epoch()),
+                report: status_report(log, fence),
+            };
+            return write_message(&mut writer, &ReplicationMessage::Ballot(ballot)).await;
+        }
+        _ => {
+            return Err(ReplicationError::Network(
+                "Expected a subscription or status request".to_string(),
+            ))
+        }
+    };
+
+    if let (Some(fence), Some(primary)) = (fence, &primary) {
+        observe(fence, primary);
+    }
+    let current = log.current_sequence();
+    let refusal = if fence.is_some_and(|fence| !fence.is_primary()) {
+        Some(format!(
+            "{} is not the primary in epoch {}",
+            log.replica_id(),
+            fence.map_or(0, |fence| fence.epoch())
+        ))
+    } else if after > current {
+        Some(format!(
+            "Replica {} is at entry {}, past the end of the log at {}",
+            replica_id, after, current
+        ))
+    } else if after < current && log.get(after + 1).is_none() {
+        Some(format!(
+            "Entry {} has been truncated from the log",
+            after + 1
+        ))
+    } else {
+        None
+    };
+    if let Some(message) = refusal {
+        warn!("Refusing replica {}: {}", replica_id, message);
+        write_message(&mut writer, &ReplicationMessage::Error(message.clone())).await?;
+        return Err(ReplicationError::SyncFailed(message));
+    }
+
+    info!(
+        "Streaming to replica {} from entry {}",
+        replica_id,
+        after + 1
+    );
+    acks.record(&replica_id, after);
+    tokio::select! {
+        result = receive_acks(&mut reader, &replica_id, acks) => result,
+        result = send_entries(&mut writer, log, after) => result,
+        _ = deposed(fence) => Err(ReplicationError::NotPrimary {
+            epoch: fence.map_or(0, |fence| fence.epoch()),
+            primary: fence.and_then(|fence| fence.current()).map(|info| info.replica_id),
+        }),
+    }
+}
+
+/// Move `fence` to the epoch of `primary` if it is newer
+fn observe(fence: &EpochFence, primary: &PrimaryInfo) {
+    if let Err(e) = fence.observe(primary) {
+        warn!("Failed to move to epoch {}: {}", primary.epoch, e);
+    }
+}
+
+/// The status of the node serving `log`
+fn status_report(log: &ReplicationLog, fence: Option<&EpochFence>) -> StatusReport {
+    StatusReport {
+        replica_id: log.replica_id().to_string(),
+        position: log.current_sequence(),
+        primary: fence.and_then(|fence| fence.current()),
+    }
+}
+
+/// Wait until `fence` no longer names this node primary
+async fn deposed(fence: Option<&EpochFence>) {
+    let fence = match fence {
+        Some(fence) => fence,
+        None => return std::future::pending().await,
+    };
+    let mut current = fence.subscribe();
+    let _ = current
+        .wait_for(|primary| {
+            primary
+                .as_ref()
+                .map_or(true, |info| info.replica_id != fence.replica_id())
+        })
+        .await;
+}
+
+async fn receive_acks(
+    reader: &mut OwnedReadHalf,
+    replica_id: &str,
+    acks: &AckTracker,
+) -> Result<()> {
+    loop {
+        match read_message(reader).await? {
+            ReplicationMessage::Ack { sequence } => acks.record(replica_id, sequence),
+            _ => {
+                return Err(ReplicationError::Network(format!(
+                    "Unexpected message from replica {}",
+                    replica_id
+                )))
+            }
+        }
+    }
+}
+
+async fn send_entries(writer: &mut OwnedWriteHalf, log: &ReplicationLog, after: u64) -> Result<()> {
+    let mut appended = log.subscribe();
+    let mut sent = after;
+    loop {
+        // Marked seen before reading, so an append racing the read still
+        // wakes the wait below
+        appended.borrow_and_update();
+        let batch = log.get_range(sent + 1, sent + MAX_BATCH_ENTRIES);
+        match batch.last() {
+            Some(last) => {
+                sent = last.sequence;
+                write_message(writer, &ReplicationMessage::Entries(batch)).await?;
+            }
+            None => {
+                if appended.changed().await.is_err() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// Replicates a primary's log into a secondary
+///
+/// Entries must carry a [`ChangeEvent`] encoded with
+/// [`ChangeEvent::to_bytes`]. Each batch of entries is appended to the
+/// secondary's log, applied, checkpointed and then acknowledged, so a
+/// client restarted on the same directory resumes after the checkpoint, and
+/// an entry is applied at most once more after a crash.
+///
+/// A deposed primary that wrote entries the new primary never received
+/// can't follow it: those entries conflict with the new primary's log and
+/// the stream stops with an error until the node is rebuilt.
+pub struct ReplicationClient {
+    follower: Arc<Follower>,
+    task: JoinHandle<()>,
+}
+
+/// Where a client finds its primary
+enum Target {
+    /// Always the same address
+    Fixed(String),
+    /// The primary of the fence's latest epoch
+    Fence(Arc<EpochFence>),
+}
+
+/// State shared between a client and its streaming task
+struct Follower {
+    replica_id: String,
+    target: Target,
+    dir: PathBuf,
+    log: Arc<ReplicationLog>,
+    hook: Arc<dyn ApplyHook>,
+    /// Sequence of the last checkpointed entry
+    applied: watch::Sender<u64>,
+    /// Held while a batch is applied; an abandoned stream's last batch may
+    /// still be running when the next stream starts
+    apply_lock: Mutex<()>,
+}
+
+impl ReplicationClient {
+    /// Start replicating from the primary at `primary` into `dir`
+    ///
+    /// `dir` holds the secondary's log and checkpoint. Must be called within
+    /// a Tokio runtime. The client keeps reconnecting until it is shut down
+    /// or dropped.
+    pub fn start(
+        replica_id: impl Into<String>,
+        primary: SocketAddr,
+        dir: impl AsRef<Path>,
+        hook: Arc<dyn ApplyHook>,
+    ) -> Result<Self> {
+        let replica_id = replica_id.into();
+        let log = Arc::new(ReplicationLog::open(&dir, replica_id.clone())?);
+        Self::spawn(
+            replica_id,
+            Target::Fixed(primary.to_string()),
+            dir.as_ref(),
+            log,
+            hook,
+        )
+    }
+
+    /// Start replicating into `log` from whichever replica `fence` says is
+    /// primary
+    ///
+    /// The client moves to each new primary as the fence learns of it, and
+    /// idles while its own node is primary. `dir` holds the checkpoint.
+    pub fn follow(
+        fence: Arc<EpochFence>,
+        log: Arc<ReplicationLog>,
+        dir: impl AsRef<Path>,
+        hook: Arc<dyn ApplyHook>,
+    ) -> Result<Self> {
+        let replica_id = fence.replica_id().to_string();
+        Self::spawn(replica_id, Target::Fence(fence), dir.as_ref(), log, hook)
+    }
+
+    fn spawn(
+        replica_id: String,
+        target: Target,
+        dir: &Path,
+        log: Arc<ReplicationLog>,
+        hook: Arc<dyn ApplyHook>,
+    ) -> Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let applied = match std::fs::read(dir.join(CHECKPOINT_FILE)) {
+            Ok(bytes) => serde_json::from_slice::<Checkpoint>(&bytes)?.sequence,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(e.into()),
+        };
+
+        let follower = Arc::new(Follower {
+            replica_id,
+            target,
+            dir: dir.to_path_buf(),
+            log,
+            hook,
+            applied: watch::channel(applied).0,
+            apply_lock: Mutex::new(()),
+        });
+        let task = tokio::spawn(follower.clone().run());
+        Ok(Self { follower, task })
+    }
+
+    /// ID this client subscribes with
+    pub fn replica_id(&self) -> &str {
+        &self.follower.replica_id
+    }
+
+    /// The secondary's copy of the log
+    pub fn log(&self) -> Arc<ReplicationLog> {
+        self.follower.log.clone()
+    }
+
+    /// Sequence of the last applied and checkpointed entry
+    pub fn applied(&self) -> u64 {
+        *self.follower.applied.borrow()
+    }
+
+    /// Wait until every entry up to `sequence` has been applied
+    pub async fn wait_for(&self, sequence: u64, timeout: Duration) -> Result<()> {
+        let mut applied = self.follower.applied.subscribe();
+        tokio::time::timeout(timeout, applied.wait_for(|&applied| applied >= sequence))
+            .await
+            .map_err(|_| {
+                ReplicationError::Timeout(format!(
+                    "Entry {} not applied, last applied is {}",
+                    sequence,
+                    self.applied()
+                ))
+            })?
+            .map_err(|_| ReplicationError::InvalidState("Client stopped".to_string()))?;
+        Ok(())
+    }
+
+    /// Stop replicating
+    pub fn shutdown(&self) {
+        self.task.abort();
+    }
+}
+
+impl Drop for ReplicationClient {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl Follower {
+    async fn run(self: Arc<Self>) {
+        loop {
+            // Subscribed before reading the primary, so no change is missed
+            let mut changes = match &self.target {
+                Target::Fixed(_) => None,
+                Target::Fence(fence) => Some(fence.subscribe()),
+            };
+            let primary = match (&self.target, changes.as_mut()) {
+                (Target::Fixed(address), _) => Some(address.clone()),
+                (_, Some(changes)) => changes
+                    .borrow_and_update()
+                    .as_ref()
+                    .filter(|info| info.replica_id != self.replica_id)
+                    .map(|info| info.address.clone()),
+                _ => None,
+            };
+            let primary_changed = async {
+                match changes.as_mut() {
+                    Some(changes) => {
+                        let _ = changes.changed().await;
+                    }
+                    None => std::future::pending().await,
+                }
+            };
+
+            let primary = match primary {
+                Some(primary) => primary,
+                None => {
+                    // Primary ourselves, or none known yet
+                    primary_changed.await;
+                    continue;
+                }
+            };
+            tokio::select! {
+                result = self.stream(&primary) => match result {
+                    Ok(()) => info!("Primary {} closed the replication stream", primary),
+                    Err(e) => warn!("Replication from {} interrupted: {}", primary, e),
+                },
+                _ = primary_changed => {
+                    debug!("Primary changed, leaving {}", primary);
+                    continue;
+                }
+            }
+            tokio::time::sleep(RECONNECT_BACKOFF).await;
+        }
+    }
+
+    /// Subscribe after the checkpoint and apply entries until the
+    /// connection fails
+    async fn stream(self: &Arc<Self>, primary: &str) -> Result<()> {
+        let mut stream = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(primary))
+            .await
+            .map_err(|_| {
+                ReplicationError::Network(format!("Timed out connecting to {}", primary))
+            })??;
+        stream.set_nodelay(true)?;
+
+        let after = *self.applied.borrow();
+        debug!("Subscribing to {} after entry {}", primary, after);
+        let known = match &self.target {
+            Target::Fixed(_) => None,
+            Target::Fence(fence) => fence.current(),
+        };
+        write_message(
+            &mut stream,
+            &ReplicationMessage::Subscribe {
+                replica_id: self.replica_id.clone(),
+                after,
+                primary: known,
+            },
+        )
+        .await?;
+
+        loop {
+            match read_message(&mut stream).await? {
+                ReplicationMessage::Entries(entries) => {
+                    let follower = self.clone();
+                    let sequence =
+                        tokio::task::spawn_blocking(move || follower.apply_batch(entries))
+                            .await
+                            .map_err(|e| ReplicationError::SyncFailed(e.to_string()))??;
+                    write_message(&mut stream, &ReplicationMessage::Ack { sequence }).await?;
+                }
+                ReplicationMessage::Error(message) => {
+                    return Err(ReplicationError::SyncFailed(format!(
+                        "Primary refused the subscription: {}",
+                        message
+                    )))
+                }
+                _ => {
+                    return Err(ReplicationError::Network(
+                        "Unexpected message from the primary".to_string(),
+                    ))
+                }
+            }
+        }
+    }
+
+    /// Append, apply and checkpoint a batch, returning the last applied
+    /// sequence
+    fn apply_batch(&self, entries: Vec<LogEntry>) -> Result<u64> {
+        let _guard = self.apply_lock.lock();
+        let mut applied = *self.applied.borrow();
+        for entry in entries {
+            if entry.sequence <= applied {
+                continue;
+            }
+            if entry.sequence != applied + 1 {
+                return Err(ReplicationError::InvalidState(format!(
+                    "Expected entry {}, got {}",
+                    applied + 1,
+                    entry.sequence
+                )));
+            }
+            if !entry.verify() {
+                return Err(ReplicationError::SyncFailed(format!(
+                    "Checksum mismatch in entry {}",
+                    entry.sequence
+                )));
+            }
+
+            let event = ChangeEvent::decode(&entry)?;
+            applied = entry.sequence;
+            self.log.append_entry(entry)?;
+            self.hook.apply(&event)?;
+        }
+
+        let checkpoint = Checkpoint::new(applied, self.replica_id.clone());
+        write_atomic(
+            &self.dir,
+            CHECKPOINT_FILE,
+            &serde_json::to_vec(&checkpoint)?,
+        )?;
+        self.applied.send_replace(applied);
+        Ok(applied)
+    }
+}
+
+/// Ask the server at `address` for its node's status, announcing `primary`
+///
+/// Every node answers with the primary it knows, so clients can ask any of
+/// them where to write.
+pub async fn request_status(address: &str, primary: Option<&PrimaryInfo>) -> Result<StatusReport> {
+    let mut stream = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(address))
+        .await
+        .map_err(|_| ReplicationError::Network(format!("Timed out connecting to {}", address)))??;
+    stream.set_nodelay(true)?;
+    write_message(
+        &mut stream,
+        &ReplicationMessage::Status {
+            primary: primary.cloned(),
+        },
+    )
+    .await?;
+    match read_message(&mut stream).await? {
+        ReplicationMessage::StatusReport(report) => Ok(report),
+        _ => Err(ReplicationError::Network(format!(
+            "Unexpected answer to a status request from {}",
+            address
+        ))),
+    }
+}
+
+/// Ask the server at `address` to vote for `proposal` becoming primary
+pub async fn request_vote(address: &str, proposal: &PrimaryInfo) -> Result<Ballot> {
+    let mut stream = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(address))
+        .await
+        .map_err(|_| ReplicationError::Network(format!("Timed out connecting to {}", address)))??;
+    stream.set_nodelay(true)?;
+    write_message(
+        &mut stream,
+        &ReplicationMessage::RequestVote {
+            proposal: proposal.clone(),
+        },
+    )
+    .await?;
+    match read_message(&mut stream).await? {
+        ReplicationMessage::Ballot(ballot) => Ok(ballot),
+        _ => Err(ReplicationError::Network(format!(
+            "Unexpected answer to a vote request from {}",
+            address
+        ))),
+    }
+}
+
+/// Read one length-prefixed message
+async fn read_message<R: AsyncRead + Unpin>(reader: &mut R) -> Result<ReplicationMessage> {
+    let len = reader.read_u32().await? as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(ReplicationError::Network(format!(
+            "Frame of {} bytes exceeds the limit of {}",
+            len, MAX_FRAME_LEN
+        )));
+    }
+    let mut frame = vec![0; len];
+    reader.read_exact(&mut frame).await?;
+    ReplicationMessage::from_bytes(&frame)
+}
+
+/// Write one length-prefixed message
+async fn write_message<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    message: &ReplicationMessage,
+) -> Result<()> {
+    let frame = message.to_bytes()?;
+    let mut buf = Vec::with_capacity(4 + frame.len());
+    buf.extend_from_slice(&(frame.len() as u32).to_be_bytes());
+    buf.extend_from_slice(&frame);
+    writer.write_all(&buf).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_roundtrip() {
+        let entry = LogEntry::new(7, b"payload".to_vec(), "r1".to_string());
+        let bytes = ReplicationMessage::Entries(vec![entry.clone()])
+            .to_bytes()
+            .unwrap();
+        match ReplicationMessage::from_bytes(&bytes).unwrap() {
+            ReplicationMessage::Entries(entries) => {
+                assert_eq!(entries.len(), 1);
+                assert_eq!(entries[0].id, entry.id);
+                assert_eq!(entries[0].sequence, 7);
+                assert!(entries[0].verify());
+            }
+            other => panic!("unexpected message {:?}", other),
+        }
+    }
+}
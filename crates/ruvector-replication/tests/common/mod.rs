@@ -0,0 +1,128 @@
+//! Helpers shared by the replication integration tests
+
+#![allow(dead_code)]
+
+use ruvector_core::types::{
+    DbOptions, DistanceMetric, HnswConfig, QuantizationConfig, VectorEntry,
+};
+use ruvector_core::VectorDB;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+pub const WAIT: Duration = Duration::from_secs(10);
+
+/// Forwards connections to a server; can cut them and refuse new ones
+pub struct Proxy {
+    pub addr: SocketAddr,
+    upstream: Arc<parking_lot::Mutex<SocketAddr>>,
+    blocked: Arc<AtomicBool>,
+    connections: Arc<parking_lot::Mutex<Vec<JoinHandle<()>>>>,
+    task: JoinHandle<()>,
+}
+
+impl Proxy {
+    pub async fn start(upstream: SocketAddr) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let upstream = Arc::new(parking_lot::Mutex::new(upstream));
+        let blocked = Arc::new(AtomicBool::new(false));
+        let connections = Arc::new(parking_lot::Mutex::new(Vec::new()));
+
+        let task = tokio::spawn({
+            let upstream = upstream.clone();
+            let blocked = blocked.clone();
+            let connections = connections.clone();
+            async move {
+                loop {
+                    let (mut inbound, _) = listener.accept().await.unwrap();
+                    if blocked.load(Ordering::SeqCst) {
+                        continue;
+                    }
+                    let target = *upstream.lock();
+                    connections.lock().push(tokio::spawn(async move {
+                        if let Ok(mut outbound) = TcpStream::connect(target).await {
+                            let _ =
+                                tokio::io::copy_bidirectional(&mut inbound, &mut outbound).await;
+                        }
+                    }));
+                }
+            }
+        });
+
+        Self {
+            addr,
+            upstream,
+            blocked,
+            connections,
+            task,
+        }
+    }
+
+    /// Drop every open connection
+    pub fn cut(&self) {
+        for connection in self.connections.lock().drain(..) {
+            connection.abort();
+        }
+    }
+
+    pub fn set_blocked(&self, blocked: bool) {
+        self.blocked.store(blocked, Ordering::SeqCst);
+    }
+
+    pub fn set_upstream(&self, upstream: SocketAddr) {
+        *self.upstream.lock() = upstream;
+    }
+}
+
+impl Drop for Proxy {
+    fn drop(&mut self) {
+        self.cut();
+        self.task.abort();
+    }
+}
+
+pub fn entry(i: usize) -> VectorEntry {
+    VectorEntry {
+        id: Some(format!("v{}", i)),
+        vector: vec![i as f32, 1.0, 2.0, 3.0],
+        metadata: None,
+    }
+}
+
+pub fn open_db(dir: &Path) -> Arc<VectorDB> {
+    std::fs::create_dir_all(dir).unwrap();
+    Arc::new(
+        VectorDB::new(DbOptions {
+            dimensions: 4,
+            distance_metric: DistanceMetric::Euclidean,
+            storage_path: dir.join("vectors.db").to_string_lossy().to_string(),
+            hnsw_config: Some(HnswConfig::default()),
+            quantization: Some(QuantizationConfig::None),
+        })
+        .unwrap(),
+    )
+}
+
+pub fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ruvector-replication-{}", name));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+/// Poll `condition` until it holds, panicking after [`WAIT`]
+pub async fn eventually(what: &str, condition: impl Fn() -> bool) {
+    let deadline = tokio::time::Instant::now() + WAIT;
+    while !condition() {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "timed out waiting until {}",
+            what
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
@@ -0,0 +1,313 @@
+//! Convergence of multi-primary payloads
+//!
+//! Replicas make random writes and deletes and exchange their states in
+//! random orders, with duplicates; once every replica has seen every state
+//! they must all hold the same documents.
+
+mod common;
+
+use common::{open_db, test_dir};
+use proptest::prelude::*;
+use ruvector_core::types::VectorEntry;
+use ruvector_replication::{ApplyHook, ChangeEvent, Crdt, CrdtApplier, CrdtPayload, PayloadSchema};
+use serde_json::json;
+use std::collections::HashMap;
+
+const REPLICAS: [&str; 3] = ["r1", "r2", "r3"];
+const TAGS: [&str; 4] = ["a", "b", "c", "d"];
+
+#[derive(Debug, Clone)]
+enum Op {
+    /// A replica writes the document
+    Write {
+        replica: usize,
+        vector: f32,
+        title: Option<u8>,
+        tags: Vec<bool>,
+        views: i64,
+    },
+    /// A replica deletes the document
+    Delete { replica: usize },
+    /// One replica merges another's state
+    Sync { from: usize, to: usize },
+}
+
+fn op_strategy() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        4 => (
+            0..REPLICAS.len(),
+            0u8..4,
+            prop::option::of(0u8..4),
+            prop::collection::vec(any::<bool>(), TAGS.len()),
+            0i64..20,
+        )
+            .prop_map(|(replica, vector, title, tags, views)| Op::Write {
+                replica,
+                vector: vector as f32,
+                title,
+                tags,
+                views,
+            }),
+        1 => (0..REPLICAS.len()).prop_map(|replica| Op::Delete { replica }),
+        3 => (0..REPLICAS.len(), 0..REPLICAS.len()).prop_map(|(from, to)| Op::Sync { from, to }),
+    ]
+}
+
+fn schema() -> PayloadSchema {
+    PayloadSchema::new().tag_set("tags").counter("views")
+}
+
+fn entry(vector: f32, title: Option<u8>, tags: &[bool], views: i64) -> VectorEntry {
+    let tags: Vec<&str> = TAGS
+        .iter()
+        .zip(tags)
+        .filter(|(_, &keep)| keep)
+        .map(|(tag, _)| *tag)
+        .collect();
+    let mut metadata = HashMap::from([
+        ("tags".to_string(), json!(tags)),
+        ("views".to_string(), json!(views)),
+    ]);
+    if let Some(title) = title {
+        metadata.insert("title".to_string(), json!(title));
+    }
+    VectorEntry {
+        id: Some("doc".to_string()),
+        vector: vec![vector, 1.0, 2.0, 3.0],
+        metadata: Some(metadata),
+    }
+}
+
+/// Run `ops` against one payload per replica, returning every state a
+/// local change produced, in order
+fn run(ops: &[Op], replicas: &mut [CrdtPayload]) -> Vec<CrdtPayload> {
+    let mut changes = Vec::new();
+    for op in ops {
+        match op {
+            Op::Write {
+                replica,
+                vector,
+                title,
+                tags,
+                views,
+            } => {
+                let written = entry(*vector, *title, tags, *views);
+                replicas[*replica]
+                    .write(REPLICAS[*replica], &written, &schema())
+                    .unwrap();
+                // A local write reads back as written
+                let read = replicas[*replica].to_entry("doc").unwrap();
+                assert_eq!(read.vector, written.vector);
+                let mut expected = written.metadata.unwrap();
+                if expected["tags"] == json!([]) {
+                    expected.remove("tags");
+                }
+                assert_eq!(read.metadata.unwrap(), expected);
+                changes.push(replicas[*replica].clone());
+            }
+            Op::Delete { replica } => {
+                replicas[*replica].delete(REPLICAS[*replica]);
+                changes.push(replicas[*replica].clone());
+            }
+            Op::Sync { from, to } => {
+                let state = replicas[*from].clone();
+                replicas[*to].merge(&state);
+            }
+        }
+    }
+    changes
+}
+
+fn history_strategy() -> impl Strategy<Value = Vec<Op>> {
+    prop::collection::vec(op_strategy(), 0..40)
+}
+
+/// Every state reached by one random history: each local change and
+/// the final state of each replica
+///
+/// States for the merge laws must come from one history, since replicas
+/// in different histories could make different writes with the same stamp.
+fn states_strategy() -> impl Strategy<Value = Vec<CrdtPayload>> {
+    history_strategy().prop_map(|ops| {
+        let mut replicas = vec![CrdtPayload::new(); REPLICAS.len()];
+        let mut states = run(&ops, &mut replicas);
+        states.extend(replicas);
+        states
+    })
+}
+
+/// Three states reached by one random history
+fn three_states_strategy() -> impl Strategy<Value = (CrdtPayload, CrdtPayload, CrdtPayload)> {
+    states_strategy().prop_flat_map(|states| {
+        let len = states.len();
+        (0..len, 0..len, 0..len)
+            .prop_map(move |(a, b, c)| (states[a].clone(), states[b].clone(), states[c].clone()))
+    })
+}
+
+fn merged(a: &CrdtPayload, b: &CrdtPayload) -> CrdtPayload {
+    let mut merged = a.clone();
+    merged.merge(b);
+    merged
+}
+
+proptest! {
+    // Property: replicas that have exchanged all states hold the same document
+    #[test]
+    fn test_replicas_converge(ops in history_strategy()) {
+        let mut replicas = vec![CrdtPayload::new(); REPLICAS.len()];
+        run(&ops, &mut replicas);
+
+        let mut everything = CrdtPayload::new();
+        for replica in &replicas {
+            everything.merge(replica);
+        }
+        for replica in &mut replicas {
+            replica.merge(&everything);
+            prop_assert_eq!(&*replica, &everything);
+            prop_assert_eq!(
+                serde_json::to_value(replica.to_entry("doc")).unwrap(),
+                serde_json::to_value(everything.to_entry("doc")).unwrap()
+            );
+        }
+    }
+
+    // Property: replaying the changes in any order, with duplicates, ends
+    // in the same state
+    #[test]
+    fn test_delivery_order_does_not_matter(
+        (changes, order) in history_strategy()
+            .prop_map(|ops| run(&ops, &mut vec![CrdtPayload::new(); REPLICAS.len()]))
+            .prop_flat_map(|changes| {
+                let len = changes.len();
+                (Just(changes), prop::collection::vec(0..len.max(1), len..len * 2 + 1))
+            })
+    ) {
+        let mut in_order = CrdtPayload::new();
+        for change in &changes {
+            in_order.merge(change);
+        }
+
+        // Every change at least once, then in the generated order
+        let mut shuffled = CrdtPayload::new();
+        for &i in order.iter().filter(|&&i| i < changes.len()) {
+            shuffled.merge(&changes[i]);
+        }
+        for change in changes.iter().rev() {
+            shuffled.merge(change);
+        }
+        prop_assert_eq!(shuffled, in_order);
+    }
+
+    // Property: merging is commutative
+    #[test]
+    fn test_merge_commutative((a, b, _) in three_states_strategy()) {
+        prop_assert_eq!(merged(&a, &b), merged(&b, &a));
+    }
+
+    // Property: merging is associative
+    #[test]
+    fn test_merge_associative((a, b, c) in three_states_strategy()) {
+        prop_assert_eq!(merged(&merged(&a, &b), &c), merged(&a, &merged(&b, &c)));
+    }
+
+    // Property: merging is idempotent
+    #[test]
+    fn test_merge_idempotent((a, b, _) in three_states_strategy()) {
+        let ab = merged(&a, &b);
+        prop_assert_eq!(merged(&ab, &b), ab.clone());
+        prop_assert_eq!(merged(&a, &a), a);
+    }
+}
+
+#[test]
+fn test_two_sites_converge() {
+    let dir = test_dir("crdt-sites");
+    let open = |site: &str| {
+        CrdtApplier::open(
+            open_db(&dir.join(site).join("db")),
+            dir.join(site).join("crdt"),
+            site,
+            schema(),
+        )
+        .unwrap()
+    };
+    let east = open("east");
+    let west = open("west");
+
+    let doc = |vector: f32, tags: &[bool], views: i64| VectorEntry {
+        id: Some("doc".to_string()),
+        ..entry(vector, None, tags, views)
+    };
+
+    // Both sites start from the same document
+    let created = east
+        .write("vectors", &doc(1.0, &[true, true, false, false], 10))
+        .unwrap();
+    west.apply(&created).unwrap();
+
+    // Concurrent edits on each side
+    let mut east_changes: Vec<ChangeEvent> = vec![east
+        .write("vectors", &doc(2.0, &[true, false, false, false], 12))
+        .unwrap()];
+    east_changes.push(east.delete("vectors", "other").unwrap());
+    let west_changes = vec![
+        west.write("vectors", &doc(3.0, &[true, true, true, false], 15))
+            .unwrap(),
+        west.write(
+            "vectors",
+            &VectorEntry {
+                id: Some("other".to_string()),
+                ..entry(7.0, Some(1), &[false; 4], 0)
+            },
+        )
+        .unwrap(),
+    ];
+
+    // Replayed on the other side, twice, as after a reconnect
+    for change in east_changes.iter().chain(&east_changes) {
+        west.apply(change).unwrap();
+    }
+    for change in west_changes.iter().rev().chain(&west_changes) {
+        east.apply(change).unwrap();
+    }
+
+    let east_doc = east.db().get("doc").unwrap().unwrap();
+    let west_doc = west.db().get("doc").unwrap().unwrap();
+    assert_eq!(east_doc.vector, west_doc.vector);
+    assert_eq!(east_doc.metadata, west_doc.metadata);
+    // Same Lamport time, so the higher replica ID's vector wins
+    assert_eq!(east_doc.vector[0], 3.0);
+    let metadata = east_doc.metadata.unwrap();
+    assert_eq!(metadata["tags"], json!(["a", "c"]));
+    assert_eq!(metadata["views"], json!(17));
+    // The merge state stays out of the user's metadata
+    assert!(!metadata.contains_key("_crdt"));
+
+    // The delete of a document east never saw doesn't remove west's write
+    assert!(east.db().get("other").unwrap().is_some());
+    assert!(west.db().get("other").unwrap().is_some());
+
+    // Tombstones survive a restart, so a stale write can't resurrect
+    let deleted = west.delete("vectors", "doc").unwrap();
+    east.apply(&deleted).unwrap();
+    assert!(east.db().get("doc").unwrap().is_none());
+    drop(east);
+    let east = open("east");
+    east.apply(&created).unwrap();
+    assert!(east.db().get("doc").unwrap().is_none());
+    assert!(!east.payload("doc").unwrap().unwrap().is_live());
+
+    // Old tombstones are dropped, recent ones kept
+    assert_eq!(
+        east.purge_tombstones(chrono::Duration::hours(1)).unwrap(),
+        0
+    );
+    assert_eq!(east.purge_tombstones(chrono::Duration::zero()).unwrap(), 1);
+    assert!(east.payload("doc").unwrap().is_none());
+    assert!(east.payload("other").unwrap().unwrap().is_live());
+
+    drop(east);
+    drop(west);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,339 @@
+//! Failover across three nodes in one process
+//!
+//! Every node is reached through a proxy, so a node can be cut off from
+//! the others and from the failover manager while it keeps running.
+
+mod common;
+
+use common::{entry, eventually, open_db, test_dir, Proxy, WAIT};
+use parking_lot::RwLock;
+use ruvector_core::VectorDB;
+use ruvector_replication::{
+    request_status, ChangeEvent, ClusterKey, EpochFence, FailoverManager, FailoverPolicy,
+    PrimaryInfo, ReplicaRole, ReplicaSet, ReplicationClient, ReplicationError, ReplicationLog,
+    ReplicationServer, SyncManager, SyncMode, TcpHealthProbe, VectorDbApplier,
+};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+const NODES: [&str; 3] = ["r1", "r2", "r3"];
+
+/// One node: a fenced log, its database, and the server and proxy it is
+/// reached through
+struct Node {
+    dir: PathBuf,
+    fence: Arc<EpochFence>,
+    log: Arc<ReplicationLog>,
+    db: Arc<VectorDB>,
+    manager: SyncManager,
+    client: Option<ReplicationClient>,
+    proxy: Proxy,
+    _server: ReplicationServer,
+}
+
+impl Node {
+    async fn open(dir: &Path, id: &str, key: Option<&ClusterKey>) -> Self {
+        let mut fence = EpochFence::open(dir.join("epoch"), id).unwrap();
+        if let Some(key) = key {
+            fence = fence.with_key(key.clone());
+        }
+        let fence = Arc::new(fence);
+        let log = Arc::new(ReplicationLog::open(dir.join("log"), id).unwrap());
+
+        let mut replica_set = ReplicaSet::new("cluster-1");
+        for other in NODES {
+            replica_set
+                .add_replica(other, other, ReplicaRole::Secondary)
+                .unwrap();
+        }
+        let mut manager =
+            SyncManager::new(Arc::new(replica_set), log.clone()).with_fence(fence.clone());
+        manager.set_sync_timeout(Duration::from_millis(300));
+        manager.set_sync_mode(SyncMode::SemiSync { min_replicas: 1 });
+
+        let server = ReplicationServer::bind_with_fence(
+            "127.0.0.1:0",
+            log.clone(),
+            manager.acks(),
+            fence.clone(),
+        )
+        .await
+        .unwrap();
+        let proxy = Proxy::start(server.local_addr()).await;
+        Self {
+            dir: dir.to_path_buf(),
+            fence,
+            log,
+            db: open_db(&dir.join("db")),
+            manager,
+            client: None,
+            proxy,
+            _server: server,
+        }
+    }
+
+    /// Follow whichever node the fence says is primary
+    fn follow(&mut self) {
+        self.client = Some(
+            ReplicationClient::follow(
+                self.fence.clone(),
+                self.log.clone(),
+                self.dir.join("checkpoint"),
+                Arc::new(VectorDbApplier::new(self.db.clone())),
+            )
+            .unwrap(),
+        );
+    }
+
+    fn applied(&self) -> u64 {
+        self.client.as_ref().map_or(0, |client| client.applied())
+    }
+
+    async fn insert(&self, i: usize) -> Result<u64, ReplicationError> {
+        let event = ChangeEvent::insert("vectors", &entry(i)).unwrap();
+        let entry = self.manager.replicate(event.to_bytes().unwrap()).await?;
+        Ok(entry.sequence)
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_failover_fences_isolated_primary() {
+    let dir = test_dir("failover");
+    let mut nodes = Vec::new();
+    for id in NODES {
+        nodes.push(Node::open(&dir.join(id), id, None).await);
+    }
+    let bootstrap = PrimaryInfo {
+        epoch: 1,
+        replica_id: "r1".to_string(),
+        address: nodes[0].proxy.addr.to_string(),
+        tag: None,
+    };
+    for node in &mut nodes {
+        node.fence.observe(&bootstrap).unwrap();
+        node.follow();
+    }
+
+    // The failover manager reaches every node through its proxy
+    let mut replica_set = ReplicaSet::new("cluster-1");
+    for (id, node) in NODES.iter().zip(&nodes) {
+        let role = if *id == "r1" {
+            ReplicaRole::Primary
+        } else {
+            ReplicaRole::Secondary
+        };
+        replica_set
+            .add_replica(*id, node.proxy.addr.to_string(), role)
+            .unwrap();
+    }
+    // Priority only breaks ties in log position
+    replica_set.update_replica("r3", |replica| replica.priority = 100);
+    let policy = FailoverPolicy {
+        health_check_interval: Duration::from_millis(50),
+        health_check_timeout: Duration::from_millis(200),
+        failure_threshold: 3,
+        ..FailoverPolicy::default()
+    };
+    let failover = Arc::new(
+        FailoverManager::with_policy(Arc::new(RwLock::new(replica_set)), policy)
+            .with_probe(Arc::new(TcpHealthProbe)),
+    );
+
+    for i in 0..10 {
+        assert_eq!(nodes[0].insert(i).await.unwrap(), i as u64 + 1);
+    }
+    eventually("both secondaries apply", || {
+        nodes[1].applied() == 10 && nodes[2].applied() == 10
+    })
+    .await;
+
+    // r3 stops replicating, so r2 ends up furthest along
+    nodes[2].client = None;
+    for i in 10..15 {
+        nodes[0].insert(i).await.unwrap();
+    }
+    eventually("r2 applies", || nodes[1].applied() == 15).await;
+
+    failover.start_monitoring().await;
+    eventually("the manager learns the primary", || {
+        failover.current_primary() == Some(bootstrap.clone())
+    })
+    .await;
+
+    // Cut r1 off from everyone; r2 is promoted in a new epoch
+    nodes[0].proxy.set_blocked(true);
+    nodes[0].proxy.cut();
+    let mut primary = failover.watch_primary();
+    let promoted = tokio::time::timeout(
+        WAIT,
+        primary.wait_for(|primary| primary.as_ref().map_or(0, |info| info.epoch) >= 2),
+    )
+    .await
+    .unwrap()
+    .unwrap()
+    .clone()
+    .unwrap();
+    assert_eq!(promoted.epoch, 2);
+    assert_eq!(promoted.replica_id, "r2");
+    assert_eq!(promoted.address, nodes[1].proxy.addr.to_string());
+    assert!(nodes[1].fence.is_primary());
+    eventually("r3 learns of the promotion", || nodes[2].fence.epoch() == 2).await;
+
+    // r3 catches up from the new primary, which takes writes
+    nodes[2].follow();
+    nodes[2]
+        .client
+        .as_ref()
+        .unwrap()
+        .wait_for(15, WAIT)
+        .await
+        .unwrap();
+    for i in 15..20 {
+        assert_eq!(nodes[1].insert(i).await.unwrap(), i as u64 + 1);
+    }
+    eventually("r3 applies", || nodes[2].applied() == 20).await;
+    assert_eq!(nodes[2].db.len().unwrap(), 20);
+
+    // Isolated, the old primary still thinks it leads but can't reach a quorum
+    assert!(nodes[0].fence.is_primary());
+    match nodes[0].insert(100).await {
+        Err(ReplicationError::QuorumNotMet { needed, available }) => {
+            assert_eq!((needed, available), (1, 0));
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    // Back in touch, it learns of the new epoch and refuses writes
+    nodes[0].proxy.set_blocked(false);
+    eventually("r1 is fenced", || nodes[0].fence.epoch() == 2).await;
+    match nodes[0].insert(101).await {
+        Err(ReplicationError::NotPrimary { epoch, primary }) => {
+            assert_eq!(epoch, 2);
+            assert_eq!(primary.as_deref(), Some("r2"));
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert_eq!(failover.current_primary(), Some(promoted.clone()));
+
+    // Any node tells clients where to write
+    let status = request_status(&nodes[0].proxy.addr.to_string(), None)
+        .await
+        .unwrap();
+    assert_eq!(status.replica_id, "r1");
+    assert_eq!(status.primary, Some(promoted.clone()));
+
+    // The epoch survives a restart
+    let reopened = EpochFence::open(dir.join("r1").join("epoch"), "r1").unwrap();
+    assert_eq!(reopened.current(), Some(promoted));
+    assert!(reopened.check_writable().is_err());
+
+    failover.stop_monitoring();
+    drop(nodes);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// A failover manager reaching every node through its proxy
+fn keyed_manager(nodes: &[Node], key: &ClusterKey) -> FailoverManager {
+    let mut replica_set = ReplicaSet::new("cluster-1");
+    for (id, node) in NODES.iter().zip(nodes) {
+        let role = if *id == "r1" {
+            ReplicaRole::Primary
+        } else {
+            ReplicaRole::Secondary
+        };
+        replica_set
+            .add_replica(*id, node.proxy.addr.to_string(), role)
+            .unwrap();
+    }
+    let policy = FailoverPolicy {
+        health_check_timeout: Duration::from_millis(200),
+        ..FailoverPolicy::default()
+    };
+    FailoverManager::with_policy(Arc::new(RwLock::new(replica_set)), policy)
+        .with_probe(Arc::new(TcpHealthProbe))
+        .with_key(key.clone())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_promotions_elect_one_primary() {
+    let dir = test_dir("failover-race");
+    let key = ClusterKey::from_passphrase("cluster-1 secret");
+    let mut nodes = Vec::new();
+    for id in NODES {
+        nodes.push(Node::open(&dir.join(id), id, Some(&key)).await);
+    }
+    let bootstrap = key.sign(PrimaryInfo {
+        epoch: 1,
+        replica_id: "r1".to_string(),
+        address: nodes[0].proxy.addr.to_string(),
+        tag: None,
+    });
+    for node in &nodes {
+        node.fence.observe(&bootstrap).unwrap();
+    }
+    nodes[0].proxy.set_blocked(true);
+
+    // Two managers race to promote different replicas, round after round
+    let first = keyed_manager(&nodes, &key);
+    let second = keyed_manager(&nodes, &key);
+    let mut elected = std::collections::HashMap::new();
+    for _ in 0..10 {
+        let (a, b) = tokio::join!(
+            first.manual_failover(Some("r2".to_string())),
+            second.manual_failover(Some("r3".to_string())),
+        );
+        let mut winners = Vec::new();
+        if a.is_ok() {
+            winners.push(first.current_primary().unwrap());
+        }
+        if b.is_ok() {
+            winners.push(second.current_primary().unwrap());
+        }
+        let seen = nodes[1..].iter().filter_map(|node| node.fence.current());
+        for info in winners.into_iter().chain(seen) {
+            let primary = elected
+                .entry(info.epoch)
+                .or_insert_with(|| info.replica_id.clone());
+            assert_eq!(
+                *primary, info.replica_id,
+                "two primaries in epoch {}",
+                info.epoch
+            );
+        }
+    }
+
+    // Alone, a manager promotes once it has learned the latest epoch
+    let latest = nodes[1]
+        .fence
+        .highest_epoch()
+        .max(nodes[2].fence.highest_epoch());
+    first.manual_failover(Some("r2".to_string())).await.unwrap();
+    let promoted = first.current_primary().unwrap();
+    assert!(promoted.epoch > latest);
+    assert_eq!(promoted.replica_id, "r2");
+    assert!(nodes[1].fence.is_primary());
+    assert_eq!(nodes[2].fence.current(), Some(promoted.clone()));
+
+    // Announcements without the cluster key are ignored
+    let mut forged = PrimaryInfo {
+        epoch: promoted.epoch + 100,
+        replica_id: "r3".to_string(),
+        address: nodes[2].proxy.addr.to_string(),
+        tag: None,
+    };
+    let address = nodes[1].proxy.addr.to_string();
+    let status = request_status(&address, Some(&forged)).await.unwrap();
+    assert_eq!(status.primary, Some(promoted.clone()));
+    forged = ClusterKey::from_passphrase("wrong secret").sign(forged);
+    let status = request_status(&address, Some(&forged)).await.unwrap();
+    assert_eq!(status.primary, Some(promoted.clone()));
+    let ballot = ruvector_replication::request_vote(&address, &forged)
+        .await
+        .unwrap();
+    assert!(!ballot.granted);
+    assert!(nodes[1].fence.is_primary());
+
+    drop(nodes);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
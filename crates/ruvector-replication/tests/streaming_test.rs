@@ -0,0 +1,179 @@
+//! Streaming replication between a primary and a secondary in one process
+//!
+//! The secondary reaches the primary through a proxy that can drop and
+//! refuse connections, to exercise resuming from the checkpoint.
+
+mod common;
+
+use common::{entry, open_db, test_dir, Proxy, WAIT};
+use ruvector_replication::{
+    ChangeEvent, ReplicaRole, ReplicaSet, ReplicationClient, ReplicationError, ReplicationLog,
+    ReplicationServer, SyncManager, SyncMode, VectorDbApplier,
+};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A primary with a durable log, serving it on an ephemeral port
+struct Primary {
+    log: Arc<ReplicationLog>,
+    manager: SyncManager,
+    server: ReplicationServer,
+}
+
+impl Primary {
+    async fn open(dir: &Path) -> Self {
+        let mut replica_set = ReplicaSet::new("cluster-1");
+        replica_set
+            .add_replica("r1", "127.0.0.1:9001", ReplicaRole::Primary)
+            .unwrap();
+        replica_set
+            .add_replica("r2", "127.0.0.1:9002", ReplicaRole::Secondary)
+            .unwrap();
+
+        let log = Arc::new(ReplicationLog::open(dir, "r1").unwrap());
+        let mut manager = SyncManager::new(Arc::new(replica_set), log.clone());
+        manager.set_sync_timeout(Duration::from_millis(300));
+        manager.set_sync_mode(SyncMode::SemiSync { min_replicas: 1 });
+        let server = ReplicationServer::bind("127.0.0.1:0", log.clone(), manager.acks())
+            .await
+            .unwrap();
+        Self {
+            log,
+            manager,
+            server,
+        }
+    }
+
+    async fn insert(&self, i: usize) -> Result<u64, ReplicationError> {
+        let event = ChangeEvent::insert("vectors", &entry(i)).unwrap();
+        let entry = self.manager.replicate(event.to_bytes().unwrap()).await?;
+        Ok(entry.sequence)
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_stream_and_resume_after_dropped_connection() {
+    let dir = test_dir("stream-resume");
+    let primary = Primary::open(&dir.join("primary")).await;
+    let proxy = Proxy::start(primary.server.local_addr()).await;
+
+    let db = open_db(&dir.join("secondary"));
+    let client = ReplicationClient::start(
+        "r2",
+        proxy.addr,
+        dir.join("secondary").join("replication"),
+        Arc::new(VectorDbApplier::new(db.clone())),
+    )
+    .unwrap();
+
+    // A semi-sync write returns once the secondary has applied it
+    for i in 0..20 {
+        let sequence = primary.insert(i).await.unwrap();
+        assert_eq!(sequence, i as u64 + 1);
+        assert!(db.get(&format!("v{}", i)).unwrap().is_some());
+    }
+    let delete = ChangeEvent::delete("vectors", "v3").to_bytes().unwrap();
+    primary.manager.replicate(delete).await.unwrap();
+    assert!(db.get("v3").unwrap().is_none());
+    assert_eq!(client.applied(), 21);
+
+    // With the secondary cut off, semi-sync writes can't be acknowledged
+    // but still reach the log
+    proxy.set_blocked(true);
+    proxy.cut();
+    match primary.insert(20).await {
+        Err(ReplicationError::QuorumNotMet { needed, available }) => {
+            assert_eq!((needed, available), (1, 0));
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+    primary.manager.set_sync_mode(SyncMode::Async);
+    for i in 21..30 {
+        primary.insert(i).await.unwrap();
+    }
+    assert_eq!(client.applied(), 21);
+
+    // Reconnected, the secondary picks up after its checkpoint
+    proxy.set_blocked(false);
+    client.wait_for(31, WAIT).await.unwrap();
+    for i in (0..30).filter(|&i| i != 3) {
+        let stored = db.get(&format!("v{}", i)).unwrap().unwrap();
+        assert_eq!(stored.vector, entry(i).vector);
+    }
+    assert_eq!(db.len().unwrap(), 29);
+
+    // The secondary's log is a copy of the primary's
+    let copy = client.log();
+    assert_eq!(copy.current_sequence(), 31);
+    for sequence in 1..=31 {
+        assert_eq!(
+            copy.get(sequence).unwrap().id,
+            primary.log.get(sequence).unwrap().id
+        );
+    }
+
+    // A restarted secondary resumes from its persisted checkpoint
+    drop(client);
+    for i in 30..35 {
+        primary.insert(i).await.unwrap();
+    }
+    let client = ReplicationClient::start(
+        "r2",
+        proxy.addr,
+        dir.join("secondary").join("replication"),
+        Arc::new(VectorDbApplier::new(db.clone())),
+    )
+    .unwrap();
+    assert_eq!(client.applied(), 31);
+    client.wait_for(36, WAIT).await.unwrap();
+    assert_eq!(db.len().unwrap(), 34);
+    assert_eq!(client.log().current_sequence(), 36);
+
+    drop(client);
+    drop(proxy);
+    drop(primary);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_primary_restart_keeps_log() {
+    let dir = test_dir("primary-restart");
+    let primary = Primary::open(&dir.join("primary")).await;
+    for i in 0..10 {
+        // Nobody is subscribed yet
+        primary.manager.set_sync_mode(SyncMode::Async);
+        primary.insert(i).await.unwrap();
+    }
+    drop(primary);
+
+    // Reopened from disk, the log is served to a new secondary from the start
+    let primary = Primary::open(&dir.join("primary")).await;
+    assert_eq!(primary.log.current_sequence(), 10);
+    let proxy = Proxy::start(primary.server.local_addr()).await;
+    let db = open_db(&dir.join("secondary"));
+    let client = ReplicationClient::start(
+        "r2",
+        proxy.addr,
+        dir.join("secondary").join("replication"),
+        Arc::new(VectorDbApplier::new(db.clone())),
+    )
+    .unwrap();
+    client.wait_for(10, WAIT).await.unwrap();
+    assert_eq!(primary.insert(10).await.unwrap(), 11);
+    assert_eq!(db.len().unwrap(), 11);
+
+    // Restart again, this time while the secondary is connected
+    drop(primary);
+    let primary = Primary::open(&dir.join("primary")).await;
+    proxy.set_upstream(primary.server.local_addr());
+    proxy.cut();
+    assert_eq!(primary.insert(11).await.unwrap(), 12);
+    client.wait_for(12, WAIT).await.unwrap();
+    assert_eq!(db.len().unwrap(), 12);
+
+    drop(client);
+    drop(proxy);
+    drop(primary);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
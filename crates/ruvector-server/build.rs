@@ -0,0 +1,18 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Use the bundled protoc so builds don't depend on a system install
+    if std::env::var_os("PROTOC").is_none() {
+        std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    }
+    tonic_build::configure()
+        .build_client(true)
+        .build_server(true)
+        .compile_protos(
+            &["proto/ruvector.proto"],
+            &[
+                std::path::PathBuf::from("proto"),
+                protoc_bin_vendored::include_path()?,
+            ],
+        )?;
+    println!("cargo:rerun-if-changed=proto/ruvector.proto");
+    Ok(())
+}
@@ -0,0 +1,320 @@
+//! API key authentication and authorization
+//!
+//! Keys are loaded from [`Config::api_keys`](crate::Config::api_keys). Each
+//! key grants a [`Scope`] and may be restricted to named collections. The
+//! [`require_api_key`] middleware authenticates every REST and gRPC request
+//! and checks the scope the route needs; gRPC handlers, whose collection name
+//! is only known from the message body, check the collection themselves with
+//! [`authorize_collection`]. Listings only show what the key may access.
+
+use crate::{error::Error, state::AppState, Result};
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, HeaderMap, Method},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use percent_encoding::percent_decode_str;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Header carrying an API key, as an alternative to `Authorization: Bearer`
+pub const API_KEY_HEADER: &str = "api-key";
+
+/// Access level granted by an API key
+///
+/// Scopes are ordered: each one includes everything below it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    /// Read collections and points
+    Read,
+    /// Read, and write or delete points
+    ReadWrite,
+    /// Everything, including creating and deleting collections and aliases
+    Admin,
+}
+
+/// An API key and what it may access
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    /// Secret presented by clients
+    pub key: String,
+    /// Access level
+    pub scope: Scope,
+    /// Collections (or aliases) the key is restricted to; `None` allows all
+    #[serde(default)]
+    pub collections: Option<Vec<String>>,
+}
+
+impl ApiKey {
+    /// Whether the key may access `collection`, directly or through `alias_of`
+    fn allows_collection(&self, collection: &str, alias_of: Option<&str>) -> bool {
+        match &self.collections {
+            None => true,
+            Some(allowed) => allowed
+                .iter()
+                .any(|name| name == collection || Some(name.as_str()) == alias_of),
+        }
+    }
+}
+
+/// What a request touches
+#[derive(Debug, PartialEq, Eq)]
+enum Target {
+    /// No authentication required
+    Public,
+    /// Nothing collection-specific; any key with the scope may call it
+    Any,
+    /// A single named collection or alias
+    Collection(String),
+    /// Server-wide state; only keys without a collection restriction
+    Server,
+}
+
+/// Access a request needs
+#[derive(Debug, PartialEq, Eq)]
+struct Access {
+    scope: Scope,
+    target: Target,
+}
+
+impl Access {
+    fn new(scope: Scope, target: Target) -> Self {
+        Self { scope, target }
+    }
+
+    fn public() -> Self {
+        Self::new(Scope::Read, Target::Public)
+    }
+}
+
+/// Validates API keys against the configured set
+#[derive(Clone)]
+pub struct Authenticator {
+    /// Keys with the BLAKE3 digest of their secret
+    keys: Arc<Vec<(blake3::Hash, ApiKey)>>,
+    state: AppState,
+}
+
+impl Authenticator {
+    /// Create an authenticator; an empty key list disables authentication
+    pub fn new(keys: Vec<ApiKey>, state: AppState) -> Self {
+        let keys = keys
+            .into_iter()
+            .map(|k| (blake3::hash(k.key.as_bytes()), k))
+            .collect();
+        Self {
+            keys: Arc::new(keys),
+            state,
+        }
+    }
+
+    /// Whether any keys are configured
+    pub fn is_enabled(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    /// Find the key presented in `headers`
+    fn authenticate(&self, headers: &HeaderMap) -> Result<&ApiKey> {
+        let presented = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .or_else(|| headers.get(API_KEY_HEADER).and_then(|v| v.to_str().ok()))
+            .ok_or_else(|| Error::Unauthorized("missing API key".to_string()))?;
+
+        // Digests compare in constant time and have a fixed length, and every
+        // key is compared, so timing reveals nothing about the secrets
+        let digest = blake3::hash(presented.trim().as_bytes());
+        self.keys
+            .iter()
+            .fold(
+                None,
+                |found, (hash, key)| {
+                    if *hash == digest {
+                        Some(key)
+                    } else {
+                        found
+                    }
+                },
+            )
+            .ok_or_else(|| Error::Unauthorized("invalid API key".to_string()))
+    }
+
+    /// Check that `key` grants `access`
+    fn authorize(&self, key: &ApiKey, access: &Access) -> Result<()> {
+        if key.scope < access.scope {
+            return Err(Error::Forbidden(format!(
+                "API key scope {:?} does not allow {:?} access",
+                key.scope, access.scope
+            )));
+        }
+
+        match &access.target {
+            Target::Public | Target::Any => Ok(()),
+            Target::Collection(name) => authorize_collection(Some(key), &self.state, name),
+            Target::Server if key.collections.is_none() => Ok(()),
+            Target::Server => Err(Error::Forbidden(
+                "API key is restricted to specific collections".to_string(),
+            )),
+        }
+    }
+}
+
+/// Check that `key` may access `collection`, by name or through an alias
+///
+/// gRPC handlers pass the [`ApiKey`] the middleware stored in the request
+/// extensions; `None` means authentication is disabled.
+pub fn authorize_collection(
+    key: Option<&ApiKey>,
+    state: &AppState,
+    collection: &str,
+) -> Result<()> {
+    let Some(key) = key else {
+        return Ok(());
+    };
+
+    let alias_of = state.manager.resolve_alias(collection);
+    if key.allows_collection(collection, alias_of.as_deref()) {
+        Ok(())
+    } else {
+        Err(Error::Forbidden(format!(
+            "API key does not allow access to collection {}",
+            collection
+        )))
+    }
+}
+
+/// Collections `key` may access, sorted by name
+pub fn visible_collections(key: Option<&ApiKey>, state: &AppState) -> Vec<String> {
+    state
+        .collection_names()
+        .into_iter()
+        .filter(|name| authorize_collection(key, state, name).is_ok())
+        .collect()
+}
+
+/// Aliases `key` may access, as `(alias, collection)` sorted by alias
+pub fn visible_aliases(key: Option<&ApiKey>, state: &AppState) -> Vec<(String, String)> {
+    state
+        .aliases()
+        .into_iter()
+        .filter(|(alias, collection)| match key {
+            Some(key) => key.allows_collection(alias, Some(collection)),
+            None => true,
+        })
+        .collect()
+}
+
+/// Middleware authenticating and authorizing every request
+///
+/// On success the matching [`ApiKey`] is added to the request extensions.
+/// Failures are answered with the [`Error`] response, or the equivalent gRPC
+/// status for gRPC requests.
+pub async fn require_api_key(
+    State(auth): State<Authenticator>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if !auth.is_enabled() {
+        return next.run(request).await;
+    }
+
+    let grpc = is_grpc(request.headers());
+    let access = if grpc {
+        grpc_access(request.uri().path())
+    } else {
+        rest_access(request.method(), request.uri().path())
+    };
+    if access.target == Target::Public {
+        return next.run(request).await;
+    }
+
+    let key = auth
+        .authenticate(request.headers())
+        .and_then(|key| auth.authorize(key, &access).map(|_| key.clone()));
+
+    match key {
+        Ok(key) => {
+            request.extensions_mut().insert(key);
+            next.run(request).await
+        }
+        Err(e) if grpc => tonic::Status::from(e).into_http().map(Body::new),
+        Err(e) => e.into_response(),
+    }
+}
+
+fn is_grpc(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/grpc"))
+}
+
+/// Access needed by a REST route
+///
+/// Segments are percent-decoded first, as the router does before handing
+/// them to handlers, so a collection is matched by the name it resolves to.
+fn rest_access(method: &Method, path: &str) -> Access {
+    let decoded: Option<Vec<String>> = path
+        .trim_matches('/')
+        .split('/')
+        .map(|segment| {
+            percent_decode_str(segment)
+                .decode_utf8()
+                .ok()
+                .map(|s| s.into_owned())
+        })
+        .collect();
+    let Some(decoded) = decoded else {
+        // Not a valid path for any route
+        return Access::new(Scope::Admin, Target::Server);
+    };
+    let segments: Vec<&str> = decoded.iter().map(String::as_str).collect();
+    let read = *method == Method::GET || *method == Method::HEAD;
+
+    match segments.as_slice() {
+        ["health"] | ["ready"] => Access::public(),
+        ["metrics"] => Access::new(Scope::Read, Target::Server),
+        ["collections"] if read => Access::new(Scope::Read, Target::Any),
+        ["collections"] => Access::new(Scope::Admin, Target::Server),
+        ["collections", name] if read => {
+            Access::new(Scope::Read, Target::Collection(name.to_string()))
+        }
+        ["collections", name] => Access::new(Scope::Admin, Target::Collection(name.to_string())),
+        ["collections", name, "points", rest @ ..] => {
+            let query = *method == Method::POST
+                && matches!(
+                    rest,
+                    [] | ["count"] | ["search"] | ["search", "batch"] | ["recommend"] | ["scroll"]
+                );
+            let scope = if read || query {
+                Scope::Read
+            } else {
+                Scope::ReadWrite
+            };
+            Access::new(scope, Target::Collection(name.to_string()))
+        }
+        ["aliases", ..] if read => Access::new(Scope::Read, Target::Any),
+        _ => Access::new(Scope::Admin, Target::Server),
+    }
+}
+
+/// Access needed by a gRPC method, given its `/package.Service/Method` path
+///
+/// Point methods and collection deletion name their collection in the
+/// message, so handlers check it with [`authorize_collection`].
+fn grpc_access(path: &str) -> Access {
+    let method = path.strip_prefix("/ruvector.v1.").unwrap_or(path);
+    match method {
+        "Collections/Get" | "Collections/List" => Access::new(Scope::Read, Target::Any),
+        "Collections/Delete" => Access::new(Scope::Admin, Target::Any),
+        "Points/Get" | "Points/Search" => Access::new(Scope::Read, Target::Any),
+        "Points/Upsert" | "Points/BulkUpsert" | "Points/Delete" => {
+            Access::new(Scope::ReadWrite, Target::Any)
+        }
+        _ => Access::new(Scope::Admin, Target::Server),
+    }
+}
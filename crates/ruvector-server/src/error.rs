@@ -1,10 +1,12 @@
 //! Error types for the ruvector server
 
 use axum::{
-    http::StatusCode,
+    http::{header, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
+use ruvector_collections::CollectionError;
+use ruvector_core::RuvectorError;
 use serde_json::json;
 
 /// Result type for server operations
@@ -29,10 +31,22 @@ pub enum Error {
     #[error("Invalid request: {0}")]
     InvalidRequest(String),
 
+    /// Missing or unknown API key
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
+    /// API key lacks the required scope or collection access
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
     /// Core library error
     #[error("Core error: {0}")]
     Core(#[from] ruvector_core::RuvectorError),
 
+    /// Collection management error
+    #[error("Collection error: {0}")]
+    Collection(#[from] CollectionError),
+
     /// Server error
     #[error("Server error: {0}")]
     Server(String),
@@ -50,15 +64,50 @@ pub enum Error {
     Internal(String),
 }
 
+/// HTTP status for a core library error
+fn core_status(error: &RuvectorError) -> StatusCode {
+    match error {
+        RuvectorError::VectorNotFound(_) => StatusCode::NOT_FOUND,
+        RuvectorError::DimensionMismatch { .. }
+        | RuvectorError::InvalidDimension(_)
+        | RuvectorError::InvalidParameter(_)
+        | RuvectorError::InvalidInput(_) => StatusCode::BAD_REQUEST,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// HTTP status for a collection management error
+fn collection_status(error: &CollectionError) -> StatusCode {
+    match error {
+        CollectionError::CollectionNotFound { .. } | CollectionError::AliasNotFound { .. } => {
+            StatusCode::NOT_FOUND
+        }
+        CollectionError::CollectionAlreadyExists { .. }
+        | CollectionError::AliasAlreadyExists { .. }
+        | CollectionError::CollectionHasAliases { .. } => StatusCode::CONFLICT,
+        CollectionError::InvalidConfiguration { .. }
+        | CollectionError::InvalidAlias { .. }
+        | CollectionError::InvalidName { .. } => StatusCode::BAD_REQUEST,
+        CollectionError::DatabaseError(e) => core_status(e),
+        CollectionError::IoError(_) | CollectionError::SerializationError(_) => {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
 impl IntoResponse for Error {
     fn into_response(self) -> Response {
+        let unauthorized = matches!(self, Error::Unauthorized(_));
         let (status, error_message) = match self {
             Error::CollectionNotFound(_) | Error::PointNotFound(_) => {
                 (StatusCode::NOT_FOUND, self.to_string())
             }
             Error::CollectionExists(_) => (StatusCode::CONFLICT, self.to_string()),
             Error::InvalidRequest(_) => (StatusCode::BAD_REQUEST, self.to_string()),
-            Error::Core(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+            Error::Unauthorized(_) => (StatusCode::UNAUTHORIZED, self.to_string()),
+            Error::Forbidden(_) => (StatusCode::FORBIDDEN, self.to_string()),
+            Error::Core(e) => (core_status(&e), e.to_string()),
+            Error::Collection(e) => (collection_status(&e), e.to_string()),
             Error::Server(_) | Error::Internal(_) => {
                 (StatusCode::INTERNAL_SERVER_ERROR, self.to_string())
             }
@@ -71,6 +120,44 @@ impl IntoResponse for Error {
             "status": status.as_u16(),
         }));
 
+        if unauthorized {
+            return (status, [(header::WWW_AUTHENTICATE, "Bearer")], body).into_response();
+        }
         (status, body).into_response()
     }
 }
+
+/// gRPC status equivalent to an HTTP status
+fn grpc_status(status: StatusCode, message: String) -> tonic::Status {
+    match status {
+        StatusCode::NOT_FOUND => tonic::Status::not_found(message),
+        StatusCode::CONFLICT => tonic::Status::already_exists(message),
+        StatusCode::BAD_REQUEST => tonic::Status::invalid_argument(message),
+        _ => tonic::Status::internal(message),
+    }
+}
+
+impl From<Error> for tonic::Status {
+    fn from(error: Error) -> Self {
+        let message = error.to_string();
+        match error {
+            Error::CollectionNotFound(_) | Error::PointNotFound(_) => {
+                tonic::Status::not_found(message)
+            }
+            Error::CollectionExists(_) => tonic::Status::already_exists(message),
+            Error::Unauthorized(_) => tonic::Status::unauthenticated(message),
+            Error::Forbidden(_) => tonic::Status::permission_denied(message),
+            Error::InvalidRequest(_) | Error::Serialization(_) => {
+                tonic::Status::invalid_argument(message)
+            }
+            Error::Core(e) => grpc_status(core_status(&e), message),
+            Error::Collection(CollectionError::CollectionHasAliases { .. }) => {
+                tonic::Status::failed_precondition(message)
+            }
+            Error::Collection(e) => grpc_status(collection_status(&e), message),
+            Error::Server(_) | Error::Config(_) | Error::Internal(_) => {
+                tonic::Status::internal(message)
+            }
+        }
+    }
+}
@@ -0,0 +1,385 @@
+//! gRPC API
+//!
+//! Tonic services generated from `proto/ruvector.proto`. They share
+//! [`AppState`] with the REST routes, so a collection created through one API
+//! is immediately visible through the other.
+
+use crate::{
+    auth::{self, ApiKey},
+    error::Error,
+    metrics::{observe_delete, observe_insert, observe_search},
+    routes::points,
+    state::AppState,
+    Result,
+};
+use prost::Message;
+use prost_types::{value::Kind, ListValue, Struct};
+use ruvector_core::{DistanceMetric, SearchResult, VectorDB, VectorEntry};
+use serde_json::{Map, Number, Value as JsonValue};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tonic::{service::Routes, Request, Response, Status, Streaming};
+
+/// Types and service stubs generated from `proto/ruvector.proto`
+pub mod proto {
+    tonic::include_proto!("ruvector.v1");
+}
+
+use proto::{
+    collections_server::{Collections, CollectionsServer},
+    points_server::{Points, PointsServer},
+};
+
+/// Build the gRPC routes for all services
+pub fn routes(state: AppState) -> Routes {
+    Routes::new(CollectionsServer::new(CollectionsService::new(
+        state.clone(),
+    )))
+    .add_service(PointsServer::new(PointsService::new(state)))
+}
+
+/// Collection management service
+#[derive(Clone)]
+pub struct CollectionsService {
+    state: AppState,
+}
+
+impl CollectionsService {
+    /// Create the service over shared application state
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+}
+
+#[tonic::async_trait]
+impl Collections for CollectionsService {
+    async fn create(
+        &self,
+        request: Request<proto::CreateCollectionRequest>,
+    ) -> std::result::Result<Response<proto::CollectionInfo>, Status> {
+        let req = request.into_inner();
+        let metric = metric_from_proto(req.metric)?;
+        let db = self
+            .state
+            .create_collection(&req.name, req.dimension as usize, metric)?;
+
+        Ok(Response::new(collection_info(req.name, &db)?))
+    }
+
+    async fn get(
+        &self,
+        request: Request<proto::GetCollectionRequest>,
+    ) -> std::result::Result<Response<proto::CollectionInfo>, Status> {
+        let key = request.extensions().get::<ApiKey>().cloned();
+        let name = request.into_inner().name;
+        let db = collection(&self.state, key.as_ref(), &name)?;
+
+        Ok(Response::new(collection_info(name, &db)?))
+    }
+
+    async fn list(
+        &self,
+        request: Request<proto::ListCollectionsRequest>,
+    ) -> std::result::Result<Response<proto::ListCollectionsResponse>, Status> {
+        let key = request.extensions().get::<ApiKey>();
+        Ok(Response::new(proto::ListCollectionsResponse {
+            collections: auth::visible_collections(key, &self.state),
+        }))
+    }
+
+    async fn delete(
+        &self,
+        request: Request<proto::DeleteCollectionRequest>,
+    ) -> std::result::Result<Response<proto::DeleteCollectionResponse>, Status> {
+        let key = request.extensions().get::<ApiKey>().cloned();
+        let name = request.into_inner().name;
+        auth::authorize_collection(key.as_ref(), &self.state, &name)?;
+        self.state.remove_collection(&name)?;
+
+        Ok(Response::new(proto::DeleteCollectionResponse {}))
+    }
+}
+
+/// Point operations service
+#[derive(Clone)]
+pub struct PointsService {
+    state: AppState,
+}
+
+impl PointsService {
+    /// Create the service over shared application state
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+
+    fn upsert_points(
+        &self,
+        key: Option<&ApiKey>,
+        req: proto::UpsertPointsRequest,
+    ) -> Result<Vec<String>> {
+        let db = collection(&self.state, key, &req.collection)?;
+        let entries: Vec<_> = req.points.into_iter().map(entry_from_proto).collect();
+        observe_insert(
+            &self.state.collection_name(&req.collection),
+            entries.len(),
+            || Ok(db.upsert_batch(entries)?),
+        )
+    }
+}
+
+#[tonic::async_trait]
+impl Points for PointsService {
+    async fn upsert(
+        &self,
+        request: Request<proto::UpsertPointsRequest>,
+    ) -> std::result::Result<Response<proto::UpsertPointsResponse>, Status> {
+        let key = request.extensions().get::<ApiKey>().cloned();
+        let ids = self.upsert_points(key.as_ref(), request.into_inner())?;
+        Ok(Response::new(proto::UpsertPointsResponse { ids }))
+    }
+
+    async fn bulk_upsert(
+        &self,
+        request: Request<Streaming<proto::UpsertPointsRequest>>,
+    ) -> std::result::Result<Response<proto::BulkUpsertResponse>, Status> {
+        let key = request.extensions().get::<ApiKey>().cloned();
+        let mut stream = request.into_inner();
+        let mut response = proto::BulkUpsertResponse::default();
+
+        // Batches committed before a failure stay committed, and the error
+        // tells the client how far it got
+        while let Some(batch) = stream
+            .message()
+            .await
+            .map_err(|status| bulk_upsert_failed(status, &response))?
+        {
+            let ids = self
+                .upsert_points(key.as_ref(), batch)
+                .map_err(|e| bulk_upsert_failed(e.into(), &response))?;
+            response.upserted += ids.len() as u64;
+            response.batches += 1;
+            if let Some(last) = ids.into_iter().last() {
+                response.last_id = last;
+            }
+        }
+
+        Ok(Response::new(response))
+    }
+
+    async fn get(
+        &self,
+        request: Request<proto::GetPointsRequest>,
+    ) -> std::result::Result<Response<proto::GetPointsResponse>, Status> {
+        let key = request.extensions().get::<ApiKey>().cloned();
+        let req = request.into_inner();
+        let db = collection(&self.state, key.as_ref(), &req.collection)?;
+        let points = db
+            .get_many(&req.ids)
+            .map_err(Error::Core)?
+            .into_iter()
+            .map(entry_to_proto)
+            .collect();
+
+        Ok(Response::new(proto::GetPointsResponse { points }))
+    }
+
+    async fn search(
+        &self,
+        request: Request<proto::SearchRequest>,
+    ) -> std::result::Result<Response<proto::SearchResponse>, Status> {
+        let key = request.extensions().get::<ApiKey>().cloned();
+        let req = request.into_inner();
+        let db = collection(&self.state, key.as_ref(), &req.collection)?;
+
+        let filter = (!req.filter.is_empty()).then(|| {
+            req.filter
+                .into_iter()
+                .map(|(key, value)| (key, value_to_json(value)))
+                .collect()
+        });
+        let search = points::SearchRequest {
+            vector: req.vector,
+            k: if req.k == 0 {
+                points::default_limit()
+            } else {
+                req.k as usize
+            },
+            score_threshold: req.score_threshold,
+            filter,
+            ef_search: req.ef_search.map(|ef| ef as usize),
+            exact_filter_fallback: req.exact_filter_fallback,
+        };
+        let results = observe_search(&self.state.collection_name(&req.collection), || {
+            points::run_search(&db, search)
+        })?
+        .into_iter()
+        .map(scored_point)
+        .collect();
+
+        Ok(Response::new(proto::SearchResponse { results }))
+    }
+
+    async fn delete(
+        &self,
+        request: Request<proto::DeletePointsRequest>,
+    ) -> std::result::Result<Response<proto::DeletePointsResponse>, Status> {
+        let key = request.extensions().get::<ApiKey>().cloned();
+        let req = request.into_inner();
+        let db = collection(&self.state, key.as_ref(), &req.collection)?;
+        let deleted = observe_delete(&self.state.collection_name(&req.collection), || {
+            db.delete_batch(&req.ids).map_err(Error::Core)
+        })?;
+
+        Ok(Response::new(proto::DeletePointsResponse {
+            deleted: deleted as u64,
+        }))
+    }
+}
+
+/// Attach the progress of a failed bulk upsert to its error status
+///
+/// The status keeps its code; its details carry the encoded
+/// [`proto::BulkUpsertResponse`] for the batches committed before the failure.
+fn bulk_upsert_failed(status: Status, progress: &proto::BulkUpsertResponse) -> Status {
+    let message = format!(
+        "{} ({} points in {} batches committed before the failure, last id {:?})",
+        status.message(),
+        progress.upserted,
+        progress.batches,
+        progress.last_id
+    );
+    Status::with_details_and_metadata(
+        status.code(),
+        message,
+        progress.encode_to_vec().into(),
+        status.metadata().clone(),
+    )
+}
+
+/// Look up a collection the request's API key may access
+fn collection(state: &AppState, key: Option<&ApiKey>, name: &str) -> Result<Arc<VectorDB>> {
+    auth::authorize_collection(key, state, name)?;
+    state
+        .get_collection(name)
+        .ok_or_else(|| Error::CollectionNotFound(name.to_string()))
+}
+
+fn collection_info(name: String, db: &VectorDB) -> Result<proto::CollectionInfo> {
+    let options = db.options();
+    Ok(proto::CollectionInfo {
+        name,
+        dimension: options.dimensions as u32,
+        metric: metric_to_proto(options.distance_metric) as i32,
+        points: db.len()? as u64,
+    })
+}
+
+fn metric_from_proto(metric: i32) -> Result<DistanceMetric> {
+    let metric = proto::DistanceMetric::try_from(metric)
+        .map_err(|_| Error::InvalidRequest(format!("unknown distance metric: {}", metric)))?;
+    Ok(match metric {
+        proto::DistanceMetric::Cosine => DistanceMetric::Cosine,
+        proto::DistanceMetric::Euclidean => DistanceMetric::Euclidean,
+        proto::DistanceMetric::DotProduct => DistanceMetric::DotProduct,
+        proto::DistanceMetric::Manhattan => DistanceMetric::Manhattan,
+    })
+}
+
+fn metric_to_proto(metric: DistanceMetric) -> proto::DistanceMetric {
+    match metric {
+        DistanceMetric::Cosine => proto::DistanceMetric::Cosine,
+        DistanceMetric::Euclidean => proto::DistanceMetric::Euclidean,
+        DistanceMetric::DotProduct => proto::DistanceMetric::DotProduct,
+        DistanceMetric::Manhattan => proto::DistanceMetric::Manhattan,
+    }
+}
+
+fn entry_from_proto(point: proto::Point) -> VectorEntry {
+    VectorEntry {
+        id: (!point.id.is_empty()).then_some(point.id),
+        vector: point.vector,
+        metadata: point.payload.map(struct_to_json),
+    }
+}
+
+fn entry_to_proto(entry: VectorEntry) -> proto::Point {
+    proto::Point {
+        id: entry.id.unwrap_or_default(),
+        vector: entry.vector,
+        payload: entry.metadata.map(json_to_struct),
+    }
+}
+
+fn scored_point(result: SearchResult) -> proto::ScoredPoint {
+    proto::ScoredPoint {
+        id: result.id,
+        score: result.score,
+        vector: result.vector.unwrap_or_default(),
+        payload: result.metadata.map(json_to_struct),
+    }
+}
+
+fn struct_to_json(payload: Struct) -> HashMap<String, JsonValue> {
+    payload
+        .fields
+        .into_iter()
+        .map(|(key, value)| (key, value_to_json(value)))
+        .collect()
+}
+
+fn json_to_struct(payload: HashMap<String, JsonValue>) -> Struct {
+    Struct {
+        fields: payload
+            .into_iter()
+            .map(|(key, value)| (key, json_to_value(value)))
+            .collect(),
+    }
+}
+
+/// Convert a protobuf value to JSON
+///
+/// Protobuf has a single double-precision number type, so whole numbers are
+/// mapped back to JSON integers to keep exact-match filters working against
+/// payloads written through the REST API.
+fn value_to_json(value: prost_types::Value) -> JsonValue {
+    match value.kind {
+        None | Some(Kind::NullValue(_)) => JsonValue::Null,
+        Some(Kind::BoolValue(b)) => JsonValue::Bool(b),
+        Some(Kind::NumberValue(n)) => {
+            if n.fract() == 0.0 && n.abs() < (1u64 << 53) as f64 {
+                JsonValue::Number(Number::from(n as i64))
+            } else {
+                Number::from_f64(n).map_or(JsonValue::Null, JsonValue::Number)
+            }
+        }
+        Some(Kind::StringValue(s)) => JsonValue::String(s),
+        Some(Kind::ListValue(list)) => {
+            JsonValue::Array(list.values.into_iter().map(value_to_json).collect())
+        }
+        Some(Kind::StructValue(s)) => JsonValue::Object(
+            s.fields
+                .into_iter()
+                .map(|(key, value)| (key, value_to_json(value)))
+                .collect::<Map<_, _>>(),
+        ),
+    }
+}
+
+fn json_to_value(value: JsonValue) -> prost_types::Value {
+    let kind = match value {
+        JsonValue::Null => Kind::NullValue(0),
+        JsonValue::Bool(b) => Kind::BoolValue(b),
+        JsonValue::Number(n) => Kind::NumberValue(n.as_f64().unwrap_or_default()),
+        JsonValue::String(s) => Kind::StringValue(s),
+        JsonValue::Array(values) => Kind::ListValue(ListValue {
+            values: values.into_iter().map(json_to_value).collect(),
+        }),
+        JsonValue::Object(map) => Kind::StructValue(Struct {
+            fields: map
+                .into_iter()
+                .map(|(key, value)| (key, json_to_value(value)))
+                .collect::<BTreeMap<_, _>>(),
+        }),
+    };
+    prost_types::Value { kind: Some(kind) }
+}
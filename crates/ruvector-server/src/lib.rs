@@ -1,20 +1,27 @@
-//! ruvector-server: REST API server for rUvector vector database
+//! ruvector-server: REST and gRPC API server for rUvector vector database
 //!
-//! This crate provides a REST API server built on axum for interacting with rUvector.
+//! This crate provides a REST API server built on axum for interacting with rUvector,
+//! plus a tonic gRPC API served on a separate port over the same collections.
 
+pub mod auth;
 pub mod error;
+pub mod grpc;
+pub mod metrics;
 pub mod routes;
 pub mod state;
 
-use axum::{routing::get, Router};
+use auth::Authenticator;
+use axum::{middleware, routing::get, Router};
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use tower_http::{
     compression::CompressionLayer,
     cors::{Any, CorsLayer},
     trace::TraceLayer,
 };
 
+pub use auth::{ApiKey, Scope};
 pub use error::{Error, Result};
 pub use state::AppState;
 
@@ -25,17 +32,37 @@ pub struct Config {
     pub host: String,
     /// Server port
     pub port: u16,
+    /// Directory holding collection data, aliases and configs
+    #[serde(default = "default_data_dir")]
+    pub data_dir: PathBuf,
+    /// gRPC port (`None` disables the gRPC API)
+    #[serde(default = "default_grpc_port")]
+    pub grpc_port: Option<u16>,
+    /// API keys accepted by the server (empty disables authentication)
+    #[serde(default)]
+    pub api_keys: Vec<ApiKey>,
     /// Enable CORS
     pub enable_cors: bool,
     /// Enable compression
     pub enable_compression: bool,
 }
 
+fn default_data_dir() -> PathBuf {
+    PathBuf::from("./data")
+}
+
+fn default_grpc_port() -> Option<u16> {
+    Some(6334)
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
             host: "127.0.0.1".to_string(),
             port: 6333,
+            data_dir: default_data_dir(),
+            grpc_port: default_grpc_port(),
+            api_keys: Vec::new(),
             enable_cors: true,
             enable_compression: true,
         }
@@ -50,29 +77,42 @@ pub struct RuvectorServer {
 
 impl RuvectorServer {
     /// Create a new server instance with default configuration
-    pub fn new() -> Self {
-        Self {
-            config: Config::default(),
-            state: AppState::new(),
-        }
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data directory cannot be opened
+    pub fn new() -> Result<Self> {
+        Self::with_config(Config::default())
     }
 
-    /// Create a new server instance with custom configuration
-    pub fn with_config(config: Config) -> Self {
-        Self {
-            config,
-            state: AppState::new(),
-        }
+    /// Create a new server instance with custom configuration, reloading the
+    /// collections stored in its data directory
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data directory cannot be opened
+    pub fn with_config(config: Config) -> Result<Self> {
+        let state = AppState::open(&config.data_dir)?;
+        Ok(Self { config, state })
+    }
+
+    /// Shared application state served by both APIs
+    pub fn state(&self) -> &AppState {
+        &self.state
     }
 
-    /// Build the router with all routes
-    fn build_router(&self) -> Router {
-        let mut router = Router::new()
+    /// Build the REST router with all routes
+    pub fn router(&self) -> Router {
+        let router = Router::new()
             .route("/health", get(routes::health::health_check))
             .route("/ready", get(routes::health::readiness))
+            .route("/metrics", get(routes::metrics::metrics))
             .nest("/collections", routes::collections::routes())
+            .nest("/aliases", routes::aliases::routes())
             .merge(routes::points::routes())
+            .route_layer(middleware::from_fn(metrics::track_requests))
             .with_state(self.state.clone());
+        let mut router = self.with_auth(router);
 
         // Add middleware layers
         router = router.layer(TraceLayer::new_for_http());
@@ -92,34 +132,72 @@ impl RuvectorServer {
         router
     }
 
+    /// Build the gRPC routes, guarded by the same API keys as the REST routes
+    pub fn grpc_routes(&self) -> tonic::service::Routes {
+        self.with_auth(grpc::routes(self.state.clone()).into_axum_router())
+            .into()
+    }
+
+    /// Require API keys on every route of `router`
+    fn with_auth(&self, router: Router) -> Router {
+        let auth = Authenticator::new(self.config.api_keys.clone(), self.state.clone());
+        router.layer(middleware::from_fn_with_state(auth, auth::require_api_key))
+    }
+
     /// Start the server
     ///
+    /// Serves the REST API and, when `grpc_port` is set, the gRPC API until
+    /// either of them stops.
+    ///
     /// # Errors
     ///
     /// Returns an error if the server fails to bind or start
     pub async fn start(self) -> Result<()> {
-        let addr: SocketAddr = format!("{}:{}", self.config.host, self.config.port)
-            .parse()
-            .map_err(|e| Error::Config(format!("Invalid address: {}", e)))?;
+        let addr = self.socket_addr(self.config.port)?;
+        let grpc_addr = self
+            .config
+            .grpc_port
+            .map(|port| self.socket_addr(port))
+            .transpose()?;
 
-        let router = self.build_router();
+        let router = self.router();
 
         tracing::info!("Starting ruvector-server on {}", addr);
+        if self.config.api_keys.is_empty() {
+            tracing::warn!("No API keys configured; authentication is disabled");
+        }
 
         let listener = tokio::net::TcpListener::bind(addr)
             .await
             .map_err(|e| Error::Server(format!("Failed to bind to {}: {}", addr, e)))?;
 
-        axum::serve(listener, router)
-            .await
-            .map_err(|e| Error::Server(format!("Server error: {}", e)))?;
+        let rest = async {
+            axum::serve(listener, router)
+                .await
+                .map_err(|e| Error::Server(format!("Server error: {}", e)))
+        };
+
+        let grpc = async {
+            let Some(grpc_addr) = grpc_addr else {
+                return Ok(());
+            };
+            tracing::info!("Starting ruvector-server gRPC API on {}", grpc_addr);
+
+            tonic::transport::Server::builder()
+                .add_routes(self.grpc_routes())
+                .serve(grpc_addr)
+                .await
+                .map_err(|e| Error::Server(format!("gRPC server error: {}", e)))
+        };
+
+        tokio::try_join!(rest, grpc)?;
 
         Ok(())
     }
-}
 
-impl Default for RuvectorServer {
-    fn default() -> Self {
-        Self::new()
+    fn socket_addr(&self, port: u16) -> Result<SocketAddr> {
+        format!("{}:{}", self.config.host, port)
+            .parse()
+            .map_err(|e| Error::Config(format!("Invalid address: {}", e)))
     }
 }
@@ -0,0 +1,74 @@
+//! Prometheus instrumentation
+//!
+//! [`track_requests`] records the latency and status of every REST route,
+//! labelled with the route pattern so path parameters don't multiply series.
+//! The `observe_*` helpers time the database operation behind a handler and
+//! feed the per-collection search, insert and delete metrics of
+//! [`MetricsRecorder`]; REST and gRPC handlers both use them.
+
+use crate::Result;
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use ruvector_metrics::MetricsRecorder;
+use std::time::Instant;
+
+/// Middleware recording the latency and status of each matched route
+///
+/// Must be added with `route_layer`, where the matched route is known;
+/// requests that match no route are not recorded.
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let Some(route) = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+    else {
+        return next.run(request).await;
+    };
+    let method = request.method().clone();
+
+    let start = Instant::now();
+    let response = next.run(request).await;
+    MetricsRecorder::record_request(
+        method.as_str(),
+        &route,
+        response.status().as_u16(),
+        start.elapsed().as_secs_f64(),
+    );
+
+    response
+}
+
+/// Run a search against `collection` and record it
+pub(crate) fn observe_search<T>(collection: &str, search: impl FnOnce() -> Result<T>) -> Result<T> {
+    let start = Instant::now();
+    let result = search();
+    MetricsRecorder::record_search(collection, start.elapsed().as_secs_f64(), result.is_ok());
+    result
+}
+
+/// Run an insert of `count` vectors into `collection` and record it
+pub(crate) fn observe_insert<T>(
+    collection: &str,
+    count: usize,
+    insert: impl FnOnce() -> Result<T>,
+) -> Result<T> {
+    let start = Instant::now();
+    let result = insert();
+    MetricsRecorder::record_insert(
+        collection,
+        start.elapsed().as_secs_f64(),
+        count,
+        result.is_ok(),
+    );
+    result
+}
+
+/// Run a delete from `collection` and record it
+pub(crate) fn observe_delete<T>(collection: &str, delete: impl FnOnce() -> Result<T>) -> Result<T> {
+    let result = delete();
+    MetricsRecorder::record_delete(collection, result.is_ok());
+    result
+}
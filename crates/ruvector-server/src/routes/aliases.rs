@@ -0,0 +1,105 @@
+//! Collection alias endpoints
+
+use crate::{
+    auth::{self, ApiKey},
+    state::AppState,
+    Result,
+};
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, post, put},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+
+/// Alias target request
+#[derive(Debug, Deserialize)]
+pub struct AliasRequest {
+    /// Collection the alias points to
+    pub collection: String,
+}
+
+/// Alias description
+#[derive(Debug, Serialize)]
+pub struct AliasInfo {
+    /// Alias name
+    pub alias: String,
+    /// Collection the alias points to
+    pub collection: String,
+}
+
+/// List of aliases response
+#[derive(Debug, Serialize)]
+pub struct AliasesList {
+    /// Aliases, sorted by name
+    pub aliases: Vec<AliasInfo>,
+}
+
+/// Create alias routes
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_aliases))
+        .route("/:alias", put(create_alias).delete(delete_alias))
+        .route("/:alias/switch", post(switch_alias))
+}
+
+/// List all aliases
+///
+/// GET /aliases
+async fn list_aliases(
+    State(state): State<AppState>,
+    key: Option<Extension<ApiKey>>,
+) -> Result<impl IntoResponse> {
+    let aliases = auth::visible_aliases(key.as_deref(), &state)
+        .into_iter()
+        .map(|(alias, collection)| AliasInfo { alias, collection })
+        .collect();
+    Ok(Json(AliasesList { aliases }))
+}
+
+/// Create an alias for a collection
+///
+/// PUT /aliases/:alias
+async fn create_alias(
+    State(state): State<AppState>,
+    Path(alias): Path<String>,
+    Json(req): Json<AliasRequest>,
+) -> Result<impl IntoResponse> {
+    state.create_alias(&alias, &req.collection)?;
+
+    let info = AliasInfo {
+        alias,
+        collection: req.collection,
+    };
+    Ok((StatusCode::CREATED, Json(info)))
+}
+
+/// Point an existing alias at a different collection
+///
+/// POST /aliases/:alias/switch
+async fn switch_alias(
+    State(state): State<AppState>,
+    Path(alias): Path<String>,
+    Json(req): Json<AliasRequest>,
+) -> Result<impl IntoResponse> {
+    state.switch_alias(&alias, &req.collection)?;
+
+    Ok(Json(AliasInfo {
+        alias,
+        collection: req.collection,
+    }))
+}
+
+/// Delete an alias
+///
+/// DELETE /aliases/:alias
+async fn delete_alias(
+    State(state): State<AppState>,
+    Path(alias): Path<String>,
+) -> Result<impl IntoResponse> {
+    state.delete_alias(&alias)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
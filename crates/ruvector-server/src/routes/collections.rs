@@ -1,16 +1,20 @@
 //! Collection management endpoints
 
-use crate::{error::Error, state::AppState, Result};
+use crate::{
+    auth::{self, ApiKey},
+    error::Error,
+    state::AppState,
+    Result,
+};
 use axum::{
-    extract::{Path, State},
+    extract::{Extension, Path, State},
     http::StatusCode,
     response::IntoResponse,
     routing::{get, post},
     Json, Router,
 };
-use ruvector_core::{types::DbOptions, DistanceMetric, VectorDB};
+use ruvector_core::DistanceMetric;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
 
 /// Collection creation request
 #[derive(Debug, Deserialize)]
@@ -55,23 +59,13 @@ async fn create_collection(
     State(state): State<AppState>,
     Json(req): Json<CreateCollectionRequest>,
 ) -> Result<impl IntoResponse> {
-    if state.contains_collection(&req.name) {
-        return Err(Error::CollectionExists(req.name));
-    }
-
-    let mut options = DbOptions::default();
-    options.dimensions = req.dimension;
-    options.distance_metric = req.metric.unwrap_or(DistanceMetric::Cosine);
-    // Use in-memory storage for server (storage path will be ignored for memory storage)
-    options.storage_path = format!("memory://{}", req.name);
-
-    let db = VectorDB::new(options.clone()).map_err(Error::Core)?;
-    state.insert_collection(req.name.clone(), Arc::new(db));
+    let metric = req.metric.unwrap_or(DistanceMetric::Cosine);
+    state.create_collection(&req.name, req.dimension, metric)?;
 
     let info = CollectionInfo {
         name: req.name,
         dimension: req.dimension,
-        metric: options.distance_metric,
+        metric,
     };
 
     Ok((StatusCode::CREATED, Json(info)))
@@ -80,8 +74,11 @@ async fn create_collection(
 /// List all collections
 ///
 /// GET /collections
-async fn list_collections(State(state): State<AppState>) -> Result<impl IntoResponse> {
-    let collections = state.collection_names();
+async fn list_collections(
+    State(state): State<AppState>,
+    key: Option<Extension<ApiKey>>,
+) -> Result<impl IntoResponse> {
+    let collections = auth::visible_collections(key.as_deref(), &state);
     Ok(Json(CollectionsList { collections }))
 }
 
@@ -92,15 +89,15 @@ async fn get_collection(
     State(state): State<AppState>,
     Path(name): Path<String>,
 ) -> Result<impl IntoResponse> {
-    let _db = state
+    let db = state
         .get_collection(&name)
         .ok_or_else(|| Error::CollectionNotFound(name.clone()))?;
 
-    // Note: VectorDB doesn't expose config directly, so we return basic info
+    let options = db.options();
     let info = CollectionInfo {
         name,
-        dimension: 0, // Would need to be stored separately or queried from DB
-        metric: DistanceMetric::Cosine, // Default assumption
+        dimension: options.dimensions,
+        metric: options.distance_metric,
     };
 
     Ok(Json(info))
@@ -113,9 +110,7 @@ async fn delete_collection(
     State(state): State<AppState>,
     Path(name): Path<String>,
 ) -> Result<impl IntoResponse> {
-    state
-        .remove_collection(&name)
-        .ok_or_else(|| Error::CollectionNotFound(name))?;
+    state.remove_collection(&name)?;
 
     Ok(StatusCode::NO_CONTENT)
 }
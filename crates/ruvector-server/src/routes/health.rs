@@ -2,45 +2,18 @@
 
 use crate::{state::AppState, Result};
 use axum::{extract::State, response::IntoResponse, Json};
-use serde::Serialize;
 
-/// Health status response
-#[derive(Debug, Serialize)]
-pub struct HealthStatus {
-    /// Server status
-    pub status: String,
-}
-
-/// Readiness status response
-#[derive(Debug, Serialize)]
-pub struct ReadinessStatus {
-    /// Server status
-    pub status: String,
-    /// Number of collections
-    pub collections: usize,
-    /// Total number of points across all collections
-    pub total_points: usize,
-}
-
-/// Simple health check endpoint
+/// Liveness check with version and uptime
 ///
 /// GET /health
-pub async fn health_check() -> Result<impl IntoResponse> {
-    Ok(Json(HealthStatus {
-        status: "healthy".to_string(),
-    }))
+pub async fn health_check(State(state): State<AppState>) -> Result<impl IntoResponse> {
+    Ok(Json(state.health.health()))
 }
 
-/// Readiness check endpoint with stats
+/// Readiness check with per-collection vector counts
 ///
 /// GET /ready
 pub async fn readiness(State(state): State<AppState>) -> Result<impl IntoResponse> {
-    let collections_count = state.collection_count();
-
-    // Note: VectorDB doesn't expose count directly, so we report collections only
-    Ok(Json(ReadinessStatus {
-        status: "ready".to_string(),
-        collections: collections_count,
-        total_points: 0, // Would require tracking or querying each DB
-    }))
+    let stats = state.collection_stats()?;
+    Ok(Json(state.health.readiness(&stats)))
 }
@@ -0,0 +1,39 @@
+//! Prometheus metrics endpoint
+
+use crate::{state::AppState, Result};
+use axum::{extract::State, http::header, response::IntoResponse};
+use ruvector_metrics::{gather_metrics, MetricsRecorder};
+
+/// Content type of the Prometheus text exposition format
+const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Export all metrics in Prometheus text format
+///
+/// Collection and HNSW gauges are refreshed from the live collections on
+/// every scrape.
+///
+/// GET /metrics
+pub async fn metrics(State(state): State<AppState>) -> Result<impl IntoResponse> {
+    let stats = state.collection_stats()?;
+    MetricsRecorder::set_collections_count(stats.len());
+
+    for collection in &stats {
+        MetricsRecorder::set_vectors_count(&collection.name, collection.vectors_count);
+        let graph = state
+            .get_collection(&collection.name)
+            .and_then(|db| db.graph_stats());
+        if let Some(graph) = graph {
+            MetricsRecorder::set_hnsw_stats(
+                &collection.name,
+                graph.nodes,
+                graph.tombstones,
+                graph.ef_search,
+            );
+        }
+    }
+
+    Ok((
+        [(header::CONTENT_TYPE, PROMETHEUS_CONTENT_TYPE)],
+        gather_metrics(),
+    ))
+}
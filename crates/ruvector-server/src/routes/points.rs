@@ -1,6 +1,11 @@
 //! Point operations endpoints
 
-use crate::{error::Error, state::AppState, Result};
+use crate::{
+    error::Error,
+    metrics::{observe_delete, observe_insert, observe_search},
+    state::AppState,
+    Result,
+};
 use axum::{
     extract::{Path, State},
     http::StatusCode,
@@ -8,7 +13,11 @@ use axum::{
     routing::{get, post, put},
     Json, Router,
 };
-use ruvector_core::{SearchQuery, SearchResult, VectorEntry};
+use ruvector_core::{
+    index::SearchParams, RecommendRequest, ScrollRequest, SearchQuery, SearchResult, VectorDB,
+    VectorEntry,
+};
+use ruvector_filter::FilterExpression;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -19,6 +28,68 @@ pub struct UpsertPointsRequest {
     pub points: Vec<VectorEntry>,
 }
 
+/// Request for several points by id
+#[derive(Debug, Deserialize)]
+pub struct GetPointsRequest {
+    /// Point ids; missing ids are skipped
+    pub ids: Vec<String>,
+}
+
+/// Points response
+#[derive(Debug, Serialize)]
+pub struct PointsResponse {
+    /// Points found, in request order
+    pub points: Vec<VectorEntry>,
+}
+
+/// Point deletion request
+///
+/// Deletes the listed ids and every point matching the filter; at least one
+/// of the two must be given.
+#[derive(Debug, Deserialize)]
+pub struct DeletePointsRequest {
+    /// Point ids to delete
+    #[serde(default)]
+    pub ids: Vec<String>,
+    /// Delete points whose payload matches this filter
+    pub filter: Option<FilterExpression>,
+}
+
+/// Deletion response
+#[derive(Debug, Serialize)]
+pub struct DeleteResponse {
+    /// Number of points deleted
+    pub deleted: usize,
+}
+
+/// Count request
+#[derive(Debug, Default, Deserialize)]
+pub struct CountRequest {
+    /// Only count points whose payload matches this filter
+    pub filter: Option<FilterExpression>,
+}
+
+/// Count response
+#[derive(Debug, Serialize)]
+pub struct CountResponse {
+    /// Number of matching points
+    pub count: usize,
+}
+
+/// Payload update request
+#[derive(Debug, Deserialize)]
+pub struct PayloadRequest {
+    /// Payload keys and values
+    pub payload: HashMap<String, serde_json::Value>,
+}
+
+/// Payload key deletion request
+#[derive(Debug, Deserialize)]
+pub struct DeletePayloadRequest {
+    /// Payload keys to remove
+    pub keys: Vec<String>,
+}
+
 /// Search request
 #[derive(Debug, Deserialize)]
 pub struct SearchRequest {
@@ -31,12 +102,51 @@ pub struct SearchRequest {
     pub score_threshold: Option<f32>,
     /// Optional metadata filters
     pub filter: Option<HashMap<String, serde_json::Value>>,
+    /// Optional HNSW efSearch override for this query (higher = better recall, slower)
+    pub ef_search: Option<usize>,
+    /// Scan every match when the graph search finds fewer than k, so k
+    /// results come back whenever k points match the filter
+    #[serde(default)]
+    pub exact_filter_fallback: bool,
 }
 
-fn default_limit() -> usize {
+pub(crate) fn default_limit() -> usize {
     10
 }
 
+/// Batch search request
+#[derive(Debug, Deserialize)]
+pub struct BatchSearchRequest {
+    /// Searches to run
+    pub searches: Vec<SearchRequest>,
+}
+
+/// Batch search response
+#[derive(Debug, Serialize)]
+pub struct BatchSearchResponse {
+    /// Results of each search, in request order
+    pub results: Vec<Vec<SearchResult>>,
+}
+
+/// Recommend-by-example request
+#[derive(Debug, Deserialize)]
+pub struct RecommendPointsRequest {
+    /// Ids of points the results should resemble
+    pub positive: Vec<String>,
+    /// Ids of points the results should not resemble
+    #[serde(default)]
+    pub negative: Vec<String>,
+    /// Number of results to return
+    #[serde(default = "default_limit")]
+    pub k: usize,
+    /// Optional score threshold
+    pub score_threshold: Option<f32>,
+    /// Optional payload filter
+    pub filter: Option<FilterExpression>,
+    /// Optional HNSW efSearch override for this query
+    pub ef_search: Option<usize>,
+}
+
 /// Search response
 #[derive(Debug, Serialize)]
 pub struct SearchResponse {
@@ -54,13 +164,38 @@ pub struct UpsertResponse {
 /// Create point routes
 pub fn routes() -> Router<AppState> {
     Router::new()
-        .route("/collections/:name/points", put(upsert_points))
+        .route(
+            "/collections/:name/points",
+            put(upsert_points).post(get_points),
+        )
+        .route("/collections/:name/points/delete", post(delete_points))
+        .route("/collections/:name/points/count", post(count_points))
         .route("/collections/:name/points/search", post(search_points))
-        .route("/collections/:name/points/:id", get(get_point))
+        .route("/collections/:name/points/search/batch", post(batch_search))
+        .route(
+            "/collections/:name/points/recommend",
+            post(recommend_points),
+        )
+        .route("/collections/:name/points/scroll", post(scroll_points))
+        .route(
+            "/collections/:name/points/:id",
+            get(get_point).delete(delete_point),
+        )
+        .route(
+            "/collections/:name/points/:id/payload",
+            post(set_payload).put(overwrite_payload),
+        )
+        .route(
+            "/collections/:name/points/:id/payload/delete",
+            post(delete_payload_keys),
+        )
 }
 
 /// Upsert points into a collection
 ///
+/// Points whose id already exists are replaced, vector and payload; a point
+/// sent without a payload has its stored payload cleared.
+///
 /// PUT /collections/:name/points
 async fn upsert_points(
     State(state): State<AppState>,
@@ -71,7 +206,10 @@ async fn upsert_points(
         .get_collection(&name)
         .ok_or_else(|| Error::CollectionNotFound(name.clone()))?;
 
-    let ids = db.insert_batch(req.points).map_err(Error::Core)?;
+    let count = req.points.len();
+    let ids = observe_insert(&state.collection_name(&name), count, || {
+        db.upsert_batch(req.points).map_err(Error::Core)
+    })?;
 
     Ok((StatusCode::OK, Json(UpsertResponse { ids })))
 }
@@ -86,25 +224,134 @@ async fn search_points(
 ) -> Result<impl IntoResponse> {
     let db = state
         .get_collection(&name)
-        .ok_or_else(|| Error::CollectionNotFound(name))?;
+        .ok_or_else(|| Error::CollectionNotFound(name.clone()))?;
+
+    let results = observe_search(&state.collection_name(&name), || run_search(&db, req))?;
+
+    Ok(Json(SearchResponse { results }))
+}
 
+/// Run several searches in one call
+///
+/// POST /collections/:name/points/search/batch
+async fn batch_search(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(req): Json<BatchSearchRequest>,
+) -> Result<impl IntoResponse> {
+    let db = state
+        .get_collection(&name)
+        .ok_or_else(|| Error::CollectionNotFound(name.clone()))?;
+
+    let collection = state.collection_name(&name);
+    let results = req
+        .searches
+        .into_iter()
+        .map(|search| observe_search(&collection, || run_search(&db, search)))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Json(BatchSearchResponse { results }))
+}
+
+/// Run a single search against a collection
+pub(crate) fn run_search(db: &VectorDB, req: SearchRequest) -> Result<Vec<SearchResult>> {
     let query = SearchQuery {
         vector: req.vector,
         k: req.k,
         filter: req.filter,
-        ef_search: None,
+        ef_search: req.ef_search,
     };
+    let params = SearchParams::default().with_exact_filter_fallback(req.exact_filter_fallback);
 
-    let mut results = db.search(query).map_err(Error::Core)?;
+    let mut results = db.search_with_params(query, &params).map_err(Error::Core)?;
 
     // Apply score threshold if provided
     if let Some(threshold) = req.score_threshold {
         results.retain(|r| r.score >= threshold);
     }
 
+    Ok(results)
+}
+
+/// Recommend points similar to positive examples and unlike negative ones
+///
+/// POST /collections/:name/points/recommend
+async fn recommend_points(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(req): Json<RecommendPointsRequest>,
+) -> Result<impl IntoResponse> {
+    let db = state
+        .get_collection(&name)
+        .ok_or_else(|| Error::CollectionNotFound(name.clone()))?;
+
+    if req.positive.is_empty() {
+        return Err(Error::InvalidRequest(
+            "at least one positive example is required".to_string(),
+        ));
+    }
+    let request = RecommendRequest {
+        positive: req.positive,
+        negative: req.negative,
+        k: req.k,
+        filter: req.filter,
+        ef_search: req.ef_search,
+    };
+    let mut results = observe_search(&state.collection_name(&name), || {
+        db.recommend(&request).map_err(Error::Core)
+    })?;
+
+    if let Some(threshold) = req.score_threshold {
+        results.retain(|r| r.score >= threshold);
+    }
+
     Ok(Json(SearchResponse { results }))
 }
 
+/// Count points, optionally only those matching a filter
+///
+/// POST /collections/:name/points/count
+async fn count_points(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    req: Option<Json<CountRequest>>,
+) -> Result<impl IntoResponse> {
+    let db = state
+        .get_collection(&name)
+        .ok_or(Error::CollectionNotFound(name))?;
+
+    let Json(req) = req.unwrap_or_default();
+    let count = db.count(req.filter.as_ref()).map_err(Error::Core)?;
+
+    Ok(Json(CountResponse { count }))
+}
+
+/// Page through the points of a collection in id order
+///
+/// The response's `next_offset` is passed back as `offset` to fetch the
+/// next page; it is `null` once every point has been returned. Pages are read
+/// independently, so writes made during the scroll may or may not be seen.
+///
+/// POST /collections/:name/points/scroll
+async fn scroll_points(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(req): Json<ScrollRequest>,
+) -> Result<impl IntoResponse> {
+    let db = state
+        .get_collection(&name)
+        .ok_or(Error::CollectionNotFound(name))?;
+
+    if req.limit == 0 {
+        return Err(Error::InvalidRequest(
+            "limit must be at least 1".to_string(),
+        ));
+    }
+    let page = db.scroll(&req).map_err(Error::Core)?;
+
+    Ok(Json(page))
+}
+
 /// Get a point by ID
 ///
 /// GET /collections/:name/points/:id
@@ -114,9 +361,136 @@ async fn get_point(
 ) -> Result<impl IntoResponse> {
     let db = state
         .get_collection(&name)
-        .ok_or_else(|| Error::CollectionNotFound(name))?;
+        .ok_or(Error::CollectionNotFound(name))?;
 
-    let entry = db.get(&id).map_err(Error::Core)?;
+    let entry = db
+        .get(&id)
+        .map_err(Error::Core)?
+        .ok_or(Error::PointNotFound(id))?;
 
     Ok(Json(entry))
 }
+
+/// Get several points by ID
+///
+/// POST /collections/:name/points
+async fn get_points(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(req): Json<GetPointsRequest>,
+) -> Result<impl IntoResponse> {
+    let db = state
+        .get_collection(&name)
+        .ok_or(Error::CollectionNotFound(name))?;
+
+    let points = db.get_many(&req.ids).map_err(Error::Core)?;
+
+    Ok(Json(PointsResponse { points }))
+}
+
+/// Delete a point by ID
+///
+/// DELETE /collections/:name/points/:id
+async fn delete_point(
+    State(state): State<AppState>,
+    Path((name, id)): Path<(String, String)>,
+) -> Result<impl IntoResponse> {
+    let db = state
+        .get_collection(&name)
+        .ok_or_else(|| Error::CollectionNotFound(name.clone()))?;
+
+    let deleted = observe_delete(&state.collection_name(&name), || {
+        db.delete(&id).map_err(Error::Core)
+    })?;
+    if !deleted {
+        return Err(Error::PointNotFound(id));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Delete points by ID and/or by payload filter
+///
+/// POST /collections/:name/points/delete
+async fn delete_points(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(req): Json<DeletePointsRequest>,
+) -> Result<impl IntoResponse> {
+    let db = state
+        .get_collection(&name)
+        .ok_or_else(|| Error::CollectionNotFound(name.clone()))?;
+
+    if req.ids.is_empty() && req.filter.is_none() {
+        return Err(Error::InvalidRequest(
+            "either ids or filter is required".to_string(),
+        ));
+    }
+
+    let deleted = observe_delete(&state.collection_name(&name), || {
+        db.delete_matching(&req.ids, req.filter.as_ref())
+            .map_err(Error::Core)
+    })?;
+
+    Ok(Json(DeleteResponse { deleted }))
+}
+
+/// Set payload keys on a point, keeping its other keys
+///
+/// POST /collections/:name/points/:id/payload
+async fn set_payload(
+    State(state): State<AppState>,
+    Path((name, id)): Path<(String, String)>,
+    Json(req): Json<PayloadRequest>,
+) -> Result<impl IntoResponse> {
+    let db = state
+        .get_collection(&name)
+        .ok_or(Error::CollectionNotFound(name))?;
+
+    if !db.set_payload(&id, req.payload).map_err(Error::Core)? {
+        return Err(Error::PointNotFound(id));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Replace the whole payload of a point
+///
+/// PUT /collections/:name/points/:id/payload
+async fn overwrite_payload(
+    State(state): State<AppState>,
+    Path((name, id)): Path<(String, String)>,
+    Json(req): Json<PayloadRequest>,
+) -> Result<impl IntoResponse> {
+    let db = state
+        .get_collection(&name)
+        .ok_or(Error::CollectionNotFound(name))?;
+
+    if !db.update_metadata(&id, req.payload).map_err(Error::Core)? {
+        return Err(Error::PointNotFound(id));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Remove payload keys from a point
+///
+/// POST /collections/:name/points/:id/payload/delete
+async fn delete_payload_keys(
+    State(state): State<AppState>,
+    Path((name, id)): Path<(String, String)>,
+    Json(req): Json<DeletePayloadRequest>,
+) -> Result<impl IntoResponse> {
+    let db = state
+        .get_collection(&name)
+        .ok_or(Error::CollectionNotFound(name))?;
+
+    if !db
+        .delete_payload_keys(&id, &req.keys)
+        .map_err(Error::Core)?
+    {
+        return Err(Error::PointNotFound(id));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,60 +1,138 @@
 //! Shared application state
 
-use dashmap::DashMap;
-use ruvector_core::VectorDB;
+use crate::{error::Error, Result};
+use ruvector_collections::{CollectionConfig, CollectionManager};
+use ruvector_core::{DistanceMetric, VectorDB};
+use ruvector_metrics::{health::CollectionStats, HealthChecker, MetricsRecorder};
+use std::path::PathBuf;
 use std::sync::Arc;
 
 /// Shared application state
+///
+/// Collections and aliases live in a [`CollectionManager`] rooted at the
+/// server's data directory, so they are reloaded when the server restarts.
 #[derive(Clone)]
 pub struct AppState {
-    /// Map of collection name to VectorDB
-    pub collections: Arc<DashMap<String, Arc<VectorDB>>>,
+    /// Collection manager owning every collection and alias
+    pub manager: Arc<CollectionManager>,
+    /// Uptime and readiness reporting
+    pub health: Arc<HealthChecker>,
 }
 
 impl AppState {
-    /// Create a new application state
-    pub fn new() -> Self {
-        Self {
-            collections: Arc::new(DashMap::new()),
-        }
+    /// Open the application state, loading any collections already stored
+    /// under `data_dir`
+    pub fn open(data_dir: impl Into<PathBuf>) -> Result<Self> {
+        let manager = CollectionManager::new(data_dir.into())?;
+        Ok(Self {
+            manager: Arc::new(manager),
+            health: Arc::new(HealthChecker::with_version(
+                env!("CARGO_PKG_VERSION").to_string(),
+            )),
+        })
     }
 
-    /// Get a collection by name
+    /// Get a collection by name or alias
     pub fn get_collection(&self, name: &str) -> Option<Arc<VectorDB>> {
-        self.collections.get(name).map(|c| c.clone())
+        self.manager
+            .get_collection(name)
+            .map(|collection| collection.read().db.clone())
+    }
+
+    /// Create an empty collection
+    ///
+    /// Used by both the REST and gRPC APIs so collections look the same
+    /// whichever one created them.
+    pub fn create_collection(
+        &self,
+        name: &str,
+        dimension: usize,
+        metric: DistanceMetric,
+    ) -> Result<Arc<VectorDB>> {
+        let config = CollectionConfig {
+            distance_metric: metric,
+            ..CollectionConfig::with_dimensions(dimension)
+        };
+        self.manager.create_collection(name, config)?;
+
+        self.get_collection(name)
+            .ok_or_else(|| Error::CollectionNotFound(name.to_string()))
     }
 
-    /// Insert a collection
-    pub fn insert_collection(&self, name: String, db: Arc<VectorDB>) {
-        self.collections.insert(name, db);
+    /// Delete a collection and its stored data
+    pub fn remove_collection(&self, name: &str) -> Result<()> {
+        self.manager.delete_collection(name)?;
+        MetricsRecorder::remove_collection(name);
+        Ok(())
     }
 
-    /// Remove a collection
-    pub fn remove_collection(&self, name: &str) -> Option<Arc<VectorDB>> {
-        self.collections.remove(name).map(|(_, c)| c)
+    /// Name of the collection `name` refers to, resolving aliases
+    ///
+    /// Metrics are labelled with this so traffic through an alias is counted
+    /// against the collection that served it.
+    pub fn collection_name(&self, name: &str) -> String {
+        self.manager
+            .resolve_alias(name)
+            .unwrap_or_else(|| name.to_string())
+    }
+
+    /// Current statistics of every collection, sorted by name
+    pub fn collection_stats(&self) -> Result<Vec<CollectionStats>> {
+        let mut stats = Vec::new();
+        for name in self.collection_names() {
+            // Skip collections deleted since they were listed
+            let Some(collection) = self.manager.get_collection(&name) else {
+                continue;
+            };
+            let (db, updated_at) = {
+                let collection = collection.read();
+                (collection.db.clone(), collection.updated_at)
+            };
+            stats.push(CollectionStats {
+                name,
+                vectors_count: db.len()?,
+                last_updated: chrono::DateTime::from_timestamp(updated_at, 0),
+            });
+        }
+        Ok(stats)
     }
 
     /// Check if a collection exists
     pub fn contains_collection(&self, name: &str) -> bool {
-        self.collections.contains_key(name)
+        self.manager.collection_exists(name)
     }
 
     /// Get all collection names
     pub fn collection_names(&self) -> Vec<String> {
-        self.collections
-            .iter()
-            .map(|entry| entry.key().clone())
-            .collect()
+        let mut names = self.manager.list_collections();
+        names.sort();
+        names
     }
 
     /// Get the number of collections
     pub fn collection_count(&self) -> usize {
-        self.collections.len()
+        self.manager.list_collections().len()
+    }
+
+    /// Create an alias for a collection
+    pub fn create_alias(&self, alias: &str, collection: &str) -> Result<()> {
+        Ok(self.manager.create_alias(alias, collection)?)
+    }
+
+    /// Point an existing alias at a different collection
+    pub fn switch_alias(&self, alias: &str, collection: &str) -> Result<()> {
+        Ok(self.manager.switch_alias(alias, collection)?)
+    }
+
+    /// Delete an alias
+    pub fn delete_alias(&self, alias: &str) -> Result<()> {
+        Ok(self.manager.delete_alias(alias)?)
     }
-}
 
-impl Default for AppState {
-    fn default() -> Self {
-        Self::new()
+    /// Get all aliases with their target collections, sorted by alias
+    pub fn aliases(&self) -> Vec<(String, String)> {
+        let mut aliases = self.manager.list_aliases();
+        aliases.sort();
+        aliases
     }
 }
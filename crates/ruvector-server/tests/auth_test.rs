@@ -0,0 +1,505 @@
+//! API key authentication and authorization tests
+
+use axum::{
+    body::Body,
+    http::{header, Method, Request, StatusCode},
+    Router,
+};
+use ruvector_server::grpc::proto::{
+    collections_client::CollectionsClient, points_client::PointsClient, CreateCollectionRequest,
+    DeleteCollectionRequest, ListCollectionsRequest, Point, SearchRequest, UpsertPointsRequest,
+};
+use ruvector_server::{ApiKey, Config, RuvectorServer, Scope};
+use serde_json::{json, Value};
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::{
+    metadata::MetadataValue,
+    transport::{Channel, Server},
+    Code, Status,
+};
+use tower::ServiceExt;
+
+fn key(key: &str, scope: Scope, collections: Option<&[&str]>) -> ApiKey {
+    ApiKey {
+        key: key.to_string(),
+        scope,
+        collections: collections.map(|c| c.iter().map(|s| s.to_string()).collect()),
+    }
+}
+
+fn server(data_dir: &std::path::Path) -> RuvectorServer {
+    let config = Config {
+        data_dir: data_dir.to_path_buf(),
+        api_keys: vec![
+            key("admin-key", Scope::Admin, None),
+            key("docs-admin-key", Scope::Admin, Some(&["docs"])),
+            key("docs-rw-key", Scope::ReadWrite, Some(&["docs"])),
+            key("read-key", Scope::Read, None),
+        ],
+        ..Config::default()
+    };
+    RuvectorServer::with_config(config).unwrap()
+}
+
+async fn call(
+    router: &Router,
+    method: Method,
+    uri: &str,
+    api_key: Option<&str>,
+    body: Option<Value>,
+) -> StatusCode {
+    let mut request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::CONTENT_TYPE, "application/json");
+    if let Some(api_key) = api_key {
+        request = request.header(header::AUTHORIZATION, format!("Bearer {}", api_key));
+    }
+    let request = request
+        .body(body.map_or_else(Body::empty, |b| Body::from(b.to_string())))
+        .unwrap();
+    router.clone().oneshot(request).await.unwrap().status()
+}
+
+async fn get_json(router: &Router, uri: &str, api_key: &str) -> Value {
+    let request = Request::get(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {}", api_key))
+        .body(Body::empty())
+        .unwrap();
+    let response = router.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+async fn serve_grpc(server: &RuvectorServer) -> Channel {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(
+        Server::builder()
+            .add_routes(server.grpc_routes())
+            .serve_with_incoming(TcpListenerStream::new(listener)),
+    );
+    Channel::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap()
+}
+
+// Interceptors must return tonic's `Status` as their error
+#[allow(clippy::result_large_err)]
+fn with_key(
+    api_key: Option<&'static str>,
+) -> impl FnMut(tonic::Request<()>) -> Result<tonic::Request<()>, Status> {
+    move |mut request| {
+        if let Some(api_key) = api_key {
+            let value = MetadataValue::try_from(format!("Bearer {}", api_key)).unwrap();
+            request.metadata_mut().insert("authorization", value);
+        }
+        Ok(request)
+    }
+}
+
+fn upsert_body() -> Value {
+    json!({"points": [{"id": "p1", "vector": [1.0, 0.0, 0.0]}]})
+}
+
+fn search_body() -> Value {
+    json!({"vector": [1.0, 0.0, 0.0], "k": 1})
+}
+
+#[tokio::test]
+async fn test_rest_scopes_and_collections() {
+    let dir = tempfile::tempdir().unwrap();
+    let router = server(dir.path()).router();
+
+    // Unauthenticated
+    assert_eq!(
+        call(&router, Method::GET, "/health", None, None).await,
+        StatusCode::OK
+    );
+    let response = router
+        .clone()
+        .oneshot(Request::get("/collections").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(response.headers()[header::WWW_AUTHENTICATE], "Bearer");
+    assert_eq!(
+        call(&router, Method::GET, "/collections", Some("wrong"), None).await,
+        StatusCode::UNAUTHORIZED
+    );
+
+    // Collections are created by unrestricted admins only
+    let create = |name: &str| Some(json!({"name": name, "dimension": 3}));
+    for api_key in ["read-key", "docs-rw-key", "docs-admin-key"] {
+        assert_eq!(
+            call(
+                &router,
+                Method::POST,
+                "/collections",
+                Some(api_key),
+                create("docs")
+            )
+            .await,
+            StatusCode::FORBIDDEN,
+            "{}",
+            api_key
+        );
+    }
+    for name in ["docs", "other"] {
+        assert_eq!(
+            call(
+                &router,
+                Method::POST,
+                "/collections",
+                Some("admin-key"),
+                create(name)
+            )
+            .await,
+            StatusCode::CREATED
+        );
+    }
+    assert_eq!(
+        call(
+            &router,
+            Method::PUT,
+            "/aliases/current",
+            Some("admin-key"),
+            Some(json!({"collection": "docs"}))
+        )
+        .await,
+        StatusCode::CREATED
+    );
+
+    // Writes need read_write on the collection, directly or through an alias
+    assert_eq!(
+        call(
+            &router,
+            Method::PUT,
+            "/collections/docs/points",
+            Some("read-key"),
+            Some(upsert_body())
+        )
+        .await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        call(
+            &router,
+            Method::PUT,
+            "/collections/current/points",
+            Some("docs-rw-key"),
+            Some(upsert_body())
+        )
+        .await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        call(
+            &router,
+            Method::PUT,
+            "/collections/other/points",
+            Some("docs-rw-key"),
+            Some(upsert_body())
+        )
+        .await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        call(
+            &router,
+            Method::DELETE,
+            "/collections/docs/points/p1",
+            Some("read-key"),
+            None
+        )
+        .await,
+        StatusCode::FORBIDDEN
+    );
+
+    // Queries sent as POST only need read
+    assert_eq!(
+        call(
+            &router,
+            Method::POST,
+            "/collections/docs/points/search",
+            Some("read-key"),
+            Some(search_body())
+        )
+        .await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        call(
+            &router,
+            Method::POST,
+            "/collections/other/points/count",
+            Some("docs-rw-key"),
+            None
+        )
+        .await,
+        StatusCode::FORBIDDEN
+    );
+
+    // Metrics expose every collection, so restricted keys can't scrape them
+    assert_eq!(
+        call(&router, Method::GET, "/metrics", Some("docs-rw-key"), None).await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        call(&router, Method::GET, "/metrics", Some("read-key"), None).await,
+        StatusCode::OK
+    );
+
+    // The api-key header works too
+    let request = Request::get("/collections/docs")
+        .header("api-key", "docs-rw-key")
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(
+        router.clone().oneshot(request).await.unwrap().status(),
+        StatusCode::OK
+    );
+
+    // Collection names are matched after percent-decoding, as routed
+    assert_eq!(
+        call(
+            &router,
+            Method::PUT,
+            "/collections/d%6Fcs/points",
+            Some("docs-rw-key"),
+            Some(upsert_body())
+        )
+        .await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        call(
+            &router,
+            Method::PUT,
+            "/collections/oth%65r/points",
+            Some("docs-rw-key"),
+            Some(upsert_body())
+        )
+        .await,
+        StatusCode::FORBIDDEN
+    );
+
+    // Listings only show what the key may access
+    assert_eq!(
+        call(
+            &router,
+            Method::PUT,
+            "/aliases/legacy",
+            Some("admin-key"),
+            Some(json!({"collection": "other"}))
+        )
+        .await,
+        StatusCode::CREATED
+    );
+    let listed = get_json(&router, "/collections", "docs-rw-key").await;
+    assert_eq!(listed["collections"], json!(["docs"]));
+    let listed = get_json(&router, "/collections", "read-key").await;
+    assert_eq!(listed["collections"], json!(["docs", "other"]));
+    let listed = get_json(&router, "/aliases", "docs-rw-key").await;
+    assert_eq!(
+        listed["aliases"],
+        json!([{"alias": "current", "collection": "docs"}])
+    );
+    let listed = get_json(&router, "/aliases", "read-key").await;
+    assert_eq!(listed["aliases"].as_array().unwrap().len(), 2);
+    assert_eq!(
+        call(
+            &router,
+            Method::DELETE,
+            "/aliases/legacy",
+            Some("admin-key"),
+            None
+        )
+        .await,
+        StatusCode::NO_CONTENT
+    );
+
+    // Collection deletion: admin scope, limited to the key's collections
+    assert_eq!(
+        call(
+            &router,
+            Method::DELETE,
+            "/collections/other",
+            Some("docs-rw-key"),
+            None
+        )
+        .await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        call(
+            &router,
+            Method::DELETE,
+            "/collections/other",
+            Some("docs-admin-key"),
+            None
+        )
+        .await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        call(
+            &router,
+            Method::DELETE,
+            "/aliases/current",
+            Some("docs-admin-key"),
+            None
+        )
+        .await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        call(
+            &router,
+            Method::DELETE,
+            "/collections/other",
+            Some("admin-key"),
+            None
+        )
+        .await,
+        StatusCode::NO_CONTENT
+    );
+}
+
+#[tokio::test]
+async fn test_grpc_scopes_and_collections() {
+    let dir = tempfile::tempdir().unwrap();
+    let server = server(dir.path());
+    let channel = serve_grpc(&server).await;
+
+    let mut collections =
+        CollectionsClient::with_interceptor(channel.clone(), with_key(Some("admin-key")));
+    for name in ["docs", "other"] {
+        collections
+            .create(CreateCollectionRequest {
+                name: name.to_string(),
+                dimension: 3,
+                metric: 0,
+            })
+            .await
+            .unwrap();
+    }
+
+    let upsert = |collection: &str| UpsertPointsRequest {
+        collection: collection.to_string(),
+        points: vec![Point {
+            id: "p1".to_string(),
+            vector: vec![1.0, 0.0, 0.0],
+            payload: None,
+        }],
+    };
+    let search = |collection: &str| SearchRequest {
+        collection: collection.to_string(),
+        vector: vec![1.0, 0.0, 0.0],
+        k: 1,
+        ..Default::default()
+    };
+
+    let mut anonymous = PointsClient::with_interceptor(channel.clone(), with_key(None));
+    let status = anonymous.search(search("docs")).await.unwrap_err();
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    let mut reader = PointsClient::with_interceptor(channel.clone(), with_key(Some("read-key")));
+    let status = reader.upsert(upsert("docs")).await.unwrap_err();
+    assert_eq!(status.code(), Code::PermissionDenied);
+
+    let mut writer = PointsClient::with_interceptor(channel.clone(), with_key(Some("docs-rw-key")));
+    writer.upsert(upsert("docs")).await.unwrap();
+    let status = writer.upsert(upsert("other")).await.unwrap_err();
+    assert_eq!(status.code(), Code::PermissionDenied);
+    let status = writer.search(search("other")).await.unwrap_err();
+    assert_eq!(status.code(), Code::PermissionDenied);
+
+    let results = reader
+        .search(search("docs"))
+        .await
+        .unwrap()
+        .into_inner()
+        .results;
+    assert_eq!(results[0].id, "p1");
+
+    let mut restricted =
+        CollectionsClient::with_interceptor(channel, with_key(Some("docs-rw-key")));
+    let listed = restricted
+        .list(ListCollectionsRequest {})
+        .await
+        .unwrap()
+        .into_inner()
+        .collections;
+    assert_eq!(listed, ["docs"]);
+    let status = restricted
+        .create(CreateCollectionRequest {
+            name: "new".to_string(),
+            dimension: 3,
+            metric: 0,
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::PermissionDenied);
+}
+
+#[tokio::test]
+async fn test_collection_delete_rule_matches_across_apis() {
+    let dir = tempfile::tempdir().unwrap();
+    let server = server(dir.path());
+    let router = server.router();
+    let channel = serve_grpc(&server).await;
+    let mut admin =
+        CollectionsClient::with_interceptor(channel.clone(), with_key(Some("admin-key")));
+    let mut restricted =
+        CollectionsClient::with_interceptor(channel, with_key(Some("docs-admin-key")));
+    let create = |name: &str| CreateCollectionRequest {
+        name: name.to_string(),
+        dimension: 3,
+        metric: 0,
+    };
+    let delete = |name: &str| DeleteCollectionRequest {
+        name: name.to_string(),
+    };
+
+    for name in ["docs", "other"] {
+        admin.create(create(name)).await.unwrap();
+    }
+
+    // An admin key scoped to docs deletes docs, and only docs, over both APIs
+    assert_eq!(
+        call(
+            &router,
+            Method::DELETE,
+            "/collections/other",
+            Some("docs-admin-key"),
+            None
+        )
+        .await,
+        StatusCode::FORBIDDEN
+    );
+    let status = restricted.delete(delete("other")).await.unwrap_err();
+    assert_eq!(status.code(), Code::PermissionDenied);
+
+    assert_eq!(
+        call(
+            &router,
+            Method::DELETE,
+            "/collections/docs",
+            Some("docs-admin-key"),
+            None
+        )
+        .await,
+        StatusCode::NO_CONTENT
+    );
+    admin.create(create("docs")).await.unwrap();
+    restricted.delete(delete("docs")).await.unwrap();
+
+    let listed = get_json(&router, "/collections", "admin-key").await;
+    assert_eq!(listed["collections"], json!(["other"]));
+}
@@ -0,0 +1,252 @@
+//! gRPC API tests against an in-process server
+
+use prost::Message;
+use ruvector_server::grpc::{
+    self,
+    proto::{
+        collections_client::CollectionsClient, points_client::PointsClient, BulkUpsertResponse,
+        CreateCollectionRequest, DeleteCollectionRequest, DeletePointsRequest, DistanceMetric,
+        GetCollectionRequest, GetPointsRequest, Point, SearchRequest, UpsertPointsRequest,
+    },
+};
+use ruvector_server::AppState;
+use std::collections::{BTreeMap, HashMap};
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::transport::{Channel, Server};
+use tonic::Code;
+
+const COLLECTION: &str = "points";
+
+async fn serve(state: AppState) -> Channel {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(
+        Server::builder()
+            .add_routes(grpc::routes(state))
+            .serve_with_incoming(TcpListenerStream::new(listener)),
+    );
+
+    Channel::from_shared(format!("http://{}", addr))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap()
+}
+
+fn point(id: &str, vector: Vec<f32>, group: &str) -> Point {
+    let payload = prost_types::Struct {
+        fields: BTreeMap::from([(
+            "group".to_string(),
+            prost_types::Value {
+                kind: Some(prost_types::value::Kind::StringValue(group.to_string())),
+            },
+        )]),
+    };
+    Point {
+        id: id.to_string(),
+        vector,
+        payload: Some(payload),
+    }
+}
+
+fn string_value(s: &str) -> prost_types::Value {
+    prost_types::Value {
+        kind: Some(prost_types::value::Kind::StringValue(s.to_string())),
+    }
+}
+
+#[tokio::test]
+async fn test_grpc_collections_and_points() {
+    let dir = tempfile::tempdir().unwrap();
+    let state = AppState::open(dir.path()).unwrap();
+    let channel = serve(state.clone()).await;
+    let mut collections = CollectionsClient::new(channel.clone());
+    let mut points = PointsClient::new(channel);
+
+    let info = collections
+        .create(CreateCollectionRequest {
+            name: COLLECTION.to_string(),
+            dimension: 3,
+            metric: DistanceMetric::Euclidean as i32,
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(info.dimension, 3);
+    assert_eq!(info.metric, DistanceMetric::Euclidean as i32);
+
+    // Shared with the REST routes
+    assert!(state.contains_collection(COLLECTION));
+    let status = collections
+        .create(CreateCollectionRequest {
+            name: COLLECTION.to_string(),
+            dimension: 3,
+            metric: DistanceMetric::Cosine as i32,
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::AlreadyExists);
+
+    let ids = points
+        .upsert(UpsertPointsRequest {
+            collection: COLLECTION.to_string(),
+            points: vec![
+                point("a", vec![0.0, 0.0, 0.0], "x"),
+                point("b", vec![1.0, 0.0, 0.0], "y"),
+            ],
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .ids;
+    assert_eq!(ids, ["a", "b"]);
+
+    // Streaming bulk upsert, one message per batch
+    let batches: Vec<UpsertPointsRequest> = (0..4)
+        .map(|batch| UpsertPointsRequest {
+            collection: COLLECTION.to_string(),
+            points: (0..25)
+                .map(|i| {
+                    let n = batch * 25 + i;
+                    point(&format!("bulk{}", n), vec![10.0 + n as f32, 5.0, 5.0], "z")
+                })
+                .collect(),
+        })
+        .collect();
+    let bulk = points
+        .bulk_upsert(tokio_stream::iter(batches))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(bulk.upserted, 100);
+    assert_eq!(bulk.batches, 4);
+    assert_eq!(bulk.last_id, "bulk99");
+
+    let info = collections
+        .get(GetCollectionRequest {
+            name: COLLECTION.to_string(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(info.points, 102);
+
+    let results = points
+        .search(SearchRequest {
+            collection: COLLECTION.to_string(),
+            vector: vec![0.9, 0.0, 0.0],
+            k: 2,
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .results;
+    let ids: Vec<_> = results.iter().map(|r| r.id.as_str()).collect();
+    assert_eq!(ids, ["b", "a"]);
+
+    let results = points
+        .search(SearchRequest {
+            collection: COLLECTION.to_string(),
+            vector: vec![0.9, 0.0, 0.0],
+            k: 5,
+            filter: HashMap::from([("group".to_string(), string_value("x"))]),
+            exact_filter_fallback: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .results;
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].id, "a");
+
+    let fetched = points
+        .get(GetPointsRequest {
+            collection: COLLECTION.to_string(),
+            ids: vec!["bulk42".to_string(), "missing".to_string()],
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .points;
+    assert_eq!(fetched.len(), 1);
+    assert_eq!(fetched[0].vector, [52.0, 5.0, 5.0]);
+    assert_eq!(
+        fetched[0].payload.as_ref().unwrap().fields["group"],
+        string_value("z")
+    );
+
+    let deleted = points
+        .delete(DeletePointsRequest {
+            collection: COLLECTION.to_string(),
+            ids: vec!["a".to_string(), "missing".to_string()],
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .deleted;
+    assert_eq!(deleted, 1);
+    assert_eq!(
+        state.get_collection(COLLECTION).unwrap().len().unwrap(),
+        101
+    );
+
+    let status = points
+        .upsert(UpsertPointsRequest {
+            collection: COLLECTION.to_string(),
+            points: vec![point("bad", vec![1.0], "x")],
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+
+    // A failing batch reports what was committed before it
+    let batches = vec![
+        UpsertPointsRequest {
+            collection: COLLECTION.to_string(),
+            points: vec![
+                point("c", vec![2.0, 0.0, 0.0], "x"),
+                point("d", vec![3.0, 0.0, 0.0], "x"),
+            ],
+        },
+        UpsertPointsRequest {
+            collection: COLLECTION.to_string(),
+            points: vec![point("bad", vec![1.0], "x")],
+        },
+        UpsertPointsRequest {
+            collection: COLLECTION.to_string(),
+            points: vec![point("e", vec![4.0, 0.0, 0.0], "x")],
+        },
+    ];
+    let status = points
+        .bulk_upsert(tokio_stream::iter(batches))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+    let progress = BulkUpsertResponse::decode(status.details()).unwrap();
+    assert_eq!(progress.upserted, 2);
+    assert_eq!(progress.batches, 1);
+    assert_eq!(progress.last_id, "d");
+    assert_eq!(
+        state.get_collection(COLLECTION).unwrap().len().unwrap(),
+        103
+    );
+
+    collections
+        .delete(DeleteCollectionRequest {
+            name: COLLECTION.to_string(),
+        })
+        .await
+        .unwrap();
+    let status = points
+        .search(SearchRequest {
+            collection: COLLECTION.to_string(),
+            vector: vec![0.0, 0.0, 0.0],
+            k: 1,
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+}
@@ -0,0 +1,205 @@
+//! Prometheus metrics, health and readiness endpoint tests
+
+use axum::{
+    body::Body,
+    http::{header, Method, Request, StatusCode},
+    Router,
+};
+use ruvector_server::{Config, RuvectorServer};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+async fn call(
+    router: &Router,
+    method: Method,
+    uri: &str,
+    body: Option<Value>,
+) -> (StatusCode, Option<String>, String) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(body.map_or_else(Body::empty, |b| Body::from(b.to_string())))
+        .unwrap();
+    let response = router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .map(|v| v.to_str().unwrap().to_string());
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (
+        status,
+        content_type,
+        String::from_utf8(bytes.to_vec()).unwrap(),
+    )
+}
+
+/// Value of the sample `series` in a Prometheus text exposition
+fn sample(metrics: &str, series: &str) -> Option<f64> {
+    metrics
+        .lines()
+        .find_map(|line| line.strip_prefix(series)?.strip_prefix(' '))
+        .map(|value| value.parse().unwrap())
+}
+
+#[tokio::test]
+async fn test_metrics_health_and_readiness() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        data_dir: dir.path().to_path_buf(),
+        ..Config::default()
+    };
+    let router = RuvectorServer::with_config(config).unwrap().router();
+
+    let (status, _, _) = call(
+        &router,
+        Method::POST,
+        "/collections",
+        Some(json!({"name": "metrics_docs", "dimension": 3})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    call(
+        &router,
+        Method::PUT,
+        "/aliases/metrics_live",
+        Some(json!({"collection": "metrics_docs"})),
+    )
+    .await;
+
+    let points: Vec<Value> = (0..10)
+        .map(|i| json!({"id": format!("p{}", i), "vector": [i as f32, 1.0, 0.0]}))
+        .collect();
+    let (status, _, _) = call(
+        &router,
+        Method::PUT,
+        "/collections/metrics_docs/points",
+        Some(json!({ "points": points })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _, _) = call(
+        &router,
+        Method::DELETE,
+        "/collections/metrics_docs/points/p9",
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    // Searches through the alias count against the collection
+    for _ in 0..2 {
+        let (status, _, _) = call(
+            &router,
+            Method::POST,
+            "/collections/metrics_live/points/search",
+            Some(json!({"vector": [1.0, 1.0, 0.0], "k": 1})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let (status, _, _) = call(
+        &router,
+        Method::POST,
+        "/collections/metrics_docs/points/search",
+        Some(json!({"vector": [1.0], "k": 1})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, content_type, metrics) = call(&router, Method::GET, "/metrics", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(content_type.unwrap().starts_with("text/plain"));
+
+    let collection = r#"{collection="metrics_docs"}"#;
+    assert_eq!(
+        sample(&metrics, &format!("ruvector_vectors_total{}", collection)),
+        Some(9.0)
+    );
+    assert_eq!(
+        sample(
+            &metrics,
+            &format!("ruvector_hnsw_graph_nodes{}", collection)
+        ),
+        Some(10.0)
+    );
+    assert_eq!(
+        sample(&metrics, &format!("ruvector_hnsw_tombstones{}", collection)),
+        Some(1.0)
+    );
+    assert!(sample(&metrics, &format!("ruvector_hnsw_ef_search{}", collection)).unwrap() > 0.0);
+    assert_eq!(
+        sample(
+            &metrics,
+            r#"ruvector_search_requests_total{collection="metrics_docs",status="success"}"#
+        ),
+        Some(2.0)
+    );
+    assert_eq!(
+        sample(
+            &metrics,
+            r#"ruvector_search_requests_total{collection="metrics_docs",status="error"}"#
+        ),
+        Some(1.0)
+    );
+    assert_eq!(
+        sample(
+            &metrics,
+            r#"ruvector_vectors_inserted_total{collection="metrics_docs"}"#
+        ),
+        Some(10.0)
+    );
+    assert_eq!(
+        sample(
+            &metrics,
+            r#"ruvector_delete_requests_total{collection="metrics_docs",status="success"}"#
+        ),
+        Some(1.0)
+    );
+
+    // Routes are labelled by pattern, not by concrete path
+    assert_eq!(
+        sample(
+            &metrics,
+            r#"ruvector_http_requests_total{method="POST",route="/collections/:name/points/search",status="200"}"#
+        ),
+        Some(2.0)
+    );
+    assert_eq!(
+        sample(
+            &metrics,
+            r#"ruvector_http_request_latency_seconds_count{method="PUT",route="/collections/:name/points"}"#
+        ),
+        Some(1.0)
+    );
+    assert!(!metrics.contains("metrics_live"));
+
+    let (status, _, body) = call(&router, Method::GET, "/ready", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let ready: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(ready["collections_count"], 1);
+    assert_eq!(ready["total_vectors"], 9);
+    assert_eq!(ready["details"]["metrics_docs"]["vectors_count"], 9);
+
+    let (status, _, body) = call(&router, Method::GET, "/health", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let health: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(health["status"], "healthy");
+    assert_eq!(health["version"], env!("CARGO_PKG_VERSION"));
+
+    // Deleted collections drop out of the gauges
+    call(&router, Method::DELETE, "/aliases/metrics_live", None).await;
+    let (status, _, _) = call(&router, Method::DELETE, "/collections/metrics_docs", None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, _, metrics) = call(&router, Method::GET, "/metrics", None).await;
+    assert_eq!(sample(&metrics, "ruvector_collections_total"), Some(0.0));
+    assert_eq!(
+        sample(
+            &metrics,
+            &format!("ruvector_hnsw_graph_nodes{}", collection)
+        ),
+        None
+    );
+}
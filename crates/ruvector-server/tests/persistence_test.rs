@@ -0,0 +1,228 @@
+//! Restart tests for the server's persistent collection state
+//!
+//! The crash test re-runs this test binary as a child process that writes
+//! through the REST API and is then killed with SIGKILL, so nothing runs on
+//! shutdown. A fresh server over the same data directory must see every
+//! acknowledged write.
+
+use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode},
+    Router,
+};
+use ruvector_server::{Config, RuvectorServer};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use tower::ServiceExt;
+
+const CHILD_DATA_DIR: &str = "RUVECTOR_SERVER_CRASH_DATA_DIR";
+
+fn open_router(data_dir: &Path) -> Router {
+    let config = Config {
+        data_dir: data_dir.to_path_buf(),
+        ..Config::default()
+    };
+    RuvectorServer::with_config(config).unwrap().router()
+}
+
+async fn call(
+    router: &Router,
+    method: Method,
+    uri: &str,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(body.map_or_else(Body::empty, |b| Body::from(b.to_string())))
+        .unwrap();
+    let response = router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+    (status, body)
+}
+
+fn points(prefix: &str, offset: f32) -> Value {
+    let points: Vec<Value> = (0..20)
+        .map(|i| {
+            json!({
+                "id": format!("{}{}", prefix, i),
+                "vector": [offset + i as f32, 0.0, 0.0],
+                "metadata": {"n": i},
+            })
+        })
+        .collect();
+    json!({ "points": points })
+}
+
+/// Writes through the REST API, then waits to be killed
+///
+/// Only does anything when spawned by `test_state_survives_kill_9`.
+#[tokio::test]
+async fn crash_child() {
+    let Ok(data_dir) = std::env::var(CHILD_DATA_DIR) else {
+        return;
+    };
+    let router = open_router(Path::new(&data_dir));
+
+    for name in ["docs_v1", "docs_v2"] {
+        let (status, _) = call(
+            &router,
+            Method::POST,
+            "/collections",
+            Some(json!({"name": name, "dimension": 3, "metric": "Euclidean"})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+    }
+    let (status, _) = call(
+        &router,
+        Method::PUT,
+        "/collections/docs_v1/points",
+        Some(points("a", 0.0)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = call(
+        &router,
+        Method::PUT,
+        "/collections/docs_v2/points",
+        Some(points("b", 100.0)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = call(
+        &router,
+        Method::DELETE,
+        "/collections/docs_v2/points/b3",
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = call(
+        &router,
+        Method::POST,
+        "/collections/docs_v2/points/b4/payload",
+        Some(json!({"payload": {"tag": "kept"}})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (status, _) = call(
+        &router,
+        Method::PUT,
+        "/aliases/current",
+        Some(json!({"collection": "docs_v1"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _) = call(
+        &router,
+        Method::POST,
+        "/aliases/current/switch",
+        Some(json!({"collection": "docs_v2"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = call(
+        &router,
+        Method::PUT,
+        "/aliases/stale",
+        Some(json!({"collection": "docs_v1"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _) = call(&router, Method::DELETE, "/aliases/stale", None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    println!("READY");
+    std::io::stdout().flush().unwrap();
+    loop {
+        tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+    }
+}
+
+#[tokio::test]
+async fn test_state_survives_kill_9() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut child = Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", "crash_child", "--nocapture", "--test-threads=1"])
+        .env(CHILD_DATA_DIR, dir.path())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let stdout = BufReader::new(child.stdout.take().unwrap());
+    // libtest prints "test crash_child ... " ahead of the marker
+    let ready = stdout
+        .lines()
+        .map_while(|line| line.ok())
+        .any(|line| line.trim_end().ends_with("READY"));
+    // SIGKILL on Unix
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert!(ready, "child exited before finishing its writes");
+
+    let router = open_router(dir.path());
+
+    let (_, body) = call(&router, Method::GET, "/collections", None).await;
+    assert_eq!(body["collections"], json!(["docs_v1", "docs_v2"]));
+    let (_, body) = call(&router, Method::GET, "/collections/docs_v2", None).await;
+    assert_eq!(body["dimension"], 3);
+    assert_eq!(body["metric"], "Euclidean");
+
+    let (_, body) = call(&router, Method::GET, "/aliases", None).await;
+    assert_eq!(
+        body["aliases"],
+        json!([{"alias": "current", "collection": "docs_v2"}])
+    );
+
+    // Reads through the alias reach docs_v2
+    let (_, body) = call(
+        &router,
+        Method::POST,
+        "/collections/current/points/count",
+        None,
+    )
+    .await;
+    assert_eq!(body["count"], 19);
+    let (status, _) = call(&router, Method::GET, "/collections/current/points/b3", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (_, body) = call(&router, Method::GET, "/collections/current/points/b4", None).await;
+    assert_eq!(body["metadata"], json!({"n": 4, "tag": "kept"}));
+
+    let (_, body) = call(
+        &router,
+        Method::POST,
+        "/collections/current/points/search",
+        Some(json!({"vector": [107.2, 0.0, 0.0], "k": 1})),
+    )
+    .await;
+    assert_eq!(body["results"][0]["id"], "b7");
+    let (_, body) = call(
+        &router,
+        Method::POST,
+        "/collections/docs_v1/points/search",
+        Some(json!({"vector": [7.2, 0.0, 0.0], "k": 1})),
+    )
+    .await;
+    assert_eq!(body["results"][0]["id"], "a7");
+
+    // Collections with aliases can't be deleted until the alias goes
+    let (status, _) = call(&router, Method::DELETE, "/collections/docs_v2", None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = call(&router, Method::DELETE, "/collections/docs_v1", None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    drop(router);
+
+    let router = open_router(dir.path());
+    let (_, body) = call(&router, Method::GET, "/collections", None).await;
+    assert_eq!(body["collections"], json!(["docs_v2"]));
+}
@@ -9,6 +9,9 @@ pub enum SnapshotError {
     #[error("Snapshot not found: {0}")]
     SnapshotNotFound(String),
 
+    #[error("Chunk not found: {0}")]
+    ChunkNotFound(String),
+
     #[error("Corrupted snapshot: {0}")]
     CorruptedSnapshot(String),
 
@@ -2,16 +2,31 @@
 //!
 //! This crate provides backup and restore capabilities for vector collections,
 //! including compression, checksums, and multiple storage backends.
+//!
+//! Large collections are snapshotted with [`SnapshotManager::begin_snapshot`],
+//! which streams records into content-addressed chunks, and
+//! [`SnapshotManager::begin_incremental_snapshot`], which stores only the
+//! changes since a parent snapshot. [`SnapshotManager::open_snapshot`]
+//! restores a base plus its deltas one chunk at a time.
 
 mod error;
 mod manager;
+#[cfg(feature = "s3")]
+mod s3;
 mod snapshot;
 mod storage;
+mod stream;
 
 pub use error::{Result, SnapshotError};
-pub use manager::SnapshotManager;
-pub use snapshot::{Snapshot, SnapshotData, SnapshotMetadata, VectorRecord};
+pub use manager::{RetentionPolicy, RetentionReport, SnapshotManager};
+#[cfg(feature = "s3")]
+pub use s3::{S3Config, S3Storage, DEFAULT_MULTIPART_THRESHOLD, DEFAULT_PART_SIZE};
+pub use snapshot::{
+    ChunkRef, CollectionConfig, DistanceMetric, HnswConfig, Snapshot, SnapshotData,
+    SnapshotManifest, SnapshotMetadata, VectorRecord,
+};
 pub use storage::{LocalStorage, SnapshotStorage};
+pub use stream::{SnapshotReader, SnapshotWriter, DEFAULT_CHUNK_SIZE};
 
 #[cfg(test)]
 mod tests {
@@ -1,16 +1,68 @@
+use std::collections::HashSet;
+
+use chrono::{Duration, Utc};
+use tokio::sync::RwLock;
+
 use crate::error::{Result, SnapshotError};
-use crate::snapshot::{Snapshot, SnapshotData};
+use crate::snapshot::{
+    CollectionConfig, Snapshot, SnapshotData, SnapshotManifest, SnapshotMetadata,
+};
 use crate::storage::SnapshotStorage;
+use crate::stream::{SnapshotReader, SnapshotWriter, DEFAULT_CHUNK_SIZE};
+
+/// Which chunked snapshots of a collection to keep
+#[derive(Debug, Clone)]
+pub struct RetentionPolicy {
+    /// Number of most recent snapshots always kept
+    pub keep_last: usize,
+
+    /// Also keep every snapshot younger than this
+    pub keep_within: Option<Duration>,
+}
+
+impl RetentionPolicy {
+    /// Keep only the `count` most recent snapshots
+    pub fn keep_last(count: usize) -> Self {
+        Self {
+            keep_last: count,
+            keep_within: None,
+        }
+    }
+}
+
+/// Outcome of applying a [`RetentionPolicy`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RetentionReport {
+    /// IDs of the snapshots deleted
+    pub snapshots_deleted: Vec<String>,
+
+    /// Number of chunks no snapshot referenced any more
+    pub chunks_deleted: usize,
+}
 
 /// Manages snapshot operations for collections
 pub struct SnapshotManager {
     storage: Box<dyn SnapshotStorage>,
+    chunk_size: usize,
+    /// Held shared by chunked snapshot writers and readers, and exclusively
+    /// by garbage collection
+    gc_lock: RwLock<()>,
 }
 
 impl SnapshotManager {
     /// Create a new snapshot manager with the given storage backend
     pub fn new(storage: Box<dyn SnapshotStorage>) -> Self {
-        Self { storage }
+        Self {
+            storage,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            gc_lock: RwLock::new(()),
+        }
+    }
+
+    /// Set the target size of chunked snapshot chunks in bytes (uncompressed)
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size;
+        self
     }
 
     /// Create a snapshot of a collection
@@ -79,11 +131,7 @@ impl SnapshotManager {
         &self,
         collection_name: &str,
     ) -> Result<Vec<Snapshot>> {
-        let all_snapshots = self.storage.list().await?;
-        Ok(all_snapshots
-            .into_iter()
-            .filter(|s| s.collection_name == collection_name)
-            .collect())
+        self.storage.list_collection(collection_name).await
     }
 
     /// Delete a snapshot by ID
@@ -155,6 +203,230 @@ impl SnapshotManager {
         let snapshots = self.list_snapshots_for_collection(collection_name).await?;
         Ok(snapshots.iter().map(|s| s.size_bytes).sum())
     }
+
+    /// Start a full chunked snapshot of a collection
+    ///
+    /// Records are streamed into the returned writer, so the collection never
+    /// has to be held in memory. Garbage collection waits until the writer
+    /// is finished or dropped.
+    ///
+    /// # Arguments
+    /// * `collection_name` - Name of the collection
+    /// * `config` - The collection configuration
+    pub async fn begin_snapshot(
+        &self,
+        collection_name: impl Into<String>,
+        config: CollectionConfig,
+    ) -> SnapshotWriter<'_> {
+        let manifest = SnapshotManifest {
+            id: uuid::Uuid::new_v4().to_string(),
+            collection_name: collection_name.into(),
+            created_at: Utc::now(),
+            parent: None,
+            config,
+            chunks: Vec::new(),
+            deleted: Vec::new(),
+        };
+        let gc_guard = self.gc_lock.read().await;
+        SnapshotWriter::new(self.storage.as_ref(), gc_guard, manifest, self.chunk_size)
+    }
+
+    /// Start an incremental snapshot holding only the changes since `parent_id`
+    ///
+    /// The collection name and configuration are taken from the parent.
+    ///
+    /// # Arguments
+    /// * `parent_id` - The chunked snapshot the changes are relative to
+    pub async fn begin_incremental_snapshot(&self, parent_id: &str) -> Result<SnapshotWriter<'_>> {
+        let gc_guard = self.gc_lock.read().await;
+        let parent = self.storage.load_manifest(parent_id).await?;
+
+        let manifest = SnapshotManifest {
+            id: uuid::Uuid::new_v4().to_string(),
+            collection_name: parent.collection_name,
+            created_at: Utc::now(),
+            parent: Some(parent.id),
+            config: parent.config,
+            chunks: Vec::new(),
+            deleted: Vec::new(),
+        };
+        Ok(SnapshotWriter::new(
+            self.storage.as_ref(),
+            gc_guard,
+            manifest,
+            self.chunk_size,
+        ))
+    }
+
+    /// Open a chunked snapshot for streaming restore
+    ///
+    /// Incremental snapshots are restored together with their chain of
+    /// parents back to the full base snapshot.
+    ///
+    /// # Arguments
+    /// * `id` - The unique snapshot identifier
+    pub async fn open_snapshot(&self, id: &str) -> Result<SnapshotReader<'_>> {
+        if id.is_empty() {
+            return Err(SnapshotError::storage("Snapshot ID cannot be empty"));
+        }
+
+        let gc_guard = self.gc_lock.read().await;
+        SnapshotReader::open(self.storage.as_ref(), gc_guard, id).await
+    }
+
+    /// Restore a chunked snapshot, chaining its base and deltas, into memory
+    ///
+    /// Prefer [`open_snapshot`](Self::open_snapshot) for large collections.
+    ///
+    /// # Arguments
+    /// * `id` - The unique snapshot identifier
+    ///
+    /// # Returns
+    /// * `SnapshotData` - The collection state as of the snapshot
+    pub async fn restore_snapshot_chain(&self, id: &str) -> Result<SnapshotData> {
+        let mut reader = self.open_snapshot(id).await?;
+        let mut vectors = Vec::new();
+        while let Some(batch) = reader.next_batch().await? {
+            vectors.extend(batch);
+        }
+
+        let manifest = reader.manifest();
+        Ok(SnapshotData {
+            metadata: SnapshotMetadata {
+                id: manifest.id.clone(),
+                collection_name: manifest.collection_name.clone(),
+                created_at: manifest.created_at.to_rfc3339(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+            },
+            config: manifest.config.clone(),
+            vectors,
+        })
+    }
+
+    /// List the chunked snapshots of a collection
+    ///
+    /// # Returns
+    /// * `Vec<SnapshotManifest>` - Manifests sorted by creation date (newest first)
+    pub async fn list_chunked_snapshots(
+        &self,
+        collection_name: &str,
+    ) -> Result<Vec<SnapshotManifest>> {
+        self.storage
+            .list_collection_manifests(collection_name)
+            .await
+    }
+
+    /// Delete a chunked snapshot and reclaim the chunks nothing else uses
+    ///
+    /// Snapshots that are the parent of another snapshot cannot be deleted.
+    ///
+    /// # Arguments
+    /// * `id` - The unique snapshot identifier
+    ///
+    /// # Returns
+    /// * `usize` - Number of chunks deleted
+    pub async fn delete_chunked_snapshot(&self, id: &str) -> Result<usize> {
+        let _gc_guard = self.gc_lock.write().await;
+
+        let manifests = self.storage.list_manifests().await?;
+        if let Some(child) = manifests.iter().find(|m| m.parent.as_deref() == Some(id)) {
+            return Err(SnapshotError::storage(format!(
+                "Snapshot {} is the parent of snapshot {}",
+                id, child.id
+            )));
+        }
+
+        self.storage.delete_manifest(id).await?;
+        self.sweep_chunks().await
+    }
+
+    /// Delete the chunked snapshots of a collection the policy doesn't keep,
+    /// then reclaim unreferenced chunks
+    ///
+    /// Snapshots kept by the policy also keep their whole parent chain, so
+    /// every kept snapshot can still be restored. Waits for in-progress
+    /// chunked snapshot writers and readers to finish.
+    ///
+    /// # Arguments
+    /// * `collection_name` - Name of the collection
+    /// * `policy` - Which snapshots to keep
+    pub async fn apply_retention(
+        &self,
+        collection_name: &str,
+        policy: &RetentionPolicy,
+    ) -> Result<RetentionReport> {
+        let _gc_guard = self.gc_lock.write().await;
+
+        let manifests = self.list_chunked_snapshots(collection_name).await?;
+        let now = Utc::now();
+        let mut kept: HashSet<&str> = HashSet::new();
+        for (idx, manifest) in manifests.iter().enumerate() {
+            let recent = idx < policy.keep_last;
+            let young = policy
+                .keep_within
+                .is_some_and(|window| now - manifest.created_at < window);
+            if !(recent || young) {
+                continue;
+            }
+
+            // Keep the chain needed to restore it
+            let mut next = Some(manifest.id.as_str());
+            while let Some(id) = next {
+                if !kept.insert(id) {
+                    break;
+                }
+                next = manifests
+                    .iter()
+                    .find(|m| m.id == id)
+                    .and_then(|m| m.parent.as_deref());
+            }
+        }
+
+        let mut report = RetentionReport::default();
+        for manifest in &manifests {
+            if !kept.contains(manifest.id.as_str()) {
+                self.storage.delete_manifest(&manifest.id).await?;
+                report.snapshots_deleted.push(manifest.id.clone());
+            }
+        }
+        report.chunks_deleted = self.sweep_chunks().await?;
+
+        Ok(report)
+    }
+
+    /// Delete every chunk no chunked snapshot references
+    ///
+    /// Also reclaims the chunks of snapshots whose writer was dropped before
+    /// finishing. Waits for in-progress chunked snapshot writers and readers
+    /// to finish.
+    ///
+    /// # Returns
+    /// * `usize` - Number of chunks deleted
+    pub async fn collect_garbage(&self) -> Result<usize> {
+        let _gc_guard = self.gc_lock.write().await;
+        self.sweep_chunks().await
+    }
+
+    /// Delete unreferenced chunks; the caller holds the GC lock exclusively
+    async fn sweep_chunks(&self) -> Result<usize> {
+        let referenced: HashSet<String> = self
+            .storage
+            .list_manifests()
+            .await?
+            .into_iter()
+            .flat_map(|m| m.chunks.into_iter().map(|c| c.checksum))
+            .collect();
+
+        let mut deleted = 0;
+        for checksum in self.storage.list_chunks().await? {
+            if !referenced.contains(&checksum) {
+                self.storage.delete_chunk(&checksum).await?;
+                deleted += 1;
+            }
+        }
+
+        Ok(deleted)
+    }
 }
 
 #[cfg(test)]
@@ -272,6 +544,114 @@ mod tests {
         let _ = std::fs::remove_dir_all(temp_dir);
     }
 
+    async fn write_chunked(
+        manager: &SnapshotManager,
+        parent: Option<&str>,
+        ids: std::ops::Range<usize>,
+    ) -> SnapshotManifest {
+        let mut writer = match parent {
+            Some(parent) => manager.begin_incremental_snapshot(parent).await.unwrap(),
+            None => {
+                let config = create_test_snapshot_data("test-collection", 0).config;
+                manager.begin_snapshot("test-collection", config).await
+            }
+        };
+        for i in ids {
+            let record = VectorRecord::new(format!("v{}", i), vec![i as f32, 0.0, 0.0], None);
+            writer.write(record).await.unwrap();
+        }
+        let manifest = writer.finish().await.unwrap();
+        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+        manifest
+    }
+
+    #[tokio::test]
+    async fn test_chunked_retention_and_gc() {
+        let temp_dir = std::env::temp_dir().join("ruvector-retention-test");
+        let storage = LocalStorage::new(temp_dir.clone());
+        let manager =
+            SnapshotManager::new(Box::new(LocalStorage::new(temp_dir.clone()))).with_chunk_size(64);
+
+        let old_base = write_chunked(&manager, None, 0..10).await;
+        let old_delta = write_chunked(&manager, Some(&old_base.id), 10..12).await;
+        let base = write_chunked(&manager, None, 0..20).await;
+        let delta1 = write_chunked(&manager, Some(&base.id), 20..25).await;
+        let delta2 = write_chunked(&manager, Some(&delta1.id), 25..30).await;
+
+        // Identical records are stored once
+        let unique_chunks: HashSet<_> = [&old_base, &old_delta, &base, &delta1, &delta2]
+            .iter()
+            .flat_map(|m| m.chunks.iter().map(|c| c.checksum.clone()))
+            .collect();
+        assert_eq!(
+            storage.list_chunks().await.unwrap().len(),
+            unique_chunks.len()
+        );
+
+        // Parents can't be deleted from under their children
+        assert!(manager.delete_chunked_snapshot(&delta1.id).await.is_err());
+
+        // Keeping the newest snapshot keeps its whole chain
+        let report = manager
+            .apply_retention("test-collection", &RetentionPolicy::keep_last(1))
+            .await
+            .unwrap();
+        let mut deleted = report.snapshots_deleted.clone();
+        deleted.sort();
+        let mut expected = vec![old_base.id.clone(), old_delta.id.clone()];
+        expected.sort();
+        assert_eq!(deleted, expected);
+        assert!(report.chunks_deleted > 0);
+
+        let remaining: HashSet<_> = storage.list_chunks().await.unwrap().into_iter().collect();
+        let referenced: HashSet<_> = [&base, &delta1, &delta2]
+            .iter()
+            .flat_map(|m| m.chunks.iter().map(|c| c.checksum.clone()))
+            .collect();
+        assert_eq!(remaining, referenced);
+
+        let restored = manager.restore_snapshot_chain(&delta2.id).await.unwrap();
+        assert_eq!(restored.vectors_count(), 30);
+
+        // A recent window keeps everything
+        let policy = RetentionPolicy {
+            keep_last: 0,
+            keep_within: Some(Duration::hours(1)),
+        };
+        let report = manager
+            .apply_retention("test-collection", &policy)
+            .await
+            .unwrap();
+        assert_eq!(report, RetentionReport::default());
+
+        // Chunks of abandoned writers are reclaimed
+        let mut writer = manager
+            .begin_incremental_snapshot(&delta2.id)
+            .await
+            .unwrap();
+        for i in 100..120 {
+            let record = VectorRecord::new(format!("v{}", i), vec![i as f32, 0.0, 0.0], None);
+            writer.write(record).await.unwrap();
+        }
+        drop(writer);
+        assert!(manager.collect_garbage().await.unwrap() > 0);
+        assert_eq!(storage.list_chunks().await.unwrap().len(), referenced.len());
+
+        // Deleting the chain from the top down reclaims every chunk
+        for manifest in [&delta2, &delta1, &base] {
+            manager.delete_chunked_snapshot(&manifest.id).await.unwrap();
+        }
+        assert!(storage.list_chunks().await.unwrap().is_empty());
+        assert!(manager
+            .list_chunked_snapshots("test-collection")
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Cleanup
+        let _ = std::fs::remove_dir_all(temp_dir);
+    }
+
     #[tokio::test]
     async fn test_snapshot_validation() {
         let temp_dir = std::env::temp_dir().join("ruvector-validation-test");
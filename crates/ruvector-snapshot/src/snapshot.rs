@@ -118,6 +118,69 @@ pub struct HnswConfig {
     pub ef_search: usize,
 }
 
+/// Manifest of a chunked snapshot
+///
+/// A full snapshot lists the chunks holding every record of the collection.
+/// An incremental snapshot names its `parent` and lists only the records
+/// written since then, plus the ids deleted from the parent's state.
+/// Restoring applies the chain from the base snapshot forward.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    /// Unique snapshot identifier
+    pub id: String,
+
+    /// Name of the collection this snapshot represents
+    pub collection_name: String,
+
+    /// Timestamp when the snapshot was created
+    pub created_at: DateTime<Utc>,
+
+    /// Snapshot this one was taken relative to; `None` for a full snapshot
+    pub parent: Option<String>,
+
+    /// Collection configuration
+    pub config: CollectionConfig,
+
+    /// Chunks holding the records written to this snapshot, in write order
+    pub chunks: Vec<ChunkRef>,
+
+    /// Ids removed from the parent's state
+    ///
+    /// Applied before this snapshot's own records, so a record written to
+    /// this snapshot is kept even if its id is also listed here.
+    pub deleted: Vec<String>,
+}
+
+impl SnapshotManifest {
+    /// Whether this snapshot only stores changes since a parent
+    pub fn is_incremental(&self) -> bool {
+        self.parent.is_some()
+    }
+
+    /// Number of records stored in this snapshot's own chunks
+    pub fn records_count(&self) -> usize {
+        self.chunks.iter().map(|c| c.records).sum()
+    }
+
+    /// Size of this snapshot's own chunks in bytes (uncompressed)
+    pub fn size_bytes(&self) -> u64 {
+        self.chunks.iter().map(|c| c.size_bytes).sum()
+    }
+}
+
+/// Reference to a content-addressed chunk of vector records
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkRef {
+    /// SHA-256 checksum of the encoded records, also the chunk's storage key
+    pub checksum: String,
+
+    /// Number of records in the chunk
+    pub records: usize,
+
+    /// Size of the encoded records in bytes (uncompressed)
+    pub size_bytes: u64,
+}
+
 /// Individual vector record in a snapshot
 #[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
 pub struct VectorRecord {
@@ -160,6 +223,13 @@ impl VectorRecord {
     pub fn dimension(&self) -> usize {
         self.vector.len()
     }
+
+    /// Approximate encoded size in bytes, used to cut chunks
+    pub(crate) fn approximate_size(&self) -> usize {
+        self.id.len()
+            + self.vector.len() * std::mem::size_of::<f32>()
+            + self.payload_json.as_ref().map_or(0, String::len)
+    }
 }
 
 #[cfg(test)]
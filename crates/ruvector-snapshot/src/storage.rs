@@ -4,11 +4,11 @@ use flate2::write::GzEncoder;
 use flate2::Compression;
 use sha2::{Digest, Sha256};
 use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tokio::fs;
 
 use crate::error::{Result, SnapshotError};
-use crate::snapshot::{Snapshot, SnapshotData};
+use crate::snapshot::{Snapshot, SnapshotData, SnapshotManifest};
 
 /// Trait for snapshot storage backends
 #[async_trait]
@@ -22,8 +22,57 @@ pub trait SnapshotStorage: Send + Sync {
     /// List all available snapshots
     async fn list(&self) -> Result<Vec<Snapshot>>;
 
+    /// List the snapshots of one collection, newest first
+    async fn list_collection(&self, collection_name: &str) -> Result<Vec<Snapshot>> {
+        let snapshots = self.list().await?;
+        Ok(snapshots
+            .into_iter()
+            .filter(|s| s.collection_name == collection_name)
+            .collect())
+    }
+
     /// Delete a snapshot from storage
     async fn delete(&self, id: &str) -> Result<()>;
+
+    /// Store a chunk of encoded vector records under its SHA-256 checksum
+    ///
+    /// Chunks are content-addressed and shared between snapshots: storing a
+    /// checksum that already exists is a no-op.
+    async fn put_chunk(&self, checksum: &str, data: &[u8]) -> Result<()>;
+
+    /// Load the encoded vector records of a chunk
+    async fn get_chunk(&self, checksum: &str) -> Result<Vec<u8>>;
+
+    /// List the checksums of all stored chunks
+    async fn list_chunks(&self) -> Result<Vec<String>>;
+
+    /// Delete a chunk
+    async fn delete_chunk(&self, checksum: &str) -> Result<()>;
+
+    /// Save the manifest of a chunked snapshot, making the snapshot visible
+    async fn save_manifest(&self, manifest: &SnapshotManifest) -> Result<()>;
+
+    /// Load the manifest of a chunked snapshot
+    async fn load_manifest(&self, id: &str) -> Result<SnapshotManifest>;
+
+    /// List all chunked snapshot manifests, newest first
+    async fn list_manifests(&self) -> Result<Vec<SnapshotManifest>>;
+
+    /// List the chunked snapshot manifests of one collection, newest first
+    async fn list_collection_manifests(
+        &self,
+        collection_name: &str,
+    ) -> Result<Vec<SnapshotManifest>> {
+        let manifests = self.list_manifests().await?;
+        Ok(manifests
+            .into_iter()
+            .filter(|m| m.collection_name == collection_name)
+            .collect())
+    }
+
+    /// Delete the manifest of a chunked snapshot; its chunks are left for
+    /// garbage collection
+    async fn delete_manifest(&self, id: &str) -> Result<()>;
 }
 
 /// Local filesystem storage backend
@@ -47,8 +96,74 @@ impl LocalStorage {
         self.base_path.join(format!("{}.metadata.json", id))
     }
 
+    /// Get the path for a chunk file
+    fn chunk_path(&self, checksum: &str) -> Result<PathBuf> {
+        // Checksums come from manifests; never let one name a path elsewhere
+        if checksum.len() != 64 || !checksum.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(SnapshotError::corrupted(format!(
+                "Invalid chunk checksum: {}",
+                checksum
+            )));
+        }
+        Ok(self.chunks_dir().join(format!("{}.chunk.gz", checksum)))
+    }
+
+    /// Get the path for a chunked snapshot manifest
+    fn manifest_path(&self, id: &str) -> Result<PathBuf> {
+        // IDs reach here from callers and parent links; keep them a file name
+        let valid = !id.is_empty()
+            && id
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+        if !valid {
+            return Err(SnapshotError::storage(format!(
+                "Invalid snapshot ID: {}",
+                id
+            )));
+        }
+        Ok(self.manifests_dir().join(format!("{}.manifest.json", id)))
+    }
+
+    fn chunks_dir(&self) -> PathBuf {
+        self.base_path.join("chunks")
+    }
+
+    fn manifests_dir(&self) -> PathBuf {
+        self.base_path.join("manifests")
+    }
+
+    /// Write a file through a temporary file and rename, so a crash never
+    /// leaves a partial file under the final name
+    async fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+        let tmp_path = path.with_extension(format!("tmp-{}", uuid::Uuid::new_v4()));
+        fs::write(&tmp_path, data).await?;
+        fs::File::open(&tmp_path).await?.sync_all().await?;
+        if let Err(e) = fs::rename(&tmp_path, path).await {
+            let _ = fs::remove_file(&tmp_path).await;
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    /// List the file names in `dir` ending with `suffix`, without the suffix
+    async fn list_stems(dir: &Path, suffix: &str) -> Result<Vec<String>> {
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut stems = Vec::new();
+        let mut entries = fs::read_dir(dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let file_name = entry.file_name();
+            if let Some(stem) = file_name.to_string_lossy().strip_suffix(suffix) {
+                stems.push(stem.to_string());
+            }
+        }
+        Ok(stems)
+    }
+
     /// Compress data using gzip
-    fn compress(data: &[u8]) -> Result<Vec<u8>> {
+    pub(crate) fn compress(data: &[u8]) -> Result<Vec<u8>> {
         let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
         encoder
             .write_all(data)
@@ -59,7 +174,7 @@ impl LocalStorage {
     }
 
     /// Decompress gzip data
-    fn decompress(data: &[u8]) -> Result<Vec<u8>> {
+    pub(crate) fn decompress(data: &[u8]) -> Result<Vec<u8>> {
         let mut decoder = GzDecoder::new(data);
         let mut decompressed = Vec::new();
         decoder
@@ -69,7 +184,7 @@ impl LocalStorage {
     }
 
     /// Calculate SHA-256 checksum
-    fn calculate_checksum(data: &[u8]) -> String {
+    pub(crate) fn calculate_checksum(data: &[u8]) -> String {
         let mut hasher = Sha256::new();
         hasher.update(data);
         format!("{:x}", hasher.finalize())
@@ -84,6 +199,62 @@ impl LocalStorage {
     }
 }
 
+/// Serialize, checksum and compress a snapshot
+///
+/// Returns the snapshot's metadata and the compressed bytes to store. The
+/// checksum covers the uncompressed data.
+pub(crate) fn encode_snapshot(snapshot_data: &SnapshotData) -> Result<(Snapshot, Vec<u8>)> {
+    // Serialize snapshot data
+    let config = bincode::config::standard();
+    let serialized = bincode::encode_to_vec(snapshot_data, config)
+        .map_err(|e| SnapshotError::SerializationError(e.to_string()))?;
+
+    // Calculate checksum before compression
+    let checksum = LocalStorage::calculate_checksum(&serialized);
+
+    // Compress data
+    let compressed = LocalStorage::compress(&serialized)?;
+
+    // Create snapshot metadata
+    let created_at = chrono::DateTime::parse_from_rfc3339(&snapshot_data.metadata.created_at)
+        .map_err(|e| SnapshotError::storage(format!("Invalid timestamp: {}", e)))?
+        .with_timezone(&chrono::Utc);
+
+    let snapshot = Snapshot {
+        id: snapshot_data.id().to_string(),
+        collection_name: snapshot_data.collection_name().to_string(),
+        created_at,
+        vectors_count: snapshot_data.vectors_count(),
+        checksum,
+        size_bytes: compressed.len() as u64,
+    };
+
+    Ok((snapshot, compressed))
+}
+
+/// Decompress stored snapshot data and verify it against its metadata
+pub(crate) fn decode_snapshot(snapshot: &Snapshot, compressed: &[u8]) -> Result<SnapshotData> {
+    // Decompress
+    let decompressed = LocalStorage::decompress(compressed)?;
+
+    // Verify checksum
+    let actual_checksum = LocalStorage::calculate_checksum(&decompressed);
+    if actual_checksum != snapshot.checksum {
+        return Err(SnapshotError::InvalidChecksum {
+            expected: snapshot.checksum.clone(),
+            actual: actual_checksum,
+        });
+    }
+
+    // Deserialize
+    let config = bincode::config::standard();
+    let (snapshot_data, _): (SnapshotData, usize) =
+        bincode::decode_from_slice(&decompressed, config)
+            .map_err(|e| SnapshotError::SerializationError(e.to_string()))?;
+
+    Ok(snapshot_data)
+}
+
 #[async_trait]
 impl SnapshotStorage for LocalStorage {
     async fn save(&self, snapshot_data: &SnapshotData) -> Result<Snapshot> {
@@ -93,35 +264,11 @@ impl SnapshotStorage for LocalStorage {
         let snapshot_path = self.snapshot_path(&id);
         let metadata_path = self.metadata_path(&id);
 
-        // Serialize snapshot data
-        let config = bincode::config::standard();
-        let serialized = bincode::encode_to_vec(snapshot_data, config)
-            .map_err(|e| SnapshotError::SerializationError(e.to_string()))?;
-
-        // Calculate checksum before compression
-        let checksum = Self::calculate_checksum(&serialized);
-
-        // Compress data
-        let compressed = Self::compress(&serialized)?;
-        let size_bytes = compressed.len() as u64;
+        let (snapshot, compressed) = encode_snapshot(snapshot_data)?;
 
         // Write compressed data
         fs::write(&snapshot_path, &compressed).await?;
 
-        // Create snapshot metadata
-        let created_at = chrono::DateTime::parse_from_rfc3339(&snapshot_data.metadata.created_at)
-            .map_err(|e| SnapshotError::storage(format!("Invalid timestamp: {}", e)))?
-            .with_timezone(&chrono::Utc);
-
-        let snapshot = Snapshot {
-            id: id.clone(),
-            collection_name: snapshot_data.collection_name().to_string(),
-            created_at,
-            vectors_count: snapshot_data.vectors_count(),
-            checksum,
-            size_bytes,
-        };
-
         // Write metadata
         let metadata_json = serde_json::to_string_pretty(&snapshot)?;
         fs::write(&metadata_path, metadata_json).await?;
@@ -145,25 +292,7 @@ impl SnapshotStorage for LocalStorage {
         // Load compressed data
         let compressed = fs::read(&snapshot_path).await?;
 
-        // Decompress
-        let decompressed = Self::decompress(&compressed)?;
-
-        // Verify checksum
-        let actual_checksum = Self::calculate_checksum(&decompressed);
-        if actual_checksum != snapshot.checksum {
-            return Err(SnapshotError::InvalidChecksum {
-                expected: snapshot.checksum,
-                actual: actual_checksum,
-            });
-        }
-
-        // Deserialize
-        let config = bincode::config::standard();
-        let (snapshot_data, _): (SnapshotData, usize) =
-            bincode::decode_from_slice(&decompressed, config)
-                .map_err(|e| SnapshotError::SerializationError(e.to_string()))?;
-
-        Ok(snapshot_data)
+        decode_snapshot(&snapshot, &compressed)
     }
 
     async fn list(&self) -> Result<Vec<Snapshot>> {
@@ -212,6 +341,87 @@ impl SnapshotStorage for LocalStorage {
 
         Ok(())
     }
+
+    async fn put_chunk(&self, checksum: &str, data: &[u8]) -> Result<()> {
+        let chunk_path = self.chunk_path(checksum)?;
+        if chunk_path.exists() {
+            // Only an intact copy may stand in for this one
+            let intact = fs::read(&chunk_path)
+                .await
+                .ok()
+                .and_then(|compressed| Self::decompress(&compressed).ok())
+                .is_some_and(|existing| Self::calculate_checksum(&existing) == checksum);
+            if intact {
+                return Ok(());
+            }
+        }
+
+        fs::create_dir_all(self.chunks_dir()).await?;
+        let compressed = Self::compress(data)?;
+        Self::write_atomic(&chunk_path, &compressed).await
+    }
+
+    async fn get_chunk(&self, checksum: &str) -> Result<Vec<u8>> {
+        let chunk_path = self.chunk_path(checksum)?;
+        if !chunk_path.exists() {
+            return Err(SnapshotError::ChunkNotFound(checksum.to_string()));
+        }
+
+        let compressed = fs::read(&chunk_path).await?;
+        Self::decompress(&compressed)
+    }
+
+    async fn list_chunks(&self) -> Result<Vec<String>> {
+        Self::list_stems(&self.chunks_dir(), ".chunk.gz").await
+    }
+
+    async fn delete_chunk(&self, checksum: &str) -> Result<()> {
+        let chunk_path = self.chunk_path(checksum)?;
+        if !chunk_path.exists() {
+            return Err(SnapshotError::ChunkNotFound(checksum.to_string()));
+        }
+
+        fs::remove_file(&chunk_path).await?;
+        Ok(())
+    }
+
+    async fn save_manifest(&self, manifest: &SnapshotManifest) -> Result<()> {
+        fs::create_dir_all(self.manifests_dir()).await?;
+        let manifest_json = serde_json::to_vec_pretty(manifest)?;
+        Self::write_atomic(&self.manifest_path(&manifest.id)?, &manifest_json).await
+    }
+
+    async fn load_manifest(&self, id: &str) -> Result<SnapshotManifest> {
+        let manifest_path = self.manifest_path(id)?;
+        if !manifest_path.exists() {
+            return Err(SnapshotError::SnapshotNotFound(id.to_string()));
+        }
+
+        let manifest_json = fs::read(&manifest_path).await?;
+        Ok(serde_json::from_slice(&manifest_json)?)
+    }
+
+    async fn list_manifests(&self) -> Result<Vec<SnapshotManifest>> {
+        let mut manifests = Vec::new();
+        for id in Self::list_stems(&self.manifests_dir(), ".manifest.json").await? {
+            manifests.push(self.load_manifest(&id).await?);
+        }
+
+        // Sort by creation date (newest first)
+        manifests.sort_by_key(|m| std::cmp::Reverse(m.created_at));
+
+        Ok(manifests)
+    }
+
+    async fn delete_manifest(&self, id: &str) -> Result<()> {
+        let manifest_path = self.manifest_path(id)?;
+        if !manifest_path.exists() {
+            return Err(SnapshotError::SnapshotNotFound(id.to_string()));
+        }
+
+        fs::remove_file(&manifest_path).await?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -273,4 +483,38 @@ mod tests {
         // Cleanup
         let _ = std::fs::remove_dir_all(temp_dir);
     }
+
+    #[tokio::test]
+    async fn test_manifest_ids_stay_in_manifest_dir() {
+        let temp_dir = std::env::temp_dir().join("ruvector-snapshot-manifest-ids");
+        let storage = LocalStorage::new(temp_dir.clone());
+
+        for id in ["../escape", "a/b", "", ".."] {
+            assert!(storage.load_manifest(id).await.is_err());
+            assert!(storage.delete_manifest(id).await.is_err());
+        }
+        assert!(!temp_dir.join("escape.manifest.json").exists());
+
+        let _ = std::fs::remove_dir_all(temp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_put_chunk_replaces_corrupt_copy() {
+        let temp_dir = std::env::temp_dir().join("ruvector-snapshot-corrupt-chunk");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        let storage = LocalStorage::new(temp_dir.clone());
+
+        let data = b"chunk records".to_vec();
+        let checksum = LocalStorage::calculate_checksum(&data);
+        storage.put_chunk(&checksum, &data).await.unwrap();
+
+        // Damage the stored chunk; storing it again must repair it
+        let path = storage.chunk_path(&checksum).unwrap();
+        std::fs::write(&path, b"garbage").unwrap();
+        assert!(storage.get_chunk(&checksum).await.is_err());
+        storage.put_chunk(&checksum, &data).await.unwrap();
+        assert_eq!(storage.get_chunk(&checksum).await.unwrap(), data);
+
+        let _ = std::fs::remove_dir_all(temp_dir);
+    }
 }
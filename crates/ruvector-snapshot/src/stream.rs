@@ -0,0 +1,480 @@
+use std::collections::{HashSet, VecDeque};
+
+use tokio::sync::RwLockReadGuard;
+
+use crate::error::{Result, SnapshotError};
+use crate::snapshot::{ChunkRef, CollectionConfig, SnapshotManifest, VectorRecord};
+use crate::storage::{LocalStorage, SnapshotStorage};
+
+/// Default target size of a snapshot chunk in bytes (uncompressed)
+pub const DEFAULT_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// Writes a chunked snapshot record by record
+///
+/// Records are buffered until the chunk size is reached, then encoded,
+/// checksummed and stored as a content-addressed chunk, so memory use is
+/// bounded by one chunk however large the collection is. The snapshot only
+/// becomes visible when [`finish`](Self::finish) saves its manifest; chunks
+/// of an unfinished snapshot are reclaimed by garbage collection.
+///
+/// Created by [`SnapshotManager::begin_snapshot`](crate::SnapshotManager::begin_snapshot)
+/// and [`SnapshotManager::begin_incremental_snapshot`](crate::SnapshotManager::begin_incremental_snapshot).
+pub struct SnapshotWriter<'a> {
+    storage: &'a dyn SnapshotStorage,
+    /// Keeps garbage collection from reclaiming chunks before the manifest
+    /// referencing them is saved
+    _gc_guard: RwLockReadGuard<'a, ()>,
+    manifest: SnapshotManifest,
+    chunk_size: usize,
+    buffer: Vec<VectorRecord>,
+    buffered_bytes: usize,
+}
+
+impl<'a> SnapshotWriter<'a> {
+    pub(crate) fn new(
+        storage: &'a dyn SnapshotStorage,
+        gc_guard: RwLockReadGuard<'a, ()>,
+        manifest: SnapshotManifest,
+        chunk_size: usize,
+    ) -> Self {
+        Self {
+            storage,
+            _gc_guard: gc_guard,
+            manifest,
+            chunk_size: chunk_size.max(1),
+            buffer: Vec::new(),
+            buffered_bytes: 0,
+        }
+    }
+
+    /// Get the ID of the snapshot being written
+    pub fn id(&self) -> &str {
+        &self.manifest.id
+    }
+
+    /// Add a record to the snapshot
+    ///
+    /// In an incremental snapshot, records replace any record with the same
+    /// id in the parent.
+    pub async fn write(&mut self, record: VectorRecord) -> Result<()> {
+        let expected_dim = self.manifest.config.dimension;
+        if record.dimension() != expected_dim {
+            return Err(SnapshotError::storage(format!(
+                "Vector {} has dimension {} but expected {}",
+                record.id,
+                record.dimension(),
+                expected_dim
+            )));
+        }
+
+        self.buffered_bytes += record.approximate_size();
+        self.buffer.push(record);
+        if self.buffered_bytes >= self.chunk_size {
+            self.flush_chunk().await?;
+        }
+        Ok(())
+    }
+
+    /// Record that a vector of the parent snapshot was deleted
+    ///
+    /// Only valid for incremental snapshots. Deletions apply to the parent's
+    /// state, so a record written to this snapshot is kept even if its id
+    /// is also deleted.
+    pub fn delete(&mut self, id: impl Into<String>) -> Result<()> {
+        if !self.manifest.is_incremental() {
+            return Err(SnapshotError::storage(
+                "Deletions can only be recorded in incremental snapshots",
+            ));
+        }
+
+        self.manifest.deleted.push(id.into());
+        Ok(())
+    }
+
+    /// Store the remaining records and save the manifest
+    ///
+    /// # Returns
+    /// * `SnapshotManifest` - The manifest of the completed snapshot
+    pub async fn finish(mut self) -> Result<SnapshotManifest> {
+        self.flush_chunk().await?;
+        self.storage.save_manifest(&self.manifest).await?;
+        Ok(self.manifest)
+    }
+
+    /// Encode the buffered records and store them as a chunk
+    async fn flush_chunk(&mut self) -> Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        let records = std::mem::take(&mut self.buffer);
+        self.buffered_bytes = 0;
+
+        let config = bincode::config::standard();
+        let encoded = bincode::encode_to_vec(&records, config)
+            .map_err(|e| SnapshotError::SerializationError(e.to_string()))?;
+        let checksum = LocalStorage::calculate_checksum(&encoded);
+
+        self.storage.put_chunk(&checksum, &encoded).await?;
+        self.manifest.chunks.push(ChunkRef {
+            checksum,
+            records: records.len(),
+            size_bytes: encoded.len() as u64,
+        });
+        Ok(())
+    }
+}
+
+/// One step of restoring a snapshot chain
+enum Step {
+    /// Emit the records of a chunk not already emitted or deleted
+    Chunk(ChunkRef),
+    /// Hide these ids in older snapshots
+    Deleted(Vec<String>),
+}
+
+/// Reads the records of a snapshot chain one chunk at a time
+///
+/// The chain is walked from the requested snapshot back to its full base
+/// snapshot, so the newest version of each record is met first. Only the
+/// ids already emitted or deleted are kept in memory, never more than one
+/// chunk of records. Each chunk's SHA-256 checksum is verified as it is read.
+///
+/// Created by [`SnapshotManager::open_snapshot`](crate::SnapshotManager::open_snapshot).
+pub struct SnapshotReader<'a> {
+    storage: &'a dyn SnapshotStorage,
+    /// Keeps garbage collection from reclaiming chunks while they are read
+    _gc_guard: RwLockReadGuard<'a, ()>,
+    chain: Vec<SnapshotManifest>,
+    steps: VecDeque<Step>,
+    seen: HashSet<String>,
+}
+
+impl<'a> SnapshotReader<'a> {
+    /// Load the manifests of the chain ending at snapshot `id`
+    pub(crate) async fn open(
+        storage: &'a dyn SnapshotStorage,
+        gc_guard: RwLockReadGuard<'a, ()>,
+        id: &str,
+    ) -> Result<Self> {
+        let mut chain: Vec<SnapshotManifest> = Vec::new();
+        let mut next = Some(id.to_string());
+
+        while let Some(id) = next {
+            if chain.iter().any(|m| m.id == id) {
+                return Err(SnapshotError::corrupted(format!(
+                    "Snapshot chain contains a cycle at {}",
+                    id
+                )));
+            }
+
+            let manifest = match storage.load_manifest(&id).await {
+                Err(SnapshotError::SnapshotNotFound(_)) if !chain.is_empty() => {
+                    return Err(SnapshotError::corrupted(format!(
+                        "Parent snapshot {} is missing",
+                        id
+                    )));
+                }
+                result => result?,
+            };
+            if let Some(child) = chain.last() {
+                if manifest.collection_name != child.collection_name {
+                    return Err(SnapshotError::corrupted(format!(
+                        "Snapshot {} belongs to collection {} but its parent {} belongs to {}",
+                        child.id, child.collection_name, manifest.id, manifest.collection_name
+                    )));
+                }
+            }
+
+            next = manifest.parent.clone();
+            chain.push(manifest);
+        }
+
+        let mut steps = VecDeque::new();
+        for manifest in &chain {
+            // Later chunks of a snapshot override earlier ones
+            for chunk in manifest.chunks.iter().rev() {
+                steps.push_back(Step::Chunk(chunk.clone()));
+            }
+            steps.push_back(Step::Deleted(manifest.deleted.clone()));
+        }
+
+        Ok(Self {
+            storage,
+            _gc_guard: gc_guard,
+            chain,
+            steps,
+            seen: HashSet::new(),
+        })
+    }
+
+    /// Get the manifest of the snapshot being restored
+    pub fn manifest(&self) -> &SnapshotManifest {
+        &self.chain[0]
+    }
+
+    /// Get the collection configuration of the snapshot being restored
+    pub fn config(&self) -> &CollectionConfig {
+        &self.chain[0].config
+    }
+
+    /// Get the manifests of the chain, from the requested snapshot back to
+    /// its full base snapshot
+    pub fn chain(&self) -> &[SnapshotManifest] {
+        &self.chain
+    }
+
+    /// Read the next batch of live records
+    ///
+    /// # Returns
+    /// * `Option<Vec<VectorRecord>>` - Records of the next chunk that are
+    ///   neither superseded nor deleted, or `None` once the chain is exhausted
+    pub async fn next_batch(&mut self) -> Result<Option<Vec<VectorRecord>>> {
+        while let Some(step) = self.steps.pop_front() {
+            match step {
+                Step::Deleted(ids) => self.seen.extend(ids),
+                Step::Chunk(chunk) => {
+                    let mut records = self.read_chunk(&chunk).await?;
+
+                    // Later records of a chunk override earlier ones
+                    records.reverse();
+                    records.retain(|record| self.seen.insert(record.id.clone()));
+                    records.reverse();
+
+                    if !records.is_empty() {
+                        return Ok(Some(records));
+                    }
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Load a chunk and verify its checksum
+    async fn read_chunk(&self, chunk: &ChunkRef) -> Result<Vec<VectorRecord>> {
+        let encoded = match self.storage.get_chunk(&chunk.checksum).await {
+            Err(SnapshotError::ChunkNotFound(checksum)) => {
+                return Err(SnapshotError::corrupted(format!(
+                    "Chunk {} referenced by the snapshot chain is missing",
+                    checksum
+                )));
+            }
+            result => result?,
+        };
+
+        let actual_checksum = LocalStorage::calculate_checksum(&encoded);
+        if actual_checksum != chunk.checksum {
+            return Err(SnapshotError::InvalidChecksum {
+                expected: chunk.checksum.clone(),
+                actual: actual_checksum,
+            });
+        }
+
+        let config = bincode::config::standard();
+        let (records, _): (Vec<VectorRecord>, usize) = bincode::decode_from_slice(&encoded, config)
+            .map_err(|e| SnapshotError::SerializationError(e.to_string()))?;
+
+        if records.len() != chunk.records {
+            return Err(SnapshotError::corrupted(format!(
+                "Chunk {} holds {} records but its manifest lists {}",
+                chunk.checksum,
+                records.len(),
+                chunk.records
+            )));
+        }
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manager::SnapshotManager;
+    use crate::snapshot::DistanceMetric;
+    use crate::storage::LocalStorage;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn test_config() -> CollectionConfig {
+        CollectionConfig {
+            dimension: 3,
+            metric: DistanceMetric::Cosine,
+            hnsw_config: None,
+        }
+    }
+
+    fn record(id: &str, x: f32) -> VectorRecord {
+        VectorRecord::new(id.to_string(), vec![x, 0.0, 0.0], Some(json!({"x": x})))
+    }
+
+    async fn restore(manager: &SnapshotManager, id: &str) -> HashMap<String, f32> {
+        let mut reader = manager.open_snapshot(id).await.unwrap();
+        let mut records = HashMap::new();
+        while let Some(batch) = reader.next_batch().await.unwrap() {
+            for record in batch {
+                assert!(records.insert(record.id, record.vector[0]).is_none());
+            }
+        }
+        records
+    }
+
+    #[tokio::test]
+    async fn test_streaming_snapshot_roundtrip() {
+        let temp_dir = std::env::temp_dir().join("ruvector-stream-roundtrip-test");
+        let manager = SnapshotManager::new(Box::new(LocalStorage::new(temp_dir.clone())))
+            .with_chunk_size(256);
+
+        let mut writer = manager
+            .begin_snapshot("test-collection", test_config())
+            .await;
+        for i in 0..100 {
+            writer
+                .write(record(&format!("v{}", i), i as f32))
+                .await
+                .unwrap();
+        }
+        assert!(writer.delete("v0").is_err());
+        let manifest = writer.finish().await.unwrap();
+
+        assert!(!manifest.is_incremental());
+        assert!(manifest.chunks.len() > 1);
+        assert_eq!(manifest.records_count(), 100);
+
+        let mut reader = manager.open_snapshot(&manifest.id).await.unwrap();
+        assert_eq!(reader.config().dimension, 3);
+        let mut restored = Vec::new();
+        while let Some(batch) = reader.next_batch().await.unwrap() {
+            restored.extend(batch);
+        }
+        drop(reader);
+        assert_eq!(restored.len(), 100);
+        let v42 = restored.iter().find(|r| r.id == "v42").unwrap();
+        assert_eq!(v42.payload(), Some(json!({"x": 42.0})));
+
+        // Wrong dimensions are rejected
+        let mut writer = manager
+            .begin_snapshot("test-collection", test_config())
+            .await;
+        let bad = VectorRecord::new("bad".to_string(), vec![1.0], None);
+        assert!(writer.write(bad).await.is_err());
+        drop(writer);
+
+        // Cleanup
+        let _ = std::fs::remove_dir_all(temp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_incremental_snapshot_chain() {
+        let temp_dir = std::env::temp_dir().join("ruvector-stream-chain-test");
+        let manager = SnapshotManager::new(Box::new(LocalStorage::new(temp_dir.clone())))
+            .with_chunk_size(128);
+
+        let mut writer = manager
+            .begin_snapshot("test-collection", test_config())
+            .await;
+        for i in 0..10 {
+            writer
+                .write(record(&format!("v{}", i), i as f32))
+                .await
+                .unwrap();
+        }
+        let base = writer.finish().await.unwrap();
+
+        // Update v1, delete v2, add v10
+        let mut writer = manager.begin_incremental_snapshot(&base.id).await.unwrap();
+        writer.write(record("v1", 100.0)).await.unwrap();
+        writer.write(record("v10", 10.0)).await.unwrap();
+        writer.delete("v2").unwrap();
+        let delta1 = writer.finish().await.unwrap();
+        assert_eq!(delta1.parent.as_deref(), Some(base.id.as_str()));
+        assert_eq!(delta1.records_count(), 2);
+
+        // Delete v10, bring v2 back, update v1 twice in one snapshot
+        let mut writer = manager
+            .begin_incremental_snapshot(&delta1.id)
+            .await
+            .unwrap();
+        writer.delete("v10").unwrap();
+        writer.write(record("v2", 200.0)).await.unwrap();
+        writer.write(record("v1", 101.0)).await.unwrap();
+        writer.write(record("v1", 102.0)).await.unwrap();
+        let delta2 = writer.finish().await.unwrap();
+
+        let state = restore(&manager, &base.id).await;
+        assert_eq!(state.len(), 10);
+        assert_eq!(state["v1"], 1.0);
+
+        let state = restore(&manager, &delta1.id).await;
+        assert_eq!(state.len(), 10);
+        assert_eq!(state["v1"], 100.0);
+        assert_eq!(state["v10"], 10.0);
+        assert!(!state.contains_key("v2"));
+
+        let state = restore(&manager, &delta2.id).await;
+        assert_eq!(state.len(), 10);
+        assert_eq!(state["v1"], 102.0);
+        assert_eq!(state["v2"], 200.0);
+        assert!(!state.contains_key("v10"));
+
+        let reader = manager.open_snapshot(&delta2.id).await.unwrap();
+        let chain: Vec<&str> = reader.chain().iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(chain, [&delta2.id, &delta1.id, &base.id]);
+        drop(reader);
+
+        let data = manager.restore_snapshot_chain(&delta2.id).await.unwrap();
+        assert_eq!(data.id(), delta2.id);
+        assert_eq!(data.vectors_count(), 10);
+
+        // Cleanup
+        let _ = std::fs::remove_dir_all(temp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_corrupted_and_missing_chunks() {
+        let temp_dir = std::env::temp_dir().join("ruvector-stream-corrupt-test");
+        let storage = LocalStorage::new(temp_dir.clone());
+        let manager = SnapshotManager::new(Box::new(LocalStorage::new(temp_dir.clone())));
+
+        let mut writer = manager
+            .begin_snapshot("test-collection", test_config())
+            .await;
+        writer.write(record("v1", 1.0)).await.unwrap();
+        let manifest = writer.finish().await.unwrap();
+        let checksum = &manifest.chunks[0].checksum;
+
+        // Replace the chunk's contents without changing its name
+        storage.delete_chunk(checksum).await.unwrap();
+        storage.put_chunk(checksum, b"tampered").await.unwrap();
+        let mut reader = manager.open_snapshot(&manifest.id).await.unwrap();
+        assert!(matches!(
+            reader.next_batch().await,
+            Err(SnapshotError::InvalidChecksum { .. })
+        ));
+        drop(reader);
+
+        storage.delete_chunk(checksum).await.unwrap();
+        let mut reader = manager.open_snapshot(&manifest.id).await.unwrap();
+        assert!(matches!(
+            reader.next_batch().await,
+            Err(SnapshotError::CorruptedSnapshot(_))
+        ));
+        drop(reader);
+
+        // Incremental snapshots whose parent is gone can't be restored
+        let mut writer = manager
+            .begin_incremental_snapshot(&manifest.id)
+            .await
+            .unwrap();
+        writer.write(record("v2", 2.0)).await.unwrap();
+        let child = writer.finish().await.unwrap();
+        storage.delete_manifest(&manifest.id).await.unwrap();
+        assert!(matches!(
+            manager.open_snapshot(&child.id).await,
+            Err(SnapshotError::CorruptedSnapshot(_))
+        ));
+
+        // Cleanup
+        let _ = std::fs::remove_dir_all(temp_dir);
+    }
+}
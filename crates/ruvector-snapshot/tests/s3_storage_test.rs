@@ -0,0 +1,431 @@
+//! `S3Storage` tests against an in-process fake of the S3 API
+//!
+//! The fake implements the subset of S3 the backend uses: object PUT, GET,
+//! HEAD and DELETE, ListObjectsV2 with small pages, and multipart uploads.
+//! Like S3 it verifies `x-amz-content-sha256` and `x-amz-checksum-sha256`
+//! server-side, and it can corrupt an upload in transit to prove that
+//! corrupted data is rejected rather than stored.
+
+#![cfg(feature = "s3")]
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, Method, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    Router,
+};
+use base64::Engine;
+use ruvector_snapshot::{
+    CollectionConfig, DistanceMetric, RetentionPolicy, S3Config, S3Storage, SnapshotData,
+    SnapshotError, SnapshotManager, SnapshotStorage, VectorRecord,
+};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+const BUCKET: &str = "backups";
+const ACCESS_KEY: &str = "test-access-key";
+/// Keys per ListObjectsV2 page, small to exercise continuation tokens
+const PAGE_SIZE: usize = 3;
+
+#[derive(Default)]
+struct FakeS3 {
+    objects: BTreeMap<String, Vec<u8>>,
+    /// Upload ID -> (key, part number -> data)
+    uploads: HashMap<String, (String, BTreeMap<u32, Vec<u8>>)>,
+    /// Keys assembled by multipart uploads, with their part counts
+    multipart_keys: Vec<(String, usize)>,
+    /// Flip a byte of the next uploaded body before verifying it
+    corrupt_next_upload: bool,
+    next_upload_id: u32,
+}
+
+type Shared = Arc<Mutex<FakeS3>>;
+
+fn error(status: StatusCode, code: &str) -> Response {
+    let body = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?><Error><Code>{}</Code><Message>{} from fake</Message></Error>",
+        code, code
+    );
+    (status, body).into_response()
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap();
+            decoded.push(u8::from_str_radix(hex, 16).unwrap());
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).unwrap()
+}
+
+fn sha256_base64(data: &[u8]) -> String {
+    base64::engine::general_purpose::STANDARD.encode(Sha256::digest(data))
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+async fn handle(
+    State(s3): State<Shared>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+
+    let credential = format!("AWS4-HMAC-SHA256 Credential={}/", ACCESS_KEY);
+    if !header("authorization").is_some_and(|a| a.starts_with(&credential)) {
+        return error(StatusCode::FORBIDDEN, "AccessDenied");
+    }
+    if header("x-amz-content-sha256") != Some(sha256_hex(&body).as_str()) {
+        return error(StatusCode::BAD_REQUEST, "XAmzContentSHA256Mismatch");
+    }
+
+    let path = uri.path().trim_start_matches('/');
+    let (bucket, key) = match path.split_once('/') {
+        Some((bucket, key)) => (bucket, Some(percent_decode(key))),
+        None => (path, None),
+    };
+    if bucket != BUCKET {
+        return error(StatusCode::NOT_FOUND, "NoSuchBucket");
+    }
+    let query: HashMap<String, String> = uri
+        .query()
+        .unwrap_or("")
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(k), percent_decode(v))
+        })
+        .collect();
+
+    let mut s3 = s3.lock().unwrap();
+    let mut body = body.to_vec();
+    if method == Method::PUT && std::mem::take(&mut s3.corrupt_next_upload) {
+        body[0] ^= 0xff;
+    }
+    if let Some(expected) = header("x-amz-checksum-sha256") {
+        if sha256_base64(&body) != expected {
+            return error(StatusCode::BAD_REQUEST, "BadDigest");
+        }
+    }
+
+    let Some(key) = key else {
+        // ListObjectsV2
+        if method != Method::GET || query.get("list-type").map(String::as_str) != Some("2") {
+            return error(StatusCode::BAD_REQUEST, "InvalidRequest");
+        }
+        let prefix = query.get("prefix").cloned().unwrap_or_default();
+        let start = query.get("continuation-token").cloned();
+        let mut keys = s3
+            .objects
+            .keys()
+            .filter(|k| k.starts_with(&prefix))
+            .filter(|k| start.as_ref().is_none_or(|start| *k > start));
+        let page: Vec<&String> = keys.by_ref().take(PAGE_SIZE).collect();
+        let truncated = keys.next().is_some();
+
+        let mut xml = String::from("<ListBucketResult>");
+        for key in &page {
+            xml.push_str(&format!(
+                "<Contents><Key>{}</Key></Contents>",
+                xml_escape(key)
+            ));
+        }
+        xml.push_str(&format!("<IsTruncated>{}</IsTruncated>", truncated));
+        if truncated {
+            xml.push_str(&format!(
+                "<NextContinuationToken>{}</NextContinuationToken>",
+                xml_escape(page.last().unwrap())
+            ));
+        }
+        xml.push_str("</ListBucketResult>");
+        return xml.into_response();
+    };
+
+    match (method, query.get("uploadId").cloned()) {
+        (Method::POST, None) if query.contains_key("uploads") => {
+            if header("x-amz-checksum-algorithm") != Some("SHA256") {
+                return error(StatusCode::BAD_REQUEST, "InvalidRequest");
+            }
+            s3.next_upload_id += 1;
+            let upload_id = format!("upload-{}", s3.next_upload_id);
+            s3.uploads
+                .insert(upload_id.clone(), (key.clone(), BTreeMap::new()));
+            format!(
+                "<InitiateMultipartUploadResult><Key>{}</Key><UploadId>{}</UploadId></InitiateMultipartUploadResult>",
+                xml_escape(&key),
+                upload_id
+            )
+            .into_response()
+        }
+        (Method::PUT, Some(upload_id)) => {
+            if header("x-amz-checksum-sha256").is_none() {
+                return error(StatusCode::BAD_REQUEST, "InvalidRequest");
+            }
+            let part_number: u32 = query["partNumber"].parse().unwrap();
+            let etag = format!("\"{}\"", sha256_hex(&body));
+            match s3.uploads.get_mut(&upload_id) {
+                Some((_, parts)) => {
+                    parts.insert(part_number, body);
+                    ([("etag", etag)], "").into_response()
+                }
+                None => error(StatusCode::NOT_FOUND, "NoSuchUpload"),
+            }
+        }
+        (Method::POST, Some(upload_id)) => {
+            let Some((upload_key, parts)) = s3.uploads.remove(&upload_id) else {
+                return error(StatusCode::NOT_FOUND, "NoSuchUpload");
+            };
+            let request = String::from_utf8(body).unwrap();
+            let mut assembled = Vec::new();
+            for (number, part) in &parts {
+                let listed = format!("<PartNumber>{}</PartNumber>", number);
+                let checksum = format!("<ChecksumSHA256>{}</ChecksumSHA256>", sha256_base64(part));
+                if !request.contains(&listed) || !request.contains(&checksum) {
+                    return error(StatusCode::BAD_REQUEST, "InvalidPart");
+                }
+                assembled.extend_from_slice(part);
+            }
+            s3.multipart_keys.push((upload_key.clone(), parts.len()));
+            s3.objects.insert(upload_key, assembled);
+            "<CompleteMultipartUploadResult></CompleteMultipartUploadResult>".into_response()
+        }
+        (Method::DELETE, Some(upload_id)) => {
+            s3.uploads.remove(&upload_id);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        (Method::PUT, None) => {
+            if header("x-amz-checksum-sha256").is_none() {
+                return error(StatusCode::BAD_REQUEST, "InvalidRequest");
+            }
+            s3.objects.insert(key, body);
+            StatusCode::OK.into_response()
+        }
+        (Method::GET, None) => match s3.objects.get(&key) {
+            Some(data) => data.clone().into_response(),
+            None => error(StatusCode::NOT_FOUND, "NoSuchKey"),
+        },
+        (Method::HEAD, None) => match s3.objects.contains_key(&key) {
+            true => StatusCode::OK.into_response(),
+            false => StatusCode::NOT_FOUND.into_response(),
+        },
+        (Method::DELETE, None) => {
+            s3.objects.remove(&key);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        _ => error(StatusCode::BAD_REQUEST, "InvalidRequest"),
+    }
+}
+
+/// Start the fake on a random port and return a storage pointing at it
+async fn start_fake(multipart_threshold: usize, part_size: usize) -> (S3Storage, Shared) {
+    let s3 = Shared::default();
+    let router = Router::new().fallback(handle).with_state(s3.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+    let mut config = S3Config::new(
+        format!("http://{}", addr),
+        BUCKET,
+        ACCESS_KEY,
+        "test-secret-key",
+    );
+    config.prefix = "cluster-a/snapshots".to_string();
+    config.multipart_threshold = multipart_threshold;
+    config.part_size = part_size;
+    (S3Storage::new(config).unwrap(), s3)
+}
+
+/// Vectors with pseudo-random components, so gzip can't shrink them much
+fn snapshot_data(collection: &str, count: usize) -> SnapshotData {
+    let mut state = 0x2545f491u32;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state as f32 / u32::MAX as f32
+    };
+    let vectors = (0..count)
+        .map(|i| {
+            let vector = (0..16).map(|_| next()).collect();
+            VectorRecord::new(format!("v{}", i), vector, Some(serde_json::json!({"n": i})))
+        })
+        .collect();
+    SnapshotData::new(collection.to_string(), config(), vectors)
+}
+
+fn config() -> CollectionConfig {
+    CollectionConfig {
+        dimension: 16,
+        metric: DistanceMetric::Cosine,
+        hnsw_config: None,
+    }
+}
+
+#[tokio::test]
+async fn test_s3_snapshot_roundtrip_with_multipart() {
+    let (storage, s3) = start_fake(4 * 1024, 1024).await;
+
+    let large = snapshot_data("docs", 200);
+    let small = snapshot_data("docs", 1);
+    let other = snapshot_data("images", 2);
+    let large_snapshot = storage.save(&large).await.unwrap();
+    storage.save(&small).await.unwrap();
+    storage.save(&other).await.unwrap();
+
+    // The large snapshot went up in several parts
+    {
+        let s3 = s3.lock().unwrap();
+        let data_key = format!(
+            "cluster-a/snapshots/collections/docs/snapshots/{}.snapshot.gz",
+            large.id()
+        );
+        let parts = s3
+            .multipart_keys
+            .iter()
+            .find(|(key, _)| *key == data_key)
+            .map(|(_, parts)| *parts)
+            .unwrap();
+        assert!(parts > 1);
+        assert_eq!(parts as u64, large_snapshot.size_bytes.div_ceil(1024));
+        assert!(s3.uploads.is_empty());
+    }
+
+    let loaded = storage.load(large.id()).await.unwrap();
+    assert_eq!(loaded.vectors_count(), 200);
+    assert_eq!(loaded.vectors[7].vector, large.vectors[7].vector);
+    assert_eq!(
+        loaded.vectors[7].payload(),
+        Some(serde_json::json!({"n": 7}))
+    );
+
+    // Listing pages through the bucket and filters by collection prefix
+    assert_eq!(storage.list().await.unwrap().len(), 3);
+    let docs = storage.list_collection("docs").await.unwrap();
+    assert_eq!(docs.len(), 2);
+    assert!(docs.iter().all(|s| s.collection_name == "docs"));
+    assert_eq!(storage.list_collection("images").await.unwrap().len(), 1);
+    assert!(storage.list_collection("doc").await.unwrap().is_empty());
+
+    storage.delete(large.id()).await.unwrap();
+    assert!(matches!(
+        storage.load(large.id()).await,
+        Err(SnapshotError::SnapshotNotFound(_))
+    ));
+    assert!(matches!(
+        storage.delete(large.id()).await,
+        Err(SnapshotError::SnapshotNotFound(_))
+    ));
+    assert_eq!(storage.list_collection("docs").await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_s3_rejects_corrupted_uploads() {
+    let (storage, s3) = start_fake(4 * 1024, 1024).await;
+
+    // Single-part upload
+    let data = snapshot_data("docs", 1);
+    s3.lock().unwrap().corrupt_next_upload = true;
+    let error = storage.save(&data).await.unwrap_err().to_string();
+    assert!(error.contains("BadDigest"), "{}", error);
+    assert!(storage.load(data.id()).await.is_err());
+
+    // One corrupted part fails and aborts the whole multipart upload
+    let data = snapshot_data("docs", 200);
+    s3.lock().unwrap().corrupt_next_upload = true;
+    let error = storage.save(&data).await.unwrap_err().to_string();
+    assert!(error.contains("BadDigest"), "{}", error);
+    {
+        let s3 = s3.lock().unwrap();
+        assert!(s3.uploads.is_empty());
+        assert!(s3.objects.is_empty());
+    }
+
+    // Uncorrupted retries succeed
+    storage.save(&data).await.unwrap();
+    assert_eq!(storage.load(data.id()).await.unwrap().vectors_count(), 200);
+}
+
+#[tokio::test]
+async fn test_s3_chunked_snapshots() {
+    let (storage, s3) = start_fake(4 * 1024, 1024).await;
+    let manager = SnapshotManager::new(Box::new(storage)).with_chunk_size(2 * 1024);
+
+    let mut writer = manager.begin_snapshot("docs", config()).await;
+    for record in snapshot_data("docs", 100).vectors {
+        writer.write(record).await.unwrap();
+    }
+    let base = writer.finish().await.unwrap();
+    assert!(base.chunks.len() > 1);
+
+    let mut writer = manager.begin_incremental_snapshot(&base.id).await.unwrap();
+    writer
+        .write(VectorRecord::new("v1".to_string(), vec![1.0; 16], None))
+        .await
+        .unwrap();
+    writer.delete("v2").unwrap();
+    let delta = writer.finish().await.unwrap();
+
+    let restored = manager.restore_snapshot_chain(&delta.id).await.unwrap();
+    assert_eq!(restored.vectors_count(), 99);
+    let v1 = restored.vectors.iter().find(|r| r.id == "v1").unwrap();
+    assert_eq!(v1.vector, vec![1.0; 16]);
+    assert!(restored.vectors.iter().all(|r| r.id != "v2"));
+
+    // Another collection's snapshots are untouched by retention
+    let mut writer = manager.begin_snapshot("images", config()).await;
+    for record in snapshot_data("images", 5).vectors {
+        writer.write(record).await.unwrap();
+    }
+    let images = writer.finish().await.unwrap();
+
+    let mut writer = manager.begin_snapshot("docs", config()).await;
+    writer
+        .write(VectorRecord::new("only".to_string(), vec![0.5; 16], None))
+        .await
+        .unwrap();
+    let latest = writer.finish().await.unwrap();
+
+    let report = manager
+        .apply_retention("docs", &RetentionPolicy::keep_last(1))
+        .await
+        .unwrap();
+    assert_eq!(report.snapshots_deleted.len(), 2);
+    assert!(report.chunks_deleted >= base.chunks.len());
+
+    let docs = manager.list_chunked_snapshots("docs").await.unwrap();
+    assert_eq!(docs.len(), 1);
+    assert_eq!(docs[0].id, latest.id);
+    let restored = manager.restore_snapshot_chain(&images.id).await.unwrap();
+    assert_eq!(restored.vectors_count(), 5);
+
+    let s3 = s3.lock().unwrap();
+    let chunks = s3
+        .objects
+        .keys()
+        .filter(|k| k.starts_with("cluster-a/snapshots/chunks/"))
+        .count();
+    assert_eq!(chunks, latest.chunks.len() + images.chunks.len());
+}
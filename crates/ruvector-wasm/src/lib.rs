@@ -304,6 +304,7 @@ impl VectorDB {
     /// * `query` - Query vector as Float32Array
     /// * `k` - Number of results to return
     /// * `filter` - Optional metadata filter object
+    /// * `ef_search` - Optional HNSW efSearch override for this query
     ///
     /// # Returns
     /// Array of search results
@@ -313,6 +314,7 @@ impl VectorDB {
         query: Float32Array,
         k: usize,
         filter: Option<JsValue>,
+        ef_search: Option<usize>,
     ) -> Result<Vec<JsSearchResult>, JsValue> {
         let query_vector: Vec<f32> = query.to_vec();
 
@@ -334,7 +336,7 @@ impl VectorDB {
             vector: query_vector,
             k,
             filter: metadata_filter,
-            ef_search: None,
+            ef_search,
         };
 
         let db = self.db.lock();
@@ -348,6 +350,15 @@ impl VectorDB {
             .collect())
     }
 
+    /// Set the default HNSW efSearch used by queries that don't override it
+    ///
+    /// # Arguments
+    /// * `ef_search` - Candidate list size (higher = better recall, slower)
+    #[wasm_bindgen(js_name = setEfSearch)]
+    pub fn set_ef_search(&self, ef_search: usize) {
+        self.db.lock().set_ef_search(ef_search);
+    }
+
     /// Delete a vector by ID
     ///
     /// # Arguments
@@ -25,7 +25,7 @@ fn test_insert_and_search() {
 
     // Search
     let query = Float32Array::from(&[1.0, 0.0, 0.0][..]);
-    let results = db.search(query, 1, None);
+    let results = db.search(query, 1, None, None);
     assert!(results.is_ok());
 
     let results = results.unwrap();
@@ -132,7 +132,7 @@ fn test_dimension_mismatch() {
     // Note: This might succeed depending on implementation
     // The search with wrong dimensions should definitely fail
     let query = Float32Array::from(&[1.0, 0.0][..]);
-    let search_result = db.search(query, 1, None);
+    let search_result = db.search(query, 1, None, None);
     assert!(search_result.is_err());
 }
 
@@ -27,6 +27,7 @@ use log::{debug, info};
 
 pub use crate::filter::FilterT;
 use anndists::dist::distances::Distance;
+use anyhow::anyhow;
 
 // TODO
 // Profiling.
@@ -1276,6 +1277,137 @@ impl<'b, T: Clone + Send + Sync, D: Distance<T> + Send + Sync> Hnsw<'b, T, D> {
         //   println!("     exitingreverse update neighbourhood for  new point {:?} ", new_point.p_id);
     } // end of reverse_update_neighborhood_simple
 
+    /// Unlinks deleted points from the neighbourhoods of live points.
+    /// For each live point having a deleted neighbour at some layer, the deleted neighbour is dropped and
+    /// replaced by the best live candidates among the remaining neighbours and the neighbours of the
+    /// deleted point at that layer (the usual local repair used by hnswlib).
+    /// Deleted points themselves are left untouched so that they can still be traversed if one of them is
+    /// the entry point. Returns the number of neighbour lists that were repaired.
+    pub fn repair_deleted(&self, is_deleted: &(dyn Fn(&DataId) -> bool + Sync)) -> usize {
+        if self.get_nb_point() == 0 {
+            return 0;
+        }
+        let points: Vec<Arc<Point<'b, T>>> = self.layer_indexed_points.into_iter().collect();
+        points
+            .par_iter()
+            .filter(|p| !is_deleted(&p.origin_id))
+            .map(|point| {
+                let mut repaired = 0;
+                let level = point.p_id.0 as usize;
+                for l in 0..=level {
+                    let has_deleted = point.neighbours.read()[l]
+                        .iter()
+                        .any(|n| is_deleted(&n.point_ref.origin_id));
+                    if !has_deleted {
+                        continue;
+                    }
+                    // gather candidates without holding our own lock while reading others
+                    let current = point.neighbours.read()[l].clone();
+                    let mut seen = HashSet::<PointId>::new();
+                    seen.insert(point.p_id);
+                    let mut candidates = Vec::<Arc<PointWithOrder<'b, T>>>::new();
+                    for n in &current {
+                        if is_deleted(&n.point_ref.origin_id) {
+                            // skip the read lock if n is ourself (cannot happen but be safe)
+                            if n.point_ref.p_id == point.p_id {
+                                continue;
+                            }
+                            for nn in &n.point_ref.neighbours.read()[l] {
+                                if !is_deleted(&nn.point_ref.origin_id)
+                                    && (nn.point_ref.p_id.0 as usize) >= l
+                                    && seen.insert(nn.point_ref.p_id)
+                                {
+                                    let d = self
+                                        .dist_f
+                                        .eval(point.data.get_v(), nn.point_ref.data.get_v());
+                                    candidates
+                                        .push(Arc::new(PointWithOrder::new(&nn.point_ref, d)));
+                                }
+                            }
+                        } else if seen.insert(n.point_ref.p_id) {
+                            candidates.push(Arc::clone(n));
+                        }
+                    }
+                    let threshold = if l > 0 {
+                        self.max_nb_connection
+                    } else {
+                        2 * self.max_nb_connection
+                    };
+                    candidates.sort_unstable();
+                    candidates.truncate(threshold);
+                    point.neighbours.write()[l] = candidates;
+                    repaired += 1;
+                }
+                repaired
+            })
+            .sum()
+    } // end of repair_deleted
+
+    /// returns the PointId of the current entry point, None if the graph is empty
+    pub fn get_entry_point_id(&self) -> Option<PointId> {
+        self.layer_indexed_points
+            .entry_point
+            .read()
+            .as_ref()
+            .map(|p| p.p_id)
+    }
+
+    /// Rebuilds a graph from a previously exported topology, without any distance computation.
+    /// Each point is given as (data, origin id, point id, neighbourhood by layer) where the neighbourhood
+    /// is as returned by [Point::get_neighborhood_id].
+    /// Points must come layer by layer and, inside a layer, in increasing rank, which is the order of
+    /// the iterator on [PointIndexation]. The graph can be extended by insertion afterwards.
+    #[allow(clippy::type_complexity)]
+    pub fn from_topology(
+        max_nb_connection: usize,
+        max_elements: usize,
+        max_layer: usize,
+        ef_construction: usize,
+        f: D,
+        points: Vec<(Vec<T>, DataId, PointId, Vec<Vec<Neighbour>>)>,
+        entry_point: Option<PointId>,
+    ) -> anyhow::Result<Self> {
+        let mut hnsw = Self::new(max_nb_connection, max_elements, max_layer, ef_construction, f);
+        let nb_point = points.len();
+        {
+            let indexation = &hnsw.layer_indexed_points;
+            let mut layers = indexation.points_by_layer.write();
+            let mut neighbourhoods = Vec::with_capacity(nb_point);
+            for (data, origin_id, p_id, neighbours) in points {
+                let l = p_id.0 as usize;
+                if l >= layers.len() || p_id.1 < 0 || p_id.1 as usize != layers[l].len() {
+                    return Err(anyhow!("point {:?} out of layer order", p_id));
+                }
+                let point = Arc::new(Point::new(data, origin_id, p_id));
+                layers[l].push(Arc::clone(&point));
+                neighbourhoods.push((point, neighbours));
+            }
+            for (point, neighbours) in &neighbourhoods {
+                let mut lists = point.neighbours.write();
+                for (l, layer_neighbours) in neighbours.iter().enumerate().take(lists.len()) {
+                    for n in layer_neighbours {
+                        let n_point = layers
+                            .get(n.p_id.0 as usize)
+                            .and_then(|layer| layer.get(n.p_id.1 as usize))
+                            .ok_or_else(|| anyhow!("dangling neighbour {:?}", n.p_id))?;
+                        lists[l].push(Arc::new(PointWithOrder::new(n_point, n.distance)));
+                    }
+                    lists[l].sort_unstable();
+                }
+            }
+            *indexation.nb_point.write() = nb_point;
+            if let Some(p_id) = entry_point {
+                let entry = layers
+                    .get(p_id.0 as usize)
+                    .and_then(|layer| layer.get(p_id.1 as usize))
+                    .ok_or_else(|| anyhow!("entry point {:?} not in graph", p_id))?;
+                hnsw.data_dimension = entry.get_v().len();
+                *indexation.entry_point.write() = Some(Arc::clone(entry));
+            }
+        }
+        Ok(hnsw)
+    } // end of from_topology
+
     pub fn get_point_indexation(&self) -> &PointIndexation<'b, T> {
         &self.layer_indexed_points
     }
@@ -131,7 +131,7 @@ async fn benchmark_replication_log() {
     let start = Instant::now();
     for i in 0..iterations {
         let data = format!("log-entry-{}", i).into_bytes();
-        log.append(data);
+        log.append(data).unwrap();
     }
     let append_elapsed = start.elapsed();
 
@@ -79,9 +79,9 @@ async fn test_replication_log() {
     let log = ReplicationLog::new("test-replica");
 
     // Append entries
-    let entry1 = log.append(b"data1".to_vec());
-    let entry2 = log.append(b"data2".to_vec());
-    let entry3 = log.append(b"data3".to_vec());
+    let entry1 = log.append(b"data1".to_vec()).unwrap();
+    let entry2 = log.append(b"data2".to_vec()).unwrap();
+    let entry3 = log.append(b"data3".to_vec()).unwrap();
 
     // Verify sequence numbers
     assert_eq!(entry1.sequence, 1);
@@ -179,11 +179,11 @@ async fn test_replica_catchup() {
     let log = Arc::new(ReplicationLog::new("primary"));
 
     // Add some entries directly to log
-    log.append(b"entry1".to_vec());
-    log.append(b"entry2".to_vec());
-    log.append(b"entry3".to_vec());
-    log.append(b"entry4".to_vec());
-    log.append(b"entry5".to_vec());
+    log.append(b"entry1".to_vec()).unwrap();
+    log.append(b"entry2".to_vec()).unwrap();
+    log.append(b"entry3".to_vec()).unwrap();
+    log.append(b"entry4".to_vec()).unwrap();
+    log.append(b"entry5".to_vec()).unwrap();
 
     let manager = SyncManager::new(Arc::new(replica_set), log);
 
@@ -259,7 +259,7 @@ async fn test_log_append_performance() {
 
     for i in 0..iterations {
         let data = format!("data-{}", i).into_bytes();
-        log.append(data);
+        log.append(data).unwrap();
     }
 
     let elapsed = start.elapsed();